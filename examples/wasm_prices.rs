@@ -0,0 +1,52 @@
+//! Fetches ETH prices from CoinGecko using only the provider library.
+//!
+//! The same code runs natively and in the browser:
+//!
+//! ```bash
+//! cargo run --example wasm_prices --no-default-features
+//! cargo build --example wasm_prices --no-default-features --target wasm32-unknown-unknown
+//! ```
+//!
+//! On wasm32 requests go through the browser's `fetch`, so the page serving the
+//! module must be allowed to reach `api.coingecko.com`, and output goes to the
+//! browser console.
+
+use boltzmann::domains::crypto::coingecko::CoinGecko;
+use boltzmann::domains::crypto::{Coin, Currency, PriceProvider};
+
+async fn print_prices() {
+    let provider = match CoinGecko::new(None) {
+        Ok(provider) => provider,
+        Err(e) => return report(&format!("Failed to build CoinGecko provider: {:#}", e)),
+    };
+
+    match provider.get_quotes(Coin::ETH, &[Currency::USD, Currency::EUR]).await {
+        Ok(quotes) => {
+            for quote in quotes {
+                report(&format!("{} = {}", quote.coin, quote.currency.format(quote.price)));
+            }
+        }
+        Err(e) => report(&format!("Failed to fetch prices: {:#}", e)),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn report(line: &str) {
+    println!("{}", line);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn report(line: &str) {
+    web_sys::console::log_1(&line.into());
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[tokio::main]
+async fn main() {
+    print_prices().await;
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    wasm_bindgen_futures::spawn_local(print_prices());
+}
@@ -1,18 +1,40 @@
 //! Swagger/OpenAPI documentation setup.
 
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
+use tracing::error;
 
 // Import response types
-use crate::domains::crypto::{Quote, Currency, Coin, QuotePerAmount, ProviderSource};
-use crate::domains::gas::price::{GasQuote, GasPrice, GasOracleSource};
+use crate::domains::crypto::aggregate::{AggregateQuote, AggregateStrategy};
+use crate::domains::crypto::feed::AggregateTick;
+use crate::domains::crypto::{Quote, Currency, Coin, QuotePerAmount, ProviderSource, MarketDepth, MarketData, PriceSpread};
+use crate::domains::gas::price::{BaseFeeTrend, Eip1559Fees, FlatGasQuote, GasBaseline, GasEta, GasQuote, GasPrice, GasPrice1559, GasOracleSource};
+use crate::domains::gas::cost::{BatchCost, GasPriceFiat, Operation, OperationCost, TierFiatCost};
+use crate::api::routes::gas::{BatchCostRequest, MultichainGasResponse};
+use crate::api::routes::health::{HealthResponse, ProviderStatus};
+use crate::api::routes::ping::{PingResponse, UpstreamPing};
+use crate::api::routes::limits::LimitsResponse;
+use crate::api::routes::crypto::PriceResponse;
+use crate::api::routes::admin::CacheFlushResponse;
+use crate::api::response::ResponseMeta;
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         crate::api::routes::crypto::get_crypto_prices,
+        crate::api::routes::crypto::get_market_depth,
+        crate::api::routes::crypto::get_market_data,
+        crate::api::routes::crypto::get_price_spread,
+        crate::api::routes::crypto::stream_aggregate,
         crate::api::routes::gas::get_gas_estimates,
+        crate::api::routes::gas::get_multichain_gas_estimates,
+        crate::api::routes::gas::estimate_batch_cost,
         crate::api::routes::health::health_check,
+        crate::api::routes::ping::ping,
+        crate::api::routes::limits::get_limits,
+        crate::api::routes::admin::flush_cache,
     ),
     components(
         schemas(
@@ -21,25 +43,103 @@ use crate::domains::gas::price::{GasQuote, GasPrice, GasOracleSource};
             Coin,
             QuotePerAmount,
             ProviderSource,
+            MarketDepth,
+            MarketData,
+            PriceSpread,
+            AggregateQuote,
+            AggregateStrategy,
+            AggregateTick,
             GasQuote,
+            FlatGasQuote,
+            MultichainGasResponse,
             GasPrice,
+            BaseFeeTrend,
+            GasPrice1559,
+            Eip1559Fees,
+            GasEta,
+            GasBaseline,
             GasOracleSource,
+            BatchCostRequest,
+            BatchCost,
+            OperationCost,
+            Operation,
+            GasPriceFiat,
+            TierFiatCost,
+            PriceResponse,
+            ResponseMeta,
+            HealthResponse,
+            ProviderStatus,
+            PingResponse,
+            UpstreamPing,
+            LimitsResponse,
+            CacheFlushResponse,
         )
     ),
     tags(
         (name = "crypto", description = "Cryptocurrency price endpoints"),
         (name = "gas", description = "Gas price oracle endpoints"),
         (name = "health", description = "Health check endpoints"),
+        (name = "admin", description = "Operator endpoints, requiring ADMIN_API_KEY"),
     ),
     info(
         title = "Boltzmann API",
         version = "0.1.0",
-        description = "Gas and fee analytics API for EVM chains",
+        description = "Gas and fee analytics API for EVM chains.\n\n\
+            Limits: at most `MAX_INFLIGHT_REQUESTS` requests (default 512) are handled at once and \
+            further requests are refused with `503`; request bodies are capped at 2 MiB; price \
+            providers may have per-minute quotas (`<PROVIDER>_RPM`), and requests over quota wait \
+            up to 5 seconds before failing with `503`. `GET /api/v1/limits` reports the values \
+            this server is configured with.",
         license(name = "AGPL-3.0", url = "https://www.gnu.org/licenses/agpl-3.0.html")
     )
 )]
 pub struct ApiDoc;
 
+/// Swagger UI at `/docs`, serving the OpenAPI document at `/api-docs/openapi.json`
 pub fn swagger_ui() -> SwaggerUi {
     SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi())
 }
+
+/// Serves the OpenAPI document as YAML at `/api-docs/openapi.yaml`.
+///
+/// Built from the same [`ApiDoc`] as the JSON document, so the two never diverge.
+pub async fn openapi_yaml() -> Response {
+    match ApiDoc::openapi().to_yaml() {
+        Ok(yaml) => ([(header::CONTENT_TYPE, "application/yaml")], yaml).into_response(),
+        Err(e) => {
+            error!("Failed to serialize OpenAPI document as YAML: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::{AppState, Config};
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn get(uri: &str) -> (Option<String>, Vec<u8>) {
+        let router = crate::api::routes::create_router(AppState::new(Config::default()));
+        let response = router.oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let content_type = response.headers().get(header::CONTENT_TYPE).map(|v| v.to_str().unwrap().to_string());
+        (content_type, to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_yaml_and_json_documents_match() {
+        let (content_type, yaml) = get("/api-docs/openapi.yaml").await;
+        assert_eq!(content_type.as_deref(), Some("application/yaml"));
+        let from_yaml: utoipa::openapi::OpenApi = serde_norway::from_slice(&yaml).unwrap();
+
+        let (_, json) = get("/api-docs/openapi.json").await;
+        let from_json: utoipa::openapi::OpenApi = serde_json::from_slice(&json).unwrap();
+
+        assert!(from_yaml == from_json, "YAML and JSON documents differ");
+        assert!(from_yaml.paths.paths.contains_key("/api/v1/gas/prices"));
+    }
+}
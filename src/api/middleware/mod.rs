@@ -0,0 +1,155 @@
+//! HTTP middleware applied to every route.
+//!
+//! - [`pretty_json`] - Pretty-prints JSON response bodies on request
+//! - [`overloaded`] - Answers requests shed by the in-flight cap with `503`
+
+use std::fmt;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Query, Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+    BoxError,
+};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::core::config::AppState;
+use crate::core::errors::AppError;
+
+/// Largest request body accepted, in bytes
+pub const MAX_REQUEST_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// The server already handles `MAX_INFLIGHT_REQUESTS` requests and refused another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overloaded;
+
+impl fmt::Display for Overloaded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Server is handling too many requests, try again shortly")
+    }
+}
+
+impl std::error::Error for Overloaded {}
+
+/// Turns a request shed by the load-shed layer into a `503` error response.
+///
+/// Used with `HandleErrorLayer` in front of `LoadShedLayer`, whose only error
+/// is that the concurrency limit behind it had no room.
+pub async fn overloaded(error: BoxError) -> AppError {
+    warn!("Shedding request: {}", error);
+    Overloaded.into()
+}
+
+/// The `pretty` query parameter, accepted by every endpoint
+#[derive(Deserialize)]
+struct PrettyParam {
+    #[serde(default)]
+    pretty: bool,
+}
+
+/// Whether the request asked for pretty output with `?pretty=true`
+fn pretty_requested(request: &Request) -> bool {
+    Query::<PrettyParam>::try_from_uri(request.uri())
+        .map(|Query(param)| param.pretty)
+        .unwrap_or(false)
+}
+
+/// Re-indents a JSON body, keeping its field order.
+fn prettify(body: &[u8]) -> serde_json::Result<Vec<u8>> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let mut pretty = Vec::with_capacity(body.len() * 2);
+    serde_transcode::transcode(&mut deserializer, &mut serde_json::Serializer::pretty(&mut pretty))?;
+    deserializer.end()?;
+    Ok(pretty)
+}
+
+/// Pretty-prints JSON responses when `?pretty=true` is passed or `PRETTY_JSON` is set.
+///
+/// Responses stay compact by default. Non-JSON responses (e.g. the Swagger UI)
+/// pass through untouched.
+pub async fn pretty_json(State(app_state): State<AppState>, request: Request, next: Next) -> Response {
+    let pretty = app_state.config.pretty_json || pretty_requested(&request);
+    let response = next.run(request).await;
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !pretty || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to buffer response for pretty-printing: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    match prettify(&bytes) {
+        Ok(pretty) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(pretty))
+        }
+        Err(e) => {
+            warn!("Response is not valid JSON, sending it compact: {}", e);
+            Response::from_parts(parts, Body::from(bytes))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::Config;
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app(config: Config) -> Router {
+        let state = AppState::new(config);
+        Router::new()
+            .route("/", get(|| async { ([(header::CONTENT_TYPE, "application/json")], r#"{"b":1,"a":[true]}"#) }))
+            .route("/text", get(|| async { "plain" }))
+            .layer(axum::middleware::from_fn_with_state(state.clone(), pretty_json))
+            .with_state(state)
+    }
+
+    async fn body(app: Router, uri: &str) -> String {
+        let response = app.oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_compact_by_default() {
+        assert_eq!(body(app(Config::default()), "/").await, r#"{"b":1,"a":[true]}"#);
+        assert_eq!(body(app(Config::default()), "/?pretty=false").await, r#"{"b":1,"a":[true]}"#);
+    }
+
+    #[tokio::test]
+    async fn test_indented_when_requested() {
+        let expected = "{\n  \"b\": 1,\n  \"a\": [\n    true\n  ]\n}";
+        assert_eq!(body(app(Config::default()), "/?pretty=true").await, expected);
+
+        let config = Config { pretty_json: true, ..Config::default() };
+        assert_eq!(body(app(config), "/").await, expected);
+    }
+
+    #[tokio::test]
+    async fn test_applies_to_api_routes() {
+        let router = crate::api::routes::create_router(AppState::new(Config::default()));
+        let health = body(router, "/api/v1/health?pretty=true").await;
+        assert!(health.starts_with("{\n  \"status\": "), "{}", health);
+    }
+
+    #[tokio::test]
+    async fn test_non_json_passes_through() {
+        assert_eq!(body(app(Config::default()), "/text?pretty=true").await, "plain");
+    }
+}
@@ -1,7 +1,13 @@
 //! HTTP API layer.
 //!
 //! This module contains all HTTP-related functionality:
+//! - `middleware` - Middleware applied to every route
+//! - `response` - Shared response envelope types
 //! - `routes` - Route handlers and endpoint definitions
+//! - `validation` - Query parameter parsing and validation helpers
 
 pub mod docs;
-pub mod routes;
\ No newline at end of file
+pub mod middleware;
+pub mod response;
+pub mod routes;
+pub mod validation;
\ No newline at end of file
@@ -0,0 +1,256 @@
+//! Shared response envelope types.
+//!
+//! Endpoints return their data next to a `meta` object carrying information
+//! about the response itself rather than the data (attribution, provenance).
+//!
+//! Maps in responses are `BTreeMap`s, never `HashMap`s, so their keys always
+//! serialize in the same, sorted order and responses can be snapshot-tested.
+//!
+//! Bodies are JSON unless the client asks for MessagePack with
+//! `Accept: application/msgpack`: handlers take a [`ResponseFormat`] and wrap
+//! their data with [`ResponseFormat::respond`]. MessagePack bodies encode
+//! structs as maps with the same field names as the JSON, so a field skipped in
+//! JSON is skipped there too. Errors are always JSON.
+//!
+//! Handlers whose data only changes upstream can tag it with a weak [`ETag`]
+//! through [`Encoded::with_etag`]; a client sending that tag back in
+//! `If-None-Match` then gets an empty `304 Not Modified` instead of the body.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts, HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::core::errors::{AppError, SerializationFailed};
+use crate::domains::crypto::Currency;
+
+/// Content type of MessagePack response bodies
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// Media types accepted as a request for MessagePack
+const MSGPACK_MEDIA_TYPES: &[&str] = &[MSGPACK_CONTENT_TYPE, "application/x-msgpack", "application/vnd.msgpack"];
+
+/// Metadata attached to API responses alongside the data
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ResponseMeta {
+    /// Attribution required by the terms of an upstream provider that contributed data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<String>,
+    /// Raw upstream response per provider, only present for debug requests with `include_raw`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub raw: Option<BTreeMap<String, serde_json::Value>>,
+    /// Caveats about the data, e.g. a currency left without an aggregate price
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Requested currencies no provider could price (see `STRICT_CURRENCIES`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_currencies: Vec<Currency>,
+}
+
+/// Encoding of a response body, chosen from the request's `Accept` header
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl ResponseFormat {
+    /// Picks the format from an `Accept` header value.
+    ///
+    /// The first listed media type that is either JSON or MessagePack wins;
+    /// quality values are ignored. Anything else, or no header, means JSON.
+    pub fn from_accept(accept: Option<&str>) -> Self {
+        accept
+            .into_iter()
+            .flat_map(|accept| accept.split(','))
+            .map(|media_type| media_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase())
+            .find_map(|media_type| {
+                if MSGPACK_MEDIA_TYPES.contains(&media_type.as_str()) {
+                    Some(ResponseFormat::MessagePack)
+                } else if media_type == "application/json" {
+                    Some(ResponseFormat::Json)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// Wraps `body` to be sent in this format.
+    pub fn respond<T: Serialize>(self, body: T) -> Encoded<T> {
+        Encoded { format: self, body, headers: HeaderMap::new(), not_modified: false }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ResponseFormat {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let accept = parts.headers.get(header::ACCEPT).and_then(|value| value.to_str().ok());
+        Ok(Self::from_accept(accept))
+    }
+}
+
+/// A response body serialized in the format the client asked for
+#[derive(Debug)]
+pub struct Encoded<T> {
+    /// Format the body is serialized in
+    pub format: ResponseFormat,
+    /// The response data
+    pub body: T,
+    /// Extra response headers, e.g. debugging aids
+    pub headers: HeaderMap,
+    /// The client already holds this body, so it is sent as an empty `304`
+    pub not_modified: bool,
+}
+
+impl<T> Encoded<T> {
+    /// Adds a response header.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Tags the response with `etag`, answering `304 Not Modified` if the
+    /// client's `If-None-Match` already lists it.
+    pub fn with_etag(mut self, etag: &ETag, if_none_match: &IfNoneMatch) -> Self {
+        self.not_modified = if_none_match.matches(etag);
+        self.with_header(header::ETAG, etag.header_value())
+    }
+}
+
+/// Weak entity tag identifying the data of a response, e.g. `W/"3f2a…"`.
+///
+/// Weak because it identifies what the data says rather than its exact bytes:
+/// the same quotes served compact or pretty-printed share a tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ETag(String);
+
+impl ETag {
+    /// Tags data by a hash of `value`, which should cover everything that
+    /// distinguishes one version of the data from another.
+    pub fn weak(value: impl Hash) -> Self {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        Self(format!("{:016x}", hasher.finish()))
+    }
+
+    fn header_value(&self) -> HeaderValue {
+        HeaderValue::from_str(&format!("W/\"{}\"", self.0)).expect("hex digits are a valid header value")
+    }
+}
+
+/// The request's `If-None-Match` header, if any
+#[derive(Debug, Clone, Default)]
+pub struct IfNoneMatch(Option<String>);
+
+impl IfNoneMatch {
+    /// Whether the header lists `etag` or is `*`.
+    ///
+    /// Uses the weak comparison `If-None-Match` calls for, so `W/` prefixes
+    /// on either side are ignored.
+    pub fn matches(&self, etag: &ETag) -> bool {
+        self.0.iter().flat_map(|value| value.split(',')).any(|candidate| {
+            let candidate = candidate.trim();
+            candidate == "*" || candidate.trim_start_matches("W/").trim_matches('"') == etag.0
+        })
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for IfNoneMatch {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let value = parts.headers.get(header::IF_NONE_MATCH).and_then(|value| value.to_str().ok());
+        Ok(Self(value.map(str::to_string)))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Encoded<T> {
+    fn into_response(self) -> Response {
+        if self.not_modified {
+            let mut response = StatusCode::NOT_MODIFIED.into_response();
+            response.headers_mut().extend(self.headers);
+            return response;
+        }
+        let mut response = match self.format {
+            ResponseFormat::Json => Json(self.body).into_response(),
+            ResponseFormat::MessagePack => match rmp_serde::to_vec_named(&self.body) {
+                Ok(bytes) => ([(header::CONTENT_TYPE, HeaderValue::from_static(MSGPACK_CONTENT_TYPE))], bytes).into_response(),
+                Err(e) => AppError::from(SerializationFailed {
+                    what: "MessagePack response".to_string(),
+                    source: Box::new(e),
+                })
+                .into_response(),
+            },
+        };
+        response.headers_mut().extend(self.headers);
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::crypto::{Coin, Currency, ProviderSource, Quote, QuotePerAmount};
+    use axum::body::to_bytes;
+
+    #[test]
+    fn test_format_from_accept() {
+        assert_eq!(ResponseFormat::from_accept(None), ResponseFormat::Json);
+        assert_eq!(ResponseFormat::from_accept(Some("*/*")), ResponseFormat::Json);
+        assert_eq!(ResponseFormat::from_accept(Some("application/msgpack")), ResponseFormat::MessagePack);
+        assert_eq!(
+            ResponseFormat::from_accept(Some("text/html, Application/X-MsgPack;q=0.9, */*;q=0.1")),
+            ResponseFormat::MessagePack
+        );
+        assert_eq!(
+            ResponseFormat::from_accept(Some("application/json, application/msgpack")),
+            ResponseFormat::Json
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quote_round_trips_through_msgpack() {
+        let quote = Quote {
+            cached: true,
+            age_seconds: 3,
+            weight: Some(0.5),
+            quote_per_amount: QuotePerAmount { amount: 2.0, total_price: 3700.5, exact_total_price: None },
+            ..Quote::new(Coin::ETH, Currency::EUR, 1850.25, ProviderSource::CoinGecko)
+        };
+
+        let response = ResponseFormat::MessagePack.respond(&quote).into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], MSGPACK_CONTENT_TYPE);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let decoded: Quote = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), serde_json::to_value(&quote).unwrap());
+
+        let response = ResponseFormat::Json.respond(&quote).into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    }
+
+    #[test]
+    fn test_if_none_match_uses_weak_comparison() {
+        let etag = ETag::weak(("ETH", 2000));
+        let tag = etag.header_value().to_str().unwrap().to_string();
+        assert!(tag.starts_with("W/\"") && tag.ends_with('"'));
+
+        let header = |value: &str| IfNoneMatch(Some(value.to_string()));
+        assert!(header(&tag).matches(&etag));
+        assert!(header(tag.trim_start_matches("W/")).matches(&etag));
+        assert!(header(&format!("\"other\", {}", tag)).matches(&etag));
+        assert!(header("*").matches(&etag));
+        assert!(!header("W/\"other\"").matches(&etag));
+        assert!(!IfNoneMatch::default().matches(&etag));
+        assert_ne!(ETag::weak(("ETH", 2001)), etag);
+    }
+}
@@ -0,0 +1,103 @@
+//! Operator endpoints.
+//!
+//! Admin endpoints change server state rather than read market data, so they
+//! require `ADMIN_API_KEY`, sent as `Authorization: Bearer <key>`. Without the
+//! variable set they are disabled and answer `501`.
+
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::api::validation::Query;
+use crate::core::config::AppState;
+use crate::core::errors::{AppError, NotConfigured, Unauthorized};
+use crate::domains::crypto::ProviderSource;
+
+/// Query parameters for cache flushes.
+#[derive(Deserialize, IntoParams)]
+pub struct CacheFlushParams {
+    /// Only flush this provider's quotes (defaults to every provider)
+    #[param(value_type = Option<String>, example = "coingecko")]
+    pub provider: Option<ProviderSource>,
+}
+
+/// Outcome of a cache flush
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CacheFlushResponse {
+    /// Fresh quotes dropped from the cache
+    #[schema(example = 3)]
+    pub flushed: usize,
+    /// Provider whose quotes were flushed; absent when every provider's were
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderSource>,
+}
+
+/// Checks the request's bearer token against `ADMIN_API_KEY`.
+fn authorize(app_state: &AppState, headers: &HeaderMap) -> Result<(), AppError> {
+    let expected = app_state
+        .config
+        .admin_api_key
+        .as_deref()
+        .ok_or_else(|| NotConfigured::new("admin API", "ADMIN_API_KEY"))?;
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match provided {
+        Some(key) if keys_match(key.trim().as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(Unauthorized.into()),
+    }
+}
+
+/// Compares two keys in time independent of where they first differ.
+fn keys_match(provided: &[u8], expected: &[u8]) -> bool {
+    provided.len() == expected.len()
+        && provided.iter().zip(expected).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Flush the quote cache.
+///
+/// Drops every cached quote, in memory or in Redis, or only `provider`'s, so
+/// the next price request goes upstream. Useful after a known upstream glitch
+/// left bad prices in the cache. Requires `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/cache/flush",
+    tag = "admin",
+    params(CacheFlushParams),
+    responses(
+        (status = 200, description = "Number of cached quotes flushed", body = CacheFlushResponse),
+        (status = 400, description = "Unknown provider"),
+        (status = 401, description = "Missing or wrong admin API key"),
+        (status = 500, description = "The cache backend could not be reached"),
+        (status = 501, description = "Admin endpoints are disabled: ADMIN_API_KEY is not set")
+    )
+)]
+#[instrument(skip_all, fields(provider = ?params.provider))]
+pub async fn flush_cache(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<CacheFlushParams>,
+) -> Result<Json<CacheFlushResponse>, AppError> {
+    authorize(&app_state, &headers)?;
+
+    let flushed = app_state.quote_cache.flush(params.provider).await?;
+    info!("🧹 Flushed {} cached quotes", flushed);
+    Ok(Json(CacheFlushResponse { flushed, provider: params.provider }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_match() {
+        assert!(keys_match(b"secret", b"secret"));
+        assert!(!keys_match(b"secreT", b"secret"));
+        assert!(!keys_match(b"secret!", b"secret"));
+        assert!(!keys_match(b"", b"secret"));
+    }
+}
@@ -3,17 +3,43 @@
 //! This module handles requests for cryptocurrency prices from multiple providers.
 //! Supports configurable amounts and currencies with fallback between providers.
 
-use axum::{extract::{Query, State}, Json};
-use serde::Deserialize;
-use utoipa::IntoParams;
-use tracing::{info, warn, error};
+use axum::extract::State;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::http::{HeaderName, HeaderValue};
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use std::collections::BTreeMap;
+use std::time::Duration;
 
-use crate::core::errors::AppError;
+use tracing::{debug, info, warn, error, instrument};
 
-use crate::core::config::AppState;
-use crate::domains::crypto::{Coin, Currency, PriceProvider, Quote};
-use crate::domains::crypto::coingecko::CoinGecko;
+use crate::core::errors::{AppError, InvalidQuery, NoCurrencies, NotConfigured, NotSupported};
+use crate::api::response::{ETag, Encoded, IfNoneMatch, ResponseFormat, ResponseMeta};
+use crate::api::validation::{comma_separated, Query};
+
+use crate::core::config::{AppState, Config};
+use crate::infrastructure::http::build_client;
+use crate::domains::crypto::aggregate::{aggregate, trusted_quotes, AggregateQuote};
+use crate::domains::crypto::{fetch_with_fallback, missing_currencies, Coin, Currency, DynPriceProvider, MarketData, MarketDepth, PriceSpread, ProviderSource, Quote};
+use crate::domains::crypto::binance::{self, Binance};
+use crate::domains::crypto::cache::{quote_key_label, CachedPriceProvider};
+use crate::domains::crypto::coingecko::{self, CoinGecko};
 use crate::domains::crypto::coinmarketcap::CoinMarketCap;
+use crate::domains::crypto::feed::{AggregateFeed, AggregateTick};
+use crate::domains::crypto::fx::{FxPriceProvider, FX_BASE};
+use crate::domains::crypto::oneinch::OneInch;
+
+/// Largest `total_price` a quote may report; anything larger is a bad `amount`
+const MAX_TOTAL_PRICE: f64 = 1e15;
+
+/// Debug header listing the quote cache keys a price request looked up
+pub const CACHE_KEY_HEADER: HeaderName = HeaderName::from_static("x-cache-key");
+
+/// How often providers without a live stream are polled for the aggregate stream
+const STREAM_POLL_INTERVAL: Duration = Duration::from_secs(10);
 
 /// Query parameters for price quote requests.
 #[derive(Deserialize, IntoParams)]
@@ -21,9 +47,231 @@ pub struct QuoteQueryParams {
     /// Number of crypto to get price for (defaults to 1)
     #[serde(default = "default_amount")]
     pub amount: usize,
-    /// Currency to get price in (defaults to USD)
+    /// Currency to get price in, or `all` for every currency in `ALL_CURRENCIES`
+    /// (defaults to USD)
+    #[serde(default)]
+    #[param(value_type = Option<String>, example = "USD")]
+    pub currency: CurrencySelection,
+    /// Comma-separated currencies to get prices in; takes precedence over `currency`
+    #[serde(default, deserialize_with = "comma_separated")]
+    #[param(value_type = Option<String>, example = "USD,EUR")]
+    pub currencies: Vec<Currency>,
+    /// Include each provider's raw upstream response in `meta.raw`
+    /// (only honored when `DEBUG` is set and `PRODUCTION` is not)
+    #[serde(default)]
+    pub include_raw: bool,
+    /// Price at this past time (RFC 3339, any UTC offset) instead of the current
+    /// price; served from CoinGecko's price history
+    #[param(value_type = Option<String>, example = "2024-01-15T12:00:00Z")]
+    pub at: Option<DateTime<Utc>>,
+}
+
+/// The `currency` query parameter: one currency, or `all` of the configured set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurrencySelection {
+    One(Currency),
+    All,
+}
+
+impl Default for CurrencySelection {
+    fn default() -> Self {
+        CurrencySelection::One(default_currency())
+    }
+}
+
+impl<'de> Deserialize<'de> for CurrencySelection {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        if raw.trim().eq_ignore_ascii_case("all") {
+            return Ok(CurrencySelection::All);
+        }
+        raw.parse().map(CurrencySelection::One).map_err(serde::de::Error::custom)
+    }
+}
+
+impl QuoteQueryParams {
+    /// Currencies to fetch: the `currencies` list when given, otherwise the single
+    /// `currency`, with `all` expanding to `all_currencies`.
+    fn requested_currencies(&self, all_currencies: &[Currency]) -> Vec<Currency> {
+        if !self.currencies.is_empty() {
+            return self.currencies.clone();
+        }
+        match self.currency {
+            CurrencySelection::One(currency) => vec![currency],
+            CurrencySelection::All => all_currencies.to_vec(),
+        }
+    }
+}
+
+/// Query parameters for market depth requests.
+#[derive(Deserialize, IntoParams)]
+pub struct DepthQueryParams {
+    /// Currency the order book is denominated in (defaults to USD)
+    #[serde(default = "default_currency")]
+    pub currency: Currency,
+    /// Provider to read the order book from (defaults to binance)
+    #[serde(default = "default_depth_provider")]
+    #[param(value_type = Option<String>, example = "binance")]
+    pub provider: ProviderSource,
+}
+
+/// Query parameters for market data requests.
+#[derive(Deserialize, IntoParams)]
+pub struct MarketQueryParams {
+    /// Cryptocurrency to describe (defaults to ETH)
+    #[serde(default = "default_coin")]
+    pub coin: Coin,
+    /// Currency prices and volumes are denominated in (defaults to USD)
+    #[serde(default = "default_currency")]
+    pub currency: Currency,
+}
+
+/// Query parameters for price spread requests.
+#[derive(Deserialize, IntoParams)]
+pub struct SpreadQueryParams {
+    /// Cryptocurrency to compare (defaults to ETH)
+    #[serde(default = "default_coin")]
+    pub coin: Coin,
+    /// Currency to compare prices in (defaults to USD)
     #[serde(default = "default_currency")]
     pub currency: Currency,
+    /// Spread in percent above which providers are flagged as disagreeing
+    /// (defaults to `SPREAD_THRESHOLD_PERCENT`)
+    pub threshold: Option<f64>,
+}
+
+/// Query parameters for the live aggregate stream.
+#[derive(Deserialize, IntoParams)]
+pub struct AggregateStreamQueryParams {
+    /// Cryptocurrency to aggregate (defaults to ETH)
+    #[serde(default = "default_coin")]
+    pub coin: Coin,
+    /// Currency to aggregate prices in (defaults to USD)
+    #[serde(default = "default_currency")]
+    pub currency: Currency,
+}
+
+/// Price quotes response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PriceResponse {
+    /// Quotes from every provider that answered, for each requested currency
+    pub quotes: Vec<Quote>,
+    /// Price per requested currency aggregated across the providers that answered
+    /// (`AGGREGATE_STRATEGY`), limited to `AGGREGATE_PROVIDERS` when set
+    pub aggregates: Vec<AggregateQuote>,
+    /// Response metadata
+    pub meta: ResponseMeta,
+}
+
+/// Wraps `provider` to convert its USD price into currencies it lacks, when `FX_RATES` is set.
+fn with_fx(config: &Config, provider: DynPriceProvider) -> DynPriceProvider {
+    if config.fx_rates.is_empty() {
+        return provider;
+    }
+    Box::new(FxPriceProvider::new(provider, config.fx_rates.clone()))
+}
+
+/// Weak ETag of a price response, from what each quote says: its provider,
+/// currency, fetch time and price.
+///
+/// Serving the same cached quotes again keeps the tag even though their
+/// `age_seconds` grows; a new upstream price changes it.
+fn price_etag(format: ResponseFormat, response: &PriceResponse) -> ETag {
+    let quotes: Vec<_> = response
+        .quotes
+        .iter()
+        .map(|quote| {
+            let book = (quote.bid.map(f64::to_bits), quote.ask.map(f64::to_bits));
+            (quote.provider, quote.currency, quote.timestamp, quote.price.to_bits(), quote.quote_per_amount.total_price.to_bits(), book)
+        })
+        .collect();
+    ETag::weak((format, quotes, &response.meta.warnings))
+}
+
+/// Returns CoinGecko's attribution when enabled and a quote in `quotes` came from CoinGecko.
+fn attribution(quotes: &[Quote], enabled: bool) -> Option<String> {
+    let from_coingecko = quotes
+        .iter()
+        .any(|quote| matches!(quote.provider, ProviderSource::CoinGecko));
+
+    (enabled && from_coingecko).then(|| coingecko::ATTRIBUTION.to_string())
+}
+
+/// Collects each provider's captured raw response, keyed by provider name.
+///
+/// Returns `None` unless raw responses were requested and are allowed, so they
+/// can never leak into responses outside debug mode.
+fn raw_responses<'a>(
+    providers: impl IntoIterator<Item = &'a (ProviderSource, DynPriceProvider)>,
+    include: bool,
+) -> Option<BTreeMap<String, serde_json::Value>> {
+    if !include {
+        return None;
+    }
+
+    let raw: BTreeMap<_, _> = providers
+        .into_iter()
+        .filter_map(|(source, provider)| Some((source.to_string(), provider.raw_response()?)))
+        .collect();
+    (!raw.is_empty()).then_some(raw)
+}
+
+/// Whether a provider was queried for a request, and if not, why
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProviderDecision {
+    /// Queried concurrently with the other primaries
+    Primary,
+    /// Queried only if every primary fails (`FALLBACK_PROVIDERS`)
+    Fallback,
+    /// Missing its API key
+    NotConfigured,
+    /// Its client could not be built
+    InitFailed,
+    /// Switched off, e.g. Binance without `BINANCE_STREAM`
+    Disabled,
+    /// Lists none of the requested markets
+    UnsupportedPair,
+}
+
+impl std::fmt::Display for ProviderDecision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ProviderDecision::Primary => "primary",
+            ProviderDecision::Fallback => "fallback",
+            ProviderDecision::NotConfigured => "skipped: not configured",
+            ProviderDecision::InitFailed => "skipped: initialization failed",
+            ProviderDecision::Disabled => "skipped: disabled",
+            ProviderDecision::UnsupportedPair => "skipped: unsupported pair",
+        })
+    }
+}
+
+/// Emits one debug event recording the decision taken for every provider.
+fn log_provider_selection(
+    primary: &[(ProviderSource, DynPriceProvider)],
+    fallback: &[(ProviderSource, DynPriceProvider)],
+    skipped: &[(ProviderSource, ProviderDecision)],
+) {
+    let decision = |source: ProviderSource| {
+        if primary.iter().any(|(s, _)| *s == source) {
+            ProviderDecision::Primary
+        } else if fallback.iter().any(|(s, _)| *s == source) {
+            ProviderDecision::Fallback
+        } else {
+            skipped
+                .iter()
+                .find(|(s, _)| *s == source)
+                .map_or(ProviderDecision::Disabled, |(_, decision)| *decision)
+        }
+    };
+
+    debug!(
+        coingecko = %decision(ProviderSource::CoinGecko),
+        coinmarketcap = %decision(ProviderSource::CoinMarketCap),
+        binance = %decision(ProviderSource::Binance),
+        oneinch = %decision(ProviderSource::OneInch),
+        "Provider selection"
+    );
 }
 
 fn default_amount() -> usize {
@@ -34,76 +282,834 @@ fn default_currency() -> Currency {
     Currency::USD
 }
 
+fn default_coin() -> Coin {
+    Coin::ETH
+}
+
+fn default_depth_provider() -> ProviderSource {
+    ProviderSource::Binance
+}
+
 /// Get cryptocurrency price quotes from available providers.
 ///
-/// This endpoint fetches ETH prices from configured providers (CoinMarketCap, CoinGecko)
-/// and returns quotes adjusted for the requested amount and currency.
+/// This endpoint fetches ETH prices from the primary providers (CoinGecko, Binance
+/// when streaming is enabled, and 1inch's on-chain aggregator when its address is
+/// configured) and returns quotes adjusted for the requested amount and currency.
+/// Fallback providers (CoinMarketCap by default, see
+/// `FALLBACK_PROVIDERS`) are only called when every primary fails. Each quote
+/// carries its provider's configured `weight`, and each currency's quotes are
+/// also aggregated into their median (or weighted mean, see `AGGREGATE_STRATEGY`)
+/// price, using only the providers in `AGGREGATE_PROVIDERS` when it is set.
+/// Currencies quoted by fewer than `AGGREGATE_MIN_PROVIDERS` of them get no
+/// aggregate, only a warning in `meta.warnings`. Currencies no provider could
+/// price are listed in `meta.missing_currencies`, unless `STRICT_CURRENCIES`
+/// makes a provider that omits any of them fail instead.
+///
+/// With `at`, the price at that past time is served from CoinGecko's price
+/// history instead; historical quotes are never cached.
+///
+/// In debug mode (`DEBUG`, never with `PRODUCTION`), the `X-Cache-Key` header
+/// lists every quote cache key the request looked up, as
+/// `<coin>:<currency>:<provider>`. The amount is not part of the key: quotes are
+/// cached per unit and scaled afterwards.
+///
+/// Responses carry a weak `ETag` derived from the quotes' providers, fetch times
+/// and prices. Sending it back in `If-None-Match` gets an empty `304` while the
+/// quotes are unchanged, e.g. still served from the quote cache.
 #[utoipa::path(
     get,
     path = "/api/v1/price/prices",
     tag = "crypto",
     params(QuoteQueryParams),
     responses(
-        (status = 200, description = "Successful response with price quotes", body = Vec<Quote>),
+        (status = 200, description = "Successful response with price quotes", body = PriceResponse),
+        (status = 304, description = "The quotes match the `If-None-Match` ETag"),
+        (status = 400, description = "Invalid or disallowed currency, no currency at all, invalid amount, or `at` in the future"),
         (status = 500, description = "No quotes available from any provider")
     )
 )]
+#[instrument(skip_all, fields(amount = params.amount, currencies = ?params.requested_currencies(&app_state.config.all_allowed_currencies())))]
 pub async fn get_crypto_prices(
     State(app_state): State<AppState>,
+    format: ResponseFormat,
+    if_none_match: IfNoneMatch,
     Query(params): Query<QuoteQueryParams>,
-) -> Result<Json<Vec<Quote>>, AppError> {
-    info!("💰 Fetching cryptocurrency prices for {} {} in {}", params.amount, Coin::ETH, params.currency);
+) -> Result<Encoded<PriceResponse>, AppError> {
+    let currencies = params.requested_currencies(&app_state.config.all_allowed_currencies());
+    if currencies.is_empty() {
+        return Err(NoCurrencies.into());
+    }
+    app_state.config.ensure_allowed(Coin::ETH, &currencies)?;
+    if let Some(at) = params.at {
+        if at > Utc::now() {
+            return Err(InvalidQuery::new(format!("at: {} is in the future", at.to_rfc3339())).into());
+        }
+        let prices = get_historical_prices(&app_state, &params, &currencies, at).await?;
+        let etag = price_etag(format, &prices);
+        return Ok(format.respond(prices).with_etag(&etag, &if_none_match));
+    }
+    info!("💰 Fetching cryptocurrency prices for {} {} in {:?}", params.amount, Coin::ETH, currencies);
 
-    let mut quotes = Vec::new();
+    let config = &app_state.config;
+    let include_raw = params.include_raw && config.debug_aids_allowed();
+    if params.include_raw && !include_raw {
+        warn!("include_raw requested but raw responses are disabled (requires DEBUG, never in PRODUCTION)");
+    }
+    let client = build_client(&config.http)?;
+    let mut providers: Vec<(ProviderSource, DynPriceProvider)> = Vec::new();
+    let mut skipped: Vec<(ProviderSource, ProviderDecision)> = Vec::new();
 
-    // Try CoinMarketCap
-    if let Some(api_key) = &app_state.config.coinmarketcap_api_key {
-        match CoinMarketCap::new(api_key.clone()) {
-        Ok(cmc_provider) => match cmc_provider.get_quotes(Coin::ETH, &[params.currency]).await {
-            Ok(cmc_quotes) => {
-                if let Some(quote) = cmc_quotes.first() {
-                    quotes.push(quote.with_amount(params.amount as f64));
-                    info!("📊 CoinMarketCap: {} {} = {}{:.2} at {}", 
-                        quote.coin, 1, quote.currency.symbol(), quote.price, quote.timestamp);
-                }
-            }
-            Err(e) => {
-                warn!("CoinMarketCap API failed: {}", e);
-            }
-        },
+    if let Some(api_key) = &config.coinmarketcap_api_key {
+        match CoinMarketCap::new_with_client(api_key.clone(), client.clone()) {
+            Ok(provider) => providers.push((
+                ProviderSource::CoinMarketCap,
+                Box::new(
+                    provider
+                        .with_base_url(&config.coinmarketcap_base_url)
+                        .with_strict_currencies(config.strict_currencies)
+                        .with_max_response_bytes(config.http.max_response_bytes)
+                        .with_retry(app_state.retry_policy())
+                        .with_raw_capture(include_raw),
+                ),
+            )),
             Err(e) => {
                 error!("CoinMarketCap initialization failed: {}", e);
+                skipped.push((ProviderSource::CoinMarketCap, ProviderDecision::InitFailed));
             }
         }
     } else {
         info!("CoinMarketCap API key not configured, skipping provider");
+        skipped.push((ProviderSource::CoinMarketCap, ProviderDecision::NotConfigured));
     }
 
-
-    // Try CoinGecko
-    match CoinGecko::new(app_state.config.coingecko_api_key.clone()) {
-        Ok(cg_provider) => match cg_provider.get_quotes(Coin::ETH, &[params.currency]).await {
-            Ok(cg_quotes) => {
-                if let Some(quote) = cg_quotes.first() {
-                    quotes.push(quote.with_amount(params.amount as f64));
-                    info!("🦎 CoinGecko: {} {} = {}{:.2} at {}", 
-                        quote.coin, 1, quote.currency.symbol(), quote.price, quote.timestamp);
+    match (&config.oneinch_oracle_address, &config.ethereum_rpc_url) {
+        (Some(oracle_address), Some(rpc_url)) => {
+            match OneInch::new_with_client(rpc_url.clone(), oracle_address, client.clone()) {
+                Ok(provider) => providers.push((ProviderSource::OneInch, Box::new(provider))),
+                Err(e) => {
+                    error!("1inch initialization failed: {}", e);
+                    skipped.push((ProviderSource::OneInch, ProviderDecision::InitFailed));
                 }
             }
-            Err(e) => {
-                warn!("CoinGecko API failed: {}", e);
-            }
-        },
+        }
+        _ => {
+            info!("1inch oracle address or Ethereum RPC URL not configured, skipping provider");
+            skipped.push((ProviderSource::OneInch, ProviderDecision::NotConfigured));
+        }
+    }
+
+    match CoinGecko::new_with_client(config.coingecko_api_key.clone(), client) {
+        Ok(provider) => providers.push((
+            ProviderSource::CoinGecko,
+            Box::new(
+                provider
+                    .with_base_url(&config.coingecko_base_url)
+                    .with_strict_currencies(config.strict_currencies)
+                    .with_max_response_bytes(config.http.max_response_bytes)
+                    .with_retry(app_state.retry_policy())
+                    .with_raw_capture(include_raw),
+            ),
+        )),
         Err(e) => {
             error!("CoinGecko initialization failed: {}", e);
+            skipped.push((ProviderSource::CoinGecko, ProviderDecision::InitFailed));
         }
     }
 
+    // Respect each provider's request quota; cache hits below do not spend it
+    providers = providers
+        .into_iter()
+        .map(|(source, provider)| (source, with_fx(config, app_state.rate_limited(source, provider))))
+        .collect();
+
+    // Reuse recently fetched quotes, unless raw upstream bodies were asked for
+    let mut cache_keys = Vec::new();
+    if !config.quote_cache_ttl.is_zero() && !include_raw {
+        cache_keys = providers
+            .iter()
+            .flat_map(|(source, _)| currencies.iter().map(|&currency| quote_key_label((Coin::ETH, currency, *source))))
+            .collect();
+        // Opted-in providers pricing every currency in one request fill in the rest of the set too
+        let fetch_currencies: Vec<Currency> = config
+            .cache_fetch_currencies
+            .iter()
+            .copied()
+            .filter(|currency| config.allowed_currencies.as_ref().is_none_or(|allowed| allowed.contains(currency)))
+            .collect();
+        providers = providers
+            .into_iter()
+            .map(|(source, provider)| {
+                let mut cached = CachedPriceProvider::new(source, provider, app_state.quote_cache.clone());
+                if source.batches_currencies() && config.cache_fetch_providers.contains(&source) {
+                    cached = cached.with_fetch_currencies(fetch_currencies.iter().copied());
+                }
+                (source, Box::new(cached) as DynPriceProvider)
+            })
+            .collect();
+    }
+
+    // The stream feed is already a cache of its own
+    if !config.binance_stream {
+        skipped.push((ProviderSource::Binance, ProviderDecision::Disabled));
+    } else if currencies.iter().all(|&currency| {
+        binance::symbol(Coin::ETH, currency).is_none() && config.fx_rates.rate(FX_BASE, currency).is_none()
+    }) {
+        skipped.push((ProviderSource::Binance, ProviderDecision::UnsupportedPair));
+    } else {
+        providers.push((ProviderSource::Binance, with_fx(config, Box::new(app_state.price_feed.clone()))));
+    }
+
+    // Fallback providers (CoinMarketCap by default) are only called when every primary fails
+    let (fallback, primary): (Vec<_>, Vec<_>) = providers
+        .into_iter()
+        .partition(|(source, _)| config.fallback_providers.contains(source));
+    log_provider_selection(&primary, &fallback, &skipped);
+
+    let mut quotes = Vec::new();
+    for quote in fetch_with_fallback(&primary, &fallback, Coin::ETH, &currencies).await {
+        app_state.provider_health.record_success(quote.provider);
+        info!("📊 {}: {} {} = {} at {}",
+            quote.provider, quote.coin, 1, quote.currency.format(quote.price), quote.timestamp);
+        quotes.push(priced_for(config, quote, params.amount)?);
+    }
+
     info!("Price fetching completed. Retrieved {} quotes", quotes.len());
 
     if quotes.is_empty() {
         return Err(anyhow::anyhow!("No quotes available from any provider").into());
     }
 
-    Ok(Json(quotes))
-}
\ No newline at end of file
+    let aggregates = aggregates(config, &quotes);
+    let meta = ResponseMeta {
+        attribution: attribution(&quotes, app_state.config.coingecko_attribution),
+        raw: raw_responses(primary.iter().chain(&fallback), include_raw),
+        warnings: too_few_providers(config, &quotes, &currencies),
+        missing_currencies: missing_currencies(&currencies, &quotes),
+    };
+
+    let prices = PriceResponse { quotes, aggregates, meta };
+    let etag = price_etag(format, &prices);
+    let response = format.respond(prices).with_etag(&etag, &if_none_match);
+    if config.debug_aids_allowed() && !cache_keys.is_empty() {
+        let keys = HeaderValue::from_str(&cache_keys.join(", ")).map_err(anyhow::Error::from)?;
+        return Ok(response.with_header(CACHE_KEY_HEADER, keys));
+    }
+    Ok(response)
+}
+
+/// Prices at a past time `at`, from CoinGecko's price history.
+///
+/// CoinGecko is the only provider with history, so there is no fallback, and
+/// the quote cache is bypassed since it only holds current prices.
+async fn get_historical_prices(
+    app_state: &AppState,
+    params: &QuoteQueryParams,
+    currencies: &[Currency],
+    at: DateTime<Utc>,
+) -> Result<PriceResponse, AppError> {
+    info!("🕰️ Fetching historical prices for {} {} in {:?} at {}", params.amount, Coin::ETH, currencies, at);
+    let config = &app_state.config;
+    let provider = CoinGecko::new_with_client(config.coingecko_api_key.clone(), build_client(&config.http)?)?
+        .with_base_url(&config.coingecko_base_url)
+        .with_max_response_bytes(config.http.max_response_bytes)
+        .with_retry(app_state.retry_policy());
+
+    // The history endpoint answers for one currency per request
+    let mut quotes = Vec::with_capacity(currencies.len());
+    for &currency in currencies {
+        if let Some(limiter) = app_state.rate_limiters.get(&ProviderSource::CoinGecko) {
+            limiter.acquire().await?;
+        }
+        let quote = provider.get_historical_quote(Coin::ETH, currency, at).await?;
+        app_state.provider_health.record_success(quote.provider);
+        info!("📊 {}: {} {} = {} at {}",
+            quote.provider, quote.coin, 1, quote.currency.format(quote.price), quote.timestamp);
+        quotes.push(priced_for(config, quote, params.amount)?);
+    }
+
+    let aggregates = aggregates(config, &quotes);
+    let meta = ResponseMeta {
+        attribution: attribution(&quotes, config.coingecko_attribution),
+        raw: None,
+        warnings: too_few_providers(config, &quotes, currencies),
+        missing_currencies: Vec::new(),
+    };
+
+    Ok(PriceResponse { quotes, aggregates, meta })
+}
+
+/// `quote` scaled to `amount` and carrying its provider's weight.
+///
+/// # Errors
+///
+/// Returns [`InvalidQuery`] if the total is not finite or exceeds [`MAX_TOTAL_PRICE`],
+/// rather than reporting an infinite or meaningless total.
+fn priced_for(config: &Config, quote: Quote, amount: usize) -> Result<Quote, AppError> {
+    let total = quote.value_of(amount as f64);
+    if !total.is_finite() || total > MAX_TOTAL_PRICE {
+        return Err(InvalidQuery::new(format!(
+            "amount: {} {} at {} totals {}, above the maximum of {}",
+            amount,
+            quote.coin,
+            quote.currency.format(quote.price),
+            total,
+            quote.currency.format(MAX_TOTAL_PRICE)
+        ))
+        .into());
+    }
+    let quote = if config.decimal_totals {
+        quote.with_exact_amount(amount as f64)?
+    } else {
+        quote.with_amount(amount as f64)
+    };
+    Ok(Quote { weight: Some(config.provider_weight(quote.provider)), ..quote })
+}
+
+/// Aggregates of `quotes` using the configured providers, strategy and minimum.
+fn aggregates(config: &Config, quotes: &[Quote]) -> Vec<AggregateQuote> {
+    let aggregates = aggregate(
+        quotes,
+        config.aggregate_providers.as_deref(),
+        config.aggregate_strategy,
+        config.aggregate_min_providers,
+        config.aggregate_max_deviation_percent,
+    );
+    for aggregate in &aggregates {
+        debug!("Aggregate {}/{} = {} from {:?}",
+            aggregate.coin, aggregate.currency, aggregate.price, aggregate.providers);
+    }
+    aggregates
+}
+
+/// Warnings for each quoted currency left without an aggregate because fewer than
+/// `AGGREGATE_MIN_PROVIDERS` trusted providers answered.
+fn too_few_providers(config: &Config, quotes: &[Quote], currencies: &[Currency]) -> Vec<String> {
+    let required = config.aggregate_min_providers.get();
+    currencies
+        .iter()
+        .filter_map(|&currency| {
+            let answered = trusted_quotes(quotes, Coin::ETH, currency, config.aggregate_providers.as_deref()).len();
+            let quoted = quotes.iter().any(|quote| quote.currency == currency);
+            (quoted && answered < required).then(|| {
+                let warning = format!(
+                    "No {}/{} aggregate: {} of the required {} trusted providers answered",
+                    Coin::ETH, currency, answered, required
+                );
+                warn!("{}", warning);
+                warning
+            })
+        })
+        .collect()
+}
+
+/// Get order-book depth for ETH from a single provider.
+///
+/// Returns the best bid and ask and the volume resting on each side of the book,
+/// so large trades can check the quoted price has liquidity behind it. Only
+/// providers with order-book data (Binance) support this; asking any other
+/// provider is a bad request.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crypto/depth",
+    tag = "crypto",
+    params(DepthQueryParams),
+    responses(
+        (status = 200, description = "Top-of-book prices and depth summary", body = MarketDepth),
+        (status = 400, description = "Provider has no order book for the market, or the currency is not allowed"),
+        (status = 501, description = "Provider is not configured")
+    )
+)]
+#[instrument(skip_all, fields(provider = %params.provider, currency = %params.currency))]
+pub async fn get_market_depth(
+    State(app_state): State<AppState>,
+    format: ResponseFormat,
+    Query(params): Query<DepthQueryParams>,
+) -> Result<Encoded<MarketDepth>, AppError> {
+    let config = &app_state.config;
+    config.ensure_allowed(Coin::ETH, &[params.currency])?;
+    let client = build_client(&config.http)?;
+
+    let provider: DynPriceProvider = match params.provider {
+        ProviderSource::Binance => Box::new(
+            Binance::new_with_client(client)
+                .with_base_url(&config.binance_base_url)
+                .with_max_response_bytes(config.http.max_response_bytes)
+                .with_retry(app_state.retry_policy()),
+        ),
+        ProviderSource::CoinGecko => Box::new(
+            CoinGecko::new_with_client(config.coingecko_api_key.clone(), client)?
+                .with_base_url(&config.coingecko_base_url)
+                .with_max_response_bytes(config.http.max_response_bytes)
+                .with_retry(app_state.retry_policy()),
+        ),
+        ProviderSource::CoinMarketCap => {
+            let api_key = config.coinmarketcap_api_key.clone()
+                .ok_or_else(|| NotConfigured::new("coinmarketcap", "COINMARKETCAP_API_KEY"))?;
+            Box::new(
+                CoinMarketCap::new_with_client(api_key, client)?
+                    .with_base_url(&config.coinmarketcap_base_url)
+                    .with_max_response_bytes(config.http.max_response_bytes)
+                    .with_retry(app_state.retry_policy()),
+            )
+        }
+        ProviderSource::OneInch => {
+            return Err(NotSupported::new("market depth from 1inch, which has no order book").into());
+        }
+    };
+
+    let provider = app_state.rate_limited(params.provider, provider);
+    let depth = provider.get_market_depth(Coin::ETH, params.currency).await?;
+    info!("📚 {} {}/{}: bid {} ask {} over {} levels",
+        depth.provider, depth.coin, depth.currency, depth.best_bid, depth.best_ask, depth.levels);
+
+    Ok(format.respond(depth))
+}
+
+/// Get market cap, 24h volume and circulating supply for a coin.
+///
+/// Reads CoinGecko's full coin document, which carries market statistics the
+/// simple price endpoint does not, and returns the figures for one currency.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crypto/market",
+    tag = "crypto",
+    params(MarketQueryParams),
+    responses(
+        (status = 200, description = "Market statistics for the coin", body = MarketData),
+        (status = 400, description = "Invalid or disallowed coin or currency"),
+        (status = 500, description = "CoinGecko request failed")
+    )
+)]
+#[instrument(skip_all, fields(coin = %params.coin, currency = %params.currency))]
+pub async fn get_market_data(
+    State(app_state): State<AppState>,
+    format: ResponseFormat,
+    Query(params): Query<MarketQueryParams>,
+) -> Result<Encoded<MarketData>, AppError> {
+    let config = &app_state.config;
+    config.ensure_allowed(params.coin, &[params.currency])?;
+    let provider = CoinGecko::new_with_client(config.coingecko_api_key.clone(), build_client(&config.http)?)?
+        .with_base_url(&config.coingecko_base_url)
+        .with_max_response_bytes(config.http.max_response_bytes)
+        .with_retry(app_state.retry_policy());
+
+    if let Some(limiter) = app_state.rate_limiters.get(&ProviderSource::CoinGecko) {
+        limiter.acquire().await?;
+    }
+    let market = provider.get_market_data(params.coin, params.currency).await?;
+    info!("📈 {} {}/{}: price {} market cap {:?}",
+        market.provider, market.coin, market.currency, market.price, market.market_cap);
+
+    Ok(format.respond(market))
+}
+
+/// Compare one market's price across every provider.
+///
+/// Fetches the price from CoinGecko, Binance, and CoinMarketCap and 1inch when configured,
+/// and reports the min, max, mean and percentage spread between them, flagging
+/// the reading when the spread exceeds the threshold. Providers that fail or do
+/// not list the market are left out.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crypto/spread",
+    tag = "crypto",
+    params(SpreadQueryParams),
+    responses(
+        (status = 200, description = "Price spread across providers", body = PriceSpread),
+        (status = 400, description = "Invalid or disallowed coin or currency, or invalid threshold"),
+        (status = 500, description = "Fewer than two providers returned a price")
+    )
+)]
+#[instrument(skip_all, fields(coin = %params.coin, currency = %params.currency))]
+pub async fn get_price_spread(
+    State(app_state): State<AppState>,
+    format: ResponseFormat,
+    Query(params): Query<SpreadQueryParams>,
+) -> Result<Encoded<PriceSpread>, AppError> {
+    let config = &app_state.config;
+    config.ensure_allowed(params.coin, &[params.currency])?;
+    let threshold = params.threshold.unwrap_or(config.spread_threshold_percent);
+    if !threshold.is_finite() || threshold < 0.0 {
+        return Err(InvalidQuery::new(format!("threshold: must be a non-negative percentage, got {threshold}")).into());
+    }
+
+    let client = build_client(&config.http)?;
+    let mut providers: Vec<(ProviderSource, DynPriceProvider)> = vec![
+        (
+            ProviderSource::CoinGecko,
+            Box::new(
+                CoinGecko::new_with_client(config.coingecko_api_key.clone(), client.clone())?
+                    .with_base_url(&config.coingecko_base_url)
+                    .with_max_response_bytes(config.http.max_response_bytes)
+                    .with_retry(app_state.retry_policy()),
+            ),
+        ),
+        (
+            ProviderSource::Binance,
+            Box::new(
+                Binance::new_with_client(client.clone())
+                    .with_base_url(&config.binance_base_url)
+                    .with_max_response_bytes(config.http.max_response_bytes)
+                    .with_retry(app_state.retry_policy()),
+            ),
+        ),
+    ];
+    if let (Some(oracle_address), Some(rpc_url)) = (&config.oneinch_oracle_address, &config.ethereum_rpc_url) {
+        providers.push((
+            ProviderSource::OneInch,
+            Box::new(OneInch::new_with_client(rpc_url.clone(), oracle_address, client.clone())?),
+        ));
+    }
+    if let Some(api_key) = &config.coinmarketcap_api_key {
+        providers.push((
+            ProviderSource::CoinMarketCap,
+            Box::new(
+                CoinMarketCap::new_with_client(api_key.clone(), client)?
+                    .with_base_url(&config.coinmarketcap_base_url)
+                    .with_max_response_bytes(config.http.max_response_bytes)
+                    .with_retry(app_state.retry_policy()),
+            ),
+        ));
+    }
+
+    let providers: Vec<(ProviderSource, DynPriceProvider)> = providers
+        .into_iter()
+        .map(|(source, provider)| (source, app_state.rate_limited(source, provider)))
+        .collect();
+
+    let currencies = [params.currency];
+    let results = futures::future::join_all(
+        providers.iter().map(|(_, provider)| provider.get_quotes(params.coin, &currencies)),
+    )
+    .await;
+
+    let mut quotes = Vec::new();
+    for ((source, _), result) in providers.iter().zip(results) {
+        match result {
+            Ok(provider_quotes) => {
+                app_state.provider_health.record_success(*source);
+                quotes.extend(provider_quotes);
+            }
+            Err(e) => warn!("{} left out of spread: {:#}", source, e),
+        }
+    }
+
+    let spread = PriceSpread::from_quotes(params.coin, params.currency, quotes, threshold)?;
+    if spread.exceeds_threshold {
+        warn!("⚠️ {}/{} providers disagree by {:.3}% (min {} max {})",
+            spread.coin, spread.currency, spread.spread_percent, spread.min, spread.max);
+    } else {
+        info!("📏 {}/{} spread {:.3}% across {} providers",
+            spread.coin, spread.currency, spread.spread_percent, spread.quotes.len());
+    }
+
+    Ok(format.respond(spread))
+}
+
+/// Stream the live aggregate price across providers over a WebSocket.
+///
+/// After the upgrade, every price tick from any provider pushes a JSON
+/// [`AggregateTick`]: the median of each provider's latest price and the
+/// low/high band around it. Binance ticks live when `BINANCE_STREAM` is set
+/// and is polled otherwise; CoinGecko is polled every 10 seconds. A provider's
+/// price counts until it is a minute behind the newest one.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crypto/stream",
+    tag = "crypto",
+    params(AggregateStreamQueryParams),
+    responses(
+        (status = 101, description = "Switching to a WebSocket carrying one aggregate per tick", body = AggregateTick),
+        (status = 400, description = "Invalid or disallowed coin or currency, or not a WebSocket upgrade request")
+    )
+)]
+#[instrument(skip_all, fields(coin = %params.coin, currency = %params.currency))]
+pub async fn stream_aggregate(
+    State(app_state): State<AppState>,
+    Query(params): Query<AggregateStreamQueryParams>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, AppError> {
+    let config = &app_state.config;
+    config.ensure_allowed(params.coin, &[params.currency])?;
+
+    let client = build_client(&config.http)?;
+    let coingecko: DynPriceProvider = Box::new(
+        CoinGecko::new_with_client(config.coingecko_api_key.clone(), client.clone())?
+            .with_base_url(&config.coingecko_base_url)
+            .with_max_response_bytes(config.http.max_response_bytes)
+            .with_retry(app_state.retry_policy()),
+    );
+    let mut streams = vec![polled(
+        ProviderSource::CoinGecko,
+        app_state.rate_limited(ProviderSource::CoinGecko, coingecko),
+        params.coin,
+        params.currency,
+    )];
+    if config.binance_stream {
+        streams.push(app_state.price_feed.updates());
+    } else {
+        let binance: DynPriceProvider = Box::new(
+            Binance::new_with_client(client)
+                .with_base_url(&config.binance_base_url)
+                .with_max_response_bytes(config.http.max_response_bytes)
+                .with_retry(app_state.retry_policy()),
+        );
+        streams.push(polled(
+            ProviderSource::Binance,
+            app_state.rate_limited(ProviderSource::Binance, binance),
+            params.coin,
+            params.currency,
+        ));
+    }
+
+    info!("📡 Streaming the {}/{} aggregate", params.coin, params.currency);
+    let ticks = AggregateFeed::new(params.coin, params.currency).ticks(streams);
+    Ok(upgrade.on_upgrade(move |socket| push_ticks(socket, ticks)))
+}
+
+/// Polls `provider` every [`STREAM_POLL_INTERVAL`], yielding each quote it returns.
+fn polled(source: ProviderSource, provider: DynPriceProvider, coin: Coin, currency: Currency) -> BoxStream<'static, Quote> {
+    let interval = tokio::time::interval(STREAM_POLL_INTERVAL);
+    stream::unfold((interval, provider), move |(mut interval, provider)| async move {
+        interval.tick().await;
+        let quotes = provider.get_quotes(coin, &[currency]).await.unwrap_or_else(|e| {
+            warn!("{} left out of the aggregate stream this tick: {:#}", source, e);
+            Vec::new()
+        });
+        Some((stream::iter(quotes), (interval, provider)))
+    })
+    .flatten()
+    .boxed()
+}
+
+/// Sends every tick to the client as JSON until either side closes.
+async fn push_ticks(mut socket: WebSocket, ticks: impl Stream<Item = AggregateTick>) {
+    let mut ticks = std::pin::pin!(ticks);
+    loop {
+        tokio::select! {
+            tick = ticks.next() => {
+                let Some(tick) = tick else { break };
+                let json = match serde_json::to_string(&tick) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        error!("Failed to serialize aggregate tick: {}", e);
+                        continue;
+                    }
+                };
+                if socket.send(Message::Text(json.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    debug!("Aggregate stream closed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::crypto::PriceProvider;
+
+    fn quote(provider: ProviderSource) -> Quote {
+        Quote::new(Coin::ETH, Currency::USD, 2000.0, provider)
+    }
+
+    #[test]
+    fn test_attribution_only_when_coingecko_contributed() {
+        let with_coingecko = [quote(ProviderSource::CoinMarketCap), quote(ProviderSource::CoinGecko)];
+        let without_coingecko = [quote(ProviderSource::CoinMarketCap)];
+
+        assert_eq!(attribution(&with_coingecko, true).as_deref(), Some(coingecko::ATTRIBUTION));
+        assert_eq!(attribution(&without_coingecko, true), None);
+        assert_eq!(attribution(&with_coingecko, false), None);
+    }
+
+    #[test]
+    fn test_attribution_serialization() {
+        let quotes = vec![quote(ProviderSource::CoinGecko)];
+        let meta = ResponseMeta { attribution: attribution(&quotes, true), ..ResponseMeta::default() };
+        let json = serde_json::to_value(PriceResponse { quotes, aggregates: Vec::new(), meta }).unwrap();
+        assert_eq!(json["meta"]["attribution"], coingecko::ATTRIBUTION);
+
+        let quotes = vec![quote(ProviderSource::CoinMarketCap)];
+        let meta = ResponseMeta { attribution: attribution(&quotes, true), ..ResponseMeta::default() };
+        let json = serde_json::to_value(PriceResponse { quotes, aggregates: Vec::new(), meta }).unwrap();
+        assert!(json["meta"].get("attribution").is_none());
+    }
+
+    /// Provider that always answers and reports a fixed raw body
+    struct RawProvider(Option<serde_json::Value>);
+
+    #[async_trait::async_trait]
+    impl PriceProvider for RawProvider {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "raw"
+        }
+
+        async fn get_quotes(&self, _coin: Coin, _currencies: &[Currency]) -> anyhow::Result<Vec<Quote>> {
+            Ok(vec![quote(ProviderSource::CoinGecko)])
+        }
+
+        fn raw_response(&self) -> Option<serde_json::Value> {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn test_raw_included_only_when_enabled() {
+        let body = serde_json::json!({ "ethereum": { "usd": 2000.0 } });
+        let providers: Vec<(ProviderSource, DynPriceProvider)> = vec![
+            (ProviderSource::CoinGecko, Box::new(RawProvider(Some(body.clone())))),
+            (ProviderSource::Binance, Box::new(RawProvider(None))),
+        ];
+
+        let raw = raw_responses(&providers, true).unwrap();
+        assert_eq!(raw.len(), 1);
+        assert_eq!(raw["coingecko"], body);
+
+        assert!(raw_responses(&providers, false).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_depth_from_provider_without_order_book_is_bad_request() {
+        use axum::response::IntoResponse;
+        use crate::core::config::Config;
+
+        let state = AppState::new(Config::default());
+        let params = DepthQueryParams { currency: Currency::USD, provider: ProviderSource::CoinGecko };
+        let response = get_market_depth(State(state.clone()), ResponseFormat::Json, Query(params)).await.into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+
+        let params = DepthQueryParams { currency: Currency::USD, provider: ProviderSource::CoinMarketCap };
+        let response = get_market_depth(State(state), ResponseFormat::Json, Query(params)).await.into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_IMPLEMENTED);
+    }
+
+    fn parse_query(query: &str) -> Result<QuoteQueryParams, String> {
+        let uri: axum::http::Uri = format!("http://localhost/api/v1/crypto/prices?{}", query).parse().unwrap();
+        axum::extract::Query::<QuoteQueryParams>::try_from_uri(&uri)
+            .map(|axum::extract::Query(params)| params)
+            .map_err(|e| e.body_text())
+    }
+
+    #[test]
+    fn test_currency_all_expands_to_configured_set() {
+        let every = Currency::all();
+
+        let params = parse_query("currency=all").unwrap();
+        assert_eq!(params.currency, CurrencySelection::All);
+        assert_eq!(params.requested_currencies(every).len(), 8);
+        assert_eq!(
+            params.requested_currencies(&[Currency::USD, Currency::JPY]),
+            vec![Currency::USD, Currency::JPY]
+        );
+
+        assert_eq!(parse_query("currency=ALL").unwrap().currency, CurrencySelection::All);
+        assert_eq!(parse_query("currency=eur").unwrap().requested_currencies(every), vec![Currency::EUR]);
+        assert_eq!(parse_query("").unwrap().requested_currencies(every), vec![Currency::USD]);
+        assert_eq!(
+            parse_query("currency=all&currencies=CHF").unwrap().requested_currencies(every),
+            vec![Currency::CHF]
+        );
+
+        let err = parse_query("currency=XYZ").err().unwrap();
+        assert!(err.contains("invalid currency 'XYZ'"), "{}", err);
+    }
+
+    /// Log output captured from a `tracing` subscriber
+    #[derive(Clone, Default)]
+    struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provider_selection_logs_skip_reasons() {
+        use crate::core::config::Config;
+
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let config = Config {
+            coingecko_base_url: "http://127.0.0.1:1".to_string(),
+            binance_stream: true,
+            ..Config::default()
+        };
+        let params = parse_query("currency=CHF").unwrap();
+        let _ = get_crypto_prices(State(AppState::new(config)), ResponseFormat::Json, IfNoneMatch::default(), Query(params)).await;
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let line = output.lines().find(|line| line.contains("Provider selection")).unwrap();
+        assert!(line.contains("coingecko=primary"), "{}", line);
+        assert!(line.contains("coinmarketcap=skipped: not configured"), "{}", line);
+        assert!(line.contains("binance=skipped: unsupported pair"), "{}", line);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_request_with_etag_is_not_modified() {
+        use axum::body::Body;
+        use axum::http::{header, Request, StatusCode};
+        use axum::{routing::get, Router};
+        use tower::ServiceExt;
+
+        let upstream = Router::new().route(
+            "/simple/price",
+            get(|| async { axum::Json(serde_json::json!({ "ethereum": { "usd": 2000.5, "last_updated_at": 1_700_000_000 } })) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = Config {
+            coingecko_base_url: format!("http://{}", listener.local_addr().unwrap()),
+            ..Config::default()
+        };
+        tokio::spawn(async move { axum::serve(listener, upstream).await.unwrap() });
+
+        let router = crate::api::routes::create_router(AppState::new(config));
+        let request = |etag: Option<&HeaderValue>| {
+            let mut request = Request::get("/api/v1/crypto/prices?currency=USD");
+            if let Some(etag) = etag {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+            router.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let first = request(None).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        let etag = first.headers()[header::ETAG].clone();
+        assert!(etag.to_str().unwrap().starts_with("W/\""));
+
+        let second = request(Some(&etag)).await.unwrap();
+        assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(second.headers()[header::ETAG], etag);
+        let body = axum::body::to_bytes(second.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+
+        let stale = HeaderValue::from_static("W/\"0000000000000000\"");
+        assert_eq!(request(Some(&stale)).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_raw_stripped_from_serialized_meta_by_default() {
+        let json = serde_json::to_value(ResponseMeta::default()).unwrap();
+        assert!(json.get("raw").is_none());
+    }
+}
@@ -1,19 +1,35 @@
 //! Gas price endpoints.
 //!
 //! This module handles requests for Ethereum gas prices from multiple oracle providers.
-//! Supports both Etherscan and Alloy (direct RPC) providers with configurable selection.
+//! Supports Etherscan, Alloy (direct RPC) and Blockscout providers with configurable selection.
+//! It also prices batches of operations at the current gas price, can
+//! annotate gas prices with their USD cost, and fetches several chains at once.
 
-use axum::{extract::{Query, State}, Json};
-use serde::Deserialize;
-use utoipa::IntoParams;
-use tracing::{info, warn, error};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
-use crate::core::errors::AppError;
+use alloy_rpc_types::BlockNumberOrTag;
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use tracing::{info, warn, error, instrument};
 
+use crate::core::errors::{utils::to_json_value, AppError, InvalidBody, InvalidQuery, NotConfigured, NotSupported};
+use crate::domains::SCHEMA_VERSION;
+
+use crate::api::response::{Encoded, ResponseFormat};
+use crate::api::validation::{comma_separated, JsonBody, Query};
 use crate::core::config::AppState;
-use crate::domains::gas::price::{GasOracle, GasQuote, GasOracleSource};
+use crate::core::health::ProviderHealth;
+use crate::infrastructure::http::build_client;
+use crate::domains::crypto::{Coin, Currency, DynPriceProvider, ProviderSource, Quote};
+use crate::domains::crypto::coingecko::CoinGecko;
+use crate::domains::gas::chain::ChainId;
+use crate::domains::gas::cost::{BatchCost, GasPriceFiat, Operation};
+use crate::domains::gas::price::{DynGasOracle, GasBaseline, GasFloors, GasQuote, GasOracleSource, GasSpeed, TierLabels};
 use crate::domains::gas::price::etherscan::EtherscanGasOracle;
-use crate::domains::gas::price::alloy::AlloyGasOracle;
+use crate::domains::gas::price::alloy::{parse_block, AlloyGasOracle, BlockNotFound};
+use crate::domains::gas::price::blockscout::BlockscoutGasOracle;
 
 /// Query parameters for gas price requests.
 #[derive(Deserialize, IntoParams)]
@@ -21,16 +37,139 @@ pub struct GasPriceQueryParams {
     /// Gas oracle provider to use (defaults to Etherscan)
     #[serde(default = "default_gas_provider")]
     pub provider: GasOracleSource,
+    /// Names for the low, average and high tiers, e.g. `slow,normal,rapid`
+    /// (defaults to `GAS_TIER_LABELS`, or `low,average,high`)
+    #[param(example = "slow,normal,rapid")]
+    pub tier_labels: Option<String>,
+    /// Block to estimate gas at: a number or `latest`/`safe`/`finalized`/`earliest`/`pending`
+    /// (Alloy provider only, defaults to `latest`)
+    #[param(example = "latest")]
+    pub block: Option<String>,
+    /// Chain to estimate gas for, as a chain id or name (defaults to Ethereum mainnet, `1`)
+    #[serde(default)]
+    #[param(value_type = Option<String>, example = "8453")]
+    pub chain_id: ChainId,
+    /// Return only this tier's price as a bare number instead of the full object:
+    /// `low`/`slow`/`safe`, `average`/`standard`/`normal` or `high`/`fast`/`rapid`
+    #[param(value_type = Option<String>, example = "fast")]
+    pub speed: Option<GasSpeed>,
+    /// Put the tier prices and timestamp at the top level next to `provider`
+    /// instead of under `gas_price` (see `FlatGasQuote`)
+    #[serde(default)]
+    pub flat: bool,
+    /// Add a `usd` object pricing each tier per gas unit and per plain transfer
+    /// (21000 gas), at CoinGecko's USD price for the chain's gas token. Costs one
+    /// extra upstream request, so it is off by default; not combinable with `speed`
+    #[serde(default)]
+    pub include_usd: bool,
+    /// Add an `eip1559` object with each tier's `max_fee_per_gas` and
+    /// `max_priority_fee_per_gas` in Gwei, from the oracle's base fee and the
+    /// tier's tip, with the max fee covering the base fee `GAS_BASE_FEE_HEADROOM`
+    /// times over; not combinable with `speed`
+    #[serde(default)]
+    pub include_1559: bool,
+    /// Add an `estimated_seconds` object with each tier's expected time to
+    /// confirmation: the oracle's own estimate when it has one (Blockscout),
+    /// otherwise derived from the chain's block time; not combinable with `speed`
+    #[serde(default)]
+    pub include_eta: bool,
+    /// Add a `baseline` object comparing the average price with this many Gwei:
+    /// whether it is below and by how much, for alerting on cheap gas; not
+    /// combinable with `speed`
+    #[param(example = 20)]
+    pub baseline_gwei: Option<f64>,
+    /// Return the tiers as computed from recent blocks, without the minimum
+    /// priority fees (`<CHAIN>_GAS_FLOORS`) applied (Alloy provider only)
+    #[serde(default)]
+    pub raw: bool,
+}
+
+/// Query parameters for multichain gas price requests.
+#[derive(Deserialize, IntoParams)]
+pub struct MultichainGasQueryParams {
+    /// Comma-separated chains to estimate gas for, as chain ids or names
+    #[serde(default, deserialize_with = "comma_separated")]
+    #[param(value_type = String, example = "ethereum,polygon,arbitrum")]
+    pub chains: Vec<ChainId>,
+    /// Gas oracle provider to use on every chain (defaults to Etherscan)
+    #[serde(default = "default_gas_provider")]
+    pub provider: GasOracleSource,
+}
+
+/// Gas quotes for several chains, keyed by chain name
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MultichainGasResponse {
+    /// Gas quote for each chain whose provider answered
+    pub chains: BTreeMap<String, GasQuote>,
+    /// Why each remaining chain has no quote, e.g. its RPC URL is not configured
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub errors: BTreeMap<String, String>,
 }
 
 fn default_gas_provider() -> GasOracleSource {
     GasOracleSource::Etherscan
 }
 
+impl Default for GasPriceQueryParams {
+    /// The parameters of a request with an empty query string
+    fn default() -> Self {
+        Self {
+            provider: default_gas_provider(),
+            tier_labels: None,
+            block: None,
+            chain_id: ChainId::default(),
+            speed: None,
+            flat: false,
+            include_usd: false,
+            include_1559: false,
+            include_eta: false,
+            baseline_gwei: None,
+            raw: false,
+        }
+    }
+}
+
+fn default_cost_currency() -> Currency {
+    Currency::USD
+}
+
+/// Request body for batch fee estimates.
+#[derive(Deserialize, ToSchema)]
+pub struct BatchCostRequest {
+    /// Operations to price, e.g. `{"type": "contract_call", "gas_limit": 120000}`
+    pub operations: Vec<Operation>,
+    /// Chain the operations run on, as a chain id or name (defaults to Ethereum mainnet, `1`)
+    #[serde(default)]
+    #[schema(value_type = Option<u64>, example = 1)]
+    pub chain_id: ChainId,
+    /// Currency for the fiat fees (defaults to USD)
+    #[serde(default = "default_cost_currency")]
+    pub currency: Currency,
+    /// Gas oracle provider to use (defaults to Etherscan)
+    #[serde(default = "default_gas_provider")]
+    pub provider: GasOracleSource,
+    /// Gas price tier to price the operations at (defaults to `average`)
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "fast")]
+    pub speed: Option<GasSpeed>,
+}
+
 /// Get current Ethereum gas prices from specified provider.
 ///
 /// This endpoint fetches gas price estimates from the selected oracle provider
-/// and returns low/average/high recommendations in Gwei.
+/// and returns low/average/high recommendations in Gwei. The tier keys can be
+/// renamed with `tier_labels`, and the Alloy provider can estimate as of a
+/// historical `block`. With `speed`, the response is just that tier's price in
+/// Gwei as a bare JSON number, for shell scripting. With `flat=true`, the prices
+/// sit at the top level like a crypto quote's. With `include_usd=true`, a `usd`
+/// object gives each tier's cost per gas unit and per plain transfer in USD.
+/// With `include_1559=true`, an `eip1559` object gives each tier's EIP-1559
+/// max fee and priority fee in Gwei. With `include_eta=true`, an
+/// `estimated_seconds` object gives each tier's expected confirmation time.
+/// With `baseline_gwei`, a `baseline` object tells whether the average price is
+/// below that many Gwei and the difference.
+/// With `raw=true`, the Alloy provider skips its minimum priority fees and
+/// reports the tiers the network actually shows.
 #[utoipa::path(
     get,
     path = "/api/v1/gas/prices",
@@ -38,78 +177,712 @@ fn default_gas_provider() -> GasOracleSource {
     params(GasPriceQueryParams),
     responses(
         (status = 200, description = "Successful response with gas price estimates", body = GasQuote),
-        (status = 500, description = "Failed to fetch gas prices from provider")
+        (status = 400, description = "Invalid tier labels, block or speed, include_usd, include_1559, include_eta or baseline_gwei with speed, a negative baseline_gwei, include_1559 from an oracle without a base fee, or block or raw with a provider other than alloy"),
+        (status = 500, description = "Failed to fetch gas prices from provider"),
+        (status = 501, description = "Requested provider is not configured"),
+        (status = 503, description = "Gas endpoints are disabled: no gas oracle is configured")
     )
 )]
+#[instrument(skip_all, fields(provider = ?params.provider, chain_id = %params.chain_id))]
 pub async fn get_gas_estimates(
     State(app_state): State<AppState>,
+    format: ResponseFormat,
     Query(params): Query<GasPriceQueryParams>,
-) -> Result<Json<GasQuote>, AppError> {
+) -> Result<Encoded<serde_json::Value>, AppError> {
+    app_state.config.ensure_gas_enabled()?;
     info!("⛽ Fetching gas prices from {:?} provider", params.provider);
 
-    let gas_quote = match params.provider {
+    let labels = match params.tier_labels.as_deref() {
+        Some(raw) => raw.parse::<TierLabels>()?,
+        None => app_state.config.gas_tier_labels.clone(),
+    };
+    let block = params.block.as_deref().map(parse_block).transpose()?;
+    if block.is_some() && !matches!(params.provider, GasOracleSource::Alloy) {
+        return Err(InvalidQuery::new("block: historical blocks are only supported by the alloy provider").into());
+    }
+    if params.raw && !matches!(params.provider, GasOracleSource::Alloy) {
+        return Err(InvalidQuery::new("raw: unclamped tiers are only supported by the alloy provider").into());
+    }
+    let usd_coin = match params.include_usd {
+        true if params.speed.is_some() => {
+            return Err(InvalidQuery::new("include_usd: not available with speed, which returns a bare number").into());
+        }
+        true => {
+            let coin = Coin::native_for_chain(params.chain_id)
+                .ok_or_else(|| NotSupported::new(format!("USD gas prices on chain {}", params.chain_id)))?;
+            app_state.config.ensure_allowed(coin, &[Currency::USD])?;
+            Some(coin)
+        }
+        false => None,
+    };
+    if params.include_1559 && params.speed.is_some() {
+        return Err(InvalidQuery::new("include_1559: not available with speed, which returns a bare number").into());
+    }
+    if params.include_eta && params.speed.is_some() {
+        return Err(InvalidQuery::new("include_eta: not available with speed, which returns a bare number").into());
+    }
+    match params.baseline_gwei {
+        Some(_) if params.speed.is_some() => {
+            return Err(InvalidQuery::new("baseline_gwei: not available with speed, which returns a bare number").into());
+        }
+        Some(baseline) if !baseline.is_finite() || baseline < 0.0 => {
+            return Err(InvalidQuery::new(format!("baseline_gwei: {} is not a non-negative number of Gwei", baseline)).into());
+        }
+        _ => {}
+    }
+
+    let gas_quote = fetch_gas_quote(&app_state, params.provider, params.chain_id, block, params.raw).await?;
+
+    info!("Gas price fetching completed. Success: {}", gas_quote.is_some());
+
+    let Some(quote) = gas_quote else {
+        return Err(anyhow::anyhow!("Failed to fetch gas prices from provider").into());
+    };
+    let mut value = match params.speed {
+        Some(speed) => return Ok(format.respond(quote.gas_price.tier_json(speed)?)),
+        None if params.flat => quote.to_flat_labeled_json(&labels)?,
+        None => quote.to_labeled_json(&labels)?,
+    };
+    if let Some(coin) = usd_coin {
+        let native_price = fetch_native_price(&app_state, coin, Currency::USD).await?;
+        let mut usd = to_json_value(&GasPriceFiat::new(params.chain_id, &quote.gas_price, &native_price)?, "USD gas prices")?;
+        if let Some(tiers) = usd.as_object_mut() {
+            labels.rename_tiers(tiers);
+        }
+        if let Some(quote) = value.as_object_mut() {
+            quote.insert("usd".to_string(), usd);
+        }
+    }
+    if params.include_1559 {
+        let fees = quote
+            .gas_price
+            .eip1559(app_state.config.gas_base_fee_headroom)
+            .ok_or_else(|| NotSupported::new(format!("EIP-1559 fees from {}, which reports no base fee", quote.provider)))?;
+        let mut eip1559 = to_json_value(&fees, "EIP-1559 gas fees")?;
+        if let Some(tiers) = eip1559.as_object_mut() {
+            labels.rename_tiers(tiers);
+        }
+        if let Some(quote) = value.as_object_mut() {
+            quote.insert("eip1559".to_string(), eip1559);
+        }
+    }
+    if params.include_eta {
+        let mut eta = to_json_value(&quote.gas_price.eta(params.chain_id.block_time()), "gas confirmation times")?;
+        if let Some(tiers) = eta.as_object_mut() {
+            labels.rename_tiers(tiers);
+        }
+        if let Some(quote) = value.as_object_mut() {
+            quote.insert("estimated_seconds".to_string(), eta);
+        }
+    }
+    if let Some(baseline) = params.baseline_gwei {
+        let baseline = to_json_value(&GasBaseline::new(quote.gas_price.average, baseline), "gas baseline comparison")?;
+        if let Some(quote) = value.as_object_mut() {
+            quote.insert("baseline".to_string(), baseline);
+        }
+    }
+    Ok(format.respond(value))
+}
+
+/// Get current gas prices for several chains at once.
+///
+/// Fetches every chain in `chains` concurrently from `provider`: Etherscan's
+/// multichain API by chain id, or each chain's own RPC endpoint
+/// (`<CHAIN>_RPC_URL`) with Alloy. A chain that fails or is not configured
+/// does not fail the request; it is listed in `errors` instead of `chains`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/gas/prices/multichain",
+    tag = "gas",
+    params(MultichainGasQueryParams),
+    responses(
+        (status = 200, description = "Gas quotes for the chains that answered, errors for the others", body = MultichainGasResponse),
+        (status = 400, description = "No chains, or an unknown chain"),
+        (status = 500, description = "No chain could be fetched"),
+        (status = 503, description = "Gas endpoints are disabled: no gas oracle is configured")
+    )
+)]
+#[instrument(skip_all, fields(provider = ?params.provider))]
+pub async fn get_multichain_gas_estimates(
+    State(app_state): State<AppState>,
+    format: ResponseFormat,
+    Query(params): Query<MultichainGasQueryParams>,
+) -> Result<Encoded<MultichainGasResponse>, AppError> {
+    app_state.config.ensure_gas_enabled()?;
+    let mut chains: Vec<ChainId> = Vec::new();
+    for chain_id in params.chains {
+        if !chains.contains(&chain_id) {
+            chains.push(chain_id);
+        }
+    }
+    if chains.is_empty() {
+        return Err(InvalidQuery::new("chains: at least one chain is required").into());
+    }
+    info!("⛽ Fetching gas prices for {} chains from {:?} provider", chains.len(), params.provider);
+
+    let results = futures::future::join_all(
+        chains
+            .iter()
+            .map(|&chain_id| fetch_gas_quote(&app_state, params.provider, chain_id, None, false)),
+    )
+    .await;
+
+    let mut response = MultichainGasResponse { chains: BTreeMap::new(), errors: BTreeMap::new() };
+    for (chain_id, result) in chains.into_iter().zip(results) {
+        let name = chain_id.name().map_or_else(|| chain_id.to_string(), str::to_string);
+        match result {
+            Ok(Some(quote)) => {
+                response.chains.insert(name, quote);
+            }
+            Ok(None) => {
+                response.errors.insert(name, "Failed to fetch gas prices from provider".to_string());
+            }
+            Err(e) => {
+                warn!("No gas prices for chain {}: {}", chain_id, e);
+                response.errors.insert(name, e.to_string());
+            }
+        }
+    }
+
+    info!("Multichain gas fetching completed. {} chains, {} failed", response.chains.len(), response.errors.len());
+    if response.chains.is_empty() {
+        return Err(anyhow::anyhow!("Failed to fetch gas prices for any chain: {:?}", response.errors).into());
+    }
+    Ok(format.respond(response))
+}
+
+/// Estimate the total fee for a batch of operations.
+///
+/// Prices native transfers, ERC-20 transfers and contract calls with their own
+/// gas limit at the provider's current gas price, and converts the fees into
+/// the chain's gas token and `currency` using CoinGecko's price for that token.
+/// Returns the totals alongside each operation's fee.
+#[utoipa::path(
+    post,
+    path = "/api/v1/gas/cost/batch",
+    tag = "gas",
+    request_body = BatchCostRequest,
+    responses(
+        (status = 200, description = "Total and per-operation fees", body = BatchCost),
+        (status = 400, description = "Malformed body, no operations, or disallowed currency"),
+        (status = 500, description = "Failed to fetch gas prices or the gas token's price"),
+        (status = 501, description = "Requested provider is not configured"),
+        (status = 503, description = "Gas endpoints are disabled: no gas oracle is configured")
+    )
+)]
+#[instrument(skip_all, fields(provider = ?request.provider, chain_id = %request.chain_id))]
+pub async fn estimate_batch_cost(
+    State(app_state): State<AppState>,
+    format: ResponseFormat,
+    JsonBody(request): JsonBody<BatchCostRequest>,
+) -> Result<Encoded<BatchCost>, AppError> {
+    app_state.config.ensure_gas_enabled()?;
+    let native_coin = Coin::native_for_chain(request.chain_id)
+        .ok_or_else(|| NotSupported::new(format!("fee estimates on chain {}", request.chain_id)))?;
+    app_state.config.ensure_allowed(native_coin, &[request.currency])?;
+    if request.operations.is_empty() {
+        return Err(InvalidBody::new("operations: at least one operation is required").into());
+    }
+
+    let gas_quote = fetch_gas_quote(&app_state, request.provider, request.chain_id, None, false)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Failed to fetch gas prices from provider"))?;
+    let gas_price_gwei = gas_quote.gas_price.tier(request.speed.unwrap_or(GasSpeed::Average));
+    let native_price = fetch_native_price(&app_state, native_coin, request.currency).await?;
+
+    let batch = BatchCost::estimate(request.chain_id, &request.operations, gas_price_gwei, &native_price)?;
+    info!("🧾 {} operations on chain {}: {} gas, {} {} ({} {})",
+        batch.operations.len(), batch.chain_id, batch.gas_limit,
+        batch.fee_native, batch.native_coin, batch.fee_fiat, batch.currency);
+
+    Ok(format.respond(batch))
+}
+
+/// Fetches CoinGecko's price of a chain's gas token `coin` in `currency`.
+///
+/// # Errors
+///
+/// Returns an error if CoinGecko fails, is over its quota, or has no price.
+async fn fetch_native_price(app_state: &AppState, coin: Coin, currency: Currency) -> Result<Quote, AppError> {
+    let config = &app_state.config;
+    let coingecko: DynPriceProvider = Box::new(
+        CoinGecko::new_with_client(config.coingecko_api_key.clone(), build_client(&config.http)?)?
+            .with_base_url(&config.coingecko_base_url)
+            .with_max_response_bytes(config.http.max_response_bytes)
+            .with_retry(app_state.retry_policy()),
+    );
+    let coingecko = app_state.rate_limited(ProviderSource::CoinGecko, coingecko);
+    let quote = coingecko
+        .get_quotes(coin, &[currency])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("CoinGecko returned no {} price in {}", coin, currency))?;
+    Ok(quote)
+}
+
+/// Fetches a gas quote from `provider` for `chain_id`, as of `block` when given
+/// and without gas floors when `raw`.
+///
+/// Returns `None` when the provider is configured but its request fails.
+/// Concurrent requests for the latest clamped prices from the same provider and
+/// chain share one upstream lookup.
+///
+/// # Errors
+///
+/// Returns [`NotConfigured`] if `provider` is not set up for `chain_id`, or
+/// [`BlockNotFound`] if the requested block does not exist.
+async fn fetch_gas_quote(
+    app_state: &AppState,
+    provider: GasOracleSource,
+    chain_id: ChainId,
+    block: Option<BlockNumberOrTag>,
+    raw: bool,
+) -> Result<Option<GasQuote>, AppError> {
+    let Some(oracle) = gas_oracle(app_state, provider, chain_id, block, raw)? else {
+        return Ok(None);
+    };
+    let health = app_state.provider_health.clone();
+
+    if block.is_some() || raw {
+        return match query_gas_oracle(oracle, provider, health).await {
+            Ok(quote) => Ok(Some(quote)),
+            Err(e) if e.downcast_ref::<BlockNotFound>().is_some() => Err(e.into()),
+            Err(e) => {
+                warn!("{} gas oracle failed: {}", provider, e);
+                Ok(None)
+            }
+        };
+    }
+
+    let quote = app_state
+        .gas_lookups
+        .run((provider, chain_id), move || async move {
+            query_gas_oracle(oracle, provider, health)
+                .await
+                .inspect_err(|e| warn!("{} gas oracle failed: {}", provider, e))
+                .ok()
+        })
+        .await;
+    Ok(quote)
+}
+
+/// Asks `oracle` for gas prices, recording the provider's success in `health`.
+async fn query_gas_oracle(
+    oracle: DynGasOracle,
+    provider: GasOracleSource,
+    health: Arc<ProviderHealth>,
+) -> anyhow::Result<GasQuote> {
+    let gas_price = oracle.get_gas_prices().await?;
+    health.record_success(provider);
+    Ok(GasQuote { schema_version: SCHEMA_VERSION, gas_price, provider })
+}
+
+/// Builds the oracle for `provider` on `chain_id`, as of `block` when given and
+/// without gas floors when `raw`.
+///
+/// Returns `None` when the provider is configured but cannot be initialized.
+///
+/// # Errors
+///
+/// Returns [`NotConfigured`] if `provider` is not set up for `chain_id`.
+fn gas_oracle(
+    app_state: &AppState,
+    provider: GasOracleSource,
+    chain_id: ChainId,
+    block: Option<BlockNumberOrTag>,
+    raw: bool,
+) -> Result<Option<DynGasOracle>, AppError> {
+    let config = &app_state.config;
+    let client = build_client(&config.http)?;
+
+    let oracle: anyhow::Result<DynGasOracle> = match provider {
         GasOracleSource::Etherscan => {
-            match &app_state.config.etherscan_api_key {
-                Some(api_key) => {
-                    match EtherscanGasOracle::new(api_key.clone()) {
-                        Ok(oracle) => {
-                            match oracle.get_gas_prices().await {
-                                Ok(gas_price) => Some(GasQuote {
-                                    gas_price,
-                                    provider: GasOracleSource::Etherscan,
-                                }),
-                                Err(e) => {
-                                    warn!("Etherscan gas oracle failed: {}", e);
-                                    None
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            error!("Etherscan gas oracle initialization failed: {}", e);
-                            None
-                        }
-                    }
+            let Some(api_key) = &config.etherscan_api_key else {
+                info!("Etherscan API key not configured, provider unavailable");
+                return Err(NotConfigured::new(GasOracleSource::Etherscan.to_string(), "ETHERSCAN_API_KEY").into());
+            };
+            EtherscanGasOracle::new_with_client(api_key.clone(), client).map(|oracle| {
+                Box::new(
+                    oracle
+                        .with_base_url(&config.etherscan_base_url)
+                        .with_chain_id(chain_id)
+                        .with_max_response_bytes(config.http.max_response_bytes)
+                        .with_retry(app_state.retry_policy()),
+                ) as DynGasOracle
+            })
+        }
+        GasOracleSource::Alloy => {
+            let Some(rpc_url) = config.rpc_url(chain_id) else {
+                let env_var = chain_id.rpc_env_var().unwrap_or("ETHEREUM_RPC_URL");
+                error!("{} not configured, Alloy provider unavailable", env_var);
+                return Err(NotConfigured::new(GasOracleSource::Alloy.to_string(), env_var).into());
+            };
+            AlloyGasOracle::new_with_client(rpc_url.to_string(), client).map(|oracle| {
+                let floors = if raw { GasFloors::NONE } else { config.gas_floors(chain_id) };
+                let oracle = oracle.with_floors(floors).with_percentile_tiers(config.alloy_percentile_tiers.clone());
+                let oracle = match block {
+                    Some(block) => oracle.with_block(block),
+                    None => oracle,
+                };
+                Box::new(oracle) as DynGasOracle
+            })
+        }
+        GasOracleSource::Blockscout => {
+            // Each Blockscout instance serves a single chain
+            let Some(base_url) = config.blockscout_url_for(chain_id) else {
+                let env_var = chain_id.blockscout_env_var().unwrap_or("BLOCKSCOUT_URL");
+                info!("{} not configured, Blockscout provider unavailable", env_var);
+                return Err(NotConfigured::new(GasOracleSource::Blockscout.to_string(), env_var).into());
+            };
+            BlockscoutGasOracle::new_with_client(base_url.to_string(), client).map(|oracle| {
+                Box::new(
+                    oracle
+                        .with_max_response_bytes(config.http.max_response_bytes)
+                        .with_retry(app_state.retry_policy()),
+                ) as DynGasOracle
+            })
+        }
+    };
+
+    match oracle {
+        Ok(oracle) => Ok(Some(oracle)),
+        Err(e) => {
+            error!("{} gas oracle initialization failed: {}", provider, e);
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use crate::core::config::Config;
+
+    async fn status_for(config: Config, provider: GasOracleSource) -> StatusCode {
+        let result = get_gas_estimates(
+            State(AppState::new(config)),
+            ResponseFormat::Json,
+            Query(GasPriceQueryParams { provider, ..Default::default() }),
+        )
+        .await;
+        result.into_response().status()
+    }
+
+    #[tokio::test]
+    async fn test_unconfigured_provider_returns_not_implemented() {
+        assert_eq!(status_for(Config::default(), GasOracleSource::Etherscan).await, StatusCode::NOT_IMPLEMENTED);
+        assert_eq!(status_for(Config::default(), GasOracleSource::Alloy).await, StatusCode::NOT_IMPLEMENTED);
+        assert_eq!(status_for(Config::default(), GasOracleSource::Blockscout).await, StatusCode::NOT_IMPLEMENTED);
+    }
+
+    #[tokio::test]
+    async fn test_failing_provider_returns_server_error() {
+        // Nothing listens on port 1, so the configured RPC endpoint fails at runtime
+        let config = Config {
+            ethereum_rpc_url: Some("http://127.0.0.1:1".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(status_for(config, GasOracleSource::Alloy).await, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    /// Mainnet Blockscout instance answering its gas price oracle with `body`, configured on top of `config`
+    async fn mock_blockscout(body: serde_json::Value, config: Config) -> AppState {
+        mock_blockscout_for(ChainId::ETHEREUM, body, config).await
+    }
+
+    /// Blockscout instance for `chain` answering its gas price oracle with `body`
+    async fn mock_blockscout_for(chain: ChainId, body: serde_json::Value, mut config: Config) -> AppState {
+        use axum::{routing::get, Router};
+
+        let app = Router::new().route("/api/v1/gas-price-oracle", get(move || async move { axum::Json(body) }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        if chain == ChainId::ETHEREUM {
+            config.blockscout_url = Some(url);
+        } else {
+            config.chain_blockscout_urls.insert(chain, url);
+        }
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        AppState::new(config)
+    }
+
+    /// Blockscout tiers with an average of 2.5 Gwei and no base fee
+    fn plain_tiers() -> serde_json::Value {
+        serde_json::json!({ "slow": 1.0, "average": 2.5, "fast": 4.0 })
+    }
+
+    fn parse_query(query: &str) -> Result<GasPriceQueryParams, (StatusCode, String)> {
+        let uri: axum::http::Uri = format!("http://localhost/api/v1/gas/prices?{}", query).parse().unwrap();
+        axum::extract::Query::<GasPriceQueryParams>::try_from_uri(&uri)
+            .map(|axum::extract::Query(params)| params)
+            .map_err(|e| (e.status(), e.body_text()))
+    }
+
+    #[test]
+    fn test_provider_param_is_case_insensitive() {
+        assert_eq!(parse_query("provider=Etherscan").unwrap().provider, GasOracleSource::Etherscan);
+        assert_eq!(parse_query("provider=ALLOY").unwrap().provider, GasOracleSource::Alloy);
+        assert_eq!(parse_query("").unwrap().provider, GasOracleSource::Etherscan);
+    }
+
+    #[test]
+    fn test_unknown_provider_lists_valid_values() {
+        let Err((status, body)) = parse_query("provider=infura") else {
+            panic!("unknown provider should be rejected");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("infura"));
+        assert!(body.contains("etherscan") && body.contains("alloy"));
+    }
+
+    #[test]
+    fn test_speed_param() {
+        assert_eq!(parse_query("").unwrap().speed, None);
+        assert_eq!(parse_query("speed=fast").unwrap().speed, Some(GasSpeed::High));
+        assert_eq!(parse_query("speed=standard").unwrap().speed, Some(GasSpeed::Average));
+
+        let Err((status, body)) = parse_query("speed=turbo") else {
+            panic!("unknown speed should be rejected");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("turbo") && body.contains("rapid"));
+    }
+
+    #[tokio::test]
+    async fn test_speed_returns_single_scalar() {
+        let state = mock_blockscout(plain_tiers(), Config::default()).await;
+        let query = |speed| GasPriceQueryParams { provider: GasOracleSource::Blockscout, speed, ..Default::default() };
+
+        let fetch = |speed| {
+            let state = state.clone();
+            async move {
+                match get_gas_estimates(State(state), ResponseFormat::Json, Query(query(speed))).await {
+                    Ok(Encoded { body, .. }) => body,
+                    Err(_) => panic!("request with speed {:?} should succeed", speed),
                 }
-                None => {
-                    info!("Etherscan API key not configured, provider unavailable");
-                    None
+            }
+        };
+
+        assert_eq!(fetch(Some(GasSpeed::High)).await, serde_json::json!(4));
+        assert_eq!(fetch(Some(GasSpeed::Low)).await, serde_json::json!(1));
+        assert_eq!(fetch(None).await["gas_price"]["average"], 2.5);
+    }
+
+    #[tokio::test]
+    async fn test_include_1559_adds_labeled_fee_caps() {
+        let tier = |price: f64| serde_json::json!({ "price": price, "base_fee": 10.0, "priority_fee": price - 10.0 });
+        let body = serde_json::json!({ "slow": tier(11.0), "average": tier(12.5), "fast": tier(15.0) });
+        let state = mock_blockscout(body, Config { gas_base_fee_headroom: 1.5, ..Config::default() }).await;
+
+        let query = GasPriceQueryParams {
+            provider: GasOracleSource::Blockscout,
+            tier_labels: Some("slow,normal,rapid".to_string()),
+            include_1559: true,
+            ..Default::default()
+        };
+        let Ok(Encoded { body, .. }) = get_gas_estimates(State(state), ResponseFormat::Json, Query(query)).await else {
+            panic!("include_1559 should succeed when the oracle reports a base fee");
+        };
+
+        // max fee = base fee * headroom + tip, tip = tier price - base fee
+        assert_eq!(body["gas_price"]["base_fee"], 10.0);
+        assert_eq!(body["eip1559"]["slow"], serde_json::json!({ "max_fee_per_gas": 16, "max_priority_fee_per_gas": 1 }));
+        assert_eq!(body["eip1559"]["normal"], serde_json::json!({ "max_fee_per_gas": 17.5, "max_priority_fee_per_gas": 2.5 }));
+        assert_eq!(body["eip1559"]["rapid"], serde_json::json!({ "max_fee_per_gas": 20, "max_priority_fee_per_gas": 5 }));
+    }
+
+    #[tokio::test]
+    async fn test_include_eta_orders_tiers_by_speed() {
+        let state = mock_blockscout(plain_tiers(), Config::default()).await;
+
+        let query = GasPriceQueryParams {
+            provider: GasOracleSource::Blockscout,
+            tier_labels: Some("slow,normal,rapid".to_string()),
+            include_eta: true,
+            ..Default::default()
+        };
+        let Ok(Encoded { body, .. }) = get_gas_estimates(State(state), ResponseFormat::Json, Query(query)).await else {
+            panic!("include_eta should succeed");
+        };
+
+        // This instance reports no times, so they come from Ethereum's block time
+        let eta = |label: &str| body["estimated_seconds"][label].as_f64().unwrap();
+        assert!(eta("rapid") < eta("normal") && eta("normal") < eta("slow"), "{}", body);
+        assert_eq!(eta("normal"), 24.0);
+    }
+
+    #[tokio::test]
+    async fn test_baseline_gwei_compares_the_average_price() {
+        let state = mock_blockscout(plain_tiers(), Config::default()).await;
+        let query = |baseline_gwei, speed| GasPriceQueryParams {
+            provider: GasOracleSource::Blockscout,
+            speed,
+            baseline_gwei,
+            ..Default::default()
+        };
+        let fetch = |baseline_gwei| {
+            let state = state.clone();
+            async move {
+                match get_gas_estimates(State(state), ResponseFormat::Json, Query(query(Some(baseline_gwei), None))).await {
+                    Ok(Encoded { body, .. }) => body["baseline"].clone(),
+                    Err(_) => panic!("baseline_gwei={} should succeed", baseline_gwei),
                 }
             }
+        };
+
+        // Average is 2.5 Gwei
+        assert_eq!(fetch(3.0).await, serde_json::json!({ "baseline_gwei": 3, "below_baseline": true, "delta_gwei": -0.5 }));
+        assert_eq!(fetch(2.0).await, serde_json::json!({ "baseline_gwei": 2, "below_baseline": false, "delta_gwei": 0.5 }));
+        assert_eq!(fetch(2.5).await["below_baseline"], false);
+
+        for (baseline_gwei, speed) in [(-1.0, None), (3.0, Some(GasSpeed::High))] {
+            let result = get_gas_estimates(State(state.clone()), ResponseFormat::Json, Query(query(Some(baseline_gwei), speed))).await;
+            assert_eq!(result.into_response().status(), StatusCode::BAD_REQUEST);
         }
-        GasOracleSource::Alloy => {
-            match &app_state.config.ethereum_rpc_url {
-                Some(rpc_url) => {
-                    match AlloyGasOracle::new(rpc_url.clone()) {
-                        Ok(oracle) => {
-                            match oracle.get_gas_prices().await {
-                                Ok(gas_price) => Some(GasQuote {
-                                    gas_price,
-                                    provider: GasOracleSource::Alloy,
-                                }),
-                                Err(e) => {
-                                    warn!("Alloy gas oracle failed: {}", e);
-                                    None
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            error!("Alloy gas oracle initialization failed: {}", e);
-                            None
+    }
+
+    #[tokio::test]
+    async fn test_multichain_tolerates_a_failing_chain() {
+        use axum::extract::Query as AxumQuery;
+        use axum::{routing::get, Router};
+        use std::collections::HashMap;
+
+        // Etherscan answers for Ethereum and reports an error for Polygon
+        let app = Router::new().route(
+            "/",
+            get(|AxumQuery(query): AxumQuery<HashMap<String, String>>| async move {
+                if query["chainid"] == "1" {
+                    axum::Json(serde_json::json!({
+                        "status": "1",
+                        "message": "OK",
+                        "result": {
+                            "LastBlock": "21000000",
+                            "SafeGasPrice": "1.5",
+                            "ProposeGasPrice": "2",
+                            "FastGasPrice": "3",
+                            "suggestBaseFee": "1.2",
+                            "gasUsedRatio": "0.5"
                         }
-                    }
-                }
-                None => {
-                    error!("Ethereum RPC URL not configured, Alloy provider unavailable");
-                    None
+                    }))
+                } else {
+                    axum::Json(serde_json::json!({ "status": "0", "message": "NOTOK", "result": "Chain not supported" }))
                 }
-            }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = Config {
+            etherscan_api_key: Some("test".to_string()),
+            etherscan_base_url: format!("http://{}/", listener.local_addr().unwrap()),
+            ..Config::default()
+        };
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let params = MultichainGasQueryParams {
+            chains: vec![ChainId::ETHEREUM, ChainId::POLYGON],
+            provider: GasOracleSource::Etherscan,
+        };
+        let Ok(Encoded { body, .. }) =
+            get_multichain_gas_estimates(State(AppState::new(config)), ResponseFormat::Json, Query(params)).await
+        else {
+            panic!("one chain answering should be enough");
+        };
+
+        assert_eq!(body.chains.keys().collect::<Vec<_>>(), ["ethereum"]);
+        assert_eq!(body.chains["ethereum"].gas_price.average, 2.0);
+        assert_eq!(body.errors.keys().collect::<Vec<_>>(), ["polygon"]);
+    }
+
+    #[tokio::test]
+    async fn test_multichain_fails_when_no_chain_answers() {
+        let config = Config {
+            etherscan_api_key: Some("test".to_string()),
+            etherscan_base_url: "http://127.0.0.1:1/".to_string(),
+            ..Config::default()
+        };
+        let params = MultichainGasQueryParams {
+            chains: vec![ChainId::ETHEREUM, ChainId::ARBITRUM],
+            provider: GasOracleSource::Etherscan,
+        };
+        let result = get_multichain_gas_estimates(State(AppState::new(config)), ResponseFormat::Json, Query(params)).await;
+        assert_eq!(result.into_response().status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_chain_id_param() {
+        assert_eq!(parse_query("").unwrap().chain_id, ChainId::ETHEREUM);
+        assert_eq!(parse_query("chain_id=8453").unwrap().chain_id, ChainId::BASE);
+        assert_eq!(parse_query("chain_id=arbitrum").unwrap().chain_id, ChainId::ARBITRUM);
+
+        for invalid in ["chain_id=0", "chain_id=12345", "chain_id=moon"] {
+            let Err((status, body)) = parse_query(invalid) else {
+                panic!("{} should be rejected", invalid);
+            };
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(body.contains("invalid chain id"));
         }
-    };
+    }
 
-    info!("Gas price fetching completed. Success: {}", gas_quote.is_some());
+    #[tokio::test]
+    async fn test_unconfigured_chain_rpc_names_its_variable() {
+        let config = Config {
+            ethereum_rpc_url: Some("http://127.0.0.1:1".to_string()),
+            ..Config::default()
+        };
+        let result = get_gas_estimates(
+            State(AppState::new(config)),
+            ResponseFormat::Json,
+            Query(GasPriceQueryParams {
+                provider: GasOracleSource::Alloy,
+                chain_id: ChainId::OPTIMISM,
+                ..Default::default()
+            }),
+        )
+        .await;
 
-    match gas_quote {
-        Some(quote) => Ok(Json(quote)),
-        None => Err(anyhow::anyhow!("Failed to fetch gas prices from provider").into()),
+        let err = result.unwrap_err();
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("OPTIMISM_RPC_URL"));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_blockscout_uses_the_chains_instance() {
+        let state = mock_blockscout_for(ChainId::BASE, plain_tiers(), Config::default()).await;
+        let query = |chain_id| GasPriceQueryParams { provider: GasOracleSource::Blockscout, chain_id, ..Default::default() };
+
+        let Ok(Encoded { body, .. }) =
+            get_gas_estimates(State(state.clone()), ResponseFormat::Json, Query(query(ChainId::BASE))).await
+        else {
+            panic!("Base has a Blockscout instance configured");
+        };
+        assert_eq!(body["gas_price"]["average"], 2.5);
+
+        // Other chains, mainnet included, have no instance of their own
+        for (chain, env_var) in [(ChainId::ARBITRUM, "ARBITRUM_BLOCKSCOUT_URL"), (ChainId::ETHEREUM, "BLOCKSCOUT_URL")] {
+            let response = get_gas_estimates(State(state.clone()), ResponseFormat::Json, Query(query(chain)))
+                .await
+                .into_response();
+            assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert!(String::from_utf8_lossy(&body).contains(env_var));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_tier_labels_are_rejected() {
+        let result = get_gas_estimates(
+            State(AppState::new(Config::default())),
+            ResponseFormat::Json,
+            Query(GasPriceQueryParams {
+                tier_labels: Some("slow,rapid".to_string()),
+                ..Default::default()
+            }),
+        )
+        .await;
+        assert_eq!(result.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -3,21 +3,99 @@
 //! This module handles health monitoring and service status endpoints
 //! for load balancers, monitoring systems, and operational readiness checks.
 
+use std::collections::BTreeMap;
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::core::config::AppState;
+
+/// Health check response body
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthResponse {
+    /// Human-readable service status
+    pub status: String,
+    /// Status of each known upstream provider, keyed by provider name
+    pub providers: BTreeMap<String, ProviderStatus>,
+    /// Moving average of how late the runtime wakes up timers, in milliseconds;
+    /// a rising value means the server is saturated
+    pub scheduling_lag_ms: f64,
+}
+
+/// Status of a single upstream provider
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProviderStatus {
+    /// Whether the provider has the configuration it needs to be used
+    pub configured: bool,
+    /// When the provider last returned data successfully (`null` if it never has)
+    pub last_success: Option<chrono::DateTime<chrono::Utc>>,
+}
+
 /// Health check endpoint for monitoring and load balancer probes.
 ///
-/// Returns a simple string response indicating the API service is running.
+/// Returns the service status along with each provider's last successful fetch
+/// and the runtime's scheduling lag.
 /// This endpoint can be used by:
 /// - Load balancers for health checks
-/// - Monitoring systems for uptime verification  
+/// - Monitoring systems for uptime verification
 /// - Container orchestration platforms for readiness probes
 #[utoipa::path(
     get,
     path = "/api/v1/health",
     tag = "health",
     responses(
-        (status = 200, description = "API is running", body = String)
+        (status = 200, description = "API is running", body = HealthResponse)
     )
 )]
-pub async fn health_check() -> &'static str {
-    "Boltzmann API is running"
-}
\ No newline at end of file
+#[tracing::instrument(skip_all)]
+pub async fn health_check(State(app_state): State<AppState>) -> Json<HealthResponse> {
+    let config = &app_state.config;
+    let known_providers = [
+        ("coinmarketcap", config.coinmarketcap_api_key.is_some()),
+        // CoinGecko works on the free tier without an API key
+        ("coingecko", true),
+        ("etherscan", config.etherscan_api_key.is_some()),
+        ("alloy", config.ethereum_rpc_url.is_some()),
+        ("blockscout", config.blockscout_url.is_some()),
+        ("1inch", config.oneinch_oracle_address.is_some() && config.ethereum_rpc_url.is_some()),
+    ];
+
+    let providers = known_providers
+        .into_iter()
+        .map(|(name, configured)| {
+            let status = ProviderStatus {
+                configured,
+                last_success: app_state.provider_health.last_success(name),
+            };
+            (name.to_string(), status)
+        })
+        .collect();
+
+    Json(HealthResponse {
+        status: "Boltzmann API is running".to_string(),
+        providers,
+        scheduling_lag_ms: app_state.scheduling_lag.average().as_secs_f64() * 1000.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::Config;
+
+    #[tokio::test]
+    async fn test_successful_fetch_updates_health() {
+        let app_state = AppState::new(Config::default());
+
+        let Json(before) = health_check(State(app_state.clone())).await;
+        assert!(before.providers["coingecko"].last_success.is_none());
+
+        app_state.provider_health.record_success("coingecko");
+
+        let Json(after) = health_check(State(app_state)).await;
+        assert!(after.providers["coingecko"].last_success.is_some());
+        assert!(after.providers["etherscan"].last_success.is_none());
+        assert!(!after.providers["etherscan"].configured);
+    }
+}
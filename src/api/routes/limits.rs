@@ -0,0 +1,70 @@
+//! Server limits endpoint.
+//!
+//! Reports the limits this instance enforces, as configured, so clients can
+//! size their requests and pace themselves instead of discovering the limits
+//! through `503`s.
+
+use std::collections::BTreeMap;
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::api::middleware::MAX_REQUEST_BODY_BYTES;
+use crate::core::config::AppState;
+use crate::domains::crypto::rate_limit::DEFAULT_MAX_WAIT;
+
+/// Limits enforced by this server
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LimitsResponse {
+    /// Requests handled at once; further requests are refused with `503` (`MAX_INFLIGHT_REQUESTS`)
+    #[schema(example = 512)]
+    pub max_inflight_requests: usize,
+    /// Largest accepted request body, in bytes
+    #[schema(example = 2097152)]
+    pub max_request_body_bytes: usize,
+    /// Largest upstream response body read, in bytes (`HTTP_MAX_RESPONSE_BYTES`)
+    pub max_upstream_response_bytes: usize,
+    /// Retries per upstream request on transient failures (`HTTP_MAX_RETRIES`)
+    pub max_upstream_retries: usize,
+    /// Retries shared by all requests before retrying stops (`HTTP_RETRY_BUDGET`)
+    pub upstream_retry_budget: usize,
+    /// Upstream requests per minute allowed per price provider, keyed by provider
+    /// name; providers without a quota are absent (`<PROVIDER>_RPM`)
+    pub provider_requests_per_minute: BTreeMap<String, u32>,
+    /// Longest a request waits for a provider's quota before failing with `503`, in seconds
+    pub provider_quota_max_wait_seconds: u64,
+    /// How long fetched quotes are reused, in seconds (`QUOTE_CACHE_TTL_SECONDS`, `0` when disabled)
+    pub quote_cache_ttl_seconds: u64,
+}
+
+/// Report the limits this server enforces.
+///
+/// Values reflect this instance's configuration, so replicas configured
+/// differently may answer differently.
+#[utoipa::path(
+    get,
+    path = "/api/v1/limits",
+    tag = "health",
+    responses(
+        (status = 200, description = "Configured server limits", body = LimitsResponse)
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn get_limits(State(app_state): State<AppState>) -> Json<LimitsResponse> {
+    let config = &app_state.config;
+    Json(LimitsResponse {
+        max_inflight_requests: config.max_inflight_requests.get(),
+        max_request_body_bytes: MAX_REQUEST_BODY_BYTES,
+        max_upstream_response_bytes: config.http.max_response_bytes,
+        max_upstream_retries: config.http.max_retries,
+        upstream_retry_budget: config.http.retry_budget,
+        provider_requests_per_minute: config
+            .provider_rpm
+            .iter()
+            .map(|(provider, rpm)| (provider.to_string(), rpm.get()))
+            .collect(),
+        provider_quota_max_wait_seconds: DEFAULT_MAX_WAIT.as_secs(),
+        quote_cache_ttl_seconds: config.quote_cache_ttl.as_secs(),
+    })
+}
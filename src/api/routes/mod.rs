@@ -3,23 +3,45 @@
 //! This module contains all the HTTP endpoint handlers for the Boltzmann API server.
 //! Routes are organized by functionality (price prices, gas estimates) and use shared app state.
 
+pub mod admin;
 pub mod crypto;
 pub mod gas;
 pub mod health;
+pub mod limits;
+pub mod ping;
 mod fees;
 mod subscriptions;
 
-use axum::{Router, routing::get};
+use axum::{Router, error_handling::HandleErrorLayer, extract::DefaultBodyLimit, middleware::from_fn_with_state, routing::{get, post}};
+use tower::ServiceBuilder;
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower::load_shed::LoadShedLayer;
 use crate::core::config::AppState;
 use crate::api::docs::swagger;
+use crate::api::middleware;
 
 /// Creates the main application router with all routes configured.
 ///
 /// This function sets up all the API endpoints using clear, RESTful patterns:
 /// - `/api/v1/price/prices` - Cryptocurrency price quotes
+/// - `/api/v1/crypto/depth` - Order-book depth
+/// - `/api/v1/crypto/market` - Market cap, volume and supply
+/// - `/api/v1/crypto/spread` - Price divergence between providers
+/// - `/api/v1/crypto/stream` - Live aggregate price (WebSocket)
 /// - `/api/v1/gas/prices` - Gas price estimates
+/// - `/api/v1/gas/prices/multichain` - Gas price estimates for several chains
+/// - `/api/v1/gas/cost/batch` - Fee estimate for a batch of operations (POST)
 /// - `/api/v1/health` - Health check endpoint
+/// - `/api/v1/ping` - Upstream latency
+/// - `/api/v1/limits` - Limits this server enforces
+/// - `/api/v1/admin/cache/flush` - Quote cache flush (POST, needs `ADMIN_API_KEY`)
 /// - `/docs` - Swagger UI documentation
+/// - `/api-docs/openapi.json`, `/api-docs/openapi.yaml` - OpenAPI document
+///
+/// Every JSON response is pretty-printed when `?pretty=true` is passed or
+/// `PRETTY_JSON` is set. Once `MAX_INFLIGHT_REQUESTS` requests are being
+/// handled, further requests are refused with `503` instead of queueing;
+/// the health check is exempt so orchestrators still see the server as up.
 ///
 /// # Arguments
 ///
@@ -29,11 +51,24 @@ use crate::api::docs::swagger;
 ///
 /// Configured Axum router ready to serve requests
 pub fn create_router(app_state: AppState) -> Router {
+    let load_shed = ServiceBuilder::new()
+        .layer(HandleErrorLayer::new(middleware::overloaded))
+        .layer(LoadShedLayer::new())
+        .layer(GlobalConcurrencyLimitLayer::new(app_state.config.max_inflight_requests.get()));
+
     Router::new()
         // API v1 routes
-        .route("/api/v1/health", get(health::health_check))
+        .route("/api/v1/ping", get(ping::ping))
+        .route("/api/v1/limits", get(limits::get_limits))
         .route("/api/v1/crypto/prices", get(crypto::get_crypto_prices))
+        .route("/api/v1/crypto/depth", get(crypto::get_market_depth))
+        .route("/api/v1/crypto/market", get(crypto::get_market_data))
+        .route("/api/v1/crypto/spread", get(crypto::get_price_spread))
+        .route("/api/v1/crypto/stream", get(crypto::stream_aggregate))
         .route("/api/v1/gas/prices", get(gas::get_gas_estimates))
+        .route("/api/v1/gas/prices/multichain", get(gas::get_multichain_gas_estimates))
+        .route("/api/v1/gas/cost/batch", post(gas::estimate_batch_cost))
+        .route("/api/v1/admin/cache/flush", post(admin::flush_cache))
         // Future endpoints (planned)
         // .route("/api/v1/gas/cost/estimates/native-transfer", get(gas::*))
         // .route("/api/v1/gas/cost/estimates/erc20-transfer", get(gas::*))
@@ -48,6 +83,12 @@ pub fn create_router(app_state: AppState) -> Router {
         // .route("/api/v1/subscriptions/gas/estimates", post(create_gas_estimates_subscription)
         // .route("/api/v1/subscriptions/gas/estimates", get(get_gas_estimates_subscription)
         // Documentation
+        .route("/api-docs/openapi.yaml", get(swagger::openapi_yaml))
         .merge(swagger::swagger_ui())
+        .layer(load_shed)
+        // Added after the load-shed layer, so health checks bypass it
+        .route("/api/v1/health", get(health::health_check))
+        .layer(DefaultBodyLimit::max(middleware::MAX_REQUEST_BODY_BYTES))
+        .layer(from_fn_with_state(app_state.clone(), middleware::pretty_json))
         .with_state(app_state)
 }
\ No newline at end of file
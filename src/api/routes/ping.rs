@@ -0,0 +1,176 @@
+//! Upstream latency endpoint.
+//!
+//! This module measures round-trip latency to each configured upstream provider
+//! with a cheap `HEAD` request, without fetching any price or gas data. It backs
+//! status pages that need to show which upstreams are reachable and how fast.
+
+use std::time::{Duration, Instant};
+
+use axum::{extract::State, Json};
+use reqwest::Client;
+use serde::Serialize;
+use tracing::{debug, instrument};
+use utoipa::ToSchema;
+
+use crate::core::config::{AppState, Config};
+use crate::core::errors::AppError;
+use crate::infrastructure::http::build_client;
+
+/// How long each upstream gets to answer before it is reported unreachable
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Upstream latency response body
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PingResponse {
+    /// One entry per configured upstream, fastest first; unreachable upstreams come last
+    pub upstreams: Vec<UpstreamPing>,
+}
+
+/// Round-trip result for a single upstream
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UpstreamPing {
+    /// Provider name
+    pub provider: String,
+    /// Whether the upstream answered within the timeout (any HTTP status counts)
+    pub reachable: bool,
+    /// Round-trip time in milliseconds (`null` when unreachable)
+    pub latency_ms: Option<f64>,
+}
+
+/// Upstreams to probe for the given configuration, as `(provider, url)` pairs.
+///
+/// Only configured providers are included; CoinGecko works without a key.
+fn upstream_targets(config: &Config) -> Vec<(String, String)> {
+    let mut targets = Vec::new();
+
+    if config.coinmarketcap_api_key.is_some() {
+        targets.push(("coinmarketcap".to_string(), "https://pro-api.coinmarketcap.com".to_string()));
+    }
+    targets.push(("coingecko".to_string(), "https://api.coingecko.com/api/v3/ping".to_string()));
+    if config.etherscan_api_key.is_some() {
+        targets.push(("etherscan".to_string(), "https://api.etherscan.io/v2/api".to_string()));
+    }
+    if let Some(rpc_url) = &config.ethereum_rpc_url {
+        targets.push(("alloy".to_string(), rpc_url.clone()));
+    }
+    if let Some(base_url) = &config.blockscout_url {
+        targets.push(("blockscout".to_string(), base_url.clone()));
+    }
+
+    targets
+}
+
+/// Sends a `HEAD` request to every target concurrently and reports their latency.
+///
+/// Results are ordered fastest first, with unreachable upstreams at the end.
+pub async fn ping_upstreams(
+    client: &Client,
+    targets: Vec<(String, String)>,
+    timeout: Duration,
+) -> Vec<UpstreamPing> {
+    let probes = targets.into_iter().map(|(provider, url)| async move {
+        let started = Instant::now();
+        let result = client.head(&url).timeout(timeout).send().await;
+        let elapsed = started.elapsed();
+
+        match result {
+            Ok(_) => UpstreamPing {
+                provider,
+                reachable: true,
+                latency_ms: Some(elapsed.as_secs_f64() * 1000.0),
+            },
+            Err(e) => {
+                debug!("Ping to {} failed: {}", provider, e);
+                UpstreamPing { provider, reachable: false, latency_ms: None }
+            }
+        }
+    });
+
+    let mut results = futures::future::join_all(probes).await;
+    results.sort_by(|a, b| match (a.latency_ms, b.latency_ms) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.provider.cmp(&b.provider),
+    });
+    results
+}
+
+/// Measure round-trip latency to each configured upstream.
+///
+/// Sends a lightweight `HEAD` request to every provider concurrently, with a short
+/// timeout each, and reports reachability and latency without fetching real data.
+#[utoipa::path(
+    get,
+    path = "/api/v1/ping",
+    tag = "health",
+    responses(
+        (status = 200, description = "Latency to each configured upstream", body = PingResponse)
+    )
+)]
+#[instrument(skip_all)]
+pub async fn ping(State(app_state): State<AppState>) -> Result<Json<PingResponse>, AppError> {
+    let client = build_client(&app_state.config.http)?;
+    let upstreams = ping_upstreams(&client, upstream_targets(&app_state.config), PING_TIMEOUT).await;
+
+    Ok(Json(PingResponse { upstreams }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::any, Router};
+
+    /// Starts a server that answers every request after `delay`
+    async fn slow_server(delay: Duration) -> String {
+        let app = Router::new().route(
+            "/",
+            any(move || async move {
+                tokio::time::sleep(delay).await;
+                "ok"
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_upstreams_are_ordered_by_latency() {
+        let slow = slow_server(Duration::from_millis(150)).await;
+        let fast = slow_server(Duration::ZERO).await;
+        let targets = vec![
+            ("slow".to_string(), slow),
+            ("down".to_string(), "http://127.0.0.1:1".to_string()),
+            ("fast".to_string(), fast),
+        ];
+
+        let client = Client::new();
+        let results = ping_upstreams(&client, targets, Duration::from_secs(2)).await;
+
+        let order: Vec<&str> = results.iter().map(|r| r.provider.as_str()).collect();
+        assert_eq!(order, ["fast", "slow", "down"]);
+        assert!(results[0].latency_ms.unwrap() < results[1].latency_ms.unwrap());
+        assert!(results[1].latency_ms.unwrap() >= 150.0);
+        assert!(!results[2].reachable);
+        assert!(results[2].latency_ms.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_slow_upstream_times_out() {
+        let slow = slow_server(Duration::from_millis(500)).await;
+
+        let client = Client::new();
+        let results = ping_upstreams(&client, vec![("slow".to_string(), slow)], Duration::from_millis(50)).await;
+
+        assert!(!results[0].reachable);
+    }
+
+    #[test]
+    fn test_only_configured_upstreams_are_probed() {
+        let targets = upstream_targets(&Config::default());
+        let names: Vec<&str> = targets.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["coingecko"]);
+    }
+}
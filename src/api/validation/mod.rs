@@ -0,0 +1,184 @@
+//! Query parameter parsing and validation helpers.
+//!
+//! Axum's `Query` extractor does not split comma-separated values, so list
+//! parameters like `?currencies=USD,EUR` go through the helpers in this module.
+//! Its rejections are also plain text, so handlers extract parameters with
+//! [`Query`] from here, which answers with the JSON error envelope instead.
+//! Request bodies are extracted with [`JsonBody`] for the same reason.
+
+use axum::extract::{FromRequest, FromRequestParts, Request};
+use axum::http::request::Parts;
+use serde::de::{DeserializeOwned, IntoDeserializer, value::StrDeserializer};
+use serde::{Deserialize, Deserializer};
+
+use crate::core::errors::{AppError, InvalidBody, InvalidQuery};
+
+/// Query string extractor whose rejections use the API's JSON error envelope.
+///
+/// A drop-in replacement for `axum::extract::Query`: a parameter that fails to
+/// deserialize (an unknown currency, a non-numeric amount) becomes a
+/// `400 Bad Request` with `{"error": {"message", "details", "status"}}` rather
+/// than axum's plain-text body.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        axum::extract::Query::<T>::try_from_uri(&parts.uri)
+            .map(|axum::extract::Query(params)| Query(params))
+            .map_err(|rejection| InvalidQuery::new(rejection.body_text()).into())
+    }
+}
+
+/// JSON request body extractor whose rejections use the API's JSON error envelope.
+///
+/// Like `axum::Json`, but a missing content type or a body that fails to
+/// deserialize becomes a `400 Bad Request` with the usual error envelope.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonBody<T>(pub T);
+
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        axum::Json::<T>::from_request(request, state)
+            .await
+            .map(|axum::Json(body)| JsonBody(body))
+            .map_err(|rejection| InvalidBody::new(rejection.body_text()).into())
+    }
+}
+
+/// Deserializes a comma-separated query value into a `Vec<T>`.
+///
+/// Whitespace around items is ignored, as are empty items, so `USD, EUR,` parses
+/// as `[USD, EUR]`. Items are matched as strings first (enum variants, text) and
+/// then as JSON scalars (numbers), which covers every list parameter we accept.
+///
+/// Use with `#[serde(default, deserialize_with = "comma_separated")]` so a missing
+/// parameter yields an empty list.
+///
+/// # Examples
+///
+/// ```rust
+/// use boltzmann::api::validation::comma_separated;
+/// use boltzmann::domains::crypto::Currency;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Params {
+///     #[serde(default, deserialize_with = "comma_separated")]
+///     currencies: Vec<Currency>,
+/// }
+///
+/// let params: Params = serde_json::from_str(r#"{"currencies": "USD, EUR"}"#).unwrap();
+/// assert_eq!(params.currencies, vec![Currency::USD, Currency::EUR]);
+/// ```
+pub fn comma_separated<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let raw = String::deserialize(deserializer)?;
+
+    raw.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let as_str: StrDeserializer<'_, serde::de::value::Error> = item.into_deserializer();
+            T::deserialize(as_str)
+                .or_else(|e| serde_json::from_str(item).map_err(|_| e))
+                .map_err(|e| serde::de::Error::custom(format!("invalid list item '{}': {}", item, e)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::crypto::Currency;
+    use axum::extract::Query;
+    use axum::http::Uri;
+
+    #[derive(Debug, Deserialize)]
+    struct ListParams {
+        #[serde(default, deserialize_with = "comma_separated")]
+        currencies: Vec<Currency>,
+        #[serde(default, deserialize_with = "comma_separated")]
+        amounts: Vec<f64>,
+    }
+
+    fn parse(query: &str) -> Result<ListParams, String> {
+        let uri: Uri = format!("http://localhost/test?{}", query).parse().unwrap();
+        Query::<ListParams>::try_from_uri(&uri)
+            .map(|Query(params)| params)
+            .map_err(|e| e.body_text())
+    }
+
+    #[tokio::test]
+    async fn test_malformed_query_is_json_error() {
+        use axum::{body::{to_bytes, Body}, http::{header, Request, StatusCode}, response::IntoResponse, routing::get, Router};
+        use tower::ServiceExt;
+
+        let app = Router::new().route(
+            "/",
+            get(|super::Query(params): super::Query<ListParams>| async move { params.amounts.len().to_string() }),
+        );
+
+        let ok = app.clone().oneshot(Request::get("/?amounts=1,2").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(ok.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(Request::get("/?amounts=lots").body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"]["status"], 400);
+        let details = body["error"]["details"].as_str().unwrap();
+        assert!(details.starts_with("Invalid query parameters:"), "{}", details);
+        assert!(details.contains("'lots'"), "{}", details);
+    }
+
+    #[test]
+    fn test_single_value() {
+        let params = parse("currencies=USD").unwrap();
+        assert_eq!(params.currencies, vec![Currency::USD]);
+        assert!(params.amounts.is_empty());
+    }
+
+    #[test]
+    fn test_multiple_values() {
+        let params = parse("currencies=USD,EUR,JPY&amounts=1,2.5").unwrap();
+        assert_eq!(params.currencies, vec![Currency::USD, Currency::EUR, Currency::JPY]);
+        assert_eq!(params.amounts, vec![1.0, 2.5]);
+    }
+
+    #[test]
+    fn test_trailing_commas_and_whitespace() {
+        let params = parse("currencies=%20USD%20,%20EUR,,&amounts=3,").unwrap();
+        assert_eq!(params.currencies, vec![Currency::USD, Currency::EUR]);
+        assert_eq!(params.amounts, vec![3.0]);
+    }
+
+    #[test]
+    fn test_missing_and_invalid_values() {
+        assert!(parse("").unwrap().currencies.is_empty());
+
+        let err = parse("currencies=USD,XYZ").unwrap_err();
+        assert!(err.contains("XYZ"));
+    }
+}
@@ -3,45 +3,485 @@
 //! This module provides centralized configuration management for the Boltzmann API server.
 //! All environment variables are loaded once at startup and stored in the app state.
 
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
+use std::str::FromStr;
+use std::sync::{Arc, Once};
+use std::time::Duration;
 use anyhow::{anyhow, Context, Result};
 
+use crate::core::errors::{GasDisabled, InvalidQuery};
+use crate::core::health::ProviderHealth;
+use crate::core::lag::SchedulingLag;
+use crate::core::single_flight::SingleFlight;
+use crate::core::tasks::{TaskRegistry, DEFAULT_SHUTDOWN_TIMEOUT};
+use crate::domains::crypto::{binance, coingecko, coinmarketcap, Coin, Currency, DynPriceProvider, ProviderSource};
+use crate::domains::gas::chain::ChainId;
+use crate::domains::gas::price::{etherscan, GasFloors, PercentileTiers, DEFAULT_BASE_FEE_HEADROOM, GasOracleSource, GasQuote, TierLabels};
+use crate::domains::crypto::aggregate::{AggregateStrategy, DEFAULT_MAX_DEVIATION_PERCENT, DEFAULT_MIN_PROVIDERS, DEFAULT_WEIGHT};
+use crate::domains::crypto::cache::{DynQuoteCache, MemoryQuoteCache, DEFAULT_QUOTE_CACHE_TTL};
+use crate::domains::crypto::feed::LatestPriceCache;
+use crate::domains::crypto::fx::FxRates;
+use crate::domains::timestamp::TimestampFormat;
+use crate::domains::crypto::rate_limit::{ProviderRateLimiter, RateLimitedPriceProvider};
+use crate::infrastructure::http::HttpClientConfig;
+use crate::infrastructure::http::retry::{RetryBudget, RetryPolicy};
+
+/// Default provider disagreement, in percent, flagged by `/crypto/spread`
+pub const DEFAULT_SPREAD_THRESHOLD_PERCENT: f64 = 1.0;
+
+/// Default cap on requests handled at once before new ones are refused
+pub const DEFAULT_MAX_INFLIGHT_REQUESTS: NonZeroUsize = NonZeroUsize::new(512).unwrap();
+
 /// Application configuration loaded from environment variables
 #[derive(Debug, Clone)]
 pub struct Config {
-    /// CoinMarketCap API key
+    /// CoinMarketCap API key; the sandbox key when `coinmarketcap_sandbox` is set
     pub coinmarketcap_api_key: Option<String>,
+    /// Whether CoinMarketCap requests go to its sandbox (`COINMARKETCAP_SANDBOX`)
+    pub coinmarketcap_sandbox: bool,
     /// CoinGecko API key  
     pub coingecko_api_key: Option<String>,
     /// Etherscan API key
     pub etherscan_api_key: Option<String>,
     /// Ethereum RPC URL (for alloy provider)
     pub ethereum_rpc_url: Option<String>,
+    /// RPC URLs for other known chains, from each chain's `<CHAIN>_RPC_URL`
+    pub chain_rpc_urls: BTreeMap<ChainId, String>,
+    /// Blockscout instance base URL for Ethereum mainnet (for blockscout provider)
+    pub blockscout_url: Option<String>,
+    /// Blockscout instance URLs for other known chains, from each chain's `<CHAIN>_BLOCKSCOUT_URL`
+    pub chain_blockscout_urls: BTreeMap<ChainId, String>,
+    /// Address of 1inch's Spot Price Aggregator on Ethereum mainnet (`ONEINCH_ORACLE_ADDRESS`);
+    /// the 1inch provider also needs `ETHEREUM_RPC_URL`
+    pub oneinch_oracle_address: Option<String>,
+    /// CoinMarketCap API host (`COINMARKETCAP_BASE_URL`), overridable for mirrors and tests
+    pub coinmarketcap_base_url: String,
+    /// CoinGecko API root (`COINGECKO_BASE_URL`), overridable for mirrors and tests
+    pub coingecko_base_url: String,
+    /// Etherscan API endpoint (`ETHERSCAN_BASE_URL`), overridable for mirrors and tests
+    pub etherscan_base_url: String,
+    /// Binance REST API root (`BINANCE_BASE_URL`), overridable for mirrors and tests
+    pub binance_base_url: String,
     /// Server host address
     pub host: String,
     /// Server port
     pub port: u16,
+    /// Fail a price request when the provider omits any requested currency
+    pub strict_currencies: bool,
+    /// Outbound HTTP client settings (proxy)
+    pub http: HttpClientConfig,
+    /// Include CoinGecko's attribution in responses containing CoinGecko data
+    pub coingecko_attribution: bool,
+    /// Stream real-time prices from Binance's WebSocket API
+    pub binance_stream: bool,
+    /// Default names for the gas tiers in responses (`GAS_TIER_LABELS`)
+    pub gas_tier_labels: TierLabels,
+    /// Per-chain overrides of the Alloy oracle's gas floors, from each chain's `<CHAIN>_GAS_FLOORS`
+    pub gas_floors: BTreeMap<ChainId, GasFloors>,
+    /// Extra named gas tiers the Alloy oracle prices at given reward percentiles
+    /// (`ALLOY_PERCENTILE_TIERS`, e.g. `economy=p20,standard=p50,priority=p90`)
+    pub alloy_percentile_tiers: PercentileTiers,
+    /// Multiple of the base fee covered by EIP-1559 max fees (`GAS_BASE_FEE_HEADROOM`)
+    pub gas_base_fee_headroom: f64,
+    /// Price providers called only when every other provider fails (`FALLBACK_PROVIDERS`)
+    pub fallback_providers: Vec<ProviderSource>,
+    /// Price providers whose quotes go into the aggregate price (`AGGREGATE_PROVIDERS`);
+    /// every provider when unset. Others are still returned as their own quotes
+    pub aggregate_providers: Option<Vec<ProviderSource>>,
+    /// How provider prices are combined into the aggregate (`AGGREGATE_STRATEGY`)
+    pub aggregate_strategy: AggregateStrategy,
+    /// Fewest trusted providers that must answer for a currency to get an aggregate
+    /// (`AGGREGATE_MIN_PROVIDERS`)
+    pub aggregate_min_providers: NonZeroUsize,
+    /// How far, in percent, a trusted quote may be from the pair's median before it
+    /// is left out of the aggregate (`AGGREGATE_MAX_DEVIATION_PERCENT`, `0` keeps every quote)
+    pub aggregate_max_deviation_percent: Option<f64>,
+    /// Trust in each price provider, from 0 to 1, from each provider's
+    /// `PROVIDER_WEIGHT_<PROVIDER>` (e.g. `PROVIDER_WEIGHT_BINANCE`); 1 when unset
+    pub provider_weights: HashMap<ProviderSource, f64>,
+    /// Currencies fetched for `currency=all` (`ALL_CURRENCIES`)
+    pub all_currencies: Vec<Currency>,
+    /// Reference rates per USD (`FX_RATES`) for converting a provider's USD price
+    /// into currencies it does not price; no conversion when empty
+    pub fx_rates: Arc<FxRates>,
+    /// Spread between providers, in percent, above which `/crypto/spread` flags disagreement
+    /// (`SPREAD_THRESHOLD_PERCENT`)
+    pub spread_threshold_percent: f64,
+    /// How quote and gas price timestamps are serialized (`TIMESTAMP_FORMAT`)
+    pub timestamp_format: TimestampFormat,
+    /// Compute quote totals with exact decimal arithmetic (`DECIMAL_TOTALS`)
+    pub decimal_totals: bool,
+    /// Enable debugging aids such as `include_raw` (`DEBUG`)
+    pub debug: bool,
+    /// Running in production (`PRODUCTION`); disables every debugging aid
+    pub production: bool,
+    /// Pretty-print every JSON response body (`PRETTY_JSON`)
+    pub pretty_json: bool,
+    /// How long shutdown waits for background tasks before aborting them (`SHUTDOWN_TIMEOUT_SECONDS`)
+    pub shutdown_timeout: Duration,
+    /// Redis server for a quote cache shared across replicas (`REDIS_URL`, requires the
+    /// `redis` feature); quotes are cached in memory when unset
+    pub redis_url: Option<String>,
+    /// How long fetched quotes are reused (`QUOTE_CACHE_TTL_SECONDS`, `0` disables caching)
+    pub quote_cache_ttl: Duration,
+    /// Currencies whose ETH quotes are refreshed into the quote cache every TTL
+    /// in the background (`WARM_CACHE_CURRENCIES`); none when empty
+    pub warm_cache_currencies: Vec<Currency>,
+    /// Currencies every cache miss also fetches from providers that price several
+    /// currencies per request, so later requests for them are hits
+    /// (`CACHE_FETCH_CURRENCIES`, `all` for `ALL_CURRENCIES`); none when empty
+    pub cache_fetch_currencies: Vec<Currency>,
+    /// Providers whose cache misses fetch `cache_fetch_currencies` too
+    /// (`CACHE_FETCH_PROVIDERS`, defaults to CoinGecko; CoinMarketCap's basic plan
+    /// rejects requests for several currencies)
+    pub cache_fetch_providers: Vec<ProviderSource>,
+    /// Probe every configured RPC endpoint once at startup (`STARTUP_HEALTHCHECK`)
+    pub startup_healthcheck: bool,
+    /// Serve the gas endpoints; `false` only when `REQUIRE_GAS=false` and no gas oracle
+    /// is configured, in which case they answer `503`
+    pub gas_enabled: bool,
+    /// Coins the API serves (`ALLOWED_COINS`); every supported coin when unset
+    pub allowed_coins: Option<Vec<Coin>>,
+    /// Currencies the API serves (`ALLOWED_CURRENCIES`); every supported currency when unset
+    pub allowed_currencies: Option<Vec<Currency>>,
+    /// Upstream requests per minute allowed per price provider, from each provider's
+    /// `<PROVIDER>_RPM` (e.g. `COINGECKO_RPM`); unlimited when unset
+    pub provider_rpm: HashMap<ProviderSource, NonZeroU32>,
+    /// Requests handled at once before new ones are refused with `503`
+    /// (`MAX_INFLIGHT_REQUESTS`); health checks are never refused
+    pub max_inflight_requests: NonZeroUsize,
+    /// Provider calls per verbose log of a call's URL and parsed values
+    /// (`PROVIDER_LOG_SAMPLE`); errors are always logged
+    pub provider_log_sample: NonZeroU64,
+    /// Key required by the admin endpoints as `Authorization: Bearer <key>` (`ADMIN_API_KEY`);
+    /// the admin endpoints answer `501` when unset
+    pub admin_api_key: Option<String>,
+}
+
+impl Default for Config {
+    /// Configuration with no providers and the default bind address.
+    ///
+    /// Useful for tests and embedding; `from_env` is the normal entry point.
+    fn default() -> Self {
+        Self {
+            coinmarketcap_api_key: None,
+            coinmarketcap_sandbox: false,
+            coingecko_api_key: None,
+            etherscan_api_key: None,
+            ethereum_rpc_url: None,
+            chain_rpc_urls: BTreeMap::new(),
+            blockscout_url: None,
+            chain_blockscout_urls: BTreeMap::new(),
+            oneinch_oracle_address: None,
+            coinmarketcap_base_url: coinmarketcap::API_URL.to_string(),
+            coingecko_base_url: coingecko::API_URL.to_string(),
+            etherscan_base_url: etherscan::API_URL.to_string(),
+            binance_base_url: binance::API_URL.to_string(),
+            host: "127.0.0.1".to_string(),
+            port: 3000,
+            strict_currencies: false,
+            http: HttpClientConfig::default(),
+            coingecko_attribution: true,
+            binance_stream: false,
+            gas_tier_labels: TierLabels::default(),
+            gas_floors: BTreeMap::new(),
+            alloy_percentile_tiers: PercentileTiers::default(),
+            gas_base_fee_headroom: DEFAULT_BASE_FEE_HEADROOM,
+            fallback_providers: vec![ProviderSource::CoinMarketCap],
+            aggregate_providers: None,
+            aggregate_strategy: AggregateStrategy::default(),
+            aggregate_min_providers: DEFAULT_MIN_PROVIDERS,
+            aggregate_max_deviation_percent: Some(DEFAULT_MAX_DEVIATION_PERCENT),
+            provider_weights: HashMap::new(),
+            all_currencies: Currency::all().to_vec(),
+            fx_rates: Arc::default(),
+            spread_threshold_percent: DEFAULT_SPREAD_THRESHOLD_PERCENT,
+            timestamp_format: TimestampFormat::default(),
+            decimal_totals: false,
+            debug: false,
+            production: false,
+            pretty_json: false,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            redis_url: None,
+            quote_cache_ttl: DEFAULT_QUOTE_CACHE_TTL,
+            warm_cache_currencies: Vec::new(),
+            cache_fetch_currencies: Vec::new(),
+            cache_fetch_providers: vec![ProviderSource::CoinGecko],
+            startup_healthcheck: false,
+            gas_enabled: true,
+            allowed_coins: None,
+            allowed_currencies: None,
+            provider_rpm: HashMap::new(),
+            max_inflight_requests: DEFAULT_MAX_INFLIGHT_REQUESTS,
+            provider_log_sample: NonZeroU64::MIN,
+            admin_api_key: None,
+        }
+    }
+}
+
+/// Environment file read by [`load_dotenv`], looked up from the working directory upwards
+const DOTENV_FILE: &str = ".env";
+
+/// Loads `.env` into the process environment, once per process.
+///
+/// Variables already set in the environment always win over `.env` values,
+/// and later calls do nothing, so the file cannot be re-read halfway through
+/// startup. Called by `main` before the runtime starts and by
+/// [`Config::from_env`] for embedders.
+pub fn load_dotenv() {
+    static LOADED: Once = Once::new();
+    LOADED.call_once(|| load_env_file(DOTENV_FILE));
+}
+
+/// Loads `filename` without overriding variables that are already set.
+///
+/// A missing file is fine; an unreadable or malformed one is reported and skipped.
+fn load_env_file(filename: &str) {
+    match dotenvy::from_filename(filename) {
+        Ok(_) => {}
+        Err(e) if e.not_found() => {}
+        Err(e) => eprintln!("⚠️  Ignoring {}: {}", filename, e),
+    }
 }
 
 impl Config {
     /// Load configuration from environment variables
     ///
-    /// This should be called once at application startup after loading .env files.
-    /// Missing optional API keys will result in None values, which providers can handle gracefully.
+    /// Loads `.env` first if [`load_dotenv`] has not already; variables set in
+    /// the environment take precedence over it. Missing optional API keys will
+    /// result in None values, which providers can handle gracefully.
     pub fn from_env() -> Result<Self> {
-        // Load dotenv first
-        dotenvy::dotenv().ok();
+        load_dotenv();
 
-        let coinmarketcap_api_key = std::env::var("COINMARKETCAP_API_KEY").ok();
+        let coinmarketcap_sandbox = env_flag("COINMARKETCAP_SANDBOX", false);
+        let (coinmarketcap_api_key, coinmarketcap_base_url) =
+            coinmarketcap_upstream(coinmarketcap_sandbox, |name| std::env::var(name).ok());
         let coingecko_api_key = std::env::var("COINGECKO_API_KEY").ok();
         let etherscan_api_key = std::env::var("ETHERSCAN_API_KEY").ok();
         let ethereum_rpc_url = std::env::var("ETHEREUM_RPC_URL").ok();
+        let blockscout_url = std::env::var("BLOCKSCOUT_URL").ok();
+        let oneinch_oracle_address = std::env::var("ONEINCH_ORACLE_ADDRESS").ok().filter(|address| !address.trim().is_empty());
+        let admin_api_key = std::env::var("ADMIN_API_KEY").ok().filter(|key| !key.trim().is_empty());
+        let coingecko_base_url = std::env::var("COINGECKO_BASE_URL")
+            .unwrap_or_else(|_| coingecko::API_URL.to_string());
+        let etherscan_base_url = std::env::var("ETHERSCAN_BASE_URL")
+            .unwrap_or_else(|_| etherscan::API_URL.to_string());
+        let binance_base_url = std::env::var("BINANCE_BASE_URL")
+            .unwrap_or_else(|_| binance::API_URL.to_string());
+        let chain_rpc_urls = ChainId::all()
+            .into_iter()
+            .filter(|chain| *chain != ChainId::ETHEREUM)
+            .filter_map(|chain| {
+                let url = std::env::var(chain.rpc_env_var()?).ok()?;
+                Some((chain, url))
+            })
+            .collect();
+        let chain_blockscout_urls = ChainId::all()
+            .into_iter()
+            .filter(|chain| *chain != ChainId::ETHEREUM)
+            .filter_map(|chain| {
+                let url = std::env::var(chain.blockscout_env_var()?).ok()?;
+                Some((chain, url))
+            })
+            .collect();
 
         let host = std::env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
         let port = std::env::var("PORT")
             .unwrap_or_else(|_| "3000".to_string())
             .parse::<u16>()
             .context("Invalid port number")?;
+        let strict_currencies = env_flag("STRICT_CURRENCIES", false);
+        let coingecko_attribution = env_flag("COINGECKO_ATTRIBUTION", true);
+        let binance_stream = env_flag("BINANCE_STREAM", false);
+        let decimal_totals = env_flag("DECIMAL_TOTALS", false);
+        let debug = env_flag("DEBUG", false);
+        let production = env_flag("PRODUCTION", false);
+        let pretty_json = env_flag("PRETTY_JSON", false);
+        let startup_healthcheck = env_flag("STARTUP_HEALTHCHECK", false);
+        let require_gas = env_flag("REQUIRE_GAS", true);
+        let allowed_coins = env_list("ALLOWED_COINS")?;
+        let allowed_currencies = env_list("ALLOWED_CURRENCIES")?;
+        let provider_rpm = [ProviderSource::CoinGecko, ProviderSource::CoinMarketCap, ProviderSource::Binance]
+            .into_iter()
+            .filter_map(|provider| {
+                let env_var = format!("{}_RPM", provider.to_string().to_uppercase());
+                let rpm = std::env::var(&env_var).ok().filter(|rpm| !rpm.trim().is_empty())?;
+                let rpm = rpm
+                    .trim()
+                    .parse::<NonZeroU32>()
+                    .with_context(|| format!("Invalid {}: expected a positive number of requests per minute", env_var));
+                Some(rpm.map(|rpm| (provider, rpm)))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        let http = HttpClientConfig::from_env()?;
+        let gas_tier_labels = match std::env::var("GAS_TIER_LABELS") {
+            Ok(labels) if !labels.is_empty() => labels.parse().context("Invalid GAS_TIER_LABELS")?,
+            _ => TierLabels::default(),
+        };
+        let gas_floors = ChainId::all()
+            .into_iter()
+            .filter_map(|chain| {
+                let env_var = chain.gas_floors_env_var()?;
+                let floors = std::env::var(env_var).ok().filter(|floors| !floors.trim().is_empty())?;
+                Some(floors.parse().with_context(|| format!("Invalid {}", env_var)).map(|floors| (chain, floors)))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        let alloy_percentile_tiers = match std::env::var("ALLOY_PERCENTILE_TIERS") {
+            Ok(tiers) if !tiers.trim().is_empty() => tiers.parse().context("Invalid ALLOY_PERCENTILE_TIERS")?,
+            _ => PercentileTiers::default(),
+        };
+        let gas_base_fee_headroom = match std::env::var("GAS_BASE_FEE_HEADROOM") {
+            Ok(headroom) if !headroom.trim().is_empty() => headroom
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|headroom| headroom.is_finite() && *headroom >= 1.0)
+                .with_context(|| format!("Invalid GAS_BASE_FEE_HEADROOM: expected a multiplier of at least 1, got {}", headroom))?,
+            _ => DEFAULT_BASE_FEE_HEADROOM,
+        };
+        let fallback_providers = match std::env::var("FALLBACK_PROVIDERS") {
+            Ok(providers) => providers
+                .split(',')
+                .map(str::trim)
+                .filter(|provider| !provider.is_empty())
+                .map(str::parse)
+                .collect::<Result<Vec<ProviderSource>>>()
+                .context("Invalid FALLBACK_PROVIDERS")?,
+            Err(_) => vec![ProviderSource::CoinMarketCap],
+        };
+        let aggregate_providers = env_list("AGGREGATE_PROVIDERS")?;
+        let aggregate_strategy = match std::env::var("AGGREGATE_STRATEGY") {
+            Ok(strategy) if !strategy.trim().is_empty() => strategy.parse().context("Invalid AGGREGATE_STRATEGY")?,
+            _ => AggregateStrategy::default(),
+        };
+        let timestamp_format = match std::env::var("TIMESTAMP_FORMAT") {
+            Ok(format) if !format.trim().is_empty() => format.parse().context("Invalid TIMESTAMP_FORMAT")?,
+            _ => TimestampFormat::default(),
+        };
+        let provider_log_sample = match std::env::var("PROVIDER_LOG_SAMPLE") {
+            Ok(rate) if !rate.trim().is_empty() => rate
+                .trim()
+                .parse()
+                .context("Invalid PROVIDER_LOG_SAMPLE: expected a positive number of calls")?,
+            _ => NonZeroU64::MIN,
+        };
+        let aggregate_min_providers = match std::env::var("AGGREGATE_MIN_PROVIDERS") {
+            Ok(min) if !min.trim().is_empty() => min
+                .trim()
+                .parse()
+                .context("Invalid AGGREGATE_MIN_PROVIDERS: expected a positive number of providers")?,
+            _ => DEFAULT_MIN_PROVIDERS,
+        };
+        let aggregate_max_deviation_percent = match std::env::var("AGGREGATE_MAX_DEVIATION_PERCENT") {
+            Ok(percent) if !percent.trim().is_empty() => {
+                let percent = percent
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|percent| percent.is_finite() && *percent >= 0.0)
+                    .with_context(|| format!("Invalid AGGREGATE_MAX_DEVIATION_PERCENT: expected a non-negative percentage, got {}", percent))?;
+                (percent > 0.0).then_some(percent)
+            }
+            _ => Some(DEFAULT_MAX_DEVIATION_PERCENT),
+        };
+        let provider_weights = [
+            ProviderSource::CoinGecko,
+            ProviderSource::CoinMarketCap,
+            ProviderSource::Binance,
+            ProviderSource::OneInch,
+        ]
+        .into_iter()
+        .filter_map(|provider| {
+            let env_var = format!("PROVIDER_WEIGHT_{}", provider.to_string().to_uppercase());
+            let weight = std::env::var(&env_var).ok().filter(|weight| !weight.trim().is_empty())?;
+            let weight = weight
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|weight| (0.0..=1.0).contains(weight))
+                .with_context(|| format!("Invalid {}: expected a weight from 0 to 1, got {}", env_var, weight));
+            Some(weight.map(|weight| (provider, weight)))
+        })
+        .collect::<Result<HashMap<_, _>>>()?;
+        let all_currencies = match std::env::var("ALL_CURRENCIES") {
+            Ok(currencies) if !currencies.trim().is_empty() => currencies
+                .split(',')
+                .map(str::trim)
+                .filter(|currency| !currency.is_empty())
+                .map(str::parse)
+                .collect::<Result<Vec<Currency>>>()
+                .context("Invalid ALL_CURRENCIES")?,
+            _ => Currency::all().to_vec(),
+        };
+        let warm_cache_currencies = match std::env::var("WARM_CACHE_CURRENCIES") {
+            Ok(currencies) if !currencies.trim().is_empty() => currencies
+                .split(',')
+                .map(str::trim)
+                .filter(|currency| !currency.is_empty())
+                .map(str::parse)
+                .collect::<Result<Vec<Currency>>>()
+                .context("Invalid WARM_CACHE_CURRENCIES")?,
+            _ => Vec::new(),
+        };
+        let cache_fetch_currencies = match std::env::var("CACHE_FETCH_CURRENCIES") {
+            Ok(currencies) if currencies.trim().eq_ignore_ascii_case("all") => all_currencies.clone(),
+            Ok(currencies) if !currencies.trim().is_empty() => currencies
+                .split(',')
+                .map(str::trim)
+                .filter(|currency| !currency.is_empty())
+                .map(str::parse)
+                .collect::<Result<Vec<Currency>>>()
+                .context("Invalid CACHE_FETCH_CURRENCIES")?,
+            _ => Vec::new(),
+        };
+        let cache_fetch_providers = match std::env::var("CACHE_FETCH_PROVIDERS") {
+            Ok(providers) => providers
+                .split(',')
+                .map(str::trim)
+                .filter(|provider| !provider.is_empty())
+                .map(str::parse)
+                .collect::<Result<Vec<ProviderSource>>>()
+                .context("Invalid CACHE_FETCH_PROVIDERS")?,
+            Err(_) => vec![ProviderSource::CoinGecko],
+        };
+        let fx_rates = match std::env::var("FX_RATES") {
+            Ok(rates) if !rates.trim().is_empty() => rates.parse().context("Invalid FX_RATES")?,
+            _ => FxRates::default(),
+        };
+        let spread_threshold_percent = match std::env::var("SPREAD_THRESHOLD_PERCENT") {
+            Ok(percent) if !percent.trim().is_empty() => percent
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|percent| percent.is_finite() && *percent >= 0.0)
+                .with_context(|| format!("Invalid SPREAD_THRESHOLD_PERCENT: {}", percent))?,
+            _ => DEFAULT_SPREAD_THRESHOLD_PERCENT,
+        };
+        let shutdown_timeout = match std::env::var("SHUTDOWN_TIMEOUT_SECONDS") {
+            Ok(seconds) if !seconds.trim().is_empty() => seconds
+                .trim()
+                .parse()
+                .map(Duration::from_secs)
+                .context("Invalid SHUTDOWN_TIMEOUT_SECONDS")?,
+            _ => DEFAULT_SHUTDOWN_TIMEOUT,
+        };
+        let redis_url = std::env::var("REDIS_URL").ok().filter(|url| !url.trim().is_empty());
+        let quote_cache_ttl = match std::env::var("QUOTE_CACHE_TTL_SECONDS") {
+            Ok(seconds) if !seconds.trim().is_empty() => seconds
+                .trim()
+                .parse()
+                .map(Duration::from_secs)
+                .context("Invalid QUOTE_CACHE_TTL_SECONDS")?,
+            _ => DEFAULT_QUOTE_CACHE_TTL,
+        };
+        let max_inflight_requests = match std::env::var("MAX_INFLIGHT_REQUESTS") {
+            Ok(max) if !max.trim().is_empty() => max
+                .trim()
+                .parse()
+                .context("Invalid MAX_INFLIGHT_REQUESTS: expected a positive number of requests")?,
+            _ => DEFAULT_MAX_INFLIGHT_REQUESTS,
+        };
 
         // Validate required configuration
         // At least one price provider is required
@@ -55,51 +495,433 @@ impl Config {
             ));
         }
 
-        // Ethereum RPC URL is required for gas functionality
-        if ethereum_rpc_url.is_none() {
-            return Err(anyhow!(
-                "❌ Ethereum RPC URL required!\n\n\
-                The ETHEREUM_RPC_URL environment variable must be set for gas price functionality.\n\
-                You can use:\n\
-                • Infura: https://infura.io/\n\
-                • Alchemy: https://www.alchemy.com/\n\
-                • Or any Ethereum JSON-RPC endpoint\n\n\
-                Add ETHEREUM_RPC_URL to your .env file to continue."
-            ));
-        }
+        // Ethereum RPC URL is required for gas functionality unless gas may be disabled
+        let gas_enabled = gas_enabled(
+            require_gas,
+            ethereum_rpc_url.is_some(),
+            etherscan_api_key.is_some() || blockscout_url.is_some(),
+        )?;
 
         println!("🔧 Configuration loaded:");
         println!("   Host: {}", host);
         println!("   Port: {}", port);
-        println!("   CoinMarketCap API: {}", if coinmarketcap_api_key.is_some() { "✅" } else { "❌" });
+        println!(
+            "   CoinMarketCap API: {}{}",
+            if coinmarketcap_api_key.is_some() { "✅" } else { "❌" },
+            if coinmarketcap_sandbox { " (sandbox)" } else { "" }
+        );
         println!("   CoinGecko API: {}", if coingecko_api_key.is_some() { "✅" } else { "❌" });
         println!("   Etherscan API: {}", if etherscan_api_key.is_some() { "✅" } else { "❌" });
         println!("   Ethereum RPC: {}", if ethereum_rpc_url.is_some() { "✅" } else { "❌" });
+        println!("   Blockscout: {}", if blockscout_url.is_some() { "✅" } else { "❌" });
+        println!("   1inch oracle: {}", if oneinch_oracle_address.is_some() { "✅" } else { "❌" });
+        println!("   Quote cache: {}", if redis_url.is_some() { "Redis" } else { "memory" });
+        if !gas_enabled {
+            println!("   ⚠️  Gas endpoints disabled: no gas oracle configured (REQUIRE_GAS=false)");
+        }
 
         Ok(Config {
             coinmarketcap_api_key,
+            coinmarketcap_sandbox,
             coingecko_api_key,
             etherscan_api_key,
             ethereum_rpc_url,
+            chain_rpc_urls,
+            blockscout_url,
+            chain_blockscout_urls,
+            oneinch_oracle_address,
+            coinmarketcap_base_url,
+            coingecko_base_url,
+            etherscan_base_url,
+            binance_base_url,
             host,
             port,
+            strict_currencies,
+            http,
+            coingecko_attribution,
+            binance_stream,
+            gas_tier_labels,
+            gas_floors,
+            alloy_percentile_tiers,
+            gas_base_fee_headroom,
+            fallback_providers,
+            aggregate_providers,
+            aggregate_strategy,
+            aggregate_min_providers,
+            aggregate_max_deviation_percent,
+            provider_weights,
+            all_currencies,
+            fx_rates: Arc::new(fx_rates),
+            spread_threshold_percent,
+            timestamp_format,
+            decimal_totals,
+            debug,
+            production,
+            pretty_json,
+            shutdown_timeout,
+            redis_url,
+            quote_cache_ttl,
+            warm_cache_currencies,
+            cache_fetch_currencies,
+            cache_fetch_providers,
+            startup_healthcheck,
+            gas_enabled,
+            allowed_coins,
+            allowed_currencies,
+            provider_rpm,
+            max_inflight_requests,
+            provider_log_sample,
+            admin_api_key,
         })
     }
+
+    /// RPC URL configured for `chain`, if any.
+    ///
+    /// Ethereum mainnet uses `ETHEREUM_RPC_URL`; other chains use their own variable.
+    pub fn rpc_url(&self, chain: ChainId) -> Option<&str> {
+        if chain == ChainId::ETHEREUM {
+            self.ethereum_rpc_url.as_deref()
+        } else {
+            self.chain_rpc_urls.get(&chain).map(String::as_str)
+        }
+    }
+
+    /// Blockscout instance URL configured for `chain`, if any.
+    ///
+    /// Ethereum mainnet uses `BLOCKSCOUT_URL`; other chains use their own variable.
+    pub fn blockscout_url_for(&self, chain: ChainId) -> Option<&str> {
+        if chain == ChainId::ETHEREUM {
+            self.blockscout_url.as_deref()
+        } else {
+            self.chain_blockscout_urls.get(&chain).map(String::as_str)
+        }
+    }
+
+    /// Configured trust in `provider`, from 0 to 1; 1 when `PROVIDER_WEIGHT_<PROVIDER>` is unset.
+    pub fn provider_weight(&self, provider: ProviderSource) -> f64 {
+        self.provider_weights.get(&provider).copied().unwrap_or(DEFAULT_WEIGHT)
+    }
+
+    /// Currencies fetched for `currency=all`: `ALL_CURRENCIES`, less any not allowed.
+    pub fn all_allowed_currencies(&self) -> Vec<Currency> {
+        self.all_currencies
+            .iter()
+            .copied()
+            .filter(|currency| self.allowed_currencies.as_ref().is_none_or(|allowed| allowed.contains(currency)))
+            .collect()
+    }
+
+    /// Rejects requests for a coin or currency outside `ALLOWED_COINS` / `ALLOWED_CURRENCIES`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidQuery`] naming the first disallowed coin or currency.
+    pub fn ensure_allowed(&self, coin: Coin, currencies: &[Currency]) -> Result<()> {
+        if let Some(allowed) = &self.allowed_coins
+            && !allowed.contains(&coin)
+        {
+            return Err(InvalidQuery::new(format!("coin {} is not available on this server", coin)).into());
+        }
+        if let Some(allowed) = &self.allowed_currencies
+            && let Some(currency) = currencies.iter().find(|currency| !allowed.contains(currency))
+        {
+            return Err(InvalidQuery::new(format!("currency {} is not available on this server", currency)).into());
+        }
+        Ok(())
+    }
+
+    /// Rejects gas requests when the server was started without a gas oracle.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GasDisabled`] unless `gas_enabled` is set.
+    pub fn ensure_gas_enabled(&self) -> Result<()> {
+        if !self.gas_enabled {
+            return Err(GasDisabled.into());
+        }
+        Ok(())
+    }
+
+    /// Every configured RPC endpoint with the chain it is configured for, in chain id order.
+    pub fn rpc_urls(&self) -> Vec<(ChainId, &str)> {
+        ChainId::all().into_iter().filter_map(|chain| Some((chain, self.rpc_url(chain)?))).collect()
+    }
+
+    /// Gas floors for `chain`: its `<CHAIN>_GAS_FLOORS` override, or the chain's defaults.
+    pub fn gas_floors(&self, chain: ChainId) -> GasFloors {
+        self.gas_floors.get(&chain).copied().unwrap_or_else(|| GasFloors::for_chain(chain))
+    }
+
+    /// Whether debugging aids (`include_raw`, the `X-Cache-Key` header) are enabled.
+    ///
+    /// Requires `DEBUG` and is always off when `PRODUCTION` is set.
+    pub fn debug_aids_allowed(&self) -> bool {
+        self.debug && !self.production
+    }
 }
 
+/// CoinMarketCap API key and base URL, reading variables through `var`.
+///
+/// In sandbox mode the key is `COINMARKETCAP_SANDBOX_API_KEY`, or CoinMarketCap's
+/// public sandbox key, and the base URL defaults to the sandbox host. An explicit
+/// `COINMARKETCAP_BASE_URL` always wins.
+fn coinmarketcap_upstream(sandbox: bool, var: impl Fn(&str) -> Option<String>) -> (Option<String>, String) {
+    let (api_key, default_url) = if sandbox {
+        let key = var("COINMARKETCAP_SANDBOX_API_KEY")
+            .filter(|key| !key.trim().is_empty())
+            .unwrap_or_else(|| coinmarketcap::SANDBOX_API_KEY.to_string());
+        (Some(key), coinmarketcap::SANDBOX_API_URL)
+    } else {
+        (var("COINMARKETCAP_API_KEY"), coinmarketcap::API_URL)
+    };
+    let base_url = var("COINMARKETCAP_BASE_URL").unwrap_or_else(|| default_url.to_string());
+    (api_key, base_url)
+}
+
+/// Whether the gas endpoints can be served, given which gas oracles are configured.
+///
+/// With `require_gas`, a missing `ETHEREUM_RPC_URL` is a startup error. Without it,
+/// gas is disabled only when no oracle at all (RPC, Etherscan or Blockscout) is set.
+fn gas_enabled(require_gas: bool, has_rpc_url: bool, has_other_oracle: bool) -> Result<bool> {
+    if require_gas && !has_rpc_url {
+        return Err(anyhow!(
+            "❌ Ethereum RPC URL required!\n\n\
+            The ETHEREUM_RPC_URL environment variable must be set for gas price functionality.\n\
+            You can use:\n\
+            • Infura: https://infura.io/\n\
+            • Alchemy: https://www.alchemy.com/\n\
+            • Or any Ethereum JSON-RPC endpoint\n\n\
+            Add ETHEREUM_RPC_URL to your .env file to continue, or set REQUIRE_GAS=false\n\
+            to start with the gas endpoints disabled."
+        ));
+    }
+    Ok(has_rpc_url || has_other_oracle)
+}
+
+/// Reads a boolean flag from the environment, falling back to `default` when unset.
+///
+/// Accepts `true`/`1`/`yes` as true (case-insensitive); any other set value is `false`.
+fn env_flag(name: &str, default: bool) -> bool {
+    std::env::var(name)
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "true" | "1" | "yes"))
+        .unwrap_or(default)
+}
+
+/// Parses a comma-separated environment variable, or `None` when it is unset or blank.
+fn env_list<T: FromStr<Err = anyhow::Error>>(name: &str) -> Result<Option<Vec<T>>> {
+    match std::env::var(name) {
+        Ok(list) if !list.trim().is_empty() => list
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<T>>>()
+            .with_context(|| format!("Invalid {}", name))
+            .map(Some),
+        _ => Ok(None),
+    }
+}
 
 /// Shared application state
 #[derive(Debug, Clone)]
 pub struct AppState {
     /// Application configuration
     pub config: Arc<Config>,
+    /// Last successful fetch time per provider
+    pub provider_health: Arc<ProviderHealth>,
+    /// Latest prices pushed by streaming providers
+    pub price_feed: Arc<LatestPriceCache>,
+    /// Background tasks cancelled at shutdown
+    pub tasks: Arc<TaskRegistry>,
+    /// How late the runtime wakes up timers, sampled in the background
+    pub scheduling_lag: Arc<SchedulingLag>,
+    /// Recently fetched quotes, in memory or in Redis
+    pub quote_cache: DynQuoteCache,
+    /// Retries left for upstream requests, shared by every provider
+    pub retry_budget: Arc<RetryBudget>,
+    /// Request quotas for providers with a `<PROVIDER>_RPM` limit
+    pub rate_limiters: Arc<HashMap<ProviderSource, Arc<ProviderRateLimiter>>>,
+    /// Latest-gas-price lookups in flight, shared by identical concurrent requests
+    pub gas_lookups: Arc<SingleFlight<(GasOracleSource, ChainId), Option<GasQuote>>>,
 }
 
 impl AppState {
     /// Create new app state with configuration
     pub fn new(config: Config) -> Self {
         Self {
+            provider_health: Arc::new(ProviderHealth::default()),
+            price_feed: Arc::new(LatestPriceCache::default()),
+            tasks: Arc::new(TaskRegistry::default()),
+            scheduling_lag: Arc::new(SchedulingLag::default()),
+            quote_cache: quote_cache(&config),
+            retry_budget: Arc::new(RetryBudget::new(
+                config.http.retry_budget,
+                config.http.retry_budget_refill_per_second,
+            )),
+            rate_limiters: Arc::new(
+                config
+                    .provider_rpm
+                    .iter()
+                    .map(|(&provider, &rpm)| (provider, Arc::new(ProviderRateLimiter::per_minute(provider, rpm))))
+                    .collect(),
+            ),
+            gas_lookups: Arc::default(),
             config: Arc::new(config),
         }
     }
-}
\ No newline at end of file
+
+    /// Retry policy for providers, drawing on the shared retry budget
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::new(self.config.http.max_retries, self.retry_budget.clone())
+    }
+
+    /// Wraps `provider` in its request quota, if `<PROVIDER>_RPM` sets one.
+    pub fn rate_limited(&self, source: ProviderSource, provider: DynPriceProvider) -> DynPriceProvider {
+        match self.rate_limiters.get(&source) {
+            Some(limiter) => Box::new(RateLimitedPriceProvider::new(provider, limiter.clone())),
+            None => provider,
+        }
+    }
+}
+
+/// The quote cache backend: Redis when `REDIS_URL` is set, memory otherwise.
+///
+/// Falls back to memory, with a warning, if the Redis URL is invalid or the
+/// `redis` feature is not compiled in.
+fn quote_cache(config: &Config) -> DynQuoteCache {
+    #[cfg(feature = "redis")]
+    if let Some(url) = &config.redis_url {
+        match crate::infrastructure::redis::RedisQuoteCache::new(url, config.quote_cache_ttl) {
+            Ok(cache) => return Arc::new(cache),
+            Err(e) => tracing::warn!("Falling back to the in-memory quote cache: {:#}", e),
+        }
+    }
+    #[cfg(not(feature = "redis"))]
+    if config.redis_url.is_some() {
+        tracing::warn!("REDIS_URL is set but the redis feature is not enabled; caching quotes in memory");
+    }
+
+    Arc::new(MemoryQuoteCache::new(config.quote_cache_ttl))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_env_wins_over_dotenv() {
+        let path = std::env::temp_dir().join(format!("boltzmann-dotenv-{}.env", std::process::id()));
+        std::fs::write(&path, "BOLTZMANN_DOTENV_TEST_SET=from-file\nBOLTZMANN_DOTENV_TEST_UNSET=from-file\n").unwrap();
+        // SAFETY: no other test reads or writes these variables
+        unsafe { std::env::set_var("BOLTZMANN_DOTENV_TEST_SET", "from-process") };
+
+        load_env_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(std::env::var("BOLTZMANN_DOTENV_TEST_SET").unwrap(), "from-process");
+        assert_eq!(std::env::var("BOLTZMANN_DOTENV_TEST_UNSET").unwrap(), "from-file");
+    }
+
+    #[test]
+    fn test_debug_aids_never_allowed_in_production() {
+        let mut config = Config::default();
+        assert!(!config.debug_aids_allowed());
+
+        config.debug = true;
+        assert!(config.debug_aids_allowed());
+
+        config.production = true;
+        assert!(!config.debug_aids_allowed());
+    }
+
+    #[test]
+    fn test_allowed_coins_and_currencies() {
+        let mut config = Config::default();
+        assert!(config.ensure_allowed(Coin::AVAX, &[Currency::JPY]).is_ok());
+        assert_eq!(config.all_allowed_currencies(), Currency::all());
+
+        config.allowed_coins = Some(vec![Coin::ETH]);
+        config.allowed_currencies = Some(vec![Currency::USD, Currency::EUR]);
+        assert!(config.ensure_allowed(Coin::ETH, &[Currency::USD, Currency::EUR]).is_ok());
+        let err = config.ensure_allowed(Coin::ETH, &[Currency::USD, Currency::GBP]).unwrap_err();
+        assert!(err.downcast_ref::<InvalidQuery>().is_some());
+        assert!(err.to_string().contains("currency GBP"), "{}", err);
+        assert!(config.ensure_allowed(Coin::MATIC, &[Currency::USD]).unwrap_err().to_string().contains("coin MATIC"));
+
+        // `currency=all` only expands to allowed currencies
+        assert_eq!(config.all_allowed_currencies(), [Currency::USD, Currency::EUR]);
+    }
+
+    #[test]
+    fn test_coinmarketcap_sandbox_selects_sandbox_upstream() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.to_string())
+        };
+        let production = env(&[("COINMARKETCAP_API_KEY", "pro-key")]);
+
+        let (key, url) = coinmarketcap_upstream(false, production);
+        assert_eq!((key.as_deref(), url.as_str()), (Some("pro-key"), coinmarketcap::API_URL));
+
+        // The production key is never sent to the sandbox
+        let (key, url) = coinmarketcap_upstream(true, production);
+        assert_eq!((key.as_deref(), url.as_str()), (Some(coinmarketcap::SANDBOX_API_KEY), coinmarketcap::SANDBOX_API_URL));
+
+        let (key, url) = coinmarketcap_upstream(true, env(&[("COINMARKETCAP_SANDBOX_API_KEY", "sandbox-key")]));
+        assert_eq!((key.as_deref(), url.as_str()), (Some("sandbox-key"), coinmarketcap::SANDBOX_API_URL));
+
+        let (_, url) = coinmarketcap_upstream(true, env(&[("COINMARKETCAP_BASE_URL", "http://127.0.0.1:9000")]));
+        assert_eq!(url, "http://127.0.0.1:9000");
+    }
+
+    #[test]
+    fn test_gas_requires_rpc_url_unless_optional() {
+        let err = gas_enabled(true, false, true).unwrap_err();
+        assert!(err.to_string().contains("REQUIRE_GAS=false"));
+        assert!(gas_enabled(true, true, false).unwrap());
+
+        // Without REQUIRE_GAS the server starts, with gas disabled only when no oracle is set
+        assert!(!gas_enabled(false, false, false).unwrap());
+        assert!(gas_enabled(false, false, true).unwrap());
+        assert!(gas_enabled(false, true, false).unwrap());
+    }
+
+    #[test]
+    fn test_rpc_url_per_chain() {
+        let mut config = Config {
+            ethereum_rpc_url: Some("https://mainnet.example".to_string()),
+            ..Config::default()
+        };
+        config.chain_rpc_urls.insert(ChainId::BASE, "https://base.example".to_string());
+
+        assert_eq!(config.rpc_url(ChainId::ETHEREUM), Some("https://mainnet.example"));
+        assert_eq!(config.rpc_url(ChainId::BASE), Some("https://base.example"));
+        assert_eq!(config.rpc_url(ChainId::ARBITRUM), None);
+        assert_eq!(
+            config.rpc_urls(),
+            [(ChainId::ETHEREUM, "https://mainnet.example"), (ChainId::BASE, "https://base.example")]
+        );
+    }
+
+    #[test]
+    fn test_blockscout_url_per_chain() {
+        let mut config = Config {
+            blockscout_url: Some("https://eth.blockscout.example".to_string()),
+            ..Config::default()
+        };
+        config.chain_blockscout_urls.insert(ChainId::BASE, "https://base.blockscout.example".to_string());
+
+        assert_eq!(config.blockscout_url_for(ChainId::ETHEREUM), Some("https://eth.blockscout.example"));
+        assert_eq!(config.blockscout_url_for(ChainId::BASE), Some("https://base.blockscout.example"));
+        assert_eq!(config.blockscout_url_for(ChainId::ARBITRUM), None);
+    }
+
+    #[test]
+    fn test_gas_floors_override_chain_defaults() {
+        let mut config = Config::default();
+        config.gas_floors.insert(ChainId::BASE, GasFloors { low: 0.0, average: 0.005, high: 0.01 });
+
+        assert_eq!(config.gas_floors(ChainId::BASE).high, 0.01);
+        assert_eq!(config.gas_floors(ChainId::OPTIMISM), GasFloors::ROLLUP);
+        assert_eq!(config.gas_floors(ChainId::ETHEREUM), GasFloors::MAINNET);
+    }
+}
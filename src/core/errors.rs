@@ -5,14 +5,27 @@
 //! and converting between different error types.
 
 use anyhow::{Context, Result as AnyhowResult};
+#[cfg(feature = "server")]
 use axum::{
     response::{IntoResponse, Response},
     http::StatusCode,
     Json,
 };
+#[cfg(feature = "server")]
 use serde_json::json;
 use tracing::error;
 
+#[cfg(feature = "server")]
+use crate::api::middleware::Overloaded;
+#[cfg(feature = "server")]
+use crate::domains::crypto::coingecko::QuotaExhausted;
+#[cfg(feature = "server")]
+use crate::domains::crypto::rate_limit::RateLimited;
+#[cfg(feature = "server")]
+use crate::domains::gas::price::quorum::QuorumNotReached;
+#[cfg(feature = "server")]
+use crate::infrastructure::http::{NonJsonResponse, ResponseTooLarge};
+
 /// Type alias for Result with anyhow::Error
 pub type Result<T> = AnyhowResult<T>;
 
@@ -56,18 +69,220 @@ where
     }
 }
 
+/// A requested provider cannot be used because its configuration is missing.
+///
+/// This is a deployment problem rather than a transient failure, so it maps to
+/// `501 Not Implemented` and clients should not retry the same request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotConfigured {
+    /// Provider that was requested
+    pub provider: String,
+    /// Environment variable that must be set to enable the provider
+    pub config_key: String,
+}
+
+impl NotConfigured {
+    /// Creates an error for `provider`, naming the missing `config_key`
+    pub fn new(provider: impl Into<String>, config_key: impl Into<String>) -> Self {
+        Self {
+            provider: provider.into(),
+            config_key: config_key.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for NotConfigured {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Provider {} is not configured: set {}", self.provider, self.config_key)
+    }
+}
+
+impl std::error::Error for NotConfigured {}
+
+/// The requested operation is not something the chosen provider can do.
+///
+/// Unlike [`NotConfigured`], configuration cannot fix this; the client has to
+/// pick another provider, so it maps to `400 Bad Request`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotSupported {
+    /// What was asked for, e.g. `market depth for ETH/CHF`
+    pub operation: String,
+}
+
+impl NotSupported {
+    /// Creates an error for an unsupported `operation`
+    pub fn new(operation: impl Into<String>) -> Self {
+        Self { operation: operation.into() }
+    }
+}
+
+impl std::fmt::Display for NotSupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Not supported by this provider: {}", self.operation)
+    }
+}
+
+impl std::error::Error for NotSupported {}
+
+/// A response body could not be serialized (to JSON or MessagePack).
+///
+/// Our own types should always serialize, so this is a bug rather than a bad
+/// request or an upstream failure: it maps to `500 Internal Server Error`
+/// whatever the underlying message says.
+#[derive(Debug)]
+pub struct SerializationFailed {
+    /// What was being serialized, e.g. `gas quote`
+    pub what: String,
+    /// The serializer's error
+    pub source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl std::fmt::Display for SerializationFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to serialize {}: {}", self.what, self.source)
+    }
+}
+
+impl std::error::Error for SerializationFailed {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// A request's query string could not be deserialized into the endpoint's parameters.
+///
+/// Maps to `400 Bad Request`; `reason` names the offending parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidQuery {
+    /// Why deserialization failed, e.g. `currency: invalid currency 'XYZ'`
+    pub reason: String,
+}
+
+impl InvalidQuery {
+    /// Creates an error for a query string rejected with `reason`
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self { reason: reason.into() }
+    }
+}
+
+impl std::fmt::Display for InvalidQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid query parameters: {}", self.reason)
+    }
+}
+
+impl std::error::Error for InvalidQuery {}
+
+/// A request's JSON body could not be deserialized or failed validation.
+///
+/// Maps to `400 Bad Request`; `reason` names the offending field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidBody {
+    /// Why the body was rejected, e.g. `operations: at least one operation is required`
+    pub reason: String,
+}
+
+impl InvalidBody {
+    /// Creates an error for a request body rejected with `reason`
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self { reason: reason.into() }
+    }
+}
+
+impl std::fmt::Display for InvalidBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid request body: {}", self.reason)
+    }
+}
+
+impl std::error::Error for InvalidBody {}
+
+/// A price request resolved to no currencies at all.
+///
+/// Happens when `currency=all` meets an `ALL_CURRENCIES` set that
+/// `ALLOWED_CURRENCIES` filters down to nothing. Providers would answer with
+/// no quotes, so the request is refused with `400 Bad Request` up front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoCurrencies;
+
+impl std::fmt::Display for NoCurrencies {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No currencies requested: the request resolved to no currency available on this server")
+    }
+}
+
+impl std::error::Error for NoCurrencies {}
+
+/// A protected endpoint was called without the right API key.
+///
+/// Maps to `401 Unauthorized`; the message never says whether a key was sent
+/// or merely wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unauthorized;
+
+impl std::fmt::Display for Unauthorized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unauthorized: a valid Authorization: Bearer <ADMIN_API_KEY> header is required")
+    }
+}
+
+impl std::error::Error for Unauthorized {}
+
+/// The server was started without any gas oracle (`REQUIRE_GAS=false`).
+///
+/// Crypto prices keep working; gas endpoints answer `503 Service Unavailable`
+/// until the server is restarted with a gas oracle configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasDisabled;
+
+impl std::fmt::Display for GasDisabled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Gas endpoints are disabled: no gas oracle is configured (set ETHEREUM_RPC_URL, ETHERSCAN_API_KEY or BLOCKSCOUT_URL)"
+        )
+    }
+}
+
+impl std::error::Error for GasDisabled {}
+
 /// Application error wrapper for HTTP responses
+#[cfg(feature = "server")]
 pub struct AppError(anyhow::Error);
 
+#[cfg(feature = "server")]
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         // Log the full error chain for debugging
         utils::log_error(&self.0, "HTTP request processing");
         
         // Determine status code based on error type
-        let status_code = if self.0.downcast_ref::<reqwest::Error>().is_some() {
+        let status_code = if self.0.downcast_ref::<SerializationFailed>().is_some() {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else if self.0.downcast_ref::<Unauthorized>().is_some() {
+            StatusCode::UNAUTHORIZED
+        } else if self.0.downcast_ref::<NotConfigured>().is_some() {
+            StatusCode::NOT_IMPLEMENTED
+        } else if self.0.downcast_ref::<QuorumNotReached>().is_some() {
+            StatusCode::CONFLICT
+        } else if self.0.downcast_ref::<NotSupported>().is_some()
+            || self.0.downcast_ref::<InvalidQuery>().is_some()
+            || self.0.downcast_ref::<InvalidBody>().is_some()
+            || self.0.downcast_ref::<NoCurrencies>().is_some()
+        {
+            StatusCode::BAD_REQUEST
+        } else if self.0.downcast_ref::<reqwest::Error>().is_some()
+            || self.0.downcast_ref::<ResponseTooLarge>().is_some()
+            || self.0.downcast_ref::<NonJsonResponse>().is_some()
+            || self.0.downcast_ref::<QuotaExhausted>().is_some()
+        {
             StatusCode::BAD_GATEWAY
-        } else if self.0.to_string().contains("API key") || self.0.to_string().contains("configuration") {
+        } else if self.0.downcast_ref::<RateLimited>().is_some()
+            || self.0.downcast_ref::<Overloaded>().is_some()
+            || self.0.downcast_ref::<GasDisabled>().is_some()
+            || self.0.to_string().contains("API key")
+            || self.0.to_string().contains("configuration")
+        {
             StatusCode::SERVICE_UNAVAILABLE
         } else if self.0.to_string().contains("parse") || self.0.to_string().contains("invalid") {
             StatusCode::BAD_REQUEST
@@ -88,6 +303,14 @@ impl IntoResponse for AppError {
     }
 }
 
+#[cfg(feature = "server")]
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(feature = "server")]
 impl<E> From<E> for AppError
 where
     E: Into<anyhow::Error>,
@@ -125,6 +348,17 @@ pub mod utils {
         anyhow::anyhow!("API error from {}: {}", provider, message)
     }
     
+    /// Serializes `value` to a JSON value, failing with [`SerializationFailed`].
+    ///
+    /// Use instead of `serde_json::to_value(..).unwrap_or_default()`, which would
+    /// hide a serialization bug behind a `null` body.
+    pub fn to_json_value<T: serde::Serialize + ?Sized>(value: &T, what: &str) -> Result<serde_json::Value> {
+        serde_json::to_value(value).map_err(|source| {
+            error!("Failed to serialize {}: {}", what, source);
+            SerializationFailed { what: what.to_string(), source: Box::new(source) }.into()
+        })
+    }
+
     /// Create a standardized error for parsing failures
     pub fn parse_error(data_type: &str, source: impl std::error::Error + Send + Sync + 'static) -> anyhow::Error {
         anyhow::Error::new(source).context(format!("Failed to parse {}", data_type))
@@ -152,7 +386,6 @@ macro_rules! ensure_context {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use anyhow::anyhow;
     
     #[test]
     fn test_error_context() {
@@ -166,12 +399,142 @@ mod tests {
         assert!(with_context.unwrap_err().to_string().contains("Crypto operation failed"));
     }
     
+    #[tokio::test]
+    async fn test_context_keeps_wrapped_error_as_source() {
+        // Nothing listens on port 1, so this yields a connection error
+        let result = reqwest::get("http://127.0.0.1:1").await;
+
+        let err = result.crypto_context("sending request to CoinGecko API").unwrap_err();
+        let source = err.source().expect("context should expose its cause");
+        assert!(source.downcast_ref::<reqwest::Error>().is_some());
+        assert!(err.chain().any(|cause| cause.downcast_ref::<reqwest::Error>().is_some()));
+    }
+
+    #[cfg(feature = "server")]
     #[test]
     fn test_app_error_conversion() {
         let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "test error");
-        let app_error: AppError = anyhow!(io_error).into();
+        let app_error: AppError = anyhow::anyhow!(io_error).into();
         
         // Should not panic
         let _response = app_error.into_response();
     }
+
+    #[cfg(feature = "server")]
+    async fn error_body(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_not_configured_is_distinct_from_runtime_failure() {
+        let unconfigured: AppError = NotConfigured::new("etherscan", "ETHERSCAN_API_KEY").into();
+        let response = unconfigured.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+        let body = error_body(response).await;
+        assert_eq!(body["error"]["status"], 501);
+        assert!(body["error"]["details"].as_str().unwrap().contains("ETHERSCAN_API_KEY"));
+
+        let failing: AppError = anyhow::anyhow!("Failed to fetch gas prices from provider").into();
+        let response = failing.into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = error_body(response).await;
+        assert!(!body["error"]["details"].as_str().unwrap().contains("not configured"));
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_gas_oracle_disagreement_is_a_conflict() {
+        let disagreement: AppError = anyhow::Error::from(QuorumNotReached { quorum: 2, agreeing: 1, answered: 3, oracles: 3 })
+            .context("Gas operation failed: quorum")
+            .into();
+        let response = disagreement.into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = error_body(response).await;
+        assert_eq!(body["error"]["status"], 409);
+    }
+
+    #[test]
+    fn test_typed_errors_convert_into_unified_result() {
+        use crate::core::parse::FieldParseError;
+        use crate::domains::gas::price::alloy::BlockNotFound;
+
+        /// Propagates a typed error with `?` and the usual provider context
+        fn propagate<E: std::error::Error + Send + Sync + 'static>(err: E) -> Result<()> {
+            Err(err).crypto_context("fetching data")
+        }
+
+        let err = propagate(FieldParseError::Missing { field: "price".to_string() }).unwrap_err();
+        assert!(matches!(err.downcast_ref::<FieldParseError>(), Some(FieldParseError::Missing { .. })));
+        assert!(format!("{:#}", err).contains("Missing numeric field 'price'"));
+
+        let err = propagate(crate::infrastructure::http::ResponseTooLarge { limit: 1024 }).unwrap_err();
+        assert_eq!(err.downcast_ref::<crate::infrastructure::http::ResponseTooLarge>().unwrap().limit, 1024);
+        #[cfg(feature = "server")]
+        assert_eq!(AppError::from(err).into_response().status(), StatusCode::BAD_GATEWAY);
+
+        let quota = crate::domains::crypto::coingecko::QuotaExhausted { message: "monthly credits used".to_string() };
+        let err = propagate(quota).unwrap_err();
+        assert!(err.downcast_ref::<crate::domains::crypto::coingecko::QuotaExhausted>().is_some());
+        #[cfg(feature = "server")]
+        assert_eq!(AppError::from(err).into_response().status(), StatusCode::BAD_GATEWAY);
+
+        let limited = crate::domains::crypto::rate_limit::RateLimited {
+            provider: crate::domains::crypto::ProviderSource::CoinGecko,
+            wait: std::time::Duration::from_secs(2),
+        };
+        let err = propagate(limited).unwrap_err();
+        assert!(err.downcast_ref::<crate::domains::crypto::rate_limit::RateLimited>().is_some());
+        #[cfg(feature = "server")]
+        assert_eq!(AppError::from(err).into_response().status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let err = propagate(NotSupported::new("market depth for ETH/CHF")).unwrap_err();
+        assert!(err.downcast_ref::<NotSupported>().is_some());
+        #[cfg(feature = "server")]
+        assert_eq!(AppError::from(err).into_response().status(), StatusCode::BAD_REQUEST);
+
+        let err = propagate(BlockNotFound { block: 10, head: 5 }).unwrap_err();
+        assert_eq!(err.downcast_ref::<BlockNotFound>().unwrap().head, 5);
+    }
+
+    /// A value whose serialization always fails, with a message that would
+    /// otherwise look like a client error
+    struct Unserializable;
+
+    impl serde::Serialize for Unserializable {
+        fn serialize<S: serde::Serializer>(&self, _serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("invalid state"))
+        }
+    }
+
+    #[test]
+    fn test_serialization_failure_is_an_error() {
+        let err = utils::to_json_value(&Unserializable, "test response").unwrap_err();
+        let failed = err.downcast_ref::<SerializationFailed>().unwrap();
+        assert_eq!(failed.what, "test response");
+        assert_eq!(err.to_string(), "Failed to serialize test response: invalid state");
+
+        assert_eq!(utils::to_json_value(&[1, 2], "list").unwrap(), serde_json::json!([1, 2]));
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_serialization_failure_is_internal_server_error() {
+        let err = utils::to_json_value(&Unserializable, "test response").unwrap_err();
+        let response = AppError::from(err).into_response();
+
+        // "invalid" in the message must not turn it into a 400
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = error_body(response).await;
+        assert_eq!(body["error"]["status"], 500);
+        assert!(body["error"]["details"].as_str().unwrap().contains("Failed to serialize test response"));
+    }
+
+    #[test]
+    fn test_not_configured_survives_context() {
+        let err = anyhow::Error::new(NotConfigured::new("alloy", "ETHEREUM_RPC_URL"))
+            .context("Gas operation failed: fetching prices");
+        assert!(err.downcast_ref::<NotConfigured>().is_some());
+    }
 }
\ No newline at end of file
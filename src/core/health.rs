@@ -0,0 +1,53 @@
+//! Provider health tracking.
+//!
+//! Records when each upstream provider last returned data successfully, so the
+//! health endpoint can tell "never worked" apart from "worked a moment ago".
+
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Last successful fetch time per provider, shared through the app state.
+///
+/// Providers are keyed by their API name (`coingecko`, `etherscan`, ...).
+#[derive(Debug, Default)]
+pub struct ProviderHealth {
+    last_success: RwLock<BTreeMap<String, DateTime<Utc>>>,
+}
+
+impl ProviderHealth {
+    /// Records a successful fetch from `provider` at the current time.
+    pub fn record_success(&self, provider: impl ToString) {
+        self.last_success
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(provider.to_string(), Utc::now());
+    }
+
+    /// Returns when `provider` last returned data, or `None` if it never has.
+    pub fn last_success(&self, provider: &str) -> Option<DateTime<Utc>> {
+        self.last_success
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(provider)
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_success() {
+        let health = ProviderHealth::default();
+        assert!(health.last_success("coingecko").is_none());
+
+        let before = Utc::now();
+        health.record_success("coingecko");
+
+        let recorded = health.last_success("coingecko").unwrap();
+        assert!(recorded >= before);
+        assert!(health.last_success("etherscan").is_none());
+    }
+}
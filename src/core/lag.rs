@@ -0,0 +1,86 @@
+//! Runtime scheduling lag.
+//!
+//! When the Tokio workers are saturated, ready tasks wait longer before they
+//! run, well before requests start timing out. A [`SchedulingLag`] sampler
+//! sleeps for a fixed period in a loop and measures how late each wake-up is
+//! compared to its deadline; the moving average of that delay is reported by
+//! the health endpoint.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Default time between lag samples
+pub const DEFAULT_SAMPLE_PERIOD: Duration = Duration::from_millis(100);
+
+/// Weight of the newest sample in the moving average
+const SMOOTHING: f64 = 0.2;
+
+/// Exponential moving average of how late a periodic timer fires.
+#[derive(Debug)]
+pub struct SchedulingLag {
+    period: Duration,
+    average_nanos: AtomicU64,
+}
+
+impl Default for SchedulingLag {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_PERIOD)
+    }
+}
+
+impl SchedulingLag {
+    /// Creates a sampler that wakes up every `period`.
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            average_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Moving average of the lag; zero until the first sample.
+    pub fn average(&self) -> Duration {
+        Duration::from_nanos(self.average_nanos.load(Ordering::Relaxed))
+    }
+
+    /// Folds one sample into the moving average.
+    pub fn record(&self, lag: Duration) {
+        let previous = self.average_nanos.load(Ordering::Relaxed) as f64;
+        let sample = lag.as_nanos().min(u64::MAX as u128) as f64;
+        let average = previous + SMOOTHING * (sample - previous);
+        self.average_nanos.store(average as u64, Ordering::Relaxed);
+    }
+
+    /// Samples the lag forever; run it on the runtime being measured.
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            let deadline = Instant::now() + self.period;
+            tokio::time::sleep_until(deadline).await;
+            self.record(Instant::now().saturating_duration_since(deadline));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_blocked_runtime_raises_lag() {
+        let lag = Arc::new(SchedulingLag::new(Duration::from_millis(10)));
+        let sampler = tokio::spawn(lag.clone().run());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let before = lag.average();
+
+        // Hog the only worker thread so the sampler's timer fires late
+        std::thread::sleep(Duration::from_millis(200));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let after = lag.average();
+        assert!(after > before + Duration::from_millis(10), "lag {:?} -> {:?}", before, after);
+        sampler.abort();
+    }
+}
@@ -2,8 +2,30 @@
 //!
 //! This module contains the core application setup and configuration:
 //! - `config` - Application configuration and environment management
-//! - `server` - Server initialization and lifecycle management
+//! - `server` - Server initialization and lifecycle management (`server` feature)
+//! - `runtime` - Tokio runtime construction and tuning
+//! - `tasks` - Background task registry and shutdown
+//! - `single_flight` - Coalescing of identical concurrent lookups
+//! - `errors` - Centralized error handling
+//! - `health` - Provider health tracking
+//! - `lag` - Runtime scheduling lag sampling
+//! - `parse` - Numeric field parsing for provider responses
+//!
+//! Only `errors` and `parse` are built for wasm32; the rest needs the native runtime.
 
+#[cfg(not(target_arch = "wasm32"))]
 pub mod config;
+#[cfg(feature = "server")]
 pub mod server;
-pub mod errors;
\ No newline at end of file
+#[cfg(not(target_arch = "wasm32"))]
+pub mod runtime;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tasks;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod single_flight;
+pub mod errors;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod health;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lag;
+pub mod parse;
\ No newline at end of file
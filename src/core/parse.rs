@@ -0,0 +1,137 @@
+//! Numeric field parsing for provider responses.
+//!
+//! Upstream APIs are inconsistent about how they encode numbers: Etherscan sends
+//! decimal strings, CoinMarketCap and CoinGecko send JSON numbers nested in objects.
+//! These helpers accept either form and report failures with the offending field name,
+//! so every provider produces the same error messages.
+
+use serde_json::Value;
+use std::fmt;
+
+/// Error returned when a numeric field cannot be read from a provider response.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldParseError {
+    /// The field is absent or `null`
+    Missing {
+        /// Name of the field that was expected
+        field: String,
+    },
+    /// The field is present but is not a finite number
+    Invalid {
+        /// Name of the field that failed to parse
+        field: String,
+        /// The raw value as received from the provider
+        value: String,
+    },
+}
+
+impl fmt::Display for FieldParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldParseError::Missing { field } => write!(f, "Missing numeric field '{}'", field),
+            FieldParseError::Invalid { field, value } => {
+                write!(f, "Invalid numeric value '{}' for field '{}'", value, field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FieldParseError {}
+
+/// Parses a JSON value holding either a number or a numeric string into an `f64`.
+///
+/// # Errors
+///
+/// Returns `FieldParseError::Missing` for `null`/absent values and
+/// `FieldParseError::Invalid` for anything that is not a finite number.
+///
+/// # Examples
+///
+/// ```rust
+/// use boltzmann::core::parse::parse_f64_field;
+/// use serde_json::json;
+///
+/// let data = json!({ "price": 2000.5, "gas": "30" });
+/// assert_eq!(parse_f64_field(&data["price"], "price").unwrap(), 2000.5);
+/// assert_eq!(parse_f64_field(&data["gas"], "gas").unwrap(), 30.0);
+/// assert!(parse_f64_field(&data["volume"], "volume").is_err());
+/// ```
+pub fn parse_f64_field(value: &Value, field: &str) -> Result<f64, FieldParseError> {
+    match value {
+        Value::Null => Err(FieldParseError::Missing { field: field.to_string() }),
+        Value::Number(number) => number
+            .as_f64()
+            .filter(|n| n.is_finite())
+            .ok_or_else(|| invalid(field, &number.to_string())),
+        Value::String(raw) => parse_f64_str(raw, field),
+        other => Err(invalid(field, &other.to_string())),
+    }
+}
+
+/// Parses a decimal string into a finite `f64`, ignoring surrounding whitespace.
+///
+/// # Errors
+///
+/// Returns `FieldParseError::Missing` for empty strings and
+/// `FieldParseError::Invalid` for non-numeric, `NaN` or infinite values.
+pub fn parse_f64_str(raw: &str, field: &str) -> Result<f64, FieldParseError> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err(FieldParseError::Missing { field: field.to_string() });
+    }
+
+    trimmed
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite())
+        .ok_or_else(|| invalid(field, raw))
+}
+
+fn invalid(field: &str, value: &str) -> FieldParseError {
+    FieldParseError::Invalid {
+        field: field.to_string(),
+        value: value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_valid_values() {
+        let data = json!({ "number": 42.5, "string": " 30.25 ", "integer": 7 });
+        assert_eq!(parse_f64_field(&data["number"], "number").unwrap(), 42.5);
+        assert_eq!(parse_f64_field(&data["string"], "string").unwrap(), 30.25);
+        assert_eq!(parse_f64_field(&data["integer"], "integer").unwrap(), 7.0);
+        assert_eq!(parse_f64_str("0.000001", "tiny").unwrap(), 0.000001);
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        let data = json!({ "price": null });
+        assert_eq!(
+            parse_f64_field(&data["price"], "price"),
+            Err(FieldParseError::Missing { field: "price".to_string() })
+        );
+        assert!(matches!(
+            parse_f64_field(&data["absent"], "absent"),
+            Err(FieldParseError::Missing { .. })
+        ));
+        assert!(matches!(parse_f64_str("  ", "SafeGasPrice"), Err(FieldParseError::Missing { .. })));
+    }
+
+    #[test]
+    fn test_non_numeric_strings() {
+        for raw in ["abc", "12abc", "NaN", "inf", "-infinity", "1e400"] {
+            let err = parse_f64_str(raw, "SafeGasPrice").unwrap_err();
+            assert!(matches!(err, FieldParseError::Invalid { .. }), "{raw} should be invalid");
+            assert!(err.to_string().contains("SafeGasPrice"));
+        }
+
+        let data = json!({ "price": { "nested": 1 }, "flag": true });
+        assert!(matches!(parse_f64_field(&data["price"], "price"), Err(FieldParseError::Invalid { .. })));
+        assert!(matches!(parse_f64_field(&data["flag"], "flag"), Err(FieldParseError::Invalid { .. })));
+    }
+}
@@ -0,0 +1,71 @@
+//! Tokio runtime construction.
+//!
+//! The server runs on a multi-threaded runtime built here rather than through
+//! `#[tokio::main]`, so high-throughput deployments can pin the worker-thread
+//! count with `TOKIO_WORKER_THREADS` instead of defaulting to one per core.
+
+use anyhow::Context;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::core::errors::Result;
+
+/// Reads the worker-thread count from `TOKIO_WORKER_THREADS`.
+///
+/// Returns `None` when unset or empty, meaning one worker per CPU core.
+///
+/// # Errors
+///
+/// Returns an error if the value is not a positive integer.
+pub fn worker_threads_from_env() -> Result<Option<usize>> {
+    match std::env::var("TOKIO_WORKER_THREADS") {
+        Ok(value) if !value.trim().is_empty() => parse_worker_threads(&value).map(Some),
+        _ => Ok(None),
+    }
+}
+
+fn parse_worker_threads(value: &str) -> Result<usize> {
+    let threads: usize = value
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid TOKIO_WORKER_THREADS: {}", value))?;
+    if threads == 0 {
+        anyhow::bail!("Invalid TOKIO_WORKER_THREADS: must be at least 1");
+    }
+    Ok(threads)
+}
+
+/// Builds the multi-threaded runtime the server runs on.
+///
+/// Uses `worker_threads` workers when given, otherwise one per CPU core.
+///
+/// # Errors
+///
+/// Returns an error if the runtime cannot be created.
+pub fn build_runtime(worker_threads: Option<usize>) -> Result<Runtime> {
+    let mut builder = Builder::new_multi_thread();
+    builder.enable_all();
+    if let Some(threads) = worker_threads {
+        builder.worker_threads(threads);
+    }
+
+    builder.build().context("building Tokio runtime")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_respects_worker_threads() {
+        let runtime = build_runtime(Some(3)).unwrap();
+        assert_eq!(runtime.metrics().num_workers(), 3);
+        assert_eq!(runtime.block_on(async { 40 + 2 }), 42);
+    }
+
+    #[test]
+    fn test_worker_threads_must_be_positive() {
+        assert_eq!(parse_worker_threads(" 8 ").unwrap(), 8);
+        assert!(parse_worker_threads("0").is_err());
+        assert!(parse_worker_threads("many").is_err());
+    }
+}
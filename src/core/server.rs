@@ -3,14 +3,26 @@
 //! This module handles the Axum server setup, middleware configuration,
 //! and the main server lifecycle.
 
+use std::time::Duration;
+
 use tokio::net::TcpListener;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::core::config::{Config, AppState};
 use crate::core::errors::Result;
 use anyhow::Context;
 use crate::api::routes;
-use crate::infrastructure::logging;
+use crate::domains::crypto::{Coin, Currency, ProviderSource};
+use crate::domains::crypto::binance::BinanceTickerFeed;
+use crate::domains::crypto::coingecko::CoinGecko;
+use crate::domains::crypto::warm::CacheWarmer;
+use crate::domains::gas::price::alloy::{probe_rpc, RpcProbe};
+use crate::domains::timestamp;
+use crate::infrastructure::http::build_client;
+use crate::infrastructure::logging::{self, sample};
+
+/// How long each RPC endpoint gets to answer the startup probe
+const RPC_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Initialize and start the Boltzmann API server.
 ///
@@ -20,6 +32,8 @@ use crate::infrastructure::logging;
 /// 3. Create application state
 /// 4. Set up routes with middleware
 /// 5. Start the server
+/// 6. On Ctrl+C or SIGTERM, drain connections and stop background tasks
+///    within `SHUTDOWN_TIMEOUT_SECONDS`
 ///
 /// # Errors
 ///
@@ -30,12 +44,12 @@ use crate::infrastructure::logging;
 ///
 /// # Examples
 ///
-/// ```rust
-/// use boltzmann::server;
+/// ```rust,no_run
+/// use boltzmann::core::server;
 ///
 /// #[tokio::main]
 /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-///     server::start().await
+///     Ok(server::start().await?)
 /// }
 /// ```
 pub async fn start() -> Result<()> {
@@ -49,6 +63,8 @@ pub async fn start() -> Result<()> {
     let config = Config::from_env()
         .context("loading application configuration")?;
     
+    timestamp::set_format(config.timestamp_format);
+    sample::set_provider_rate(config.provider_log_sample);
     let app_state = AppState::new(config);
     info!("✅ Configuration loaded successfully");
     
@@ -60,7 +76,24 @@ pub async fn start() -> Result<()> {
     info!("  CoinGecko API: {}", if app_state.config.coingecko_api_key.is_some() { "✅" } else { "❌" });
     info!("  Etherscan API: {}", if app_state.config.etherscan_api_key.is_some() { "✅" } else { "❌" });
     info!("  Ethereum RPC: {}", if app_state.config.ethereum_rpc_url.is_some() { "✅" } else { "❌" });
+
+    if app_state.config.startup_healthcheck {
+        probe_rpc_endpoints(&app_state.config).await?;
+    }
     
+    // Measure how late the runtime runs ready tasks, reported by the health check
+    app_state.tasks.spawn_cancellable(app_state.scheduling_lag.clone().run());
+
+    // Start real-time price streams
+    if app_state.config.binance_stream {
+        spawn_binance_feeds(&app_state);
+    }
+
+    // Keep the quote cache filled for the busiest pairs
+    if !app_state.config.warm_cache_currencies.is_empty() {
+        spawn_cache_warmer(&app_state)?;
+    }
+
     // Create router with all routes configured
     let app = routes::create_router(app_state.clone());
     info!("🔗 Routes configured successfully");
@@ -76,9 +109,116 @@ pub async fn start() -> Result<()> {
     info!("🌐 Server listening on {}", bind_addr);
     info!("📚 Swagger UI available at: http://{}/docs", bind_addr);
     
-    axum::serve(listener, app.into_make_service()).await
+    axum::serve(listener, app.into_make_service())
+        .with_graceful_shutdown(shutdown_signal())
+        .await
         .context("running HTTP server")?;
 
+    info!("🛑 Shutting down, stopping background tasks");
+    app_state.tasks.shutdown(app_state.config.shutdown_timeout).await;
+
+    logging::shutdown_tracing();
+
+    Ok(())
+}
+
+/// Checks every configured RPC endpoint with `eth_chainId` and logs the outcome.
+///
+/// Unreachable endpoints and endpoints reporting a different chain are logged as
+/// warnings but do not stop the server, since the node may recover later.
+///
+/// # Errors
+///
+/// Returns an error if the HTTP client cannot be built.
+async fn probe_rpc_endpoints(config: &Config) -> Result<()> {
+    let client = build_client(&config.http)?;
+    let probes = config.rpc_urls().into_iter().map(|(chain, url)| {
+        let client = client.clone();
+        async move { (chain, probe_rpc(&client, url, chain, RPC_PROBE_TIMEOUT).await) }
+    });
+
+    for (chain, probe) in futures::future::join_all(probes).await {
+        match probe {
+            RpcProbe::Reachable => info!("  RPC for chain {}: ✅ reachable", chain),
+            RpcProbe::ChainMismatch { reported } => {
+                warn!("  RPC for chain {}: ⚠️ reports chain id {}, check its URL", chain, reported)
+            }
+            RpcProbe::Unreachable(reason) => warn!("  RPC for chain {}: ❌ unreachable ({})", chain, reason),
+        }
+    }
+    Ok(())
+}
+
+/// Resolves when the process receives Ctrl+C or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Starts a Binance ticker stream for every currency Binance has an ETH market in.
+///
+/// All feeds write into the shared `price_feed` cache in the app state and are
+/// registered with its task registry so they stop at shutdown.
+fn spawn_binance_feeds(app_state: &AppState) {
+    for &currency in Currency::all() {
+        if let Ok(feed) = BinanceTickerFeed::new(Coin::ETH, currency) {
+            let feed = feed.with_cache(app_state.price_feed.clone());
+            app_state.tasks.spawn_cancellable(feed.run());
+            info!("📡 Streaming {}/{} from Binance", Coin::ETH, currency);
+        }
+    }
+}
+
+/// Refreshes CoinGecko's ETH quotes in `WARM_CACHE_CURRENCIES` into the quote
+/// cache once per `QUOTE_CACHE_TTL_SECONDS`, staggered across the interval.
+///
+/// Does nothing when the quote cache is disabled.
+///
+/// # Errors
+///
+/// Returns an error if the CoinGecko client cannot be built.
+fn spawn_cache_warmer(app_state: &AppState) -> Result<()> {
+    let config = &app_state.config;
+    if config.quote_cache_ttl.is_zero() {
+        warn!("WARM_CACHE_CURRENCIES is set but the quote cache is disabled, not warming");
+        return Ok(());
+    }
+    let coingecko = CoinGecko::new_with_client(config.coingecko_api_key.clone(), build_client(&config.http)?)?
+        .with_base_url(&config.coingecko_base_url)
+        .with_strict_currencies(config.strict_currencies)
+        .with_max_response_bytes(config.http.max_response_bytes)
+        .with_retry(app_state.retry_policy());
+    let provider = app_state.rate_limited(ProviderSource::CoinGecko, Box::new(coingecko));
+    let pairs: Vec<_> = config.warm_cache_currencies.iter().map(|&currency| (Coin::ETH, currency)).collect();
+    info!("🔥 Warming {} pairs from CoinGecko every {:?}", pairs.len(), config.quote_cache_ttl);
+
+    let warmer = CacheWarmer::new(ProviderSource::CoinGecko, provider, app_state.quote_cache.clone(), config.quote_cache_ttl)
+        .with_pairs(pairs);
+    app_state.tasks.spawn_cancellable(warmer.run());
     Ok(())
 }
 
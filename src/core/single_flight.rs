@@ -0,0 +1,91 @@
+//! Request coalescing.
+//!
+//! A burst of identical requests should not each reach the same upstream. A
+//! [`SingleFlight`] runs one lookup per key at a time: callers that arrive
+//! while a lookup for their key is in flight wait for it and share its result
+//! instead of starting their own. Nothing is kept once the lookup finishes, so
+//! this deduplicates concurrent work without caching anything.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+use futures::future::{BoxFuture, FutureExt, Shared};
+
+/// Coalesces concurrent lookups for the same key into one.
+pub struct SingleFlight<K, V> {
+    calls: Mutex<HashMap<K, Shared<BoxFuture<'static, V>>>>,
+}
+
+impl<K, V> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self { calls: Mutex::default() }
+    }
+}
+
+impl<K, V> fmt::Debug for SingleFlight<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let in_flight = self.calls.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).len();
+        f.debug_struct("SingleFlight").field("in_flight", &in_flight).finish()
+    }
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone + Send + Sync + 'static,
+{
+    /// Returns the result of the lookup in flight for `key`, starting `lookup` if there is none.
+    ///
+    /// The lookup keeps running as long as any caller still waits for it, even
+    /// if the caller that started it goes away.
+    pub async fn run<Fut>(&self, key: K, lookup: impl FnOnce() -> Fut) -> V
+    where
+        Fut: Future<Output = V> + Send + 'static,
+    {
+        let call = {
+            let mut calls = self.calls.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            calls.entry(key.clone()).or_insert_with(|| lookup().boxed().shared()).clone()
+        };
+        let result = call.clone().await;
+
+        // The first caller back retires the lookup; later callers start a new one
+        let mut calls = self.calls.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if calls.get(&key).is_some_and(|current| current.ptr_eq(&call)) {
+            calls.remove(&key);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_lookups_for_a_key_run_once() {
+        let flights = SingleFlight::<&str, usize>::default();
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let lookup = |lookups: Arc<AtomicUsize>| {
+            move || async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                lookups.fetch_add(1, Ordering::SeqCst) + 1
+            }
+        };
+
+        let results = futures::future::join_all((0..10).map(|_| flights.run("eth", lookup(lookups.clone())))).await;
+        assert_eq!(results, [1; 10]);
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        // Other keys do not wait on it, and finished lookups are not reused
+        let (a, b) = tokio::join!(flights.run("eth", lookup(lookups.clone())), flights.run("base", lookup(lookups.clone())));
+        assert_eq!(a + b, 5);
+        assert_eq!(lookups.load(Ordering::SeqCst), 3);
+        assert_eq!(format!("{:?}", flights), "SingleFlight { in_flight: 0 }");
+    }
+}
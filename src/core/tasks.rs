@@ -0,0 +1,139 @@
+//! Background task registry.
+//!
+//! Long-running tasks (price streams, cache refreshers, pollers) are spawned
+//! through a [`TaskRegistry`] instead of bare `tokio::spawn`, so graceful
+//! shutdown can cancel them and wait a bounded time for them to finish.
+
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Default time to wait for background tasks at shutdown
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tracks background tasks and cancels them at shutdown.
+///
+/// Every task receives a [`CancellationToken`] that fires when shutdown begins.
+/// Tasks should stop promptly once it does; any still running after the
+/// shutdown timeout are aborted.
+#[derive(Debug, Default)]
+pub struct TaskRegistry {
+    token: CancellationToken,
+    tasks: Mutex<JoinSet<()>>,
+}
+
+impl TaskRegistry {
+    /// Spawns a background task, passing it the shutdown token.
+    pub fn spawn<F, Fut>(&self, task: F)
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let future = task(self.token.clone());
+        self.tasks.lock().unwrap().spawn(future);
+    }
+
+    /// Spawns a task that is simply dropped when shutdown begins.
+    ///
+    /// For tasks with no cleanup to do, such as reconnecting stream loops.
+    pub fn spawn_cancellable<Fut>(&self, future: Fut)
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.spawn(|token| async move {
+            token.run_until_cancelled_owned(future).await;
+        });
+    }
+
+    /// Number of tasks that have not finished yet
+    pub fn running(&self) -> usize {
+        self.tasks.lock().unwrap().len()
+    }
+
+    /// Cancels every task and waits up to `timeout` for them to finish.
+    ///
+    /// Tasks still running when the timeout elapses are aborted. Returns how
+    /// many tasks had to be aborted.
+    pub async fn shutdown(&self, timeout: Duration) -> usize {
+        self.token.cancel();
+        let mut tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        let total = tasks.len();
+
+        let drained = tokio::time::timeout(timeout, async {
+            while let Some(result) = tasks.join_next().await {
+                if let Err(e) = result {
+                    warn!("Background task failed during shutdown: {}", e);
+                }
+            }
+        })
+        .await;
+
+        if drained.is_ok() {
+            info!("{} background task(s) stopped", total);
+            return 0;
+        }
+
+        let aborted = tasks.len();
+        warn!("Aborting {} background task(s) still running after {:?}", aborted, timeout);
+        tasks.shutdown().await;
+        aborted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_long_running_task_aborted_within_timeout() {
+        let registry = TaskRegistry::default();
+        let dropped = Arc::new(AtomicBool::new(false));
+
+        struct SetOnDrop(Arc<AtomicBool>);
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        // Ignores the token entirely, like a poller stuck on a slow upstream
+        let guard = SetOnDrop(dropped.clone());
+        registry.spawn(|_token| async move {
+            let _guard = guard;
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+        });
+        assert_eq!(registry.running(), 1);
+
+        let started = tokio::time::Instant::now();
+        let aborted = registry.shutdown(Duration::from_secs(5)).await;
+
+        assert_eq!(aborted, 1);
+        assert_eq!(started.elapsed(), Duration::from_secs(5));
+        assert!(dropped.load(Ordering::SeqCst));
+        assert_eq!(registry.running(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cooperative_tasks_stop_without_waiting_for_timeout() {
+        let registry = TaskRegistry::default();
+        let cleaned_up = Arc::new(AtomicBool::new(false));
+
+        let flag = cleaned_up.clone();
+        registry.spawn(|token| async move {
+            token.cancelled().await;
+            flag.store(true, Ordering::SeqCst);
+        });
+        registry.spawn_cancellable(std::future::pending::<()>());
+
+        let started = tokio::time::Instant::now();
+        assert_eq!(registry.shutdown(Duration::from_secs(5)).await, 0);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(cleaned_up.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,366 @@
+//! Price aggregated across providers.
+//!
+//! A single provider can be stale or briefly wrong, so the prices endpoint also
+//! reports an [`AggregateQuote`]: the median of every provider's price for the
+//! same coin and currency. The median ignores one bad reading out of three, but
+//! a flaky provider still counts, so the providers that take part can be limited
+//! to a trusted set (`AGGREGATE_PROVIDERS`) while every provider's own quote is
+//! still returned as is.
+//!
+//! With the weighted strategy (`AGGREGATE_STRATEGY=weighted`) the aggregate is
+//! instead the mean of the prices weighted by each quote's `weight`, so sources
+//! can be trusted by degree rather than all or nothing.
+//!
+//! An "aggregate" of a single provider's price is no safer than that price, so
+//! pairs quoted by fewer than `AGGREGATE_MIN_PROVIDERS` trusted providers get no
+//! aggregate at all.
+//!
+//! A provider that is wildly wrong, e.g. off by a decimal point, would still drag
+//! the weighted mean, so quotes further than `AGGREGATE_MAX_DEVIATION_PERCENT`
+//! from the median are discarded first (see [`without_outliers`]).
+
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use super::{Coin, Currency, ProviderSource, Quote};
+
+/// Weight of a quote whose provider has no configured weight
+pub const DEFAULT_WEIGHT: f64 = 1.0;
+
+/// Fewest trusted quotes a pair needs for an aggregate by default
+pub const DEFAULT_MIN_PROVIDERS: NonZeroUsize = NonZeroUsize::new(2).unwrap();
+
+/// How far, in percent, a quote may be from the median before it is discarded by default
+pub const DEFAULT_MAX_DEVIATION_PERCENT: f64 = 10.0;
+
+/// How providers' prices are combined into an aggregate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateStrategy {
+    /// Median price; every provider counts the same
+    #[default]
+    Median,
+    /// Mean price weighted by each quote's `weight`
+    Weighted,
+}
+
+impl AggregateStrategy {
+    /// Accepted strategy names
+    pub const NAMES: &'static [&'static str] = &["median", "weighted"];
+}
+
+impl fmt::Display for AggregateStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AggregateStrategy::Median => "median",
+            AggregateStrategy::Weighted => "weighted",
+        })
+    }
+}
+
+impl FromStr for AggregateStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "median" => Ok(AggregateStrategy::Median),
+            "weighted" => Ok(AggregateStrategy::Weighted),
+            _ => anyhow::bail!(
+                "unknown aggregate strategy '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            ),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AggregateStrategy {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse()
+            .map_err(|_| serde::de::Error::unknown_variant(&raw, Self::NAMES))
+    }
+}
+
+/// Price for one coin and currency aggregated across several providers
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AggregateQuote {
+    /// The cryptocurrency being quoted
+    pub coin: Coin,
+    /// The fiat currency the price is denominated in
+    pub currency: Currency,
+    /// Aggregated price per single unit across `providers`
+    pub price: f64,
+    /// How `price` was aggregated
+    pub strategy: AggregateStrategy,
+    /// Amount of the cryptocurrency `total_price` is for
+    pub amount: f64,
+    /// `price` for `amount`
+    pub total_price: f64,
+    /// Providers whose quotes were aggregated, in the order they were returned
+    pub providers: Vec<ProviderSource>,
+    /// When the oldest aggregated quote was fetched
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Aggregates `quotes` into one quote per coin and currency using `strategy`.
+///
+/// Only quotes from providers in `trusted` are aggregated; `None` trusts every
+/// provider. With `max_deviation_percent`, outliers among those are discarded
+/// with [`without_outliers`]. Pairs appear in the order of their first quote,
+/// and pairs with fewer than `min_providers` quotes left have no aggregate. The
+/// weighted strategy also skips pairs whose quotes all weigh zero.
+pub fn aggregate(
+    quotes: &[Quote],
+    trusted: Option<&[ProviderSource]>,
+    strategy: AggregateStrategy,
+    min_providers: NonZeroUsize,
+    max_deviation_percent: Option<f64>,
+) -> Vec<AggregateQuote> {
+    let mut pairs: Vec<(Coin, Currency)> = Vec::new();
+    for quote in quotes {
+        if !pairs.contains(&(quote.coin, quote.currency)) {
+            pairs.push((quote.coin, quote.currency));
+        }
+    }
+
+    pairs
+        .into_iter()
+        .filter_map(|(coin, currency)| {
+            let mut included = trusted_quotes(quotes, coin, currency, trusted);
+            if let Some(max_deviation_percent) = max_deviation_percent {
+                included = without_outliers(included, max_deviation_percent);
+            }
+            if included.len() < min_providers.get() {
+                return None;
+            }
+            let timestamp = included.iter().map(|quote| quote.timestamp).min()?;
+            let price = match strategy {
+                AggregateStrategy::Median => median(included.iter().map(|quote| quote.price).collect()),
+                AggregateStrategy::Weighted => weighted_mean(&included)?,
+            };
+            let amount = included[0].quote_per_amount.amount;
+
+            Some(AggregateQuote {
+                coin,
+                currency,
+                price,
+                strategy,
+                amount,
+                total_price: price * amount,
+                providers: included.iter().map(|quote| quote.provider).collect(),
+                timestamp,
+            })
+        })
+        .collect()
+}
+
+/// The quotes for `coin` in `currency` from providers in `trusted` (every provider for `None`)
+pub fn trusted_quotes<'a>(
+    quotes: &'a [Quote],
+    coin: Coin,
+    currency: Currency,
+    trusted: Option<&[ProviderSource]>,
+) -> Vec<&'a Quote> {
+    quotes
+        .iter()
+        .filter(|quote| quote.coin == coin && quote.currency == currency)
+        .filter(|quote| trusted.is_none_or(|trusted| trusted.contains(&quote.provider)))
+        .collect()
+}
+
+/// Discards quotes more than `max_deviation_percent` away from the median of `quotes`.
+///
+/// The median is taken over every quote, the suspect one included: with three
+/// providers, the median of the two others would be their mean, which the
+/// outlier itself skews. Two quotes are kept as they are, since there is no
+/// telling which of them is wrong. Each discarded quote is logged.
+pub fn without_outliers(quotes: Vec<&Quote>, max_deviation_percent: f64) -> Vec<&Quote> {
+    if quotes.len() <= 2 {
+        return quotes;
+    }
+    let median = median(quotes.iter().map(|quote| quote.price).collect());
+    if median <= 0.0 {
+        return quotes;
+    }
+
+    quotes
+        .into_iter()
+        .filter(|quote| {
+            let deviation = (quote.price - median).abs() / median * 100.0;
+            let outlier = deviation > max_deviation_percent;
+            if outlier {
+                warn!(
+                    "Discarding {} {}/{} price {} from the aggregate: {:.1}% off the median {}",
+                    quote.provider, quote.coin, quote.currency, quote.price, deviation, median
+                );
+            }
+            !outlier
+        })
+        .collect()
+}
+
+/// Middle value of `values`, or the mean of the two middle values for an even count
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+/// Mean of the quotes' prices weighted by their `weight`, or `None` if they all weigh zero
+fn weighted_mean(quotes: &[&Quote]) -> Option<f64> {
+    let weight = |quote: &Quote| quote.weight.unwrap_or(DEFAULT_WEIGHT);
+    let total_weight: f64 = quotes.iter().map(|quote| weight(quote)).sum();
+    (total_weight > 0.0)
+        .then(|| quotes.iter().map(|quote| quote.price * weight(quote)).sum::<f64>() / total_weight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: NonZeroUsize = NonZeroUsize::MIN;
+
+    fn quote(provider: ProviderSource, currency: Currency, price: f64) -> Quote {
+        Quote::new(Coin::ETH, currency, price, provider)
+    }
+
+    #[test]
+    fn test_median_per_currency() {
+        let quotes = [
+            quote(ProviderSource::CoinGecko, Currency::USD, 2000.0),
+            quote(ProviderSource::Binance, Currency::USD, 2010.0),
+            quote(ProviderSource::CoinGecko, Currency::EUR, 1850.0),
+            quote(ProviderSource::OneInch, Currency::USD, 1500.0),
+            quote(ProviderSource::OneInch, Currency::EUR, 1860.0),
+        ];
+
+        let aggregates = aggregate(&quotes, None, AggregateStrategy::Median, ONE, None);
+        assert_eq!(aggregates.len(), 2);
+        assert_eq!((aggregates[0].currency, aggregates[0].price), (Currency::USD, 2000.0));
+        assert_eq!(
+            aggregates[0].providers,
+            [ProviderSource::CoinGecko, ProviderSource::Binance, ProviderSource::OneInch]
+        );
+        // Even count: mean of the middle two
+        assert_eq!((aggregates[1].currency, aggregates[1].price), (Currency::EUR, 1855.0));
+    }
+
+    #[test]
+    fn test_untrusted_providers_do_not_move_the_aggregate() {
+        let trusted = [ProviderSource::CoinGecko, ProviderSource::Binance];
+        let honest = [
+            quote(ProviderSource::CoinGecko, Currency::USD, 2000.0),
+            quote(ProviderSource::Binance, Currency::USD, 2010.0),
+        ];
+        let mut skewed = honest.to_vec();
+        skewed.push(quote(ProviderSource::OneInch, Currency::USD, 9000.0));
+        skewed.push(quote(ProviderSource::CoinMarketCap, Currency::USD, 9500.0));
+
+        let aggregate_of = |quotes: &[Quote]| aggregate(quotes, Some(&trusted), AggregateStrategy::Median, ONE, None).remove(0);
+        assert_eq!(aggregate_of(&honest).price, 2005.0);
+        assert_eq!(aggregate_of(&skewed).price, 2005.0);
+        assert_eq!(aggregate_of(&skewed).providers, trusted);
+
+        // Trusting everyone lets the outliers in
+        assert_eq!(aggregate(&skewed, None, AggregateStrategy::Median, ONE, None)[0].price, 5505.0);
+    }
+
+    #[test]
+    fn test_no_aggregate_without_trusted_quotes() {
+        let quotes = [quote(ProviderSource::OneInch, Currency::USD, 2000.0).with_amount(2.0)];
+
+        assert!(aggregate(&quotes, Some(&[ProviderSource::CoinGecko]), AggregateStrategy::Median, ONE, None).is_empty());
+        let aggregates = aggregate(&quotes, None, AggregateStrategy::Median, ONE, None);
+        assert_eq!((aggregates[0].amount, aggregates[0].total_price), (2.0, 4000.0));
+    }
+
+    #[test]
+    fn test_weights_move_the_weighted_aggregate() {
+        let weighted = |provider, price, weight| Quote { weight, ..quote(provider, Currency::USD, price) };
+        let quotes = [
+            weighted(ProviderSource::CoinGecko, 2000.0, Some(1.0)),
+            weighted(ProviderSource::Binance, 2100.0, Some(0.25)),
+            // No configured weight counts fully
+            weighted(ProviderSource::OneInch, 2000.0, None),
+        ];
+
+        // (2000 + 525 + 2000) / 2.25
+        let aggregates = aggregate(&quotes, None, AggregateStrategy::Weighted, ONE, None);
+        assert!((aggregates[0].price - 2011.111111).abs() < 1e-6);
+        assert_eq!(aggregates[0].strategy, AggregateStrategy::Weighted);
+
+        // The median ignores weights
+        assert_eq!(aggregate(&quotes, None, AggregateStrategy::Median, ONE, None)[0].price, 2000.0);
+
+        let untrusted = [weighted(ProviderSource::CoinGecko, 2000.0, Some(0.0))];
+        assert!(aggregate(&untrusted, None, AggregateStrategy::Weighted, ONE, None).is_empty());
+    }
+
+    #[test]
+    fn test_too_few_providers_have_no_aggregate() {
+        let quotes = [
+            quote(ProviderSource::CoinGecko, Currency::USD, 2000.0),
+            quote(ProviderSource::Binance, Currency::USD, 2010.0),
+            quote(ProviderSource::CoinGecko, Currency::EUR, 1850.0),
+        ];
+
+        let aggregates = aggregate(&quotes, None, AggregateStrategy::Median, DEFAULT_MIN_PROVIDERS, None);
+        assert_eq!(aggregates.len(), 1);
+        assert_eq!((aggregates[0].currency, aggregates[0].price), (Currency::USD, 2005.0));
+
+        // Untrusted providers do not count towards the minimum
+        let trusted = [ProviderSource::CoinGecko];
+        assert!(aggregate(&quotes, Some(&trusted), AggregateStrategy::Median, DEFAULT_MIN_PROVIDERS, None).is_empty());
+    }
+
+    #[test]
+    fn test_outlier_is_excluded_from_the_aggregate() {
+        let quotes = [
+            quote(ProviderSource::CoinGecko, Currency::USD, 2000.0),
+            quote(ProviderSource::Binance, Currency::USD, 2010.0),
+            // Off by a decimal point
+            quote(ProviderSource::OneInch, Currency::USD, 200.0),
+        ];
+
+        let aggregates = aggregate(&quotes, None, AggregateStrategy::Weighted, ONE, Some(DEFAULT_MAX_DEVIATION_PERCENT));
+        assert_eq!(aggregates[0].price, 2005.0);
+        assert_eq!(aggregates[0].providers, [ProviderSource::CoinGecko, ProviderSource::Binance]);
+
+        // Without the filter the outlier drags the mean down
+        let unfiltered = aggregate(&quotes, None, AggregateStrategy::Weighted, ONE, None);
+        assert!((unfiltered[0].price - 1403.333333).abs() < 1e-6);
+
+        // Discarded quotes do not count towards the minimum
+        let three = NonZeroUsize::new(3).unwrap();
+        assert!(aggregate(&quotes, None, AggregateStrategy::Median, three, Some(DEFAULT_MAX_DEVIATION_PERCENT)).is_empty());
+    }
+
+    #[test]
+    fn test_two_quotes_are_never_outliers() {
+        let quotes = [
+            quote(ProviderSource::CoinGecko, Currency::USD, 2000.0),
+            quote(ProviderSource::OneInch, Currency::USD, 200.0),
+        ];
+        let aggregates = aggregate(&quotes, None, AggregateStrategy::Median, ONE, Some(DEFAULT_MAX_DEVIATION_PERCENT));
+        assert_eq!(aggregates[0].providers.len(), 2);
+        assert_eq!(aggregates[0].price, 1100.0);
+    }
+
+    #[test]
+    fn test_strategy_names() {
+        assert_eq!("Weighted".parse::<AggregateStrategy>().unwrap(), AggregateStrategy::Weighted);
+        assert_eq!(AggregateStrategy::default().to_string(), "median");
+        assert!("mean".parse::<AggregateStrategy>().is_err());
+    }
+}
@@ -0,0 +1,565 @@
+//! Binance price provider and real-time price feed.
+//!
+//! This module streams ticker updates from Binance's public WebSocket API
+//! (`wss://stream.binance.com:9443/ws/<symbol>@ticker`) into a [`LatestPriceCache`].
+//! No API key is required. The feed reconnects with exponential backoff when the
+//! connection drops.
+//!
+//! [`Binance`] queries the public REST API instead, and is the provider to use
+//! for order-book depth (`/api/v3/depth`). Its quotes are priced at the mid of
+//! the best bid and ask (`/api/v3/ticker/bookTicker`), which they also carry;
+//! streamed quotes carry them next to the last traded price.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use boltzmann::domains::crypto::{Coin, Currency};
+//! use boltzmann::domains::crypto::binance::BinanceTickerFeed;
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let feed = BinanceTickerFeed::new(Coin::ETH, Currency::USD)?;
+//! let cache = feed.cache();
+//! feed.spawn();
+//!
+//! if let Some(quote) = cache.get(Coin::ETH, Currency::USD) {
+//!     println!("ETH price: ${:.2}", quote.price);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, info, warn};
+
+use super::feed::LatestPriceCache;
+use super::{Coin, Currency, MarketDepth, PriceProvider, ProviderSource, Quote};
+use crate::core::errors::{ErrorContext, NotSupported, Result};
+use crate::core::parse::parse_f64_str;
+use crate::infrastructure::http::{build_client, read_json_body_limited, HttpClientConfig, DEFAULT_MAX_RESPONSE_BYTES};
+use crate::infrastructure::http::retry::RetryPolicy;
+
+/// Binance public WebSocket stream endpoint
+const STREAM_URL: &str = "wss://stream.binance.com:9443/ws";
+
+/// Binance public REST API
+pub const API_URL: &str = "https://api.binance.com";
+
+/// Order-book levels requested per side by default
+const DEFAULT_DEPTH_LIMIT: usize = 20;
+
+/// Order book snapshot as returned by `/api/v3/depth`
+#[derive(Debug, Deserialize)]
+struct OrderBook {
+    /// `[price, quantity]` levels, best (highest) bid first
+    bids: Vec<[String; 2]>,
+    /// `[price, quantity]` levels, best (lowest) ask first
+    asks: Vec<[String; 2]>,
+}
+
+/// Best bid and ask as returned by `/api/v3/ticker/bookTicker`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BookTicker {
+    symbol: String,
+    bid_price: String,
+    ask_price: String,
+}
+
+/// 24h ticker event as sent on the `<symbol>@ticker` stream
+#[derive(Debug, Deserialize)]
+struct TickerEvent {
+    /// Trading pair symbol, e.g. `ETHUSDT`
+    #[serde(rename = "s")]
+    symbol: String,
+    /// Last traded price
+    #[serde(rename = "c")]
+    last_price: String,
+    /// Best bid price
+    #[serde(rename = "b")]
+    best_bid: Option<String>,
+    /// Best ask price
+    #[serde(rename = "a")]
+    best_ask: Option<String>,
+    /// Event time in milliseconds since the epoch
+    #[serde(rename = "E")]
+    event_time: i64,
+}
+
+/// Returns the Binance trading pair for a coin/currency, if Binance lists one.
+///
+/// USD prices come from the USDT market, which is where Binance's USD liquidity is.
+pub fn symbol(coin: Coin, currency: Currency) -> Option<String> {
+    let quote_asset = match currency {
+        Currency::USD => "USDT",
+        Currency::EUR => "EUR",
+        _ => return None,
+    };
+    Some(format!("{}{}", coin, quote_asset))
+}
+
+/// Binance REST price provider.
+///
+/// Serves latest trade prices and order-book depth from Binance's public REST
+/// API. No API key is required.
+pub struct Binance {
+    client: Client,
+    base_url: String,
+    depth_limit: usize,
+    max_response_bytes: usize,
+    retry: RetryPolicy,
+}
+
+impl Binance {
+    /// Creates a new Binance provider against the public REST API.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be created.
+    pub fn new() -> Result<Self> {
+        let client = build_client(&HttpClientConfig::default())
+            .crypto_context("creating HTTP client for Binance")?;
+
+        Ok(Self::new_with_client(client))
+    }
+
+    /// Creates a new Binance provider using an existing HTTP client.
+    pub fn new_with_client(client: Client) -> Self {
+        Self {
+            client,
+            base_url: API_URL.to_string(),
+            depth_limit: DEFAULT_DEPTH_LIMIT,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Sends requests to a different REST endpoint, e.g. a regional mirror.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets how many order-book levels per side are summarized in market depth.
+    pub fn with_depth_limit(mut self, levels: usize) -> Self {
+        self.depth_limit = levels;
+        self
+    }
+
+    /// Sets the largest response body accepted from the API, in bytes.
+    pub fn with_max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
+    }
+
+    /// Retries transient failures (connection errors, timeouts, `502`-`504`)
+    /// under `policy`; by default requests are never retried.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// GETs `path` from the REST API and returns the response body.
+    async fn get(&self, path: &str, query: &[(&str, String)]) -> Result<String> {
+        let url = format!("{}{}", self.base_url, path);
+        let response = self.retry.send(|| self.client.get(&url).query(query)).await
+            .crypto_context("sending request to Binance API")?;
+
+        let status = response.status();
+        let body = read_json_body_limited(response, self.max_response_bytes).await
+            .crypto_context("reading response body from Binance API")?;
+        if !status.is_success() {
+            anyhow::bail!("Binance API returned {}: {}", status, body);
+        }
+        Ok(body)
+    }
+}
+
+#[async_trait]
+impl PriceProvider for Binance {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        ProviderSource::Binance.name()
+    }
+
+    /// Returns the mid of the best bid and ask for each requested currency Binance lists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if none of the currencies has a Binance market or a
+    /// request fails.
+    #[tracing::instrument(skip(self), fields(provider = "binance"))]
+    async fn get_quotes(
+        &self,
+        coin: Coin,
+        currencies: &[Currency],
+    ) -> std::result::Result<Vec<Quote>, Self::Error> {
+        let mut quotes = Vec::new();
+
+        for &currency in currencies {
+            let Some(symbol) = symbol(coin, currency) else {
+                debug!("Binance has no market for {}/{}", coin, currency);
+                continue;
+            };
+
+            let body = self.get("/api/v3/ticker/bookTicker", &[("symbol", symbol)]).await?;
+            quotes.push(parse_book_ticker(&body, coin, currency)?);
+        }
+
+        if quotes.is_empty() {
+            anyhow::bail!("Binance has no market for {} in {:?}", coin, currencies);
+        }
+        Ok(quotes)
+    }
+
+    /// Summarizes the top `depth_limit` levels of the pair's order book.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NotSupported`] if Binance has no market for the pair, or an
+    /// error if the request fails or the book is empty.
+    #[tracing::instrument(skip(self), fields(provider = "binance"))]
+    async fn get_market_depth(
+        &self,
+        coin: Coin,
+        currency: Currency,
+    ) -> std::result::Result<MarketDepth, Self::Error> {
+        let symbol = symbol(coin, currency)
+            .ok_or_else(|| NotSupported::new(format!("Binance market depth for {}/{}", coin, currency)))?;
+
+        let body = self
+            .get("/api/v3/depth", &[("symbol", symbol), ("limit", self.depth_limit.to_string())])
+            .await?;
+
+        parse_depth(&body, coin, currency)
+    }
+}
+
+/// Parses a `/api/v3/ticker/bookTicker` response into a quote priced at the mid.
+///
+/// # Errors
+///
+/// Returns an error if the body is not a book ticker or a price is invalid.
+fn parse_book_ticker(text: &str, coin: Coin, currency: Currency) -> Result<Quote> {
+    let ticker: BookTicker = serde_json::from_str(text)
+        .context("parsing Binance book ticker")?;
+    let bid = parse_f64_str(&ticker.bid_price, "bidPrice")
+        .with_context(|| format!("parsing best bid for {}", ticker.symbol))?;
+    let ask = parse_f64_str(&ticker.ask_price, "askPrice")
+        .with_context(|| format!("parsing best ask for {}", ticker.symbol))?;
+    let price = (bid + ask) / 2.0;
+
+    Ok(Quote {
+        market: Some(ticker.symbol),
+        bid: Some(bid),
+        ask: Some(ask),
+        ..Quote::new(coin, currency, price, ProviderSource::Binance)
+    })
+}
+
+/// Parses a `/api/v3/depth` order book into a depth summary for the given pair.
+///
+/// # Errors
+///
+/// Returns an error if the body is not an order book, a level is not numeric,
+/// or either side of the book is empty.
+fn parse_depth(text: &str, coin: Coin, currency: Currency) -> Result<MarketDepth> {
+    let book: OrderBook = serde_json::from_str(text)
+        .context("parsing Binance order book")?;
+
+    let side = |levels: &[[String; 2]], name: &str| -> Result<(f64, f64)> {
+        let mut best = None;
+        let mut volume = 0.0;
+        for [price, quantity] in levels {
+            let price = parse_f64_str(price, name)
+                .with_context(|| format!("parsing {} price", name))?;
+            best.get_or_insert(price);
+            volume += parse_f64_str(quantity, name)
+                .with_context(|| format!("parsing {} quantity", name))?;
+        }
+        let best = best.with_context(|| format!("Binance order book has no {}", name))?;
+        Ok((best, volume))
+    };
+
+    let (best_bid, bid_volume) = side(&book.bids, "bids")?;
+    let (best_ask, ask_volume) = side(&book.asks, "asks")?;
+
+    Ok(MarketDepth {
+        coin,
+        currency,
+        provider: ProviderSource::Binance,
+        best_bid,
+        best_ask,
+        spread: best_ask - best_bid,
+        bid_volume,
+        ask_volume,
+        levels: book.bids.len().max(book.asks.len()),
+        timestamp: chrono::Utc::now(),
+    })
+}
+
+/// Streams Binance ticker updates for one pair into a shared cache.
+pub struct BinanceTickerFeed {
+    url: String,
+    coin: Coin,
+    currency: Currency,
+    cache: Arc<LatestPriceCache>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl BinanceTickerFeed {
+    /// Creates a feed for the given pair against Binance's public stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if Binance has no market for the coin/currency pair.
+    pub fn new(coin: Coin, currency: Currency) -> Result<Self> {
+        let symbol = symbol(coin, currency)
+            .with_context(|| format!("Binance has no market for {}/{}", coin, currency))?;
+        let url = format!("{}/{}@ticker", STREAM_URL, symbol.to_lowercase());
+
+        Ok(Self::new_with_url(url, coin, currency))
+    }
+
+    /// Creates a feed reading ticker events from a custom WebSocket URL.
+    pub fn new_with_url(url: String, coin: Coin, currency: Currency) -> Self {
+        Self {
+            url,
+            coin,
+            currency,
+            cache: Arc::new(LatestPriceCache::default()),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets the reconnect delay, doubling from `initial` up to `max` on repeated failures.
+    pub fn with_reconnect_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Writes updates into an existing cache, so several feeds can share one.
+    pub fn with_cache(mut self, cache: Arc<LatestPriceCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Returns the cache this feed writes to.
+    pub fn cache(&self) -> Arc<LatestPriceCache> {
+        Arc::clone(&self.cache)
+    }
+
+    /// Runs the feed on a background task until the task is aborted.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    /// Connects, streams updates into the cache, and reconnects with backoff forever.
+    pub async fn run(self) {
+        let mut backoff = self.initial_backoff;
+
+        loop {
+            match self.stream_once().await {
+                Ok(()) => {
+                    info!("Binance stream {} closed, reconnecting", self.url);
+                    backoff = self.initial_backoff;
+                }
+                Err(e) => warn!("Binance stream {} failed: {:#}", self.url, e),
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(self.max_backoff);
+        }
+    }
+
+    /// Streams one connection until the server closes it or an error occurs.
+    async fn stream_once(&self) -> Result<()> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .context("connecting to Binance WebSocket stream")?;
+        info!("📡 Connected to Binance stream {}", self.url);
+
+        while let Some(message) = socket.next().await {
+            match message.context("reading Binance WebSocket message")? {
+                Message::Text(text) => match parse_ticker(&text, self.coin, self.currency) {
+                    Ok(quote) => self.cache.update(quote),
+                    Err(e) => debug!("Ignoring Binance message: {:#}", e),
+                },
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a ticker event into a quote for the given pair.
+///
+/// # Errors
+///
+/// Returns an error if the message is not a ticker event or its price is invalid.
+fn parse_ticker(text: &str, coin: Coin, currency: Currency) -> Result<Quote> {
+    let event: TickerEvent = serde_json::from_str(text)
+        .context("parsing Binance ticker event")?;
+    let price = parse_f64_str(&event.last_price, "c")
+        .with_context(|| format!("parsing last price for {}", event.symbol))?;
+    let timestamp = chrono::DateTime::from_timestamp_millis(event.event_time)
+        .unwrap_or_else(chrono::Utc::now);
+    // The book is informational here, so a malformed side is left out rather than dropping the tick
+    let book_side = |price: &Option<String>, field| price.as_deref().and_then(|price| parse_f64_str(price, field).ok());
+
+    Ok(Quote {
+        timestamp,
+        market: Some(event.symbol),
+        bid: book_side(&event.best_bid, "b"),
+        ask: book_side(&event.best_ask, "a"),
+        ..Quote::new(coin, currency, price, ProviderSource::Binance)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::SinkExt;
+    use tokio::net::TcpListener;
+
+    fn ticker(price: &str) -> String {
+        format!(r#"{{"e":"24hrTicker","E":1700000000000,"s":"ETHUSDT","c":"{}","b":"1999.9","a":"2000.1"}}"#, price)
+    }
+
+    #[test]
+    fn test_symbol_mapping() {
+        assert_eq!(symbol(Coin::ETH, Currency::USD).as_deref(), Some("ETHUSDT"));
+        assert_eq!(symbol(Coin::ETH, Currency::EUR).as_deref(), Some("ETHEUR"));
+        assert_eq!(symbol(Coin::ETH, Currency::CHF), None);
+        assert!(BinanceTickerFeed::new(Coin::ETH, Currency::CHF).is_err());
+    }
+
+    #[test]
+    fn test_parse_ticker() {
+        let quote = parse_ticker(&ticker("2000.55"), Coin::ETH, Currency::USD).unwrap();
+        assert_eq!(quote.price, 2000.55);
+        assert!(matches!(quote.provider, ProviderSource::Binance));
+        assert_eq!(quote.market.as_deref(), Some("ETHUSDT"));
+        assert_eq!((quote.bid, quote.ask), (Some(1999.9), Some(2000.1)));
+        assert_eq!(quote.timestamp.timestamp_millis(), 1_700_000_000_000);
+
+        assert!(parse_ticker(r#"{"result":null,"id":1}"#, Coin::ETH, Currency::USD).is_err());
+    }
+
+    #[test]
+    fn test_parse_book_ticker() {
+        let body = r#"{"symbol":"ETHUSDT","bidPrice":"2000.10","bidQty":"1.5","askPrice":"2000.30","askQty":"2.0"}"#;
+        let quote = parse_book_ticker(body, Coin::ETH, Currency::USD).unwrap();
+        assert_eq!((quote.bid, quote.ask), (Some(2000.1), Some(2000.3)));
+        assert!((quote.price - 2000.2).abs() < 1e-9);
+        assert_eq!(quote.unit_price, quote.price);
+        assert_eq!(quote.market.as_deref(), Some("ETHUSDT"));
+
+        assert!(parse_book_ticker(r#"{"symbol":"ETHUSDT","price":"2000.10"}"#, Coin::ETH, Currency::USD).is_err());
+    }
+
+    const ORDER_BOOK: &str = r#"{
+        "lastUpdateId": 1027024,
+        "bids": [["2000.10", "1.5"], ["2000.00", "3.25"], ["1999.50", "10"]],
+        "asks": [["2000.30", "0.75"], ["2000.50", "2.0"]]
+    }"#;
+
+    #[test]
+    fn test_parse_depth() {
+        let depth = parse_depth(ORDER_BOOK, Coin::ETH, Currency::USD).unwrap();
+
+        assert_eq!(depth.best_bid, 2000.10);
+        assert_eq!(depth.best_ask, 2000.30);
+        assert!((depth.spread - 0.20).abs() < 1e-9);
+        assert_eq!(depth.bid_volume, 14.75);
+        assert_eq!(depth.ask_volume, 2.75);
+        assert_eq!(depth.levels, 3);
+        assert!(matches!(depth.provider, ProviderSource::Binance));
+    }
+
+    #[test]
+    fn test_parse_depth_rejects_empty_or_malformed_books() {
+        assert!(parse_depth(r#"{"lastUpdateId":1,"bids":[],"asks":[["1","1"]]}"#, Coin::ETH, Currency::USD).is_err());
+        assert!(parse_depth(r#"{"lastUpdateId":1,"bids":[["x","1"]],"asks":[["1","1"]]}"#, Coin::ETH, Currency::USD).is_err());
+        assert!(parse_depth(r#"{"code":-1121,"msg":"Invalid symbol."}"#, Coin::ETH, Currency::USD).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_market_depth_from_rest_api() {
+        use axum::{extract::Query, routing::get, Router};
+        use std::collections::HashMap;
+
+        let app = Router::new().route(
+            "/api/v3/depth",
+            get(|Query(query): Query<HashMap<String, String>>| async move {
+                assert_eq!(query["symbol"], "ETHUSDT");
+                assert_eq!(query["limit"], "5");
+                ORDER_BOOK
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let provider = Binance::new_with_client(Client::new())
+            .with_base_url(base_url)
+            .with_depth_limit(5);
+        let depth = provider.get_market_depth(Coin::ETH, Currency::USD).await.unwrap();
+        assert_eq!(depth.best_bid, 2000.10);
+
+        let err = provider.get_market_depth(Coin::ETH, Currency::CHF).await.unwrap_err();
+        assert!(err.downcast_ref::<NotSupported>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_feed_updates_cache_and_reconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // Each connection sends one ticker and closes, forcing the feed to reconnect
+        tokio::spawn(async move {
+            for price in ["2000.5", "2001.25"] {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                ws.send(Message::text(ticker(price))).await.unwrap();
+                ws.close(None).await.unwrap();
+            }
+        });
+
+        let feed = BinanceTickerFeed::new_with_url(url, Coin::ETH, Currency::USD)
+            .with_reconnect_backoff(Duration::from_millis(10), Duration::from_millis(10));
+        let cache = feed.cache();
+        let handle = feed.spawn();
+
+        let mut seen = Vec::new();
+        for _ in 0..200 {
+            if let Some(quote) = cache.get(Coin::ETH, Currency::USD)
+                && seen.last() != Some(&quote.price)
+            {
+                seen.push(quote.price);
+            }
+            if seen.last() == Some(&2001.25) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        handle.abort();
+
+        assert_eq!(seen.last(), Some(&2001.25));
+        assert!(cache.get(Coin::ETH, Currency::EUR).is_none());
+    }
+}
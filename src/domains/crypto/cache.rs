@@ -0,0 +1,534 @@
+//! Quote caching across requests.
+//!
+//! A [`QuoteCache`] stores the latest quote per `(Coin, Currency, ProviderSource)`
+//! for a limited time, so repeated requests within the TTL do not hit upstream
+//! APIs again. [`MemoryQuoteCache`] keeps quotes in-process and is the default;
+//! deployments running several replicas can share one cache through Redis
+//! (see `infrastructure::redis`, behind the `redis` feature).
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use boltzmann::domains::crypto::{Coin, Currency, PriceProvider, ProviderSource};
+//! use boltzmann::domains::crypto::cache::{CachedPriceProvider, MemoryQuoteCache};
+//! use boltzmann::domains::crypto::coingecko::CoinGecko;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let cache = Arc::new(MemoryQuoteCache::new(Duration::from_secs(10)));
+//! let provider = CachedPriceProvider::new(ProviderSource::CoinGecko, Box::new(CoinGecko::new(None)?), cache);
+//!
+//! // The second call within 10 seconds is served from the cache
+//! let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await?;
+//! let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tracing::{debug, warn};
+
+use super::{Coin, CurrenciesNotPriced, Currency, DynPriceProvider, MarketDepth, PriceProvider, ProviderSource, Quote};
+use crate::core::errors::Result;
+
+/// Default time a cached quote stays fresh
+pub const DEFAULT_QUOTE_CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// Identifies one cached quote
+pub type QuoteKey = (Coin, Currency, ProviderSource);
+
+/// A quote read back from a [`QuoteCache`]
+#[derive(Debug, Clone)]
+pub struct CachedQuote {
+    /// The quote as it was stored
+    pub quote: Quote,
+    /// Time since the quote was stored
+    pub age: Duration,
+}
+
+impl CachedQuote {
+    /// The quote, marked as served from the cache with its age.
+    pub fn into_quote(self) -> Quote {
+        Quote { cached: true, age_seconds: self.age.as_secs(), ..self.quote }
+    }
+}
+
+/// Storage for recently fetched quotes.
+///
+/// Implementations decide how long entries live; [`get`](QuoteCache::get) must
+/// never return an expired quote.
+#[async_trait]
+pub trait QuoteCache: fmt::Debug + Send + Sync {
+    /// Returns the cached quote for `key`, if one is still fresh.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache backend cannot be reached.
+    async fn get(&self, key: QuoteKey) -> Result<Option<CachedQuote>>;
+
+    /// Stores `quote` under its coin, currency and provider.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache backend cannot be reached.
+    async fn put(&self, quote: &Quote) -> Result<()>;
+
+    /// Drops every fresh quote, or only `provider`'s, so the next read misses.
+    ///
+    /// Returns how many fresh quotes were dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache backend cannot be reached.
+    async fn flush(&self, provider: Option<ProviderSource>) -> Result<usize>;
+}
+
+/// Shared handle to a cache backend
+pub type DynQuoteCache = Arc<dyn QuoteCache>;
+
+/// The key a quote is stored under
+pub fn quote_key(quote: &Quote) -> QuoteKey {
+    (quote.coin, quote.currency, quote.provider)
+}
+
+/// `key` as `<coin>:<currency>:<provider>`, e.g. `ETH:USD:coingecko`.
+///
+/// Backends derive their storage keys from it, and debug responses echo it in
+/// `X-Cache-Key`.
+pub fn quote_key_label((coin, currency, provider): QuoteKey) -> String {
+    format!("{}:{}:{}", coin, currency, provider)
+}
+
+/// Every key a quote can be stored under, or only `provider`'s.
+///
+/// Coins, currencies and providers are closed sets, so backends that cannot
+/// list their keys can still address all of them.
+pub fn all_quote_keys(provider: Option<ProviderSource>) -> impl Iterator<Item = QuoteKey> {
+    let providers = match provider {
+        Some(provider) => vec![provider],
+        None => ProviderSource::all().to_vec(),
+    };
+    providers.into_iter().flat_map(|provider| {
+        Coin::all()
+            .iter()
+            .flat_map(move |&coin| Currency::all().iter().map(move |&currency| (coin, currency, provider)))
+    })
+}
+
+/// In-process cache, local to one server instance.
+#[derive(Debug)]
+pub struct MemoryQuoteCache {
+    ttl: Duration,
+    quotes: RwLock<HashMap<QuoteKey, (Quote, Instant)>>,
+}
+
+impl MemoryQuoteCache {
+    /// Creates an empty cache whose entries expire after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, quotes: RwLock::default() }
+    }
+}
+
+impl Default for MemoryQuoteCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUOTE_CACHE_TTL)
+    }
+}
+
+#[async_trait]
+impl QuoteCache for MemoryQuoteCache {
+    async fn get(&self, key: QuoteKey) -> Result<Option<CachedQuote>> {
+        let quotes = self.quotes.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(quotes
+            .get(&key)
+            .map(|(quote, stored_at)| CachedQuote { quote: quote.clone(), age: stored_at.elapsed() })
+            .filter(|cached| cached.age < self.ttl))
+    }
+
+    async fn put(&self, quote: &Quote) -> Result<()> {
+        self.quotes
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(quote_key(quote), (quote.clone(), Instant::now()));
+        Ok(())
+    }
+
+    async fn flush(&self, provider: Option<ProviderSource>) -> Result<usize> {
+        let mut quotes = self.quotes.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut flushed = 0;
+        quotes.retain(|&(_, _, source), (_, stored_at)| {
+            if provider.is_some_and(|provider| provider != source) {
+                return true;
+            }
+            // Expired entries go too, but were no longer served
+            if stored_at.elapsed() < self.ttl {
+                flushed += 1;
+            }
+            false
+        });
+        Ok(flushed)
+    }
+}
+
+/// Serves quotes from a [`QuoteCache`], calling the wrapped provider on a miss.
+///
+/// A request is only answered from the cache when every requested currency is
+/// cached; otherwise the provider is called for all of them and the results are
+/// stored. With [`with_fetch_currencies`](Self::with_fetch_currencies), a miss
+/// also fetches those currencies in the same call, so a request for another
+/// one shortly after is a hit. Quotes served from the cache are marked `cached` with their
+/// `age_seconds`. Cache failures are logged and treated as misses, so an unreachable
+/// cache never fails a request.
+pub struct CachedPriceProvider {
+    source: ProviderSource,
+    provider: DynPriceProvider,
+    cache: DynQuoteCache,
+    fetch_currencies: Vec<Currency>,
+}
+
+impl CachedPriceProvider {
+    /// Wraps `provider`, whose quotes are cached under `source`.
+    pub fn new(source: ProviderSource, provider: DynPriceProvider, cache: DynQuoteCache) -> Self {
+        Self { source, provider, cache, fetch_currencies: Vec::new() }
+    }
+
+    /// Also fetches and caches `currencies` on every miss.
+    ///
+    /// Only worth it for providers that price several currencies in one
+    /// upstream request (see [`ProviderSource::batches_currencies`]); others
+    /// would make a request per extra currency.
+    pub fn with_fetch_currencies(mut self, currencies: impl IntoIterator<Item = Currency>) -> Self {
+        self.fetch_currencies = currencies.into_iter().collect();
+        self
+    }
+
+    /// Cached quotes for every currency, or `None` if any is missing.
+    async fn cached(&self, coin: Coin, currencies: &[Currency]) -> Option<Vec<Quote>> {
+        let mut quotes = Vec::with_capacity(currencies.len());
+        for &currency in currencies {
+            match self.cache.get((coin, currency, self.source)).await {
+                Ok(Some(cached)) => quotes.push(cached.into_quote()),
+                Ok(None) => return None,
+                Err(e) => {
+                    warn!("Quote cache lookup failed: {:#}", e);
+                    return None;
+                }
+            }
+        }
+        Some(quotes)
+    }
+}
+
+#[async_trait]
+impl PriceProvider for CachedPriceProvider {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Returns cached quotes when all are fresh, otherwise fetches and caches them.
+    ///
+    /// A fetch also asks for the configured extra currencies and caches their
+    /// quotes, but only the requested currencies are returned. If that wider
+    /// request fails only because a strict provider lacks some of the extra
+    /// currencies, it is retried with only the requested currencies. Any other
+    /// failure, such as a timeout or rate limit, is returned without a retry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the quotes are not cached and the provider fails, or
+    /// prices none of the requested currencies.
+    async fn get_quotes(
+        &self,
+        coin: Coin,
+        currencies: &[Currency],
+    ) -> std::result::Result<Vec<Quote>, Self::Error> {
+        if !currencies.is_empty()
+            && let Some(quotes) = self.cached(coin, currencies).await
+        {
+            debug!("Serving {} {:?} from cache for {}", coin, currencies, self.source);
+            return Ok(quotes);
+        }
+
+        let mut request = currencies.to_vec();
+        request.extend(self.fetch_currencies.iter().filter(|currency| !currencies.contains(currency)));
+        let quotes = match self.provider.get_quotes(coin, &request).await {
+            Ok(quotes) => quotes,
+            Err(e) if only_extras_unpriced(&e, currencies) => {
+                debug!("{} failed for {:?}, retrying with {:?} only: {:#}", self.source, request, currencies, e);
+                request.truncate(currencies.len());
+                self.provider.get_quotes(coin, &request).await?
+            }
+            Err(e) => return Err(e),
+        };
+        for quote in &quotes {
+            if let Err(e) = self.cache.put(quote).await {
+                warn!("Failed to cache {} quote: {:#}", self.source, e);
+            }
+        }
+
+        if request.len() == currencies.len() {
+            return Ok(quotes);
+        }
+        let requested: Vec<Quote> = quotes.into_iter().filter(|quote| currencies.contains(&quote.currency)).collect();
+        if requested.is_empty() {
+            anyhow::bail!("{} returned no price for {} in {:?}", self.source, coin, currencies);
+        }
+        Ok(requested)
+    }
+
+    async fn get_market_depth(
+        &self,
+        coin: Coin,
+        currency: Currency,
+    ) -> std::result::Result<MarketDepth, Self::Error> {
+        self.provider.get_market_depth(coin, currency).await
+    }
+
+    fn raw_response(&self) -> Option<serde_json::Value> {
+        self.provider.raw_response()
+    }
+}
+
+/// Whether `error` is a provider rejecting only currencies outside `requested`,
+/// so asking for `requested` alone can succeed.
+fn only_extras_unpriced(error: &anyhow::Error, requested: &[Currency]) -> bool {
+    error
+        .downcast_ref::<CurrenciesNotPriced>()
+        .is_some_and(|unpriced| unpriced.currencies.iter().all(|currency| !requested.contains(currency)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn quote(currency: Currency, price: f64) -> Quote {
+        Quote::new(Coin::ETH, currency, price, ProviderSource::CoinGecko)
+    }
+
+    /// Provider that counts calls and answers with a fixed price
+    struct CountingProvider(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl PriceProvider for CountingProvider {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        async fn get_quotes(&self, _coin: Coin, currencies: &[Currency]) -> anyhow::Result<Vec<Quote>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(currencies.iter().map(|&currency| quote(currency, 2000.0)).collect())
+        }
+    }
+
+    /// Strict provider that only prices USD and counts calls
+    struct UsdOnlyProvider(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl PriceProvider for UsdOnlyProvider {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "usd-only"
+        }
+
+        async fn get_quotes(&self, _coin: Coin, currencies: &[Currency]) -> anyhow::Result<Vec<Quote>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let unpriced: Vec<Currency> = currencies.iter().copied().filter(|&currency| currency != Currency::USD).collect();
+            if !unpriced.is_empty() {
+                return Err(CurrenciesNotPriced { provider: "usd-only".to_string(), currencies: unpriced }.into());
+            }
+            Ok(currencies.iter().map(|&currency| quote(currency, 2000.0)).collect())
+        }
+    }
+
+    /// Provider whose upstream always times out, counting calls
+    struct TimingOutProvider(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl PriceProvider for TimingOutProvider {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "timing-out"
+        }
+
+        async fn get_quotes(&self, _coin: Coin, _currencies: &[Currency]) -> anyhow::Result<Vec<Quote>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            anyhow::bail!("operation timed out")
+        }
+    }
+
+    #[test]
+    fn test_quote_key_label() {
+        assert_eq!(quote_key_label((Coin::ETH, Currency::EUR, ProviderSource::CoinMarketCap)), "ETH:EUR:coinmarketcap");
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache_round_trip_and_expiry() {
+        let cache = MemoryQuoteCache::new(Duration::from_secs(10));
+        let key = (Coin::ETH, Currency::USD, ProviderSource::CoinGecko);
+        assert!(cache.get(key).await.unwrap().is_none());
+
+        cache.put(&quote(Currency::USD, 2000.0)).await.unwrap();
+        assert_eq!(cache.get(key).await.unwrap().unwrap().quote.price, 2000.0);
+
+        // Keys include the provider and currency
+        assert!(cache.get((Coin::ETH, Currency::USD, ProviderSource::Binance)).await.unwrap().is_none());
+        assert!(cache.get((Coin::ETH, Currency::EUR, ProviderSource::CoinGecko)).await.unwrap().is_none());
+
+        // Backdate the entry to its expiry instead of waiting for it
+        cache.quotes.write().unwrap().get_mut(&key).unwrap().1 -= Duration::from_secs(10);
+        assert!(cache.get(key).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_flush_drops_quotes_of_one_or_every_provider() {
+        let cache = MemoryQuoteCache::default();
+        cache.put(&quote(Currency::USD, 2000.0)).await.unwrap();
+        cache.put(&quote(Currency::EUR, 1850.0)).await.unwrap();
+        cache.put(&Quote { provider: ProviderSource::Binance, ..quote(Currency::USD, 2001.0) }).await.unwrap();
+
+        assert_eq!(cache.flush(Some(ProviderSource::CoinGecko)).await.unwrap(), 2);
+        assert!(cache.get((Coin::ETH, Currency::USD, ProviderSource::CoinGecko)).await.unwrap().is_none());
+        assert!(cache.get((Coin::ETH, Currency::USD, ProviderSource::Binance)).await.unwrap().is_some());
+
+        assert_eq!(cache.flush(None).await.unwrap(), 1);
+        assert!(cache.get((Coin::ETH, Currency::USD, ProviderSource::Binance)).await.unwrap().is_none());
+        assert_eq!(cache.flush(None).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_all_quote_keys_cover_every_pair() {
+        let pairs = Coin::all().len() * Currency::all().len();
+        assert_eq!(all_quote_keys(None).count(), pairs * ProviderSource::all().len());
+        assert!(all_quote_keys(Some(ProviderSource::Binance)).all(|(_, _, provider)| provider == ProviderSource::Binance));
+        assert_eq!(all_quote_keys(Some(ProviderSource::Binance)).count(), pairs);
+    }
+
+    #[tokio::test]
+    async fn test_cached_provider_skips_upstream_when_all_currencies_cached() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache: DynQuoteCache = Arc::new(MemoryQuoteCache::default());
+        let provider = CachedPriceProvider::new(
+            ProviderSource::CoinGecko,
+            Box::new(CountingProvider(calls.clone())),
+            cache,
+        );
+
+        provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+        let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+        assert_eq!(quotes[0].price, 2000.0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // EUR is not cached yet, so both currencies are fetched again
+        provider.get_quotes(Coin::ETH, &[Currency::USD, Currency::EUR]).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        provider.get_quotes(Coin::ETH, &[Currency::EUR, Currency::USD]).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_one_fetch_caches_every_fetch_currency() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = Arc::new(MemoryQuoteCache::default());
+        let provider = CachedPriceProvider::new(
+            ProviderSource::CoinGecko,
+            Box::new(CountingProvider(calls.clone())),
+            cache.clone(),
+        )
+        .with_fetch_currencies([Currency::USD, Currency::EUR, Currency::GBP]);
+
+        // Only the requested currency comes back...
+        let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+        assert_eq!(quotes.iter().map(|quote| quote.currency).collect::<Vec<_>>(), [Currency::USD]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // ...but the same call cached the others
+        for currency in [Currency::EUR, Currency::GBP] {
+            assert!(cache.get((Coin::ETH, currency, ProviderSource::CoinGecko)).await.unwrap().is_some());
+        }
+        let quotes = provider.get_quotes(Coin::ETH, &[Currency::EUR]).await.unwrap();
+        assert!(quotes[0].cached);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Currencies outside the set still cost a call of their own
+        provider.get_quotes(Coin::ETH, &[Currency::JPY]).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_widened_fetch_retries_only_unpriced_extras() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let provider = CachedPriceProvider::new(
+            ProviderSource::CoinGecko,
+            Box::new(UsdOnlyProvider(calls.clone())),
+            Arc::new(MemoryQuoteCache::default()),
+        )
+        .with_fetch_currencies([Currency::EUR, Currency::GBP]);
+
+        // The widened call fails on EUR and GBP only, so USD alone is asked for again
+        let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+        assert_eq!(quotes[0].currency, Currency::USD);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // A requested currency is unpriced too, so a narrower call cannot help
+        let err = provider.get_quotes(Coin::ETH, &[Currency::JPY]).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<CurrenciesNotPriced>().unwrap().currencies, [Currency::JPY, Currency::EUR, Currency::GBP]);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_widened_fetch_failure_is_not_retried() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let provider = CachedPriceProvider::new(
+            ProviderSource::CoinGecko,
+            Box::new(TimingOutProvider(calls.clone())),
+            Arc::new(MemoryQuoteCache::default()),
+        )
+        .with_fetch_currencies([Currency::EUR, Currency::GBP]);
+
+        let err = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cache_hits_report_cached_and_age() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = Arc::new(MemoryQuoteCache::default());
+        let provider = CachedPriceProvider::new(
+            ProviderSource::CoinGecko,
+            Box::new(CountingProvider(calls.clone())),
+            cache.clone(),
+        );
+
+        let fresh = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+        assert!(!fresh[0].cached);
+        assert_eq!(fresh[0].age_seconds, 0);
+
+        // Backdate the stored entry so the hit has a measurable age
+        let key = (Coin::ETH, Currency::USD, ProviderSource::CoinGecko);
+        cache.quotes.write().unwrap().get_mut(&key).unwrap().1 -= Duration::from_secs(3);
+
+        let hit = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+        assert!(hit[0].cached);
+        assert_eq!(hit[0].age_seconds, 3);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Amount-scaled copies keep the cache metadata
+        let scaled = hit[0].with_amount(2.0);
+        assert!(scaled.cached);
+        assert_eq!(scaled.age_seconds, 3);
+    }
+}
@@ -7,11 +7,11 @@
 //! # Examples
 //!
 //! ```rust
-//! use boltzmann::crypto::{Coin, Currency};
-//! use boltzmann::crypto::coingecko::CoinGecko;
+//! use boltzmann::domains::crypto::{Coin, Currency, PriceProvider};
+//! use boltzmann::domains::crypto::coingecko::CoinGecko;
 //!
 //! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-//! let provider = CoinGecko::new()?;
+//! let provider = CoinGecko::new(None)?;
 //! let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await?;
 //!
 //! println!("ETH price: ${:.2}", quotes[0].price);
@@ -19,13 +19,97 @@
 //! # }
 //! ```
 
-use super::{Coin, Currency, PriceProvider, Quote, QuotePerAmount, ProviderSource};
+use std::collections::HashMap;
+
+use super::{Coin, Currency, MarketData, PriceProvider, Quote, QuoteBatch, ProviderSource};
 use crate::core::errors::{Result, ErrorContext};
+use crate::core::parse::{parse_f64_field, FieldParseError};
+use crate::infrastructure::http::{build_client, read_body_limited, read_json_body_limited, EtagCache, HttpClientConfig, RawCapture, DEFAULT_MAX_RESPONSE_BYTES};
+use crate::infrastructure::http::retry::RetryPolicy;
 use async_trait::async_trait;
-use reqwest::Client;
+use chrono::{DateTime, Utc};
+use reqwest::{header, Client, StatusCode};
+use serde::Deserialize;
 use serde_json::Value;
 use anyhow::Context;
 
+/// CoinGecko public API root
+pub const API_URL: &str = "https://api.coingecko.com/api/v3";
+
+/// Attribution CoinGecko's terms require when displaying its data.
+pub const ATTRIBUTION: &str = "Price data by CoinGecko (https://www.coingecko.com)";
+
+/// Price history searched on each side of a historical timestamp
+const HISTORY_WINDOW: chrono::Duration = chrono::Duration::hours(12);
+
+/// CoinGecko refused a request because the API key's monthly credits are used up.
+///
+/// Unlike the per-minute rate limit, this does not clear within seconds, so the
+/// request is not retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaExhausted {
+    /// CoinGecko's explanation, from the response body
+    pub message: String,
+}
+
+impl std::fmt::Display for QuotaExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CoinGecko monthly API quota exhausted: {}", self.message)
+    }
+}
+
+impl std::error::Error for QuotaExhausted {}
+
+/// Error message of a CoinGecko `429` body, if it is about the monthly quota.
+///
+/// Both limits answer `429`; the body's `status.error_message` (or `error`)
+/// says which one was hit. Per-minute limits talk about the "rate limit",
+/// monthly ones about "monthly" call "credits".
+fn monthly_quota_message(body: &str) -> Option<String> {
+    let json: Value = serde_json::from_str(body).ok()?;
+    let message = json
+        .pointer("/status/error_message")
+        .or_else(|| json.get("error"))
+        .and_then(Value::as_str)?;
+    let lower = message.to_lowercase();
+    (lower.contains("monthly") || lower.contains("credit")).then(|| message.to_string())
+}
+
+/// The parts of a `/coins/{id}` response used for [`MarketData`]
+#[derive(Deserialize)]
+struct CoinDetails {
+    market_data: Option<CoinMarketData>,
+    last_updated: Option<DateTime<Utc>>,
+}
+
+/// `market_data` of a `/coins/{id}` response; prices and volumes are keyed by
+/// lowercase currency code
+#[derive(Deserialize)]
+struct CoinMarketData {
+    #[serde(default)]
+    current_price: HashMap<String, Option<f64>>,
+    #[serde(default)]
+    market_cap: HashMap<String, Option<f64>>,
+    #[serde(default)]
+    total_volume: HashMap<String, Option<f64>>,
+    circulating_supply: Option<f64>,
+    price_change_percentage_24h: Option<f64>,
+}
+
+/// A `/coins/{id}/market_chart/range` response; each price is `[unix millis, price]`
+#[derive(Deserialize)]
+struct MarketChart {
+    #[serde(default)]
+    prices: Vec<(f64, Option<f64>)>,
+}
+
+/// Outcome of a (possibly conditional) GET request
+enum Fetched {
+    /// The upstream sent a body, with its ETag if it has one
+    Body { body: String, etag: Option<String> },
+    /// The resource still matches the ETag that was sent
+    NotModified,
+}
 
 /// CoinGecko price provider.
 ///
@@ -36,16 +120,22 @@ use anyhow::Context;
 /// # Examples
 ///
 /// ```rust
-/// use boltzmann::crypto::coingecko::CoinGecko;
+/// use boltzmann::domains::crypto::coingecko::CoinGecko;
 ///
 /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
-/// let provider = CoinGecko::new()?;
+/// let provider = CoinGecko::new(None)?;
 /// # Ok(())
 /// # }
 /// ```
 pub struct CoinGecko {
     client: Client,
     api_key: Option<String>,
+    base_url: String,
+    strict_currencies: bool,
+    max_response_bytes: usize,
+    retry: RetryPolicy,
+    raw: RawCapture,
+    etags: EtagCache<QuoteBatch>,
 }
 
 impl CoinGecko {
@@ -56,37 +146,85 @@ impl CoinGecko {
     ///
     /// # Errors
     ///
-    /// Returns `CoinGeckoError::ApiError` if the API key format is invalid.
-    /// Returns `CoinGeckoError::RequestError` if the HTTP client cannot be created.
+    /// Returns an error if the API key format is invalid or the HTTP client
+    /// cannot be created.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use boltzmann::crypto::coingecko::CoinGecko;
+    /// use boltzmann::domains::crypto::coingecko::CoinGecko;
     ///
     /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// // Works with or without API key
-    /// let provider = CoinGecko::new()?;
+    /// let provider = CoinGecko::new(None)?;
     /// # Ok(())
     /// # }
     /// ```
     pub fn new(api_key: Option<String>) -> Result<Self> {
+        let client = build_client(&HttpClientConfig::default())
+            .crypto_context("creating HTTP client for CoinGecko")?;
+
+        Self::new_with_client(api_key, client)
+    }
 
-        let mut headers = reqwest::header::HeaderMap::new();
+    /// Creates a new CoinGecko provider using an existing HTTP client.
+    ///
+    /// Use this to share a client built with [`build_client`] so outbound settings
+    /// such as the proxy apply to CoinGecko requests.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API key is not a valid header value.
+    pub fn new_with_client(api_key: Option<String>, client: Client) -> Result<Self> {
         if let Some(ref key) = api_key {
-            headers.insert(
-                "x-cg-demo-api-key",
-                reqwest::header::HeaderValue::from_str(key)
-                    .context("Invalid CoinGecko API key format")?,
-            );
+            reqwest::header::HeaderValue::from_str(key)
+                .context("Invalid CoinGecko API key format")?;
         }
 
-        let client = Client::builder()
-            .default_headers(headers)
-            .build()
-            .crypto_context("creating HTTP client for CoinGecko")?;
+        Ok(Self {
+            client,
+            api_key,
+            base_url: API_URL.to_string(),
+            strict_currencies: false,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            retry: RetryPolicy::default(),
+            raw: RawCapture::default(),
+            etags: EtagCache::default(),
+        })
+    }
 
-        Ok(Self { client, api_key })
+    /// Sends requests to a different API root, e.g. a mock server in tests.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets whether a response missing any requested currency fails the request.
+    ///
+    /// By default the provider is lenient and returns the currencies it could price.
+    pub fn with_strict_currencies(mut self, strict: bool) -> Self {
+        self.strict_currencies = strict;
+        self
+    }
+
+    /// Sets the largest response body accepted from the API, in bytes.
+    pub fn with_max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
+    }
+
+    /// Retries transient failures (connection errors, timeouts, `502`-`504`)
+    /// under `policy`; by default requests are never retried.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Keeps the raw body of the most recent response, exposed via
+    /// [`PriceProvider::raw_response`] for debugging.
+    pub fn with_raw_capture(mut self, enabled: bool) -> Self {
+        self.raw = RawCapture::new(enabled);
+        self
     }
 
     /// Converts our Currency enum to CoinGecko's currency identifier.
@@ -110,6 +248,10 @@ impl CoinGecko {
     /// Makes a single API call to fetch prices for one coin in multiple currencies.
     /// This is more efficient than making separate calls for each currency.
     ///
+    /// Requests are conditional: when an earlier response carried an ETag it is
+    /// sent as `If-None-Match`, and a `304 Not Modified` reuses the quotes parsed
+    /// from that response without reparsing anything.
+    ///
     /// # Arguments
     ///
     /// * `coin` - The cryptocurrency to fetch prices for
@@ -121,8 +263,10 @@ impl CoinGecko {
     ///
     /// # Errors
     ///
-    /// Returns various `CoinGeckoError` types if the request fails,
-    /// rate limit is exceeded, or the response cannot be parsed.
+    /// Returns an error if the request fails, the rate limit is exceeded, or the
+    /// response cannot be parsed. The underlying cause (e.g. the `reqwest::Error`)
+    /// is kept as the error's source.
+    #[tracing::instrument(skip(self), fields(provider = "coingecko"))]
     async fn fetch_quotes(
         &self,
         coin: Coin,
@@ -139,39 +283,211 @@ impl CoinGecko {
             .collect();
 
         let url = format!(
-            "https://api.coingecko.com/api/v3/simple/price?ids={}&vs_currencies={}&include_last_updated_at=true",
+            "{}/simple/price?ids={}&vs_currencies={}&include_last_updated_at=true",
+            self.base_url,
             coin_id,
             currency_codes.join(",")
         );
 
-        let mut request = self.client.get(&url);
-        
-        // Add API key header if available
-        if let Some(ref api_key) = self.api_key {
-            request = request.header("x-cg-demo-api-key", api_key);
-        }
-        
-        let response = request
-            .send()
-            .await
-            .crypto_context("sending request to CoinGecko API")?;
+        let etag = self.etags.etag(&url);
+        let batch = match self.get(&url, etag.as_deref()).await? {
+            Fetched::NotModified => match self.etags.get(&url) {
+                Some(batch) => {
+                    tracing::debug!("CoinGecko prices unchanged, reusing cached quotes");
+                    batch
+                }
+                None => anyhow::bail!("CoinGecko answered 304 Not Modified without a cached response"),
+            },
+            Fetched::Body { body, etag } => {
+                let json: Value = serde_json::from_str(&body)
+                    .context("parsing JSON response from CoinGecko API")?;
+                let batch = self.parse_quotes(&json, coin, currencies)?;
+                if let Some(etag) = etag {
+                    self.etags.store(url, etag, batch.clone());
+                }
+                batch
+            }
+        };
+
+        batch.into_quotes(self.strict_currencies, "CoinGecko")
+    }
+
+    /// Sends a GET request to `url`, conditional on `etag` when one is given.
+    ///
+    /// Per-minute rate limiting is retried with backoff under the retry policy;
+    /// an exhausted monthly quota fails straight away with [`QuotaExhausted`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the rate limit is still exceeded
+    /// after retrying, the monthly quota is exhausted, or the body exceeds the
+    /// size limit.
+    async fn get(&self, url: &str, etag: Option<&str>) -> Result<Fetched> {
+        let request = || {
+            let mut request = self.client.get(url);
+
+            // Add API key header if available
+            if let Some(ref api_key) = self.api_key {
+                request = request.header("x-cg-demo-api-key", api_key);
+            }
+            if let Some(etag) = etag {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+            request
+        };
 
-        if response.status() == 429 {
-            anyhow::bail!("CoinGecko API rate limit exceeded");
+        let mut retries = 0;
+        let response = loop {
+            let response = self
+                .retry
+                .send(request)
+                .await
+                .crypto_context("sending request to CoinGecko API")?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS {
+                break response;
+            }
+
+            let body = read_body_limited(response, self.max_response_bytes).await.unwrap_or_default();
+            if let Some(message) = monthly_quota_message(&body) {
+                return Err(QuotaExhausted { message }.into());
+            }
+            if !self.retry.backoff(retries).await {
+                anyhow::bail!("CoinGecko API rate limit exceeded");
+            }
+            retries += 1;
+        };
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Fetched::NotModified);
         }
 
-        let body = response
-            .text()
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = read_json_body_limited(response, self.max_response_bytes)
             .await
             .crypto_context("reading response body from CoinGecko API")?;
+        self.raw.record(&body);
+        Ok(Fetched::Body { body, etag })
+    }
+
+    /// Fetches market cap, 24h volume, circulating supply and price change from `/coins/{id}`.
+    ///
+    /// The full coin document is large, so localization, tickers, community and
+    /// developer data are switched off in the request and only `market_data` is
+    /// deserialized from the response.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the rate limit is exceeded, or the
+    /// response has no price for `currency`.
+    #[tracing::instrument(skip(self), fields(provider = "coingecko"))]
+    pub async fn get_market_data(&self, coin: Coin, currency: Currency) -> Result<MarketData> {
+        let url = format!(
+            "{}/coins/{}?localization=false&tickers=false&market_data=true&community_data=false&developer_data=false&sparkline=false",
+            self.base_url,
+            coin.coingecko_id()
+        );
+
+        match self.get(&url, None).await? {
+            Fetched::Body { body, .. } => self.parse_market_data(&body, coin, currency),
+            Fetched::NotModified => anyhow::bail!("CoinGecko answered 304 Not Modified to an unconditional request"),
+        }
+    }
 
-        let json: Value = serde_json::from_str(&body)
-            .context("parsing JSON response from CoinGecko API")?;
+    /// Picks `currency`'s figures out of a `/coins/{id}` response.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body is not a coin document or has no price in `currency`.
+    fn parse_market_data(&self, body: &str, coin: Coin, currency: Currency) -> Result<MarketData> {
+        let details: CoinDetails = serde_json::from_str(body)
+            .context("parsing coin market data from CoinGecko API")?;
+        let market = details
+            .market_data
+            .with_context(|| format!("No market data for {} in CoinGecko response", coin))?;
+
+        let code = self.currency_to_coingecko_id(currency);
+        let in_currency = |values: &HashMap<String, Option<f64>>| values.get(code).copied().flatten();
+        let price = in_currency(&market.current_price)
+            .with_context(|| format!("No {} price for {} in CoinGecko market data", currency, coin))?;
+
+        Ok(MarketData {
+            coin,
+            currency,
+            provider: ProviderSource::CoinGecko,
+            price,
+            market_cap: in_currency(&market.market_cap),
+            total_volume_24h: in_currency(&market.total_volume),
+            circulating_supply: market.circulating_supply,
+            price_change_percentage_24h: market.price_change_percentage_24h,
+            timestamp: details.last_updated.unwrap_or_else(Utc::now),
+        })
+    }
+
+    /// Fetches the price of `coin` in `currency` closest to `at` from `/coins/{id}/market_chart/range`.
+    ///
+    /// CoinGecko's history is sampled every 5 minutes to every day depending on
+    /// how far back `at` is, so the quote's timestamp is that of the nearest
+    /// sample within 12 hours of `at`, not `at` itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the rate limit is exceeded, or
+    /// CoinGecko has no price within 12 hours of `at`.
+    #[tracing::instrument(skip(self), fields(provider = "coingecko"))]
+    pub async fn get_historical_quote(&self, coin: Coin, currency: Currency, at: DateTime<Utc>) -> Result<Quote> {
+        let to = (at + HISTORY_WINDOW).min(Utc::now());
+        let url = format!(
+            "{}/coins/{}/market_chart/range?vs_currency={}&from={}&to={}",
+            self.base_url,
+            coin.coingecko_id(),
+            self.currency_to_coingecko_id(currency),
+            (at - HISTORY_WINDOW).timestamp(),
+            to.timestamp()
+        );
+
+        match self.get(&url, None).await? {
+            Fetched::Body { body, .. } => self.parse_historical_quote(&body, coin, currency, at),
+            Fetched::NotModified => anyhow::bail!("CoinGecko answered 304 Not Modified to an unconditional request"),
+        }
+    }
 
-        let mut quotes = Vec::new();
+    /// Picks the price sample closest to `at` out of a `/market_chart/range` response.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body is not a price chart or has no price sample.
+    fn parse_historical_quote(&self, body: &str, coin: Coin, currency: Currency, at: DateTime<Utc>) -> Result<Quote> {
+        let chart: MarketChart = serde_json::from_str(body)
+            .context("parsing price history from CoinGecko API")?;
+        let (timestamp, price) = chart
+            .prices
+            .into_iter()
+            .filter_map(|(millis, price)| Some((DateTime::from_timestamp_millis(millis as i64)?, price?)))
+            .min_by_key(|(timestamp, _)| (*timestamp - at).abs())
+            .with_context(|| format!("No {} price for {} near {} in CoinGecko history", currency, coin, at.to_rfc3339()))?;
+
+        Ok(Quote {
+            timestamp,
+            ..Quote::new(coin, currency, price, ProviderSource::CoinGecko)
+        })
+    }
+
+    /// Builds quotes for each requested currency from a CoinGecko response.
+    ///
+    /// Currencies without a price in the response are reported in
+    /// [`QuoteBatch::missing`] instead of failing the whole batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the response contains no data for the coin.
+    fn parse_quotes(&self, json: &Value, coin: Coin, currencies: &[Currency]) -> Result<QuoteBatch> {
+        let mut batch = QuoteBatch::default();
         let timestamp = chrono::Utc::now();
 
-        let coin_data = &json[coin_id];
+        let coin_data = &json[coin.coingecko_id()];
 
         if coin_data.is_null() {
             anyhow::bail!("No data found for coin {} in CoinGecko response", coin);
@@ -180,31 +496,36 @@ impl CoinGecko {
         for &currency in currencies {
             let currency_code = self.currency_to_coingecko_id(currency);
 
-            if let Some(price) = coin_data[currency_code].as_f64() {
-                quotes.push(Quote {
-                    coin,
-                    currency,
-                    price,
-                    provider: ProviderSource::CoinGecko,
-                    timestamp,
-                    quote_per_amount: QuotePerAmount {
-                        amount: 1.0,
-                        total_price: price,
-                    },
-                });
-            } else {
-                anyhow::bail!("Price not found for {} in {} from CoinGecko", coin, currency);
-            }
+            let price = match parse_f64_field(&coin_data[currency_code], currency_code) {
+                Ok(price) => price,
+                Err(FieldParseError::Missing { .. }) => {
+                    batch.missing.push(currency);
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("parsing {} price from CoinGecko", currency));
+                }
+            };
+
+            batch.quotes.push(Quote {
+                timestamp,
+                ..Quote::new(coin, currency, price, ProviderSource::CoinGecko)
+            });
         }
 
-        Ok(quotes)
+        Ok(batch)
     }
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl PriceProvider for CoinGecko {
     type Error = anyhow::Error;
 
+    fn name(&self) -> &'static str {
+        ProviderSource::CoinGecko.name()
+    }
+
     async fn get_quotes(
         &self,
         coin: Coin,
@@ -212,4 +533,293 @@ impl PriceProvider for CoinGecko {
     ) -> std::result::Result<Vec<Quote>, Self::Error> {
         self.fetch_quotes(coin, currencies).await
     }
+
+    fn raw_response(&self) -> Option<Value> {
+        self.raw.last()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::crypto::missing_currencies;
+    use serde_json::json;
+
+    fn sample_response() -> Value {
+        json!({
+            "ethereum": {
+                "usd": 2000.5,
+                "eur": 1850.25,
+                "last_updated_at": 1700000000
+            }
+        })
+    }
+
+    #[test]
+    fn test_missing_currency_returns_others() {
+        let provider = CoinGecko::new(None).unwrap();
+        let currencies = [Currency::USD, Currency::JPY, Currency::EUR];
+
+        let batch = provider.parse_quotes(&sample_response(), Coin::ETH, &currencies).unwrap();
+        assert_eq!(batch.missing, vec![Currency::JPY]);
+
+        let quotes = batch.into_quotes(false, "CoinGecko").unwrap();
+        assert_eq!(quotes.len(), 2);
+        assert_eq!(quotes[0].currency, Currency::USD);
+        assert_eq!(quotes[1].currency, Currency::EUR);
+        assert_eq!(quotes[1].price, 1850.25);
+        assert_eq!(missing_currencies(&currencies, &quotes), vec![Currency::JPY]);
+    }
+
+    #[test]
+    fn test_missing_currency_strict_mode_fails() {
+        let provider = CoinGecko::new(None).unwrap().with_strict_currencies(true);
+        let currencies = [Currency::USD, Currency::JPY];
+
+        let err = provider
+            .parse_quotes(&sample_response(), Coin::ETH, &currencies)
+            .unwrap()
+            .into_quotes(provider.strict_currencies, "CoinGecko")
+            .unwrap_err();
+        assert!(err.to_string().contains("JPY"));
+    }
+
+    #[test]
+    fn test_all_currencies_missing_fails_in_lenient_mode() {
+        let provider = CoinGecko::new(None).unwrap();
+
+        let err = provider
+            .parse_quotes(&sample_response(), Coin::ETH, &[Currency::JPY])
+            .unwrap()
+            .into_quotes(false, "CoinGecko")
+            .unwrap_err();
+        assert!(err.to_string().contains("Price not found"));
+    }
+
+    /// Trimmed `/coins/ethereum` response; the real one carries dozens of currencies
+    const COIN_DETAILS: &str = r#"{
+        "id": "ethereum",
+        "symbol": "eth",
+        "name": "Ethereum",
+        "market_data": {
+            "current_price": { "usd": 2000.5, "eur": 1850.25, "jpy": null },
+            "market_cap": { "usd": 240500000000.0, "eur": 222400000000.0 },
+            "total_volume": { "usd": 15300000000.0, "eur": 14150000000.0 },
+            "circulating_supply": 120200000.5,
+            "price_change_percentage_24h": -1.25,
+            "ath": { "usd": 4878.26 }
+        },
+        "last_updated": "2023-11-14T22:13:20.000Z"
+    }"#;
+
+    #[test]
+    fn test_parse_market_data() {
+        let provider = CoinGecko::new(None).unwrap();
+
+        let market = provider.parse_market_data(COIN_DETAILS, Coin::ETH, Currency::EUR).unwrap();
+        assert_eq!(market.price, 1850.25);
+        assert_eq!(market.market_cap, Some(222_400_000_000.0));
+        assert_eq!(market.total_volume_24h, Some(14_150_000_000.0));
+        assert_eq!(market.circulating_supply, Some(120_200_000.5));
+        assert_eq!(market.price_change_percentage_24h, Some(-1.25));
+        assert_eq!(market.timestamp.timestamp(), 1_700_000_000);
+        assert!(matches!(market.provider, ProviderSource::CoinGecko));
+    }
+
+    #[test]
+    fn test_parse_market_data_requires_a_price() {
+        let provider = CoinGecko::new(None).unwrap();
+
+        let err = provider.parse_market_data(COIN_DETAILS, Coin::ETH, Currency::JPY).unwrap_err();
+        assert!(err.to_string().contains("No JPY price"), "{}", err);
+        assert!(provider.parse_market_data(r#"{"error":"coin not found"}"#, Coin::ETH, Currency::USD).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_market_data_from_coins_endpoint() {
+        use axum::{extract::RawQuery, routing::get, Router};
+
+        let app = Router::new().route(
+            "/coins/ethereum",
+            get(|RawQuery(query): RawQuery| async move {
+                assert!(query.unwrap_or_default().contains("tickers=false"));
+                ([(reqwest::header::CONTENT_TYPE, "application/json")], COIN_DETAILS)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let provider = CoinGecko::new(None).unwrap().with_base_url(url);
+        let market = provider.get_market_data(Coin::ETH, Currency::USD).await.unwrap();
+        assert_eq!(market.price, 2000.5);
+        assert_eq!(market.market_cap, Some(240_500_000_000.0));
+    }
+
+    const MARKET_CHART: &str = r#"{
+        "prices": [[1700000000000, 2001.5], [1700000300000, 2003.0], [1700000600000, null], [1700000900000, 1999.0]],
+        "market_caps": [[1700000000000, 240500000000.0]],
+        "total_volumes": [[1700000000000, 15000000000.0]]
+    }"#;
+
+    #[test]
+    fn test_parse_historical_quote_picks_nearest_sample() {
+        let provider = CoinGecko::new(None).unwrap();
+        let at = |seconds| DateTime::from_timestamp(seconds, 0).unwrap();
+
+        let quote = provider.parse_historical_quote(MARKET_CHART, Coin::ETH, Currency::USD, at(1_700_000_400)).unwrap();
+        assert_eq!(quote.price, 2003.0);
+        assert_eq!(quote.timestamp, at(1_700_000_300));
+
+        // Samples without a price are skipped
+        let quote = provider.parse_historical_quote(MARKET_CHART, Coin::ETH, Currency::USD, at(1_700_000_620)).unwrap();
+        assert_eq!(quote.price, 1999.0);
+
+        let err = provider
+            .parse_historical_quote(r#"{"prices": []}"#, Coin::ETH, Currency::EUR, at(1_700_000_000))
+            .unwrap_err();
+        assert!(err.to_string().contains("No EUR price"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_historical_quote_from_market_chart_range() {
+        use axum::{extract::RawQuery, routing::get, Router};
+
+        let app = Router::new().route(
+            "/coins/ethereum/market_chart/range",
+            get(|RawQuery(query): RawQuery| async move {
+                let query = query.unwrap_or_default();
+                assert!(query.contains("vs_currency=usd"), "{}", query);
+                assert!(query.contains("from=1699957200") && query.contains("to=1700043600"), "{}", query);
+                ([(reqwest::header::CONTENT_TYPE, "application/json")], MARKET_CHART)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let provider = CoinGecko::new(None).unwrap().with_base_url(url);
+        let at = DateTime::from_timestamp(1_700_000_400, 0).unwrap();
+        let quote = provider.get_historical_quote(Coin::ETH, Currency::USD, at).await.unwrap();
+        assert_eq!(quote.price, 2003.0);
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_reuses_cached_quotes() {
+        use axum::{http::HeaderMap, response::IntoResponse, routing::get, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        // First request gets a body and an ETag; later ones must be conditional
+        // and get an empty 304, which would fail to parse if it were read
+        let requests = Arc::new(AtomicUsize::new(0));
+        let seen = requests.clone();
+        let app = Router::new().route(
+            "/simple/price",
+            get(move |headers: HeaderMap| async move {
+                if seen.fetch_add(1, Ordering::SeqCst) == 0 {
+                    assert!(headers.get("if-none-match").is_none());
+                    return (
+                        [(reqwest::header::ETAG, "\"v1\""), (reqwest::header::CONTENT_TYPE, "application/json")],
+                        r#"{"ethereum":{"usd":2000.5}}"#,
+                    )
+                        .into_response();
+                }
+                assert_eq!(headers["if-none-match"], "\"v1\"");
+                axum::http::StatusCode::NOT_MODIFIED.into_response()
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let provider = CoinGecko::new(None).unwrap().with_base_url(url).with_raw_capture(true);
+        let first = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+        let second = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert_eq!(second[0].price, 2000.5);
+        assert_eq!(second[0].timestamp, first[0].timestamp);
+        // The 304 body was never read, so the raw capture still holds the first response
+        assert_eq!(provider.raw_response(), Some(json!({ "ethereum": { "usd": 2000.5 } })));
+    }
+
+    /// CoinGecko's `429` body when the per-minute limit is hit
+    const RATE_LIMITED: &str = r#"{"status":{"error_code":429,"error_message":"You've exceeded the Rate Limit. Please visit https://www.coingecko.com/en/api/pricing to subscribe to our API plans for higher rate limits."}}"#;
+
+    /// CoinGecko's `429` body once the monthly credits are used up
+    const MONTHLY_QUOTA: &str = r#"{"status":{"error_code":429,"error_message":"You've exceeded the monthly call credit limit for your plan. Please upgrade at https://www.coingecko.com/en/api/pricing."}}"#;
+
+    /// Serves `429` with `body` for the first `failures` requests, then a price
+    async fn rate_limited(body: &'static str, failures: usize) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use axum::{http::StatusCode as AxumStatus, response::IntoResponse, routing::get, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let seen = requests.clone();
+        let app = Router::new().route(
+            "/simple/price",
+            get(move || async move {
+                if seen.fetch_add(1, Ordering::SeqCst) < failures {
+                    return (AxumStatus::TOO_MANY_REQUESTS, body).into_response();
+                }
+                axum::Json(json!({ "ethereum": { "usd": 2000.5 } })).into_response()
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, requests)
+    }
+
+    fn retrying() -> RetryPolicy {
+        use crate::infrastructure::http::retry::RetryBudget;
+        RetryPolicy::new(2, std::sync::Arc::new(RetryBudget::new(10, 0)))
+    }
+
+    #[test]
+    fn test_monthly_quota_detected_from_body() {
+        assert!(monthly_quota_message(MONTHLY_QUOTA).unwrap().contains("monthly call credit"));
+        assert!(monthly_quota_message(r#"{"error":"Monthly credits exhausted"}"#).is_some());
+        assert_eq!(monthly_quota_message(RATE_LIMITED), None);
+        assert_eq!(monthly_quota_message("Too Many Requests"), None);
+    }
+
+    #[tokio::test]
+    async fn test_per_minute_rate_limit_is_retried() {
+        use std::sync::atomic::Ordering;
+
+        let (url, requests) = rate_limited(RATE_LIMITED, 1).await;
+        let provider = CoinGecko::new(None).unwrap().with_base_url(url).with_retry(retrying());
+
+        let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+        assert_eq!(quotes[0].price, 2000.5);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_per_minute_rate_limit_gives_up_after_max_retries() {
+        use std::sync::atomic::Ordering;
+
+        let (url, requests) = rate_limited(RATE_LIMITED, usize::MAX).await;
+        let provider = CoinGecko::new(None).unwrap().with_base_url(url).with_retry(retrying());
+
+        let err = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap_err();
+        assert!(err.to_string().contains("rate limit exceeded"), "{}", err);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_monthly_quota_is_not_retried() {
+        use std::sync::atomic::Ordering;
+
+        let (url, requests) = rate_limited(MONTHLY_QUOTA, usize::MAX).await;
+        let provider = CoinGecko::new(None).unwrap().with_base_url(url).with_retry(retrying());
+
+        let err = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap_err();
+        assert!(err.downcast_ref::<QuotaExhausted>().is_some(), "{:#}", err);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
 }
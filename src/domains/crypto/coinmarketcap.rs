@@ -7,11 +7,11 @@
 //! # Examples
 //!
 //! ```rust
-//! use boltzmann::crypto::{Coin, Currency};
-//! use boltzmann::crypto::coinmarketcap::CoinMarketCap;
+//! use boltzmann::domains::crypto::{Coin, Currency, PriceProvider};
+//! use boltzmann::domains::crypto::coinmarketcap::CoinMarketCap;
 //!
 //! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-//! let provider = CoinMarketCap::new()?;
+//! let provider = CoinMarketCap::new("your_api_key".to_string())?;
 //! let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await?;
 //!
 //! println!("ETH price: ${:.2}", quotes[0].price);
@@ -19,14 +19,26 @@
 //! # }
 //! ```
 
-use super::{Coin, Currency, PriceProvider, Quote, QuotePerAmount, ProviderSource};
+use super::{Coin, Currency, PriceProvider, Quote, QuoteBatch, ProviderSource};
 use crate::core::errors::{Result, ErrorContext};
+use crate::core::parse::{parse_f64_field, FieldParseError};
+use crate::infrastructure::http::{build_client, read_json_body_limited, HttpClientConfig, RawCapture, DEFAULT_MAX_RESPONSE_BYTES};
+use crate::infrastructure::http::retry::RetryPolicy;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::Value;
 use anyhow::Context;
 
 
+/// CoinMarketCap Pro API
+pub const API_URL: &str = "https://pro-api.coinmarketcap.com";
+
+/// CoinMarketCap sandbox API, serving mock data without spending credits
+pub const SANDBOX_API_URL: &str = "https://sandbox-api.coinmarketcap.com";
+
+/// Public API key CoinMarketCap publishes for its sandbox
+pub const SANDBOX_API_KEY: &str = "b54bcf4d-1bca-4e8e-9a24-22ff2c3d462c";
+
 /// CoinMarketCap price provider.
 ///
 /// This struct handles fetching cryptocurrency prices from the CoinMarketCap API.
@@ -35,24 +47,28 @@ use anyhow::Context;
 /// # Examples
 ///
 /// ```rust
-/// use boltzmann::crypto::coinmarketcap::CoinMarketCap;
+/// use boltzmann::domains::crypto::coinmarketcap::CoinMarketCap;
 ///
 /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
-/// let provider = CoinMarketCap::new()?;
+/// let provider = CoinMarketCap::new("your_api_key".to_string())?;
 /// # Ok(())
 /// # }
 /// ```
 pub struct CoinMarketCap {
-    #[allow(dead_code)] // Used indirectly via client default headers
     api_key: String,
     client: Client,
+    base_url: String,
+    strict_currencies: bool,
+    max_response_bytes: usize,
+    retry: RetryPolicy,
+    raw: RawCapture,
 }
 
 impl CoinMarketCap {
     /// Creates a new CoinMarketCap provider instance.
     ///
     /// Reads the API key from the `COINMARKETCAP_API_KEY` environment variable
-    /// and sets up an HTTP client with the required headers.
+    /// and sets up an HTTP client using the default outbound settings.
     ///
     /// # Errors
     ///
@@ -62,7 +78,7 @@ impl CoinMarketCap {
     /// # Examples
     ///
     /// ```rust
-    /// use boltzmann::crypto::coinmarketcap::CoinMarketCap;
+    /// use boltzmann::domains::crypto::coinmarketcap::CoinMarketCap;
     ///
     /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// let provider = CoinMarketCap::new("api_key".to_string())?;
@@ -70,23 +86,71 @@ impl CoinMarketCap {
     /// # }
     /// ```
     pub fn new(api_key: String) -> Result<Self> {
+        let client = build_client(&HttpClientConfig::default())
+            .crypto_context("creating HTTP client for CoinMarketCap")?;
+
+        Self::new_with_client(api_key, client)
+    }
+
+    /// Creates a new CoinMarketCap provider using an existing HTTP client.
+    ///
+    /// Use this to share a client built with [`build_client`] so outbound settings
+    /// such as the proxy apply to CoinMarketCap requests.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API key is empty or not a valid header value.
+    pub fn new_with_client(api_key: String, client: Client) -> Result<Self> {
         if api_key.is_empty() {
             anyhow::bail!("CoinMarketCap API key cannot be empty");
         }
 
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            "X-CMC_PRO_API_KEY",
-            reqwest::header::HeaderValue::from_str(&api_key)
-                .context("Invalid API key format")?,
-        );
+        reqwest::header::HeaderValue::from_str(&api_key)
+            .context("Invalid API key format")?;
 
-        let client = Client::builder()
-            .default_headers(headers)
-            .build()
-            .crypto_context("creating HTTP client for CoinMarketCap")?;
+        Ok(Self {
+            api_key,
+            client,
+            base_url: API_URL.to_string(),
+            strict_currencies: false,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            retry: RetryPolicy::default(),
+            raw: RawCapture::default(),
+        })
+    }
+
+    /// Sends requests to a different API host, e.g. a mock server in tests.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets whether a response missing any requested currency fails the request.
+    ///
+    /// By default the provider is lenient and returns the currencies it could price.
+    pub fn with_strict_currencies(mut self, strict: bool) -> Self {
+        self.strict_currencies = strict;
+        self
+    }
+
+    /// Sets the largest response body accepted from the API, in bytes.
+    pub fn with_max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
+    }
 
-        Ok(Self { api_key, client })
+    /// Retries transient failures (connection errors, timeouts, `502`-`504`)
+    /// under `policy`; by default requests are never retried.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Keeps the raw body of the most recent response, exposed via
+    /// [`PriceProvider::raw_response`] for debugging.
+    pub fn with_raw_capture(mut self, enabled: bool) -> Self {
+        self.raw = RawCapture::new(enabled);
+        self
     }
 
     /// Internal method to fetch quotes from CoinMarketCap API.
@@ -106,6 +170,7 @@ impl CoinMarketCap {
     /// # Errors
     ///
     /// Returns an error if the request fails or the response cannot be parsed.
+    #[tracing::instrument(skip(self), fields(provider = "coinmarketcap"))]
     async fn fetch_quotes(
         &self,
         coin: Coin,
@@ -122,30 +187,43 @@ impl CoinMarketCap {
             .collect();
 
         let url = format!(
-            "https://pro-api.coinmarketcap.com/v2/cryptocurrency/quotes/latest?id={}&convert={}",
+            "{}/v2/cryptocurrency/quotes/latest?id={}&convert={}",
+            self.base_url,
             coin_id,
             currency_codes.join(",")
         );
 
         let response = self
-            .client
-            .get(&url)
-            .send()
+            .retry
+            .send(|| self.client.get(&url).header("X-CMC_PRO_API_KEY", &self.api_key))
             .await
             .crypto_context("sending request to CoinMarketCap API")?;
 
-        let body = response
-            .text()
+        let body = read_json_body_limited(response, self.max_response_bytes)
             .await
             .crypto_context("reading response body from CoinMarketCap API")?;
+        self.raw.record(&body);
 
         let json: Value = serde_json::from_str(&body)
             .context("parsing JSON response from CoinMarketCap API")?;
 
-        let mut quotes = Vec::new();
+        self.parse_quotes(&json, coin, currencies)?
+            .into_quotes(self.strict_currencies, "CoinMarketCap")
+    }
+
+    /// Builds quotes for each requested currency from a CoinMarketCap response.
+    ///
+    /// Currencies without a price in the response are reported in
+    /// [`QuoteBatch::missing`] instead of failing the whole batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the response contains no data for the coin.
+    fn parse_quotes(&self, json: &Value, coin: Coin, currencies: &[Currency]) -> Result<QuoteBatch> {
+        let mut batch = QuoteBatch::default();
         let timestamp = chrono::Utc::now();
 
-        let coin_id_str = coin_id.to_string();
+        let coin_id_str = coin.coinmarketcap_id().to_string();
         let coin_data = &json["data"][&coin_id_str];
 
         if coin_data.is_null() {
@@ -156,31 +234,36 @@ impl CoinMarketCap {
             let currency_code = currency.to_string().to_uppercase();
             let quote_data = &coin_data["quote"][&currency_code];
 
-            if let Some(price) = quote_data["price"].as_f64() {
-                quotes.push(Quote {
-                    coin,
-                    currency,
-                    price,
-                    provider: ProviderSource::CoinMarketCap,
-                    timestamp,
-                    quote_per_amount: QuotePerAmount {
-                        amount: 1.0,
-                        total_price: price,
-                    },
-                });
-            } else {
-                anyhow::bail!("Price not found for {} in {} from CoinMarketCap", coin, currency);
-            }
+            let price = match parse_f64_field(&quote_data["price"], "price") {
+                Ok(price) => price,
+                Err(FieldParseError::Missing { .. }) => {
+                    batch.missing.push(currency);
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("parsing {} price from CoinMarketCap", currency));
+                }
+            };
+
+            batch.quotes.push(Quote {
+                timestamp,
+                ..Quote::new(coin, currency, price, ProviderSource::CoinMarketCap)
+            });
         }
 
-        Ok(quotes)
+        Ok(batch)
     }
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl PriceProvider for CoinMarketCap {
     type Error = anyhow::Error;
 
+    fn name(&self) -> &'static str {
+        ProviderSource::CoinMarketCap.name()
+    }
+
     async fn get_quotes(
         &self,
         coin: Coin,
@@ -188,4 +271,60 @@ impl PriceProvider for CoinMarketCap {
     ) -> std::result::Result<Vec<Quote>, Self::Error> {
         self.fetch_quotes(coin, currencies).await
     }
+
+    fn raw_response(&self) -> Option<Value> {
+        self.raw.last()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::crypto::missing_currencies;
+    use serde_json::json;
+
+    fn sample_response() -> Value {
+        json!({
+            "data": {
+                "1027": {
+                    "quote": {
+                        "USD": { "price": 2000.5 },
+                        "EUR": { "price": 1850.25 }
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_missing_currency_returns_others() {
+        let provider = CoinMarketCap::new("test_key".to_string()).unwrap();
+        let currencies = [Currency::USD, Currency::EUR, Currency::JPY];
+
+        let batch = provider.parse_quotes(&sample_response(), Coin::ETH, &currencies).unwrap();
+        assert_eq!(batch.missing, vec![Currency::JPY]);
+
+        let quotes = batch.into_quotes(false, "CoinMarketCap").unwrap();
+        assert_eq!(quotes.len(), 2);
+        assert_eq!(quotes[0].currency, Currency::USD);
+        assert_eq!(quotes[0].price, 2000.5);
+        assert_eq!(quotes[1].currency, Currency::EUR);
+        assert_eq!(missing_currencies(&currencies, &quotes), vec![Currency::JPY]);
+    }
+
+    #[test]
+    fn test_missing_currency_strict_mode_fails() {
+        let provider = CoinMarketCap::new("test_key".to_string())
+            .unwrap()
+            .with_strict_currencies(true);
+        let currencies = [Currency::USD, Currency::JPY];
+
+        let err = provider
+            .parse_quotes(&sample_response(), Coin::ETH, &currencies)
+            .unwrap()
+            .into_quotes(provider.strict_currencies, "CoinMarketCap")
+            .unwrap_err();
+        assert!(err.to_string().contains("JPY"));
+    }
 }
@@ -0,0 +1,277 @@
+//! Real-time price feed storage.
+//!
+//! Streaming providers push every update into a `LatestPriceCache`, and readers
+//! (subscription endpoints, handlers) take the most recent quote from it instead
+//! of polling the provider's REST API.
+//!
+//! An [`AggregateFeed`] combines several providers' quote streams into a live
+//! aggregate: on every tick it recomputes the median of each provider's latest
+//! price and the band between the lowest and highest of them.
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+use super::aggregate::{aggregate, AggregateStrategy};
+use super::{Coin, Currency, PriceProvider, ProviderSource, Quote};
+
+/// Updates buffered per subscriber before a slow one starts skipping them
+const UPDATE_CHANNEL_CAPACITY: usize = 64;
+
+/// How old a provider's latest quote may get before it leaves the aggregate by default
+pub const DEFAULT_MAX_QUOTE_AGE: Duration = Duration::from_secs(60);
+
+/// Most recent streamed quote per coin/currency pair.
+#[derive(Debug)]
+pub struct LatestPriceCache {
+    quotes: RwLock<HashMap<(Coin, Currency), Quote>>,
+    updates: broadcast::Sender<Quote>,
+}
+
+impl Default for LatestPriceCache {
+    fn default() -> Self {
+        Self {
+            quotes: RwLock::default(),
+            updates: broadcast::channel(UPDATE_CHANNEL_CAPACITY).0,
+        }
+    }
+}
+
+impl LatestPriceCache {
+    /// Replaces the cached quote for the quote's coin/currency pair.
+    pub fn update(&self, quote: Quote) {
+        // No subscribers is not an error, the quote is still cached
+        let _ = self.updates.send(quote.clone());
+        self.quotes
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert((quote.coin, quote.currency), quote);
+    }
+
+    /// Returns the latest quote for the pair, if one has been received.
+    pub fn get(&self, coin: Coin, currency: Currency) -> Option<Quote> {
+        self.quotes
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&(coin, currency))
+            .cloned()
+    }
+
+    /// Streams every quote received from now on, for any pair.
+    ///
+    /// A subscriber that falls behind skips the updates it missed rather than
+    /// replaying stale prices.
+    pub fn updates(&self) -> BoxStream<'static, Quote> {
+        stream::unfold(self.updates.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(quote) => return Some((quote, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .boxed()
+    }
+}
+
+/// Serves the latest streamed quotes, so a feed can stand in for a REST provider.
+#[async_trait]
+impl PriceProvider for LatestPriceCache {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        "stream"
+    }
+
+    /// Returns the cached quotes for the requested currencies that have been streamed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if none of the requested pairs has been received yet.
+    async fn get_quotes(
+        &self,
+        coin: Coin,
+        currencies: &[Currency],
+    ) -> std::result::Result<Vec<Quote>, Self::Error> {
+        let quotes: Vec<Quote> = currencies
+            .iter()
+            .filter_map(|currency| self.get(coin, *currency))
+            .collect();
+
+        if quotes.is_empty() {
+            anyhow::bail!("No streamed price for {} in {:?}", coin, currencies);
+        }
+        Ok(quotes)
+    }
+}
+
+/// Live price for one pair aggregated across providers, with its confidence band
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct AggregateTick {
+    /// The cryptocurrency being quoted
+    pub coin: Coin,
+    /// The fiat currency the price is denominated in
+    pub currency: Currency,
+    /// Median of the providers' latest prices
+    pub price: f64,
+    /// Lowest latest price, the bottom of the band
+    pub low: f64,
+    /// Highest latest price, the top of the band
+    pub high: f64,
+    /// `(high - low) / price`, in percent
+    pub spread_percent: f64,
+    /// Providers whose latest quote is in the aggregate, in the order they first ticked
+    pub providers: Vec<ProviderSource>,
+    /// When the newest aggregated quote was fetched
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Aggregates the latest quote of every provider for one pair as they tick.
+///
+/// Providers update at their own rate, so each keeps contributing its latest
+/// quote until a newer one arrives. A quote more than `max_age` older than the
+/// newest one is stale and drops out until its provider ticks again.
+#[derive(Debug)]
+pub struct AggregateFeed {
+    coin: Coin,
+    currency: Currency,
+    max_age: Duration,
+    latest: Vec<Quote>,
+}
+
+impl AggregateFeed {
+    /// Creates a feed for the pair with [`DEFAULT_MAX_QUOTE_AGE`].
+    pub fn new(coin: Coin, currency: Currency) -> Self {
+        Self {
+            coin,
+            currency,
+            max_age: DEFAULT_MAX_QUOTE_AGE,
+            latest: Vec::new(),
+        }
+    }
+
+    /// Sets how far behind the newest quote a provider's quote may fall.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Records `quote` as its provider's latest and returns the new aggregate.
+    ///
+    /// Quotes for another pair are ignored and yield `None`.
+    pub fn update(&mut self, quote: Quote) -> Option<AggregateTick> {
+        if quote.coin != self.coin || quote.currency != self.currency || !quote.price.is_finite() {
+            return None;
+        }
+        match self.latest.iter_mut().find(|latest| latest.provider == quote.provider) {
+            Some(latest) => *latest = quote,
+            None => self.latest.push(quote),
+        }
+
+        let newest = self.latest.iter().map(|quote| quote.timestamp).max()?;
+        let max_age = chrono::Duration::from_std(self.max_age).unwrap_or(chrono::Duration::MAX);
+        let fresh: Vec<Quote> = self
+            .latest
+            .iter()
+            .filter(|quote| newest - quote.timestamp <= max_age)
+            .cloned()
+            .collect();
+
+        let median = aggregate(&fresh, None, AggregateStrategy::Median, NonZeroUsize::MIN, None).pop()?;
+        let low = fresh.iter().map(|quote| quote.price).fold(f64::INFINITY, f64::min);
+        let high = fresh.iter().map(|quote| quote.price).fold(f64::NEG_INFINITY, f64::max);
+
+        Some(AggregateTick {
+            coin: self.coin,
+            currency: self.currency,
+            price: median.price,
+            low,
+            high,
+            spread_percent: if median.price > 0.0 { (high - low) / median.price * 100.0 } else { 0.0 },
+            providers: median.providers,
+            timestamp: newest,
+        })
+    }
+
+    /// Merges the providers' quote streams and yields the aggregate after every tick.
+    ///
+    /// Ends once every provider stream has ended.
+    pub fn ticks(mut self, providers: Vec<BoxStream<'static, Quote>>) -> impl Stream<Item = AggregateTick> {
+        stream::select_all(providers).filter_map(move |quote| std::future::ready(self.update(quote)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn quote(provider: ProviderSource, price: f64, seconds: i64) -> Quote {
+        Quote {
+            timestamp: DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap(),
+            ..Quote::new(Coin::ETH, Currency::USD, price, provider)
+        }
+    }
+
+    fn channel_stream(mut receiver: mpsc::UnboundedReceiver<Quote>) -> BoxStream<'static, Quote> {
+        stream::poll_fn(move |cx| receiver.poll_recv(cx)).boxed()
+    }
+
+    #[tokio::test]
+    async fn test_ticks_push_median_of_latest_quotes() {
+        let (fast, fast_rx) = mpsc::unbounded_channel();
+        let (slow, slow_rx) = mpsc::unbounded_channel();
+        let feed = AggregateFeed::new(Coin::ETH, Currency::USD);
+        let mut ticks = Box::pin(feed.ticks(vec![channel_stream(fast_rx), channel_stream(slow_rx)]));
+
+        fast.send(quote(ProviderSource::Binance, 2000.0, 0)).unwrap();
+        let tick = ticks.next().await.unwrap();
+        assert_eq!((tick.price, tick.spread_percent), (2000.0, 0.0));
+
+        slow.send(quote(ProviderSource::CoinGecko, 2020.0, 1)).unwrap();
+        let tick = ticks.next().await.unwrap();
+        assert_eq!((tick.price, tick.low, tick.high), (2010.0, 2000.0, 2020.0));
+        assert_eq!(tick.providers, [ProviderSource::Binance, ProviderSource::CoinGecko]);
+
+        // The fast provider ticks again while the slow one's quote still counts
+        fast.send(quote(ProviderSource::Binance, 2040.0, 2)).unwrap();
+        let tick = ticks.next().await.unwrap();
+        assert_eq!((tick.price, tick.low, tick.high), (2030.0, 2020.0, 2040.0));
+        assert!((tick.spread_percent - 20.0 / 2030.0 * 100.0).abs() < 1e-9);
+
+        drop((fast, slow));
+        assert!(ticks.next().await.is_none());
+    }
+
+    #[test]
+    fn test_stale_and_foreign_quotes_are_left_out() {
+        let mut feed = AggregateFeed::new(Coin::ETH, Currency::USD).with_max_age(Duration::from_secs(30));
+        feed.update(quote(ProviderSource::CoinGecko, 1900.0, 0));
+
+        let tick = feed.update(quote(ProviderSource::Binance, 2000.0, 31)).unwrap();
+        assert_eq!(tick.price, 2000.0);
+        assert_eq!(tick.providers, [ProviderSource::Binance]);
+
+        let eur = Quote { currency: Currency::EUR, ..quote(ProviderSource::Binance, 1800.0, 32) };
+        assert!(feed.update(eur).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cache_broadcasts_updates() {
+        let cache = LatestPriceCache::default();
+        let mut updates = cache.updates();
+
+        cache.update(quote(ProviderSource::Binance, 2000.0, 0));
+        assert_eq!(updates.next().await.unwrap().price, 2000.0);
+        assert_eq!(cache.get(Coin::ETH, Currency::USD).unwrap().price, 2000.0);
+    }
+}
@@ -0,0 +1,248 @@
+//! Fiat conversion for providers that lack a currency.
+//!
+//! Some providers only price a coin in a few currencies (Binance has no CHF
+//! market, for instance). Rather than leaving such a provider out, its USD
+//! price can be converted with a reference rate from `FX_RATES`, e.g.
+//! `FX_RATES=CHF=0.88,JPY=151.2` for 1 USD = 0.88 CHF = 151.2 JPY.
+//! [`FxPriceProvider`] asks the wrapped provider for USD alongside the requested
+//! currencies and fills in the ones it did not price.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use boltzmann::domains::crypto::{Coin, Currency, PriceProvider};
+//! use boltzmann::domains::crypto::binance::Binance;
+//! use boltzmann::domains::crypto::fx::{FxPriceProvider, FxRates};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let rates: FxRates = "CHF=0.88".parse()?;
+//! let provider = FxPriceProvider::new(Box::new(Binance::new()?), Arc::new(rates));
+//!
+//! let quotes = provider.get_quotes(Coin::ETH, &[Currency::CHF]).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tracing::debug;
+
+use super::{Coin, Currency, DynPriceProvider, MarketDepth, PriceProvider, Quote};
+use crate::core::errors::Result;
+
+/// Currency every reference rate is quoted against
+pub const FX_BASE: Currency = Currency::USD;
+
+/// Reference exchange rates, as units of each currency per one [`FX_BASE`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FxRates {
+    rates: HashMap<Currency, f64>,
+}
+
+impl FxRates {
+    /// Rates from units of each currency per one [`FX_BASE`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a rate is not a positive finite number.
+    pub fn new(rates: impl IntoIterator<Item = (Currency, f64)>) -> Result<Self> {
+        let rates: HashMap<Currency, f64> = rates.into_iter().collect();
+        if let Some((currency, rate)) = rates.iter().find(|(_, rate)| !rate.is_finite() || **rate <= 0.0) {
+            anyhow::bail!("invalid FX rate {} for {}: must be a positive number", rate, currency);
+        }
+        Ok(Self { rates })
+    }
+
+    /// Whether no rate is configured
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+
+    /// Units of `to` per one unit of `from`, if both currencies have a rate.
+    pub fn rate(&self, from: Currency, to: Currency) -> Option<f64> {
+        let per_base = |currency: Currency| {
+            if currency == FX_BASE { Some(1.0) } else { self.rates.get(&currency).copied() }
+        };
+        if from == to {
+            return Some(1.0);
+        }
+        Some(per_base(to)? / per_base(from)?)
+    }
+
+    /// Returns `quote` repriced in `currency`, if a rate between the two is known.
+    pub fn convert(&self, quote: &Quote, currency: Currency) -> Option<Quote> {
+        let rate = self.rate(quote.currency, currency)?;
+        let price = quote.price * rate;
+        let converted = Quote {
+            currency,
+            price,
+            unit_price: price,
+            currency_numeric: currency.iso_numeric(),
+            bid: quote.bid.map(|bid| bid * rate),
+            ask: quote.ask.map(|ask| ask * rate),
+            ..quote.clone()
+        };
+        Some(converted.with_amount(quote.quote_per_amount.amount))
+    }
+}
+
+impl FromStr for FxRates {
+    type Err = anyhow::Error;
+
+    /// Parses comma-separated `CURRENCY=rate` pairs, e.g. `CHF=0.88,JPY=151.2`.
+    fn from_str(s: &str) -> Result<Self> {
+        let rates = s
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (currency, rate) = pair
+                    .split_once('=')
+                    .ok_or_else(|| anyhow::anyhow!("invalid FX rate '{}': expected CURRENCY=rate", pair))?;
+                let rate = rate
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| anyhow::anyhow!("invalid FX rate '{}': '{}' is not a number", pair, rate.trim()))?;
+                Ok((currency.parse()?, rate))
+            })
+            .collect::<Result<Vec<(Currency, f64)>>>()?;
+        Self::new(rates)
+    }
+}
+
+/// Converts the wrapped provider's [`FX_BASE`] price into currencies it does not price.
+pub struct FxPriceProvider {
+    provider: DynPriceProvider,
+    rates: Arc<FxRates>,
+}
+
+impl FxPriceProvider {
+    /// Wraps `provider`, converting with `rates` where it lacks a currency.
+    pub fn new(provider: DynPriceProvider, rates: Arc<FxRates>) -> Self {
+        Self { provider, rates }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl PriceProvider for FxPriceProvider {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Fetches quotes from the wrapped provider, also asking for [`FX_BASE`],
+    /// and converts its base price into each requested currency it left out.
+    ///
+    /// Quotes the provider returns itself are kept as they are, and only
+    /// requested currencies are returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns the provider's error, or an error if no requested currency could
+    /// be priced even with conversion.
+    async fn get_quotes(
+        &self,
+        coin: Coin,
+        currencies: &[Currency],
+    ) -> std::result::Result<Vec<Quote>, Self::Error> {
+        let mut request = currencies.to_vec();
+        if !request.contains(&FX_BASE) {
+            request.push(FX_BASE);
+        }
+        let fetched = self.provider.get_quotes(coin, &request).await?;
+
+        let quotes: Vec<Quote> = currencies
+            .iter()
+            .filter_map(|&currency| {
+                if let Some(quote) = fetched.iter().find(|quote| quote.currency == currency) {
+                    return Some(quote.clone());
+                }
+                let base = fetched.iter().find(|quote| quote.currency == FX_BASE)?;
+                let converted = self.rates.convert(base, currency)?;
+                debug!("Converted {} {}/{} price to {} at the reference rate", base.provider, coin, FX_BASE, currency);
+                Some(converted)
+            })
+            .collect();
+
+        if quotes.is_empty() {
+            anyhow::bail!("No price for {} in {:?}, even with FX conversion", coin, currencies);
+        }
+        Ok(quotes)
+    }
+
+    async fn get_market_depth(
+        &self,
+        coin: Coin,
+        currency: Currency,
+    ) -> std::result::Result<MarketDepth, Self::Error> {
+        self.provider.get_market_depth(coin, currency).await
+    }
+
+    fn raw_response(&self) -> Option<serde_json::Value> {
+        self.provider.raw_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::crypto::ProviderSource;
+
+    /// Provider that, like Binance for CHF, only prices ETH in USD
+    struct UsdOnlyProvider;
+
+    #[async_trait]
+    impl PriceProvider for UsdOnlyProvider {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "usd_only"
+        }
+
+        async fn get_quotes(&self, coin: Coin, currencies: &[Currency]) -> anyhow::Result<Vec<Quote>> {
+            anyhow::ensure!(currencies.contains(&Currency::USD), "only USD is priced");
+            Ok(vec![Quote {
+                market: Some("ETHUSDT".to_string()),
+                ..Quote::new(coin, Currency::USD, 2000.0, ProviderSource::Binance)
+            }])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_usd_only_provider_converted_to_chf() {
+        let rates: FxRates = "CHF=0.88".parse().unwrap();
+        let provider = FxPriceProvider::new(Box::new(UsdOnlyProvider), Arc::new(rates));
+
+        let quotes = provider.get_quotes(Coin::ETH, &[Currency::CHF]).await.unwrap();
+        assert_eq!(quotes.len(), 1);
+        let chf = &quotes[0];
+        assert_eq!(chf.currency, Currency::CHF);
+        assert!((chf.price - 1760.0).abs() < 1e-9);
+        assert_eq!(chf.unit_price, chf.price);
+        assert_eq!(chf.currency_numeric, Some(756));
+        assert_eq!(chf.provider, ProviderSource::Binance);
+
+        // Currencies without a rate are still left out
+        let quotes = provider.get_quotes(Coin::ETH, &[Currency::JPY, Currency::USD]).await.unwrap();
+        assert_eq!(quotes.iter().map(|quote| quote.currency).collect::<Vec<_>>(), [Currency::USD]);
+        assert!(provider.get_quotes(Coin::ETH, &[Currency::JPY]).await.is_err());
+    }
+
+    #[test]
+    fn test_rates_parse_and_cross() {
+        let rates: FxRates = "CHF=0.88, EUR = 0.92".parse().unwrap();
+        assert_eq!(rates.rate(Currency::USD, Currency::CHF), Some(0.88));
+        assert!((rates.rate(Currency::EUR, Currency::CHF).unwrap() - 0.88 / 0.92).abs() < 1e-12);
+        assert_eq!(rates.rate(Currency::USD, Currency::JPY), None);
+
+        assert!("CHF".parse::<FxRates>().is_err());
+        assert!("CHF=-1".parse::<FxRates>().is_err());
+        assert!("XYZ=1".parse::<FxRates>().is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! Hedged requests across price providers.
+//!
+//! Instead of waiting for each provider in a fallback chain to fail before trying
+//! the next, a [`HedgedPriceProvider`] fires the top providers concurrently and
+//! returns whichever answers first, cancelling the rest. This trades extra
+//! upstream calls for lower tail latency.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use boltzmann::domains::crypto::{Coin, Currency, DynPriceProvider, PriceProvider, ProviderSource};
+//! use boltzmann::domains::crypto::coingecko::CoinGecko;
+//! use boltzmann::domains::crypto::coinmarketcap::CoinMarketCap;
+//! use boltzmann::domains::crypto::hedged::HedgedPriceProvider;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let providers: Vec<(ProviderSource, DynPriceProvider)> = vec![
+//!     (ProviderSource::CoinGecko, Box::new(CoinGecko::new(None)?)),
+//!     (ProviderSource::CoinMarketCap, Box::new(CoinMarketCap::new("your_api_key".to_string())?)),
+//! ];
+//! let provider = HedgedPriceProvider::new(providers)
+//!     .with_hedge_delay(Duration::from_millis(100));
+//! let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::stream::{FuturesUnordered, StreamExt};
+use tracing::{debug, warn};
+
+use super::{Coin, Currency, DynPriceProvider, PriceProvider, ProviderSource, Quote};
+
+/// Providers raced concurrently by default
+const DEFAULT_HEDGE_COUNT: usize = 2;
+
+/// Races the top providers and returns the first successful answer.
+///
+/// The first `hedge_count` providers are started concurrently, each one
+/// `hedge_delay` after the previous, so a hedge is only sent when the earlier
+/// providers are slow. The first provider to return quotes wins and the other
+/// in-flight requests are dropped. If every hedged provider fails, the remaining
+/// providers are tried one at a time, in order, like a plain fallback chain.
+pub struct HedgedPriceProvider {
+    providers: Vec<(ProviderSource, DynPriceProvider)>,
+    hedge_count: usize,
+    hedge_delay: Duration,
+}
+
+impl HedgedPriceProvider {
+    /// Creates a hedged provider over `providers`, in priority order.
+    ///
+    /// Defaults to racing the top two providers with no delay between them.
+    pub fn new(providers: Vec<(ProviderSource, DynPriceProvider)>) -> Self {
+        Self {
+            providers,
+            hedge_count: DEFAULT_HEDGE_COUNT,
+            hedge_delay: Duration::ZERO,
+        }
+    }
+
+    /// Sets how many providers are raced concurrently (at least one).
+    pub fn with_hedge_count(mut self, count: usize) -> Self {
+        self.hedge_count = count.max(1);
+        self
+    }
+
+    /// Sets how long to wait for a provider before sending the next hedge.
+    pub fn with_hedge_delay(mut self, delay: Duration) -> Self {
+        self.hedge_delay = delay;
+        self
+    }
+
+    /// Races the hedged providers, returning the first non-empty result.
+    async fn race(
+        &self,
+        hedged: &[(ProviderSource, DynPriceProvider)],
+        coin: Coin,
+        currencies: &[Currency],
+    ) -> Option<Vec<Quote>> {
+        let mut pending: FuturesUnordered<_> = hedged
+            .iter()
+            .enumerate()
+            .map(|(index, (source, provider))| {
+                let delay = self.hedge_delay * index as u32;
+                async move {
+                    if !delay.is_zero() {
+                        tokio::time::sleep(delay).await;
+                        debug!("Sending hedged request to {}", source);
+                    }
+                    (*source, provider.get_quotes(coin, currencies).await)
+                }
+            })
+            .collect();
+
+        while let Some((source, result)) = pending.next().await {
+            match result {
+                Ok(quotes) if !quotes.is_empty() => return Some(quotes),
+                Ok(_) => warn!("{} returned no quotes", source),
+                Err(e) => warn!("{} failed: {:#}", source, e),
+            }
+        }
+        None
+    }
+}
+
+#[async_trait]
+impl PriceProvider for HedgedPriceProvider {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        "hedged"
+    }
+
+    /// Returns quotes from the first hedged provider to answer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if every provider fails or returns no quotes.
+    async fn get_quotes(
+        &self,
+        coin: Coin,
+        currencies: &[Currency],
+    ) -> std::result::Result<Vec<Quote>, Self::Error> {
+        let split = self.hedge_count.min(self.providers.len());
+        let (hedged, rest) = self.providers.split_at(split);
+
+        if let Some(quotes) = self.race(hedged, coin, currencies).await {
+            return Ok(quotes);
+        }
+
+        for (source, provider) in rest {
+            match provider.get_quotes(coin, currencies).await {
+                Ok(quotes) if !quotes.is_empty() => return Ok(quotes),
+                Ok(_) => warn!("{} returned no quotes", source),
+                Err(e) => warn!("{} failed: {:#}", source, e),
+            }
+        }
+
+        anyhow::bail!("No quotes available from any provider")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Provider that answers after `delay`, counting calls and completed answers
+    struct DelayedProvider {
+        source: ProviderSource,
+        delay: Duration,
+        price: Option<f64>,
+        calls: Arc<AtomicUsize>,
+        completed: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl PriceProvider for DelayedProvider {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "delayed"
+        }
+
+        async fn get_quotes(&self, coin: Coin, currencies: &[Currency]) -> anyhow::Result<Vec<Quote>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.completed.fetch_add(1, Ordering::SeqCst);
+            let price = self.price.ok_or_else(|| anyhow::anyhow!("{} is down", self.source))?;
+            Ok(currencies
+                .iter()
+                .map(|&currency| Quote::new(coin, currency, price, self.source))
+                .collect())
+        }
+    }
+
+    struct Counters {
+        calls: Arc<AtomicUsize>,
+        completed: Arc<AtomicUsize>,
+    }
+
+    fn delayed(
+        source: ProviderSource,
+        delay_ms: u64,
+        price: Option<f64>,
+    ) -> ((ProviderSource, DynPriceProvider), Counters) {
+        let counters = Counters { calls: Arc::default(), completed: Arc::default() };
+        let provider = DelayedProvider {
+            source,
+            delay: Duration::from_millis(delay_ms),
+            price,
+            calls: counters.calls.clone(),
+            completed: counters.completed.clone(),
+        };
+        ((source, Box::new(provider)), counters)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_faster_hedge_wins_and_slow_provider_is_cancelled() {
+        let (slow, slow_counters) = delayed(ProviderSource::CoinMarketCap, 1_000, Some(2000.0));
+        let (fast, _) = delayed(ProviderSource::CoinGecko, 50, Some(2001.0));
+
+        let provider = HedgedPriceProvider::new(vec![slow, fast]);
+        let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+
+        assert_eq!(quotes[0].provider, ProviderSource::CoinGecko);
+        assert_eq!(slow_counters.calls.load(Ordering::SeqCst), 1);
+
+        // The slow request was dropped, so it never completes
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(slow_counters.completed.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hedge_not_sent_when_first_answers_within_delay() {
+        let (first, _) = delayed(ProviderSource::CoinGecko, 50, Some(2000.0));
+        let (second, second_counters) = delayed(ProviderSource::CoinMarketCap, 10, Some(2001.0));
+
+        let provider = HedgedPriceProvider::new(vec![first, second])
+            .with_hedge_delay(Duration::from_millis(100));
+        let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+
+        assert_eq!(quotes[0].provider, ProviderSource::CoinGecko);
+        assert_eq!(second_counters.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_hedges_fall_through_to_remaining_providers() {
+        let (first, _) = delayed(ProviderSource::CoinGecko, 10, None);
+        let (second, _) = delayed(ProviderSource::CoinMarketCap, 10, None);
+        let (third, third_counters) = delayed(ProviderSource::Binance, 10, Some(2002.0));
+
+        let provider = HedgedPriceProvider::new(vec![first, second, third]);
+        let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+        assert_eq!(quotes[0].provider, ProviderSource::Binance);
+        assert_eq!(third_counters.calls.load(Ordering::SeqCst), 1);
+
+        let (only, _) = delayed(ProviderSource::CoinGecko, 10, None);
+        let provider = HedgedPriceProvider::new(vec![only]).with_hedge_count(0);
+        assert!(provider.get_quotes(Coin::ETH, &[Currency::USD]).await.is_err());
+    }
+}
@@ -6,33 +6,59 @@
 //! # Examples
 //!
 //! ```rust
-//! use boltzmann::crypto::{Coin, Currency, PriceProvider};
-//! use boltzmann::crypto::coinmarketcap::CoinMarketCap;
+//! use boltzmann::domains::crypto::{Coin, Currency, PriceProvider};
+//! use boltzmann::domains::crypto::coinmarketcap::CoinMarketCap;
 //!
 //! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-//! let provider = CoinMarketCap::new()?;
+//! let provider = CoinMarketCap::new("your_api_key".to_string())?;
 //! let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD, Currency::EUR]).await?;
 //!
 //! for quote in quotes {
-//!     println!("{} = {}{}", quote.coin, quote.currency.symbol(), quote.price);
+//!     println!("{} = {}", quote.coin, quote.currency.format(quote.price));
 //! }
 //! # Ok(())
 //! # }
 //! ```
 
+use anyhow::Context;
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use rust_decimal::{Decimal, RoundingStrategy};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
 use serde::{Deserialize, Serialize};
+use crate::core::errors::{NotSupported, Result};
+use crate::domains::gas::chain::ChainId;
+use std::cmp::Ordering;
 use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
 use utoipa::ToSchema;
 
+pub mod aggregate;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod binance;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cache;
 pub mod coingecko;
 pub mod coinmarketcap;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod feed;
+pub mod fx;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod hedged;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod oneinch;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod rate_limit;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod warm;
 
 /// Supported fiat currencies for price conversion.
 ///
 /// This enum represents the fiat currencies that can be used to fetch
-/// cryptocurrency prices from supported providers.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+/// cryptocurrency prices from supported providers. Codes and common aliases
+/// are matched case-insensitively when parsed or deserialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
 pub enum Currency {
     /// US Dollar
     USD,
@@ -55,11 +81,16 @@ pub enum Currency {
 /// Supported cryptocurrencies.
 ///
 /// This enum represents the cryptocurrencies supported by the price providers.
-/// Each coin has corresponding IDs for different API providers.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+/// Each coin has corresponding IDs for different API providers. Symbols and
+/// common aliases are matched case-insensitively when parsed or deserialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
 pub enum Coin {
-    /// Ethereum
+    /// Ethereum, also the gas token of most L2s
     ETH,
+    /// Polygon's gas token
+    MATIC,
+    /// Avalanche C-Chain's gas token
+    AVAX,
 }
 
 impl fmt::Display for Currency {
@@ -77,21 +108,132 @@ impl fmt::Display for Currency {
     }
 }
 
+/// Common names and symbols accepted for a [`Currency`] besides its ISO code.
+///
+/// Kept conservative: an alias that could mean several currencies belongs in
+/// [`AMBIGUOUS_CURRENCY_ALIASES`] instead, so it is rejected rather than guessed.
+const CURRENCY_ALIASES: &[(&str, Currency)] = &[
+    ("$", Currency::USD),
+    ("dollar", Currency::USD),
+    ("dollars", Currency::USD),
+    ("€", Currency::EUR),
+    ("euro", Currency::EUR),
+    ("euros", Currency::EUR),
+    ("£", Currency::GBP),
+    ("pound", Currency::GBP),
+    ("pounds", Currency::GBP),
+    ("yen", Currency::JPY),
+    ("yuan", Currency::CNY),
+    ("rmb", Currency::CNY),
+    ("c$", Currency::CAD),
+    ("a$", Currency::AUD),
+];
+
+/// Aliases shared by several currencies, e.g. `¥` for both CNY and JPY.
+const AMBIGUOUS_CURRENCY_ALIASES: &[(&str, &[Currency])] = &[
+    ("¥", &[Currency::CNY, Currency::JPY]),
+];
+
+/// Common names and symbols accepted for a [`Coin`] besides its ticker.
+const COIN_ALIASES: &[(&str, Coin)] = &[
+    ("ether", Coin::ETH),
+    ("ethereum", Coin::ETH),
+    ("weth", Coin::ETH),
+    ("Ξ", Coin::ETH),
+    ("pol", Coin::MATIC),
+    ("polygon", Coin::MATIC),
+    ("avalanche", Coin::AVAX),
+    ("wavax", Coin::AVAX),
+];
+
+/// Looks `raw` up in an alias table, case-insensitively.
+fn resolve_alias<T: Copy>(aliases: &[(&str, T)], raw: &str) -> Option<T> {
+    let raw = raw.to_lowercase();
+    aliases.iter().find(|(alias, _)| alias.to_lowercase() == raw).map(|&(_, value)| value)
+}
+
+impl FromStr for Currency {
+    type Err = anyhow::Error;
+
+    /// Parses an ISO currency code, case-insensitively (`usd` or `USD`), or one of
+    /// a few common aliases (`$`, `dollar`, `euro`). Ambiguous aliases like `¥`
+    /// are rejected.
+    fn from_str(s: &str) -> Result<Self> {
+        let code = s.trim();
+        if let Some(&(_, candidates)) = AMBIGUOUS_CURRENCY_ALIASES
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(code))
+        {
+            anyhow::bail!(
+                "ambiguous currency '{}', use an ISO code such as: {}",
+                s,
+                candidates.iter().map(Currency::to_string).collect::<Vec<_>>().join(", ")
+            );
+        }
+        Currency::all()
+            .iter()
+            .copied()
+            .find(|currency| currency.to_string().eq_ignore_ascii_case(code))
+            .or_else(|| resolve_alias(CURRENCY_ALIASES, code))
+            .ok_or_else(|| anyhow::anyhow!(
+                "invalid currency '{}', expected one of: {}",
+                s,
+                Currency::all().iter().map(Currency::to_string).collect::<Vec<_>>().join(", ")
+            ))
+    }
+}
+
 impl fmt::Display for Coin {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Coin::ETH => write!(f, "ETH"),
+            Coin::MATIC => write!(f, "MATIC"),
+            Coin::AVAX => write!(f, "AVAX"),
         }
     }
 }
 
+impl FromStr for Coin {
+    type Err = anyhow::Error;
+
+    /// Parses a coin symbol, case-insensitively (`eth` or `ETH`), or one of a
+    /// few common aliases (`ether`, `WETH`, `Ξ`).
+    fn from_str(s: &str) -> Result<Self> {
+        let symbol = s.trim();
+        Coin::all()
+            .iter()
+            .copied()
+            .find(|coin| coin.to_string().eq_ignore_ascii_case(symbol))
+            .or_else(|| resolve_alias(COIN_ALIASES, symbol))
+            .ok_or_else(|| anyhow::anyhow!(
+                "invalid coin '{}', expected one of: {}",
+                s,
+                Coin::all().iter().map(Coin::to_string).collect::<Vec<_>>().join(", ")
+            ))
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Coin {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 impl Currency {
     /// Returns the currency symbol for display purposes.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use boltzmann::crypto::Currency;
+    /// use boltzmann::domains::crypto::Currency;
     ///
     /// assert_eq!(Currency::USD.symbol(), "$");
     /// assert_eq!(Currency::EUR.symbol(), "€");
@@ -109,6 +251,42 @@ impl Currency {
         }
     }
 
+    /// Returns the number of minor-unit digits (ISO 4217): 0 for JPY, 2 for the rest.
+    pub fn decimals(&self) -> u32 {
+        match self {
+            Currency::JPY => 0,
+            Currency::USD | Currency::EUR | Currency::CHF | Currency::CNY
+            | Currency::GBP | Currency::CAD | Currency::AUD => 2,
+        }
+    }
+
+    /// Returns the ISO 4217 numeric code, e.g. 840 for USD and 978 for EUR.
+    ///
+    /// Only fiat currencies have one; a crypto denominator would return `None`.
+    pub fn iso_numeric(&self) -> Option<u16> {
+        Some(match self {
+            Currency::USD => 840,
+            Currency::EUR => 978,
+            Currency::CHF => 756,
+            Currency::CNY => 156,
+            Currency::GBP => 826,
+            Currency::JPY => 392,
+            Currency::CAD => 124,
+            Currency::AUD => 36,
+        })
+    }
+
+    /// Rounds `value` to this currency's minor unit, half away from zero.
+    pub fn round(&self, value: Decimal) -> Decimal {
+        value.round_dp_with_strategy(self.decimals(), RoundingStrategy::MidpointAwayFromZero)
+    }
+
+    /// Formats `value` for display with the currency symbol and minor units,
+    /// e.g. `$4001.00` or `¥300000`.
+    pub fn format(&self, value: f64) -> String {
+        format!("{}{:.*}", self.symbol(), self.decimals() as usize, value)
+    }
+
     /// Returns all supported currencies.
     ///
     /// This is useful for fetching prices in all available currencies.
@@ -128,6 +306,8 @@ impl Coin {
     pub fn coinmarketcap_id(&self) -> u32 {
         match self {
             Coin::ETH => 1027,
+            Coin::MATIC => 3890,
+            Coin::AVAX => 5805,
         }
     }
 
@@ -137,6 +317,24 @@ impl Coin {
     pub fn coingecko_id(&self) -> &'static str {
         match self {
             Coin::ETH => "ethereum",
+            Coin::MATIC => "matic-network",
+            Coin::AVAX => "avalanche-2",
+        }
+    }
+
+    /// Returns the coin gas is paid in on `chain`.
+    ///
+    /// Fees on a chain must be priced in its own gas token: ETH on mainnet and
+    /// the rollups, MATIC on Polygon. Returns `None` for chains whose gas token
+    /// is not known.
+    pub fn native_for_chain(chain: ChainId) -> Option<Coin> {
+        match chain {
+            ChainId::ETHEREUM | ChainId::OPTIMISM | ChainId::BASE | ChainId::ARBITRUM | ChainId::SEPOLIA => {
+                Some(Coin::ETH)
+            }
+            ChainId::POLYGON => Some(Coin::MATIC),
+            ChainId::AVALANCHE => Some(Coin::AVAX),
+            _ => None,
         }
     }
 
@@ -144,7 +342,7 @@ impl Coin {
     ///
     /// This is useful for fetching prices for all available crypto.
     pub fn all() -> &'static [Coin] {
-        &[Coin::ETH]
+        &[Coin::ETH, Coin::MATIC, Coin::AVAX]
     }
 }
 
@@ -155,15 +353,90 @@ pub struct QuotePerAmount {
     pub amount: f64,
     /// The total price for this amount
     pub total_price: f64,
+    /// The total price computed with exact decimal arithmetic, as a decimal string.
+    ///
+    /// Only present when decimal totals are enabled (`DECIMAL_TOTALS`); `total_price`
+    /// is always kept for compatibility.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "6000.15")]
+    pub exact_total_price: Option<Decimal>,
 }
 
 /// Supported price provider sources
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+///
+/// Names are matched case-insensitively when parsed or deserialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
 pub enum ProviderSource {
     #[serde(rename = "coinmarketcap")]
     CoinMarketCap,
     #[serde(rename = "coingecko")]
     CoinGecko,
+    #[serde(rename = "binance")]
+    Binance,
+    /// 1inch Spot Price Aggregator, read on-chain
+    #[serde(rename = "1inch")]
+    OneInch,
+}
+
+impl fmt::Display for ProviderSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl ProviderSource {
+    /// Accepted provider names
+    pub const NAMES: &'static [&'static str] = &["coinmarketcap", "coingecko", "binance", "1inch"];
+
+    /// The provider's name in logs, metrics and query parameters
+    pub const fn name(self) -> &'static str {
+        match self {
+            ProviderSource::CoinMarketCap => "coinmarketcap",
+            ProviderSource::CoinGecko => "coingecko",
+            ProviderSource::Binance => "binance",
+            ProviderSource::OneInch => "1inch",
+        }
+    }
+
+    /// Whether the provider prices several currencies in a single upstream request.
+    ///
+    /// CoinGecko and CoinMarketCap take a list of currencies; Binance has a
+    /// market per pair and the 1inch oracle a rate per token, so they fetch
+    /// each currency on its own.
+    pub const fn batches_currencies(self) -> bool {
+        matches!(self, ProviderSource::CoinGecko | ProviderSource::CoinMarketCap)
+    }
+
+    /// Returns all supported price providers.
+    pub fn all() -> &'static [ProviderSource] {
+        &[ProviderSource::CoinMarketCap, ProviderSource::CoinGecko, ProviderSource::Binance, ProviderSource::OneInch]
+    }
+}
+
+impl FromStr for ProviderSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "coinmarketcap" => Ok(ProviderSource::CoinMarketCap),
+            "coingecko" => Ok(ProviderSource::CoinGecko),
+            "binance" => Ok(ProviderSource::Binance),
+            "1inch" | "oneinch" => Ok(ProviderSource::OneInch),
+            _ => anyhow::bail!(
+                "unknown price provider '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            ),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ProviderSource {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse()
+            .map_err(|_| serde::de::Error::unknown_variant(&raw, Self::NAMES))
+    }
 }
 
 /// A cryptocurrency price quote at a specific point in time.
@@ -171,21 +444,96 @@ pub enum ProviderSource {
 /// Contains the coin, currency, unit price, and quote information for a specific amount.
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Quote {
+    /// Response contract version, see [`SCHEMA_VERSION`](crate::domains::SCHEMA_VERSION)
+    #[serde(default = "crate::domains::current_schema_version")]
+    #[schema(example = 1)]
+    pub schema_version: u32,
     /// The cryptocurrency being quoted
     pub coin: Coin,
     /// The fiat currency the price is denominated in
     pub currency: Currency,
     /// The price per single unit of the cryptocurrency
     pub price: f64,
+    /// Same as `price`, named so it is not mistaken for `quote_per_amount.total_price`
+    #[schema(example = 2000.5)]
+    pub unit_price: f64,
     /// The provider that supplied this quote
     pub provider: ProviderSource,
-    /// When this quote was fetched
+    /// When this quote was fetched, in `TIMESTAMP_FORMAT` (RFC 3339 by default)
+    #[serde(with = "crate::domains::timestamp")]
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Whether this quote was served from the quote cache instead of fetched for this request
+    #[serde(default)]
+    pub cached: bool,
+    /// Seconds since this quote was fetched; 0 for fresh quotes
+    #[serde(default)]
+    pub age_seconds: u64,
+    /// ISO 4217 numeric code of `currency` (840 for USD), see [`Currency::iso_numeric`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = 840)]
+    pub currency_numeric: Option<u16>,
+    /// Trading pair the price comes from, for exchange providers (`ETHUSDT` on
+    /// Binance); `null` for index and aggregator providers
+    #[serde(default)]
+    #[schema(example = "ETHUSDT")]
+    pub market: Option<String>,
+    /// Best bid on the exchange's order book, for exchange providers that report
+    /// it; `null` for index and aggregator providers
+    #[serde(default)]
+    #[schema(example = 2000.25)]
+    pub bid: Option<f64>,
+    /// Best ask on the exchange's order book, for exchange providers that report
+    /// it; `null` for index and aggregator providers
+    #[serde(default)]
+    #[schema(example = 2000.75)]
+    pub ask: Option<f64>,
+    /// How far the provider is trusted, from 0 to 1 (`PROVIDER_WEIGHT_<PROVIDER>`,
+    /// default 1); weighs the quote in the weighted aggregate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(example = 0.8)]
+    pub weight: Option<f64>,
     /// Quote information for a specific amount
     pub quote_per_amount: QuotePerAmount,
 }
 
 impl Quote {
+    /// Creates a quote for one unit of `coin` at `price`, fetched now.
+    ///
+    /// The quote is fresh (`cached: false`) and carries no market, order book or
+    /// weight; providers that report those set them with struct update syntax.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use boltzmann::domains::crypto::{Quote, Coin, Currency, ProviderSource};
+    ///
+    /// let quote = Quote {
+    ///     market: Some("ETHUSDT".to_string()),
+    ///     ..Quote::new(Coin::ETH, Currency::USD, 2000.0, ProviderSource::Binance)
+    /// };
+    /// assert_eq!(quote.unit_price, 2000.0);
+    /// assert_eq!(quote.quote_per_amount.total_price, 2000.0);
+    /// ```
+    pub fn new(coin: Coin, currency: Currency, price: f64, provider: ProviderSource) -> Self {
+        Self {
+            schema_version: crate::domains::SCHEMA_VERSION,
+            coin,
+            currency,
+            price,
+            unit_price: price,
+            provider,
+            timestamp: chrono::Utc::now(),
+            cached: false,
+            age_seconds: 0,
+            currency_numeric: currency.iso_numeric(),
+            market: None,
+            bid: None,
+            ask: None,
+            weight: None,
+            quote_per_amount: QuotePerAmount { amount: 1.0, total_price: price, exact_total_price: None },
+        }
+    }
+
     /// Creates a new quote with quote information for the given amount.
     ///
     /// This is useful for calculating the total value of a specific amount
@@ -198,35 +546,502 @@ impl Quote {
     /// # Examples
     ///
     /// ```rust
-    /// use boltzmann::crypto::{Quote, QuotePerAmount, Coin, Currency};
-    /// use chrono::Utc;
+    /// use boltzmann::domains::crypto::{Quote, Coin, Currency, ProviderSource};
     ///
-    /// let quote = Quote {
-    ///     coin: Coin::ETH,
-    ///     currency: Currency::USD,
-    ///     price: 2000.0,
-    ///     timestamp: Utc::now(),
-    ///     quote_per_amount: QuotePerAmount { amount: 1.0, total_price: 2000.0 },
-    /// };
+    /// let quote = Quote::new(Coin::ETH, Currency::USD, 2000.0, ProviderSource::CoinGecko);
     ///
     /// let total_value = quote.with_amount(2.5);
     /// assert_eq!(total_value.quote_per_amount.total_price, 5000.0);
     /// ```
     pub fn with_amount(&self, amount: f64) -> Self {
         Self {
-            coin: self.coin,
-            currency: self.currency,
-            price: self.price,
-            provider: self.provider.clone(),
-            timestamp: self.timestamp,
             quote_per_amount: QuotePerAmount {
                 amount,
-                total_price: self.price * amount,
+                total_price: self.value_of(amount),
+                exact_total_price: None,
             },
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of this quote for `amount` units, with the total computed in decimal.
+    ///
+    /// `price * amount` in `f64` accumulates rounding error (`0.1 * 3` is
+    /// `0.30000000000000004`). Here both operands are converted to their shortest
+    /// decimal representation first, so the product is exact; `total_price` is set
+    /// to the nearest `f64` of that exact total.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the price or amount is not finite or the total overflows.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use boltzmann::domains::crypto::{Quote, Coin, Currency, ProviderSource};
+    ///
+    /// let quote = Quote::new(Coin::ETH, Currency::USD, 0.1, ProviderSource::CoinGecko);
+    ///
+    /// let exact = quote.with_exact_amount(3.0).unwrap();
+    /// assert_eq!(exact.quote_per_amount.exact_total_price.unwrap().to_string(), "0.3");
+    /// ```
+    pub fn with_exact_amount(&self, amount: f64) -> Result<Self> {
+        let exact_total = self.exact_value_of(amount)?;
+        let mut quote = self.with_amount(amount);
+        quote.quote_per_amount.total_price = exact_total.to_f64().unwrap_or(quote.quote_per_amount.total_price);
+        quote.quote_per_amount.exact_total_price = Some(exact_total);
+        Ok(quote)
+    }
+
+    /// Returns the exact decimal value of `amount` units of the coin at this quote's price.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the price or amount is not finite or the total overflows.
+    pub fn exact_value_of(&self, amount: f64) -> Result<Decimal> {
+        let price = Decimal::from_f64(self.price)
+            .with_context(|| format!("price {} cannot be represented as a decimal", self.price))?;
+        let amount = Decimal::from_f64(amount)
+            .with_context(|| format!("amount {} cannot be represented as a decimal", amount))?;
+
+        price
+            .checked_mul(amount)
+            .with_context(|| format!("total of {} x {} overflows", amount, price))
+    }
+
+    /// Returns the value of `amount` units of the coin at this quote's price.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use boltzmann::domains::crypto::{Quote, Coin, Currency, ProviderSource};
+    ///
+    /// let quote = Quote::new(Coin::ETH, Currency::USD, 2000.0, ProviderSource::CoinGecko);
+    ///
+    /// assert_eq!(quote.value_of(0.5), 1000.0);
+    /// ```
+    pub fn value_of(&self, amount: f64) -> f64 {
+        self.price * amount
+    }
+
+    /// Compares two quotes by unit price.
+    ///
+    /// Returns `None` when the quotes are for different coins or currencies,
+    /// since their prices are not comparable.
+    pub fn cmp_price(&self, other: &Quote) -> Option<Ordering> {
+        if self.coin != other.coin || self.currency != other.currency {
+            return None;
+        }
+        Some(self.price.total_cmp(&other.price))
+    }
+}
+
+/// Sorts quotes by ascending unit price.
+///
+/// Ties are broken by provider name so the order is deterministic across runs.
+///
+/// # Errors
+///
+/// Returns an error if the quotes are not all for the same coin and currency.
+pub fn sort_by_price(quotes: &mut [Quote]) -> Result<()> {
+    ensure_same_market(quotes)?;
+
+    quotes.sort_by(|a, b| {
+        a.price
+            .total_cmp(&b.price)
+            .then_with(|| a.provider.to_string().cmp(&b.provider.to_string()))
+    });
+    Ok(())
+}
+
+/// Returns the spread (highest minus lowest unit price) across quotes.
+///
+/// # Errors
+///
+/// Returns an error if `quotes` is empty or the quotes are not all for the
+/// same coin and currency.
+pub fn price_spread(quotes: &[Quote]) -> Result<f64> {
+    if quotes.is_empty() {
+        anyhow::bail!("Cannot compute a spread without quotes");
+    }
+    ensure_same_market(quotes)?;
+
+    let prices = quotes.iter().map(|q| q.price);
+    let max = prices.clone().fold(f64::NEG_INFINITY, f64::max);
+    let min = prices.fold(f64::INFINITY, f64::min);
+
+    Ok(max - min)
+}
+
+/// Ensures every quote shares the coin and currency of the first one.
+fn ensure_same_market(quotes: &[Quote]) -> Result<()> {
+    if let Some(first) = quotes.first()
+        && let Some(other) = quotes.iter().find(|q| q.cmp_price(first).is_none())
+    {
+        anyhow::bail!(
+            "Cannot compare quotes for different markets: {}/{} and {}/{}",
+            first.coin, first.currency, other.coin, other.currency
+        );
+    }
+    Ok(())
+}
+
+/// A provider had no price for some requested currencies.
+///
+/// Returned by [`QuoteBatch::into_quotes`] when strict mode rejects a partial
+/// batch, or when nothing could be priced at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrenciesNotPriced {
+    /// Name of the provider, e.g. `CoinGecko`
+    pub provider: String,
+    /// The currencies it did not price, in request order
+    pub currencies: Vec<Currency>,
+}
+
+impl fmt::Display for CurrenciesNotPriced {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let currencies = self.currencies.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", ");
+        write!(f, "Price not found for {} from {}", currencies, self.provider)
+    }
+}
+
+impl std::error::Error for CurrenciesNotPriced {}
+
+/// Quotes parsed from a single provider response.
+///
+/// Providers return prices for every requested currency in one call, but an
+/// upstream may omit some of them. `missing` lists the currencies that could
+/// not be priced so callers can decide whether a partial result is acceptable.
+#[derive(Debug, Clone, Default)]
+pub struct QuoteBatch {
+    /// Quotes for the currencies the provider priced
+    pub quotes: Vec<Quote>,
+    /// Requested currencies absent from the provider response
+    pub missing: Vec<Currency>,
+}
+
+impl QuoteBatch {
+    /// Resolves the batch into quotes according to the currency mode.
+    ///
+    /// In strict mode any missing currency fails the whole batch. In lenient mode
+    /// the priced currencies are returned and the missing ones are logged, failing
+    /// only when nothing could be priced at all.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CurrenciesNotPriced`] naming the provider and the missing currencies.
+    pub fn into_quotes(self, strict: bool, provider: &str) -> Result<Vec<Quote>> {
+        if self.missing.is_empty() {
+            return Ok(self.quotes);
+        }
+
+        if strict || self.quotes.is_empty() {
+            return Err(CurrenciesNotPriced { provider: provider.to_string(), currencies: self.missing }.into());
+        }
+
+        let missing = self.missing
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        tracing::warn!("{} did not price {}, returning partial quotes", provider, missing);
+        Ok(self.quotes)
+    }
+}
+
+/// Requested `currencies` that none of `quotes` is priced in, in request order.
+///
+/// Lenient providers leave unpriced currencies out of their quotes; this tells
+/// a caller of [`PriceProvider::get_quotes`] which ones those were.
+pub fn missing_currencies(currencies: &[Currency], quotes: &[Quote]) -> Vec<Currency> {
+    currencies
+        .iter()
+        .copied()
+        .filter(|&currency| !quotes.iter().any(|quote| quote.currency == currency))
+        .collect()
+}
+
+/// Sums the value of multiple holdings priced in the same currency.
+///
+/// Each entry pairs a quote with the amount held of the quoted coin.
+/// An empty portfolio is worth `0.0`.
+///
+/// # Errors
+///
+/// Returns an error if the quotes are not all denominated in the same currency.
+pub fn total_value(holdings: &[(&Quote, f64)]) -> Result<f64> {
+    let Some((first, _)) = holdings.first() else {
+        return Ok(0.0);
+    };
+
+    let mut total = 0.0;
+    for (quote, amount) in holdings {
+        if quote.currency != first.currency {
+            anyhow::bail!(
+                "Cannot sum holdings in mismatched currencies: {} and {}",
+                first.currency, quote.currency
+            );
+        }
+        total += quote.value_of(*amount);
+    }
+
+    Ok(total)
+}
+
+/// Sums the exact decimal value of several holdings.
+///
+/// Same as [`total_value`], but without `f64` rounding error accumulating over
+/// many holdings.
+///
+/// # Errors
+///
+/// Returns an error if the quotes are not all denominated in the same currency,
+/// or if a price or amount cannot be represented as a decimal.
+pub fn total_value_exact(holdings: &[(&Quote, f64)]) -> Result<Decimal> {
+    let Some((first, _)) = holdings.first() else {
+        return Ok(Decimal::ZERO);
+    };
+
+    let mut total = Decimal::ZERO;
+    for (quote, amount) in holdings {
+        if quote.currency != first.currency {
+            anyhow::bail!(
+                "Cannot sum holdings in mismatched currencies: {} and {}",
+                first.currency, quote.currency
+            );
+        }
+        total = total
+            .checked_add(quote.exact_value_of(*amount)?)
+            .context("portfolio total overflows")?;
+    }
+
+    Ok(total)
+}
+
+/// Fetches quotes for several coins concurrently, returning results in input order.
+///
+/// At most `limit` provider calls are in flight at once (a `limit` of zero is treated
+/// as one). Each coin gets its own result so a failure for one coin does not discard
+/// the others.
+///
+/// # Examples
+///
+/// ```rust
+/// use boltzmann::domains::crypto::{fetch_quotes_for_coins, Coin, Currency};
+/// use boltzmann::domains::crypto::coingecko::CoinGecko;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = CoinGecko::new(None)?;
+/// let results = fetch_quotes_for_coins(&provider, Coin::all(), &[Currency::USD], 4).await;
+///
+/// for (coin, result) in Coin::all().iter().zip(results) {
+///     println!("{}: {:?}", coin, result.map(|quotes| quotes.len()));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn fetch_quotes_for_coins<P>(
+    provider: &P,
+    coins: &[Coin],
+    currencies: &[Currency],
+    limit: usize,
+) -> Vec<std::result::Result<Vec<Quote>, P::Error>>
+where
+    P: PriceProvider + Sync,
+{
+    fetch_ordered(coins.iter().copied(), limit, |coin| provider.get_quotes(coin, currencies)).await
+}
+
+/// Runs `fetch` for every item with bounded concurrency, preserving input order.
+///
+/// Futures complete in whatever order the upstreams answer; each result is tagged
+/// with its input index and placed back into position once all have finished.
+pub async fn fetch_ordered<T, F, Fut, R>(
+    items: impl IntoIterator<Item = T>,
+    limit: usize,
+    fetch: F,
+) -> Vec<R>
+where
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = R>,
+{
+    let mut indexed: Vec<(usize, R)> = stream::iter(items.into_iter().enumerate())
+        .map(|(index, item)| {
+            let fut = fetch(item);
+            async move { (index, fut.await) }
+        })
+        .buffer_unordered(limit.max(1))
+        .collect()
+        .await;
+
+    indexed.sort_by_key(|(index, _)| *index);
+    indexed.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Order-book liquidity around the current price for one market.
+///
+/// Summarizes the top levels of the book a provider returned: the best bid and
+/// ask, and how much of the coin is resting on each side within those levels.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MarketDepth {
+    /// The cryptocurrency being quoted
+    pub coin: Coin,
+    /// The fiat currency the book is denominated in
+    pub currency: Currency,
+    /// The provider that supplied the book
+    pub provider: ProviderSource,
+    /// Highest bid price
+    pub best_bid: f64,
+    /// Lowest ask price
+    pub best_ask: f64,
+    /// `best_ask - best_bid`
+    pub spread: f64,
+    /// Amount of the coin bid across the returned levels
+    pub bid_volume: f64,
+    /// Amount of the coin offered across the returned levels
+    pub ask_volume: f64,
+    /// Number of price levels summarized on each side
+    pub levels: usize,
+    /// When the book was fetched
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Market statistics for a coin, beyond its spot price.
+///
+/// Currency-denominated figures are in `currency`; supply is in units of the coin.
+/// Fields the provider has no value for are `null`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MarketData {
+    /// The cryptocurrency described
+    pub coin: Coin,
+    /// The fiat currency prices and volumes are denominated in
+    pub currency: Currency,
+    /// The provider that supplied the data
+    pub provider: ProviderSource,
+    /// Current price of one coin
+    pub price: f64,
+    /// Total market capitalization
+    pub market_cap: Option<f64>,
+    /// Trading volume over the last 24 hours
+    pub total_volume_24h: Option<f64>,
+    /// Coins currently in circulation
+    pub circulating_supply: Option<f64>,
+    /// Price change over the last 24 hours, in percent
+    pub price_change_percentage_24h: Option<f64>,
+    /// When the provider last updated the data
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// How far apart providers' prices for one market are.
+///
+/// A wide spread means at least one provider is stale or wrong (or that there is
+/// an arbitrage opportunity), so a single reading should not be trusted blindly.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PriceSpread {
+    /// The cryptocurrency being compared
+    pub coin: Coin,
+    /// The fiat currency of the compared prices
+    pub currency: Currency,
+    /// Lowest price across providers
+    pub min: f64,
+    /// Highest price across providers
+    pub max: f64,
+    /// Mean price across providers
+    pub mean: f64,
+    /// `(max - min) / mean`, in percent
+    pub spread_percent: f64,
+    /// Spread above which providers are considered to disagree, in percent
+    pub threshold_percent: f64,
+    /// Whether `spread_percent` is above `threshold_percent`
+    pub exceeds_threshold: bool,
+    /// The quote each provider returned
+    pub quotes: Vec<Quote>,
+}
+
+impl PriceSpread {
+    /// Computes the spread between `quotes`, flagging it when above `threshold_percent`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fewer than two quotes are given, or if they are not
+    /// all for `coin` in `currency`.
+    pub fn from_quotes(coin: Coin, currency: Currency, quotes: Vec<Quote>, threshold_percent: f64) -> Result<Self> {
+        if quotes.len() < 2 {
+            anyhow::bail!(
+                "Price spread needs quotes from at least two providers, got {}",
+                quotes.len()
+            );
         }
+        if let Some(other) = quotes.iter().find(|q| q.coin != coin || q.currency != currency) {
+            anyhow::bail!(
+                "Cannot compare {}/{} with a {}/{} quote",
+                coin, currency, other.coin, other.currency
+            );
+        }
+
+        let min = quotes.iter().map(|q| q.price).fold(f64::INFINITY, f64::min);
+        let max = quotes.iter().map(|q| q.price).fold(f64::NEG_INFINITY, f64::max);
+        let mean = quotes.iter().map(|q| q.price).sum::<f64>() / quotes.len() as f64;
+        let spread_percent = if mean > 0.0 { (max - min) / mean * 100.0 } else { 0.0 };
+
+        Ok(Self {
+            coin,
+            currency,
+            min,
+            max,
+            mean,
+            spread_percent,
+            threshold_percent,
+            exceeds_threshold: spread_percent > threshold_percent,
+            quotes,
+        })
     }
 }
 
+/// A price provider chosen at runtime, e.g. from configuration
+pub type DynPriceProvider = Box<dyn PriceProvider<Error = anyhow::Error> + Send + Sync>;
+
+/// Fetches quotes from the primary providers, consulting fallbacks only if every primary fails.
+///
+/// All primaries are queried concurrently and every quote they return is kept.
+/// Fallbacks (providers whose credits are scarce) are then tried one at a time,
+/// in order, and the first one that returns quotes wins. A provider that errors
+/// or returns no quotes counts as failed.
+pub async fn fetch_with_fallback(
+    primary: &[(ProviderSource, DynPriceProvider)],
+    fallback: &[(ProviderSource, DynPriceProvider)],
+    coin: Coin,
+    currencies: &[Currency],
+) -> Vec<Quote> {
+    let results = futures::future::join_all(
+        primary.iter().map(|(_, provider)| provider.get_quotes(coin, currencies)),
+    )
+    .await;
+
+    let mut quotes = Vec::new();
+    for ((source, _), result) in primary.iter().zip(results) {
+        match result {
+            Ok(provider_quotes) => quotes.extend(provider_quotes),
+            Err(e) => tracing::warn!("{} failed: {:#}", source, e),
+        }
+    }
+    if !quotes.is_empty() {
+        return quotes;
+    }
+
+    for (source, provider) in fallback {
+        tracing::info!("All primary providers failed, falling back to {}", source);
+        match provider.get_quotes(coin, currencies).await {
+            Ok(provider_quotes) if !provider_quotes.is_empty() => return provider_quotes,
+            Ok(_) => tracing::warn!("{} returned no quotes", source),
+            Err(e) => tracing::warn!("{} failed: {:#}", source, e),
+        }
+    }
+
+    Vec::new()
+}
+
 /// Trait for cryptocurrency price providers.
 ///
 /// This trait defines the interface that all price providers must implement.
@@ -236,25 +1051,33 @@ impl Quote {
 /// # Examples
 ///
 /// ```rust
-/// use boltzmann::crypto::{Coin, Currency, PriceProvider};
-/// use boltzmann::crypto::coinmarketcap::CoinMarketCap;
+/// use boltzmann::domains::crypto::{Coin, Currency, PriceProvider};
+/// use boltzmann::domains::crypto::coinmarketcap::CoinMarketCap;
 ///
 /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-/// let provider = CoinMarketCap::new()?;
+/// let provider = CoinMarketCap::new("your_api_key".to_string())?;
 ///
 /// // Fetch ETH price in USD and EUR
 /// let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD, Currency::EUR]).await?;
 ///
 /// for quote in quotes {
-///     println!("{} = {}{:.2}", quote.coin, quote.currency.symbol(), quote.price);
+///     println!("{} = {}", quote.coin, quote.currency.format(quote.price));
 /// }
 /// # Ok(())
 /// # }
 /// ```
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 pub trait PriceProvider {
     /// The error type returned by this provider
-    type Error;
+    type Error: From<NotSupported>;
+
+    /// Short lowercase name identifying the provider in logs and metrics,
+    /// e.g. `coingecko`.
+    ///
+    /// Upstream providers report their [`ProviderSource`] name; wrappers such as
+    /// the cache report the name of the provider they wrap.
+    fn name(&self) -> &'static str;
 
     /// Fetches prices for a single coin in multiple currencies.
     ///
@@ -279,5 +1102,447 @@ pub trait PriceProvider {
         &self,
         coin: Coin,
         currencies: &[Currency],
-    ) -> Result<Vec<Quote>, Self::Error>;
+    ) -> std::result::Result<Vec<Quote>, Self::Error>;
+
+    /// Fetches top-of-book prices and a depth summary for one market.
+    ///
+    /// Only providers with order-book data implement this; the rest return
+    /// [`NotSupported`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Self::Error` if the provider has no order book for the market
+    /// or the request fails.
+    async fn get_market_depth(
+        &self,
+        coin: Coin,
+        currency: Currency,
+    ) -> std::result::Result<MarketDepth, Self::Error> {
+        Err(NotSupported::new(format!("market depth for {}/{}", coin, currency)).into())
+    }
+
+    /// Raw body of the most recent upstream response, if the provider captured it.
+    ///
+    /// Only meant for debugging; providers capture nothing unless asked to.
+    fn raw_response(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+/// Shared providers delegate to the provider they wrap.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl<P> PriceProvider for std::sync::Arc<P>
+where
+    P: PriceProvider + Send + Sync + ?Sized,
+{
+    type Error = P::Error;
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    async fn get_quotes(
+        &self,
+        coin: Coin,
+        currencies: &[Currency],
+    ) -> std::result::Result<Vec<Quote>, Self::Error> {
+        (**self).get_quotes(coin, currencies).await
+    }
+
+    async fn get_market_depth(
+        &self,
+        coin: Coin,
+        currency: Currency,
+    ) -> std::result::Result<MarketDepth, Self::Error> {
+        (**self).get_market_depth(coin, currency).await
+    }
+
+    fn raw_response(&self) -> Option<serde_json::Value> {
+        (**self).raw_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::SCHEMA_VERSION;
+
+    fn quote(coin: Coin, currency: Currency, price: f64) -> Quote {
+        quote_from(ProviderSource::CoinGecko, coin, currency, price)
+    }
+
+    fn quote_from(provider: ProviderSource, coin: Coin, currency: Currency, price: f64) -> Quote {
+        Quote::new(coin, currency, price, provider)
+    }
+
+    #[test]
+    fn test_missing_currencies_keeps_request_order() {
+        let quotes = [quote(Coin::ETH, Currency::EUR, 1850.0)];
+        let requested = [Currency::JPY, Currency::EUR, Currency::USD];
+
+        assert_eq!(missing_currencies(&requested, &quotes), vec![Currency::JPY, Currency::USD]);
+        assert!(missing_currencies(&[Currency::EUR], &quotes).is_empty());
+    }
+
+    #[test]
+    fn test_unit_price_matches_price() {
+        let json = serde_json::to_value(quote(Coin::ETH, Currency::USD, 2000.5).with_amount(3.0)).unwrap();
+        assert_eq!(json["price"], 2000.5);
+        assert_eq!(json["unit_price"], json["price"]);
+        assert_eq!(json["quote_per_amount"]["total_price"], 6001.5);
+    }
+
+    #[test]
+    fn test_quote_carries_schema_version() {
+        let json = serde_json::to_value(quote(Coin::ETH, Currency::USD, 2000.0)).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+
+        let mut legacy = json.clone();
+        legacy.as_object_mut().unwrap().remove("schema_version");
+        let parsed: Quote = serde_json::from_value(legacy).unwrap();
+        assert_eq!(parsed.schema_version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_iso_numeric_codes() {
+        assert_eq!(Currency::USD.iso_numeric(), Some(840));
+        assert_eq!(Currency::EUR.iso_numeric(), Some(978));
+        assert_eq!(Currency::AUD.iso_numeric(), Some(36));
+
+        let json = serde_json::to_value(quote(Coin::ETH, Currency::USD, 2000.0)).unwrap();
+        assert_eq!(json["currency"], "USD");
+        assert_eq!(json["currency_numeric"], 840);
+    }
+
+    #[test]
+    fn test_currency_minor_units() {
+        assert_eq!(Currency::JPY.decimals(), 0);
+        assert_eq!(Currency::USD.decimals(), 2);
+
+        let total = Decimal::from_str("300000.5").unwrap();
+        assert_eq!(Currency::JPY.round(total).to_string(), "300001");
+        assert_eq!(Currency::USD.round(Decimal::from_str("4001.005").unwrap()).to_string(), "4001.01");
+        assert_eq!(Currency::USD.round(Decimal::from_str("4001.004").unwrap()).to_string(), "4001.00");
+
+        assert_eq!(Currency::JPY.format(299_999.6), "¥300000");
+        assert_eq!(Currency::USD.format(4001.0), "$4001.00");
+    }
+
+    #[test]
+    fn test_currency_and_coin_aliases() {
+        for (raw, currency) in [("$", Currency::USD), ("usd", Currency::USD), ("Dollar", Currency::USD), ("€", Currency::EUR), ("yen", Currency::JPY)] {
+            assert_eq!(raw.parse::<Currency>().unwrap(), currency, "{}", raw);
+        }
+        for raw in ["Ether", "WETH", "Ξ", "eth"] {
+            assert_eq!(raw.parse::<Coin>().unwrap(), Coin::ETH, "{}", raw);
+        }
+
+        let currency: Currency = serde_json::from_str("\"dollar\"").unwrap();
+        assert_eq!(currency, Currency::USD);
+        let coin: Coin = serde_json::from_str("\"ether\"").unwrap();
+        assert_eq!(coin, Coin::ETH);
+    }
+
+    #[test]
+    fn test_ambiguous_currency_alias_is_rejected() {
+        let err = "¥".parse::<Currency>().unwrap_err().to_string();
+        assert!(err.contains("ambiguous"), "{}", err);
+        assert!(err.contains("CNY") && err.contains("JPY"), "{}", err);
+    }
+
+    #[test]
+    fn test_native_coin_per_chain() {
+        assert_eq!(Coin::native_for_chain(ChainId::ETHEREUM), Some(Coin::ETH));
+        assert_eq!(Coin::native_for_chain(ChainId::BASE), Some(Coin::ETH));
+        assert_eq!(Coin::native_for_chain(ChainId::POLYGON), Some(Coin::MATIC));
+        assert_eq!(Coin::native_for_chain(ChainId::AVALANCHE), Some(Coin::AVAX));
+        assert_eq!(Coin::native_for_chain(ChainId::new(999_999).unwrap()), None);
+
+        // Every known chain has a gas token
+        assert!(ChainId::all().into_iter().all(|chain| Coin::native_for_chain(chain).is_some()));
+    }
+
+    #[test]
+    fn test_price_spread_from_three_providers() {
+        let quotes = vec![
+            quote_from(ProviderSource::CoinGecko, Coin::ETH, Currency::USD, 2000.0),
+            quote_from(ProviderSource::CoinMarketCap, Coin::ETH, Currency::USD, 2010.0),
+            quote_from(ProviderSource::Binance, Coin::ETH, Currency::USD, 1990.0),
+        ];
+
+        let spread = PriceSpread::from_quotes(Coin::ETH, Currency::USD, quotes.clone(), 1.5).unwrap();
+        assert_eq!(spread.min, 1990.0);
+        assert_eq!(spread.max, 2010.0);
+        assert_eq!(spread.mean, 2000.0);
+        assert!((spread.spread_percent - 1.0).abs() < 1e-9);
+        assert!(!spread.exceeds_threshold);
+        assert_eq!(spread.quotes.len(), 3);
+
+        let spread = PriceSpread::from_quotes(Coin::ETH, Currency::USD, quotes, 0.5).unwrap();
+        assert!(spread.exceeds_threshold);
+    }
+
+    #[test]
+    fn test_price_spread_needs_two_matching_quotes() {
+        let usd = quote(Coin::ETH, Currency::USD, 2000.0);
+        let eur = quote(Coin::ETH, Currency::EUR, 1850.0);
+
+        assert!(PriceSpread::from_quotes(Coin::ETH, Currency::USD, vec![usd.clone()], 1.0).is_err());
+        assert!(PriceSpread::from_quotes(Coin::ETH, Currency::USD, vec![usd, eur], 1.0).is_err());
+    }
+
+    #[test]
+    fn test_value_of() {
+        let eth = quote(Coin::ETH, Currency::USD, 2000.0);
+        assert_eq!(eth.value_of(2.5), 5000.0);
+        assert_eq!(eth.with_amount(2.5).quote_per_amount.total_price, 5000.0);
+    }
+
+    #[test]
+    fn test_total_value_two_assets() {
+        let eth_usd = quote(Coin::ETH, Currency::USD, 2000.0);
+        let other_usd = quote(Coin::ETH, Currency::USD, 1500.0);
+
+        let total = total_value(&[(&eth_usd, 2.0), (&other_usd, 0.5)]).unwrap();
+        assert_eq!(total, 4750.0);
+    }
+
+    #[test]
+    fn test_total_value_currency_mismatch() {
+        let eth_usd = quote(Coin::ETH, Currency::USD, 2000.0);
+        let eth_eur = quote(Coin::ETH, Currency::EUR, 1800.0);
+
+        let err = total_value(&[(&eth_usd, 1.0), (&eth_eur, 1.0)]).unwrap_err();
+        assert!(err.to_string().contains("mismatched currencies"));
+    }
+
+    #[test]
+    fn test_total_value_empty() {
+        assert_eq!(total_value(&[]).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_sort_by_price() {
+        let mut quotes = vec![
+            quote_from(ProviderSource::CoinMarketCap, Coin::ETH, Currency::USD, 2010.0),
+            quote_from(ProviderSource::CoinGecko, Coin::ETH, Currency::USD, 1995.0),
+            quote_from(ProviderSource::CoinMarketCap, Coin::ETH, Currency::USD, 2000.0),
+            quote_from(ProviderSource::CoinGecko, Coin::ETH, Currency::USD, 2000.0),
+        ];
+
+        sort_by_price(&mut quotes).unwrap();
+
+        let ordered: Vec<(f64, String)> = quotes.iter().map(|q| (q.price, q.provider.to_string())).collect();
+        assert_eq!(ordered, vec![
+            (1995.0, "coingecko".to_string()),
+            (2000.0, "coingecko".to_string()),
+            (2000.0, "coinmarketcap".to_string()),
+            (2010.0, "coinmarketcap".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_price_spread() {
+        let quotes = vec![
+            quote(Coin::ETH, Currency::USD, 2000.0),
+            quote(Coin::ETH, Currency::USD, 2012.5),
+            quote(Coin::ETH, Currency::USD, 1990.0),
+        ];
+        assert_eq!(price_spread(&quotes).unwrap(), 22.5);
+        assert_eq!(price_spread(&quotes[..1]).unwrap(), 0.0);
+        assert!(price_spread(&[]).is_err());
+    }
+
+    #[test]
+    fn test_exact_total_avoids_f64_rounding() {
+        let quote = quote(Coin::ETH, Currency::USD, 0.1);
+
+        // f64 multiplication drifts
+        assert_eq!(quote.with_amount(3.0).quote_per_amount.total_price, 0.30000000000000004);
+
+        let exact = quote.with_exact_amount(3.0).unwrap();
+        assert_eq!(exact.quote_per_amount.exact_total_price, Some(Decimal::new(3, 1)));
+        assert_eq!(exact.quote_per_amount.total_price, 0.3);
+    }
+
+    #[test]
+    fn test_exact_portfolio_total() {
+        let quote = quote(Coin::ETH, Currency::USD, 0.1);
+        let holdings = vec![(&quote, 1.0); 10];
+
+        assert_ne!(total_value(&holdings).unwrap(), 1.0);
+        assert_eq!(total_value_exact(&holdings).unwrap(), Decimal::ONE);
+
+        let eur = quote_from(ProviderSource::CoinGecko, Coin::ETH, Currency::EUR, 0.1);
+        assert!(total_value_exact(&[(&quote, 1.0), (&eur, 1.0)]).is_err());
+    }
+
+    #[test]
+    fn test_exact_total_serialization() {
+        let quote = quote(Coin::ETH, Currency::USD, 2000.05);
+
+        let json = serde_json::to_value(quote.with_amount(3.0)).unwrap();
+        assert!(json["quote_per_amount"].get("exact_total_price").is_none());
+
+        let json = serde_json::to_value(quote.with_exact_amount(3.0).unwrap()).unwrap();
+        assert_eq!(json["quote_per_amount"]["exact_total_price"], "6000.15");
+        assert!(quote.with_exact_amount(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_mismatched_markets_are_not_compared() {
+        let usd = quote(Coin::ETH, Currency::USD, 2000.0);
+        let eur = quote(Coin::ETH, Currency::EUR, 1800.0);
+
+        assert_eq!(usd.cmp_price(&eur), None);
+        assert!(sort_by_price(&mut [usd.clone(), eur.clone()]).is_err());
+        assert!(price_spread(&[usd, eur]).is_err());
+    }
+
+    /// Provider returning a fixed price (or failing when `price` is `None`) and counting calls
+    struct CountingProvider {
+        source: ProviderSource,
+        price: Option<f64>,
+        calls: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl PriceProvider for CountingProvider {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        async fn get_quotes(&self, coin: Coin, currencies: &[Currency]) -> Result<Vec<Quote>> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let price = self.price.ok_or_else(|| anyhow::anyhow!("{} is down", self.source))?;
+            Ok(currencies.iter().map(|c| quote_from(self.source, coin, *c, price)).collect())
+        }
+    }
+
+    fn counting(
+        source: ProviderSource,
+        price: Option<f64>,
+    ) -> ((ProviderSource, DynPriceProvider), std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let provider = CountingProvider { source, price, calls: calls.clone() };
+        ((source, Box::new(provider)), calls)
+    }
+
+    #[tokio::test]
+    async fn test_fallback_not_called_when_a_primary_succeeds() {
+        use std::sync::atomic::Ordering;
+
+        let (coingecko, _) = counting(ProviderSource::CoinGecko, Some(2000.0));
+        let (binance, _) = counting(ProviderSource::Binance, None);
+        let (cmc, cmc_calls) = counting(ProviderSource::CoinMarketCap, Some(2001.0));
+
+        let quotes = fetch_with_fallback(&[coingecko, binance], &[cmc], Coin::ETH, &[Currency::USD]).await;
+
+        assert_eq!(quotes.len(), 1);
+        assert_eq!(quotes[0].provider, ProviderSource::CoinGecko);
+        assert_eq!(cmc_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_used_when_all_primaries_fail() {
+        use std::sync::atomic::Ordering;
+
+        let (coingecko, coingecko_calls) = counting(ProviderSource::CoinGecko, None);
+        let (cmc, cmc_calls) = counting(ProviderSource::CoinMarketCap, Some(2001.0));
+
+        let quotes = fetch_with_fallback(&[coingecko], &[cmc], Coin::ETH, &[Currency::USD]).await;
+
+        assert_eq!(quotes.len(), 1);
+        assert_eq!(quotes[0].provider, ProviderSource::CoinMarketCap);
+        assert_eq!(coingecko_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cmc_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_provider_source_from_str() {
+        assert_eq!("coinmarketcap".parse::<ProviderSource>().unwrap(), ProviderSource::CoinMarketCap);
+        assert_eq!("binance".parse::<ProviderSource>().unwrap(), ProviderSource::Binance);
+        assert_eq!("CoinGecko".parse::<ProviderSource>().unwrap(), ProviderSource::CoinGecko);
+        assert!("kraken".parse::<ProviderSource>().is_err());
+    }
+
+    #[test]
+    fn test_provider_source_deserialization_is_case_insensitive() {
+        for raw in ["\"coingecko\"", "\"CoinGecko\"", "\"COINGECKO\""] {
+            let source: ProviderSource = serde_json::from_str(raw).unwrap();
+            assert_eq!(source, ProviderSource::CoinGecko);
+        }
+
+        let err = serde_json::from_str::<ProviderSource>("\"kraken\"").unwrap_err().to_string();
+        assert!(err.contains("kraken"));
+        assert!(ProviderSource::NAMES.iter().all(|name| err.contains(name)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fetch_ordered_preserves_input_order() {
+        // Later items resolve first: delays shrink along the input.
+        let delays = [50u64, 40, 30, 20, 10];
+        let completed = std::sync::Mutex::new(Vec::new());
+
+        let results = fetch_ordered(delays, 5, |delay| {
+            let completed = &completed;
+            async move {
+                tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+                completed.lock().unwrap().push(delay);
+                delay
+            }
+        })
+        .await;
+
+        assert_eq!(*completed.lock().unwrap(), vec![10, 20, 30, 40, 50]);
+        assert_eq!(results, vec![50, 40, 30, 20, 10]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fetch_ordered_respects_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let results = fetch_ordered(0..10, 3, |i| {
+            let (in_flight, peak) = (&in_flight, &peak);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(10 - i)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                i
+            }
+        })
+        .await;
+
+        assert_eq!(results, (0..10).collect::<Vec<_>>());
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_providers_report_their_names() {
+        use super::binance::Binance;
+        use super::cache::{CachedPriceProvider, MemoryQuoteCache};
+        use super::coingecko::CoinGecko;
+        use super::coinmarketcap::CoinMarketCap;
+        use super::feed::LatestPriceCache;
+        use super::oneinch::OneInch;
+
+        assert_eq!(CoinGecko::new(None).unwrap().name(), "coingecko");
+        assert_eq!(CoinMarketCap::new("key".to_string()).unwrap().name(), "coinmarketcap");
+        assert_eq!(Binance::new().unwrap().name(), "binance");
+        let oneinch = OneInch::new("http://localhost:8545".to_string(), "0x0AdDd25a91563696D8567Df78D5A01C9a991F9B8");
+        assert_eq!(oneinch.unwrap().name(), "1inch");
+        assert_eq!(LatestPriceCache::default().name(), "stream");
+
+        // Wrappers report the provider they wrap
+        let cache = std::sync::Arc::new(MemoryQuoteCache::new(std::time::Duration::from_secs(60)));
+        let cached = CachedPriceProvider::new(ProviderSource::Binance, Box::new(Binance::new().unwrap()), cache);
+        assert_eq!(cached.name(), "binance");
+    }
 }
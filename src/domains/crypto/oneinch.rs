@@ -0,0 +1,252 @@
+//! 1inch Spot Price Aggregator provider.
+//!
+//! Reads prices on-chain from 1inch's Spot Price Aggregator (the `OffchainOracle`
+//! contract) on Ethereum mainnet, through the node at `ETHEREUM_RPC_URL`. The
+//! aggregator weighs the rates of many DEX pools by their liquidity, so prices
+//! reflect what can actually be traded on-chain.
+//!
+//! There is no fiat on-chain: a coin is priced against a stablecoin, USDC for
+//! USD and EURC for EUR, and the stablecoin is taken to hold its peg. Other
+//! currencies are not served.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use boltzmann::domains::crypto::{Coin, Currency, PriceProvider};
+//! use boltzmann::domains::crypto::oneinch::OneInch;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = OneInch::new(
+//!     "https://eth.llamarpc.com".to_string(),
+//!     "0x0AdDd25a91563696D8567Df78D5A01C9a991F9B8",
+//! )?;
+//! let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await?;
+//!
+//! println!("ETH price on-chain: ${:.2}", quotes[0].price);
+//! # Ok(())
+//! # }
+//! ```
+
+use alloy_primitives::{address, Address, U256};
+use alloy_provider::ProviderBuilder;
+use alloy_sol_types::sol;
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::Client;
+use tracing::debug;
+
+use super::{Coin, Currency, PriceProvider, ProviderSource, Quote};
+use crate::core::errors::{ErrorContext, Result};
+use crate::infrastructure::http::{build_client, HttpClientConfig};
+
+sol! {
+    /// The Spot Price Aggregator's rate interface
+    #[sol(rpc)]
+    interface IOffchainOracle {
+        function getRate(address srcToken, address dstToken, bool useWrappers) external view returns (uint256 weightedRate);
+    }
+}
+
+/// Decimals of the aggregator's fixed-point rates
+const RATE_DECIMALS: u8 = 18;
+
+/// An ERC-20 token on Ethereum mainnet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Token {
+    address: Address,
+    decimals: u8,
+}
+
+/// The token a coin is priced as. ETH is priced as WETH, which trades 1:1.
+fn coin_token(coin: Coin) -> Option<Token> {
+    match coin {
+        Coin::ETH => Some(Token { address: address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"), decimals: 18 }),
+        Coin::MATIC => Some(Token { address: address!("7D1AfA7B718fb893dB30A3aBc0Cfc608AaCfeBB0"), decimals: 18 }),
+        Coin::AVAX => None,
+    }
+}
+
+/// The stablecoin standing in for a fiat currency
+fn currency_token(currency: Currency) -> Option<Token> {
+    match currency {
+        // USDC
+        Currency::USD => Some(Token { address: address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"), decimals: 6 }),
+        // EURC
+        Currency::EUR => Some(Token { address: address!("1aBaEA1f7C830bD89Acc67eC4af516284b1bC33c"), decimals: 6 }),
+        _ => None,
+    }
+}
+
+/// Converts a `getRate` result into the price of one whole `src` token in `dst` tokens.
+///
+/// The rate is the amount of `dst` per unit of `src`, both in their smallest
+/// units, as an 18-decimal fixed-point number. A whole `src` token is therefore
+/// worth `rate * 10^src_decimals / 10^dst_decimals / 10^18` whole `dst` tokens.
+fn rate_to_price(rate: U256, src_decimals: u8, dst_decimals: u8) -> f64 {
+    let exponent = i32::from(src_decimals) - i32::from(dst_decimals) - i32::from(RATE_DECIMALS);
+    // U256 has no lossless f64 conversion; its decimal form parses exactly enough
+    let rate: f64 = rate.to_string().parse().unwrap_or(f64::NAN);
+    rate * 10f64.powi(exponent)
+}
+
+/// 1inch Spot Price Aggregator provider.
+///
+/// No API key is required, only an Ethereum mainnet RPC endpoint and the
+/// aggregator's address.
+pub struct OneInch {
+    rpc_url: String,
+    oracle: Address,
+    client: Client,
+}
+
+impl OneInch {
+    /// Creates a provider reading the aggregator at `oracle_address` through `rpc_url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC URL is empty, the address is malformed, or the
+    /// HTTP client cannot be created.
+    pub fn new(rpc_url: String, oracle_address: &str) -> Result<Self> {
+        let client = build_client(&HttpClientConfig::default())
+            .crypto_context("creating HTTP client for 1inch")?;
+
+        Self::new_with_client(rpc_url, oracle_address, client)
+    }
+
+    /// Creates a provider that sends RPC requests through an existing HTTP client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC URL is empty or the address is malformed.
+    pub fn new_with_client(rpc_url: String, oracle_address: &str, client: Client) -> Result<Self> {
+        if rpc_url.is_empty() {
+            anyhow::bail!("Ethereum RPC URL cannot be empty");
+        }
+        let oracle = oracle_address
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid 1inch oracle address: {}", oracle_address))?;
+
+        Ok(Self { rpc_url, oracle, client })
+    }
+}
+
+#[async_trait]
+impl PriceProvider for OneInch {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        ProviderSource::OneInch.name()
+    }
+
+    /// Reads the aggregated on-chain rate for each currency with a stablecoin.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the coin has no mainnet token, none of the currencies
+    /// has a stablecoin, or the RPC call fails.
+    #[tracing::instrument(skip(self), fields(provider = "1inch"))]
+    async fn get_quotes(
+        &self,
+        coin: Coin,
+        currencies: &[Currency],
+    ) -> std::result::Result<Vec<Quote>, Self::Error> {
+        let src = coin_token(coin).with_context(|| format!("1inch has no Ethereum token for {}", coin))?;
+        let url = self.rpc_url.parse()
+            .with_context(|| format!("Invalid RPC URL: {}", self.rpc_url))?;
+        let provider = ProviderBuilder::new().connect_reqwest(self.client.clone(), url);
+        let oracle = IOffchainOracle::new(self.oracle, provider);
+
+        let mut quotes = Vec::new();
+        for &currency in currencies {
+            let Some(dst) = currency_token(currency) else {
+                debug!("1inch has no stablecoin for {}", currency);
+                continue;
+            };
+
+            let rate = oracle
+                .getRate(src.address, dst.address, true)
+                .call()
+                .await
+                .crypto_context("calling getRate on the 1inch Spot Price Aggregator")?;
+            if rate.is_zero() {
+                anyhow::bail!("1inch Spot Price Aggregator has no liquidity for {}/{}", coin, currency);
+            }
+            let price = rate_to_price(rate, src.decimals, dst.decimals);
+
+            quotes.push(Quote::new(coin, currency, price, ProviderSource::OneInch));
+        }
+
+        if quotes.is_empty() {
+            anyhow::bail!("1inch has no stablecoin for any of {:?}", currencies);
+        }
+        Ok(quotes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Json, Router};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_rate_to_price_with_token_decimals() {
+        // 1 WETH (18 decimals) = 2500.5 USDC (6 decimals): 2500.5e6 units per 1e18 wei, times 1e18
+        let rate = U256::from(2_500_500_000u64);
+        assert!((rate_to_price(rate, 18, 6) - 2500.5).abs() < 1e-9);
+
+        // Same decimals on both sides: the rate is the price, scaled by 1e18
+        let rate = U256::from(1_250_000_000_000_000_000u64);
+        assert!((rate_to_price(rate, 18, 18) - 1.25).abs() < 1e-12);
+
+        // A 6-decimal token priced in an 18-decimal one
+        let rate = U256::from(10u64).pow(U256::from(30u64));
+        assert!((rate_to_price(rate, 6, 18) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_token_tables() {
+        assert_eq!(coin_token(Coin::ETH).unwrap().decimals, 18);
+        assert!(coin_token(Coin::AVAX).is_none());
+        assert_eq!(currency_token(Currency::USD).unwrap().decimals, 6);
+        assert!(currency_token(Currency::JPY).is_none());
+    }
+
+    #[test]
+    fn test_construction() {
+        let oracle = "0x0AdDd25a91563696D8567Df78D5A01C9a991F9B8";
+        assert!(OneInch::new("http://localhost:8545".to_string(), oracle).is_ok());
+        assert!(OneInch::new(String::new(), oracle).is_err());
+        assert!(OneInch::new("http://localhost:8545".to_string(), "0x1234").is_err());
+    }
+
+    /// JSON-RPC node answering every `eth_call` with a rate of 2500.5 USDC per WETH
+    async fn mock_node() -> String {
+        let app = Router::new().route(
+            "/",
+            post(|Json(request): Json<Value>| async move {
+                assert_eq!(request["method"], "eth_call");
+                Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": "0x00000000000000000000000000000000000000000000000000000000950a9a20" }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_quotes_from_onchain_rate() {
+        let provider = OneInch::new(mock_node().await, "0x0AdDd25a91563696D8567Df78D5A01C9a991F9B8").unwrap();
+
+        // JPY has no stablecoin and is left out
+        let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD, Currency::JPY]).await.unwrap();
+        assert_eq!(quotes.len(), 1);
+        assert_eq!(quotes[0].currency, Currency::USD);
+        assert_eq!(quotes[0].provider, ProviderSource::OneInch);
+        assert!((quotes[0].price - 2500.5).abs() < 1e-9);
+
+        assert!(provider.get_quotes(Coin::AVAX, &[Currency::USD]).await.is_err());
+    }
+}
@@ -0,0 +1,229 @@
+//! Per-provider request quotas.
+//!
+//! Some upstreams allow only a few requests per minute (CoinGecko's free tier
+//! about 30). Rather than finding out through `429`s, a provider can be given a
+//! quota with `<PROVIDER>_RPM`. [`RateLimitedPriceProvider`] waits for the quota
+//! before calling through, and fails without calling the upstream when the wait
+//! would be longer than the limiter's cap.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::num::NonZeroU32;
+//! use std::sync::Arc;
+//! use boltzmann::domains::crypto::{Coin, Currency, PriceProvider, ProviderSource};
+//! use boltzmann::domains::crypto::coingecko::CoinGecko;
+//! use boltzmann::domains::crypto::rate_limit::{ProviderRateLimiter, RateLimitedPriceProvider};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let limiter = Arc::new(ProviderRateLimiter::per_minute(ProviderSource::CoinGecko, NonZeroU32::new(30).unwrap()));
+//! let provider = RateLimitedPriceProvider::new(Box::new(CoinGecko::new(None)?), limiter);
+//!
+//! let quotes = provider.get_quotes(Coin::ETH, &[Currency::USD]).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use governor::clock::Clock;
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use tokio::time::Instant;
+use tracing::debug;
+
+use super::{Coin, Currency, DynPriceProvider, MarketDepth, PriceProvider, ProviderSource, Quote};
+use crate::core::errors::Result;
+
+/// Longest a request waits for its provider's quota by default
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(5);
+
+/// A provider's quota would only allow the request after waiting longer than the cap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimited {
+    /// Provider whose quota is used up
+    pub provider: ProviderSource,
+    /// How long the request would still have had to wait
+    pub wait: Duration,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} request quota used up, next request allowed in {:?}", self.provider, self.wait)
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Request quota for one provider, shared by every request through `AppState`.
+pub struct ProviderRateLimiter {
+    provider: ProviderSource,
+    limiter: DefaultDirectRateLimiter,
+    max_wait: Duration,
+}
+
+impl fmt::Debug for ProviderRateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProviderRateLimiter")
+            .field("provider", &self.provider)
+            .field("max_wait", &self.max_wait)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProviderRateLimiter {
+    /// Allows `requests` per minute to `provider`, all of which may be used in a burst.
+    pub fn per_minute(provider: ProviderSource, requests: NonZeroU32) -> Self {
+        Self {
+            provider,
+            limiter: RateLimiter::direct(Quota::per_minute(requests)),
+            max_wait: DEFAULT_MAX_WAIT,
+        }
+    }
+
+    /// Sets how long a request may wait for the quota before failing.
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Waits until the quota allows one more request.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RateLimited`] straight away if the wait would exceed the cap.
+    pub async fn acquire(&self) -> Result<()> {
+        let started = Instant::now();
+        loop {
+            let Err(not_until) = self.limiter.check() else {
+                return Ok(());
+            };
+            let wait = not_until.wait_time_from(self.limiter.clock().now());
+            if started.elapsed() + wait > self.max_wait {
+                return Err(RateLimited { provider: self.provider, wait }.into());
+            }
+            debug!("Waiting {:?} for the {} request quota", wait, self.provider);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Calls the wrapped provider only as often as its [`ProviderRateLimiter`] allows.
+pub struct RateLimitedPriceProvider {
+    provider: DynPriceProvider,
+    limiter: Arc<ProviderRateLimiter>,
+}
+
+impl RateLimitedPriceProvider {
+    /// Wraps `provider`, spending one request from `limiter` per upstream call.
+    pub fn new(provider: DynPriceProvider, limiter: Arc<ProviderRateLimiter>) -> Self {
+        Self { provider, limiter }
+    }
+}
+
+#[async_trait]
+impl PriceProvider for RateLimitedPriceProvider {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Waits for the quota, then fetches quotes from the wrapped provider.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RateLimited`] if the quota does not allow a request in time,
+    /// or the provider's error.
+    async fn get_quotes(
+        &self,
+        coin: Coin,
+        currencies: &[Currency],
+    ) -> std::result::Result<Vec<Quote>, Self::Error> {
+        self.limiter.acquire().await?;
+        self.provider.get_quotes(coin, currencies).await
+    }
+
+    async fn get_market_depth(
+        &self,
+        coin: Coin,
+        currency: Currency,
+    ) -> std::result::Result<MarketDepth, Self::Error> {
+        self.limiter.acquire().await?;
+        self.provider.get_market_depth(coin, currency).await
+    }
+
+    fn raw_response(&self) -> Option<serde_json::Value> {
+        self.provider.raw_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Provider that counts calls and answers immediately
+    struct CountingProvider(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl PriceProvider for CountingProvider {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        async fn get_quotes(&self, coin: Coin, currencies: &[Currency]) -> anyhow::Result<Vec<Quote>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(currencies
+                .iter()
+                .map(|&currency| Quote::new(coin, currency, 2000.0, ProviderSource::CoinGecko))
+                .collect())
+        }
+    }
+
+    fn limited(rpm: u32, max_wait: Duration) -> (RateLimitedPriceProvider, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let limiter = ProviderRateLimiter::per_minute(ProviderSource::CoinGecko, NonZeroU32::new(rpm).unwrap())
+            .with_max_wait(max_wait);
+        let provider = RateLimitedPriceProvider::new(Box::new(CountingProvider(calls.clone())), Arc::new(limiter));
+        (provider, calls)
+    }
+
+    #[tokio::test]
+    async fn test_calls_beyond_the_burst_are_paced() {
+        // 600 per minute: one request every 100ms once the burst is spent
+        let (provider, calls) = limited(600, Duration::from_secs(5));
+        for _ in 0..600 {
+            provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+        }
+
+        let started = std::time::Instant::now();
+        for _ in 0..3 {
+            provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+        }
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(250), "three paced calls took {:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(1), "three paced calls took {:?}", elapsed);
+        assert_eq!(calls.load(Ordering::SeqCst), 603);
+    }
+
+    #[tokio::test]
+    async fn test_wait_beyond_the_cap_fails_without_calling_upstream() {
+        // One request per minute: the second would wait far longer than the cap
+        let (provider, calls) = limited(1, Duration::from_millis(50));
+        provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap();
+
+        let started = std::time::Instant::now();
+        let err = provider.get_quotes(Coin::ETH, &[Currency::USD]).await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_millis(50));
+        let limited = err.downcast_ref::<RateLimited>().unwrap();
+        assert_eq!(limited.provider, ProviderSource::CoinGecko);
+        assert!(limited.wait > Duration::from_secs(50));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
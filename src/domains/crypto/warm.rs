@@ -0,0 +1,173 @@
+//! Quote cache warming.
+//!
+//! A [`CacheWarmer`] keeps the quote cache filled for a fixed set of pairs by
+//! fetching them on a timer, so requests for those pairs are served from the
+//! cache instead of waiting on the upstream.
+//!
+//! Refreshing every pair on the same tick would send the upstream a burst of
+//! requests once per interval. Instead each pair starts at its own offset
+//! within the interval (see [`refresh_offset`]) and keeps that phase, so the
+//! requests are spread over the whole interval.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::Duration;
+
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn};
+
+use super::cache::DynQuoteCache;
+use super::{Coin, Currency, DynPriceProvider, ProviderSource};
+
+/// Refreshes the cached quotes of some pairs from one provider on a timer.
+pub struct CacheWarmer {
+    source: ProviderSource,
+    provider: DynPriceProvider,
+    cache: DynQuoteCache,
+    pairs: Vec<(Coin, Currency)>,
+    interval: Duration,
+}
+
+impl CacheWarmer {
+    /// Creates a warmer storing `provider`'s quotes in `cache` every `interval`.
+    ///
+    /// Warms nothing until pairs are added with [`with_pairs`](Self::with_pairs).
+    pub fn new(source: ProviderSource, provider: DynPriceProvider, cache: DynQuoteCache, interval: Duration) -> Self {
+        Self { source, provider, cache, pairs: Vec::new(), interval }
+    }
+
+    /// Sets the coin/currency pairs to keep warm.
+    pub fn with_pairs(mut self, pairs: impl IntoIterator<Item = (Coin, Currency)>) -> Self {
+        self.pairs = pairs.into_iter().collect();
+        self
+    }
+
+    /// Refreshes every pair once per interval, each at its own offset, forever.
+    pub async fn run(self) {
+        let count = self.pairs.len();
+        let refreshes = self.pairs.iter().enumerate().map(|(index, &pair)| {
+            let offset = refresh_offset(pair, index, count, self.interval);
+            let warmer = &self;
+            async move {
+                tokio::time::sleep(offset).await;
+                let mut ticks = tokio::time::interval(warmer.interval);
+                ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                loop {
+                    ticks.tick().await;
+                    warmer.refresh(pair).await;
+                }
+            }
+        });
+        futures::future::join_all(refreshes).await;
+    }
+
+    /// Fetches one pair and stores its quotes; failures are logged and retried next interval.
+    async fn refresh(&self, (coin, currency): (Coin, Currency)) {
+        let quotes = match self.provider.get_quotes(coin, &[currency]).await {
+            Ok(quotes) => quotes,
+            Err(e) => {
+                warn!("Warming {}/{} from {} failed: {}", coin, currency, self.source, e);
+                return;
+            }
+        };
+        for quote in &quotes {
+            if let Err(e) = self.cache.put(quote).await {
+                warn!("Failed to store warmed {}/{} quote: {}", coin, currency, e);
+            }
+        }
+        debug!("Warmed {}/{} from {}", coin, currency, self.source);
+    }
+}
+
+/// Delay before the first refresh of `pair`, the `index`th of `count` pairs.
+///
+/// The interval is cut into `count` equal slots and each pair refreshes at a
+/// point in its own slot picked by hashing the pair. The offset is always
+/// shorter than `interval`, and no two pairs share a slot, so refreshes never
+/// bunch up however the hashes fall.
+pub fn refresh_offset(pair: (Coin, Currency), index: usize, count: usize, interval: Duration) -> Duration {
+    let mut hasher = DefaultHasher::new();
+    pair.hash(&mut hasher);
+    // Top 53 bits as a fraction in [0, 1), exact in an f64
+    let within_slot = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+    interval.mul_f64((index as f64 + within_slot) / count.max(1) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use tokio::time::Instant;
+
+    use crate::domains::crypto::cache::{MemoryQuoteCache, QuoteCache};
+    use crate::domains::crypto::{PriceProvider, Quote};
+
+    /// Provider recording when each currency was fetched
+    struct RecordingProvider {
+        started: Instant,
+        fetches: Arc<Mutex<Vec<(Currency, Duration)>>>,
+    }
+
+    #[async_trait]
+    impl PriceProvider for RecordingProvider {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        async fn get_quotes(&self, coin: Coin, currencies: &[Currency]) -> anyhow::Result<Vec<Quote>> {
+            let currency = currencies[0];
+            self.fetches.lock().unwrap().push((currency, self.started.elapsed()));
+            Ok(vec![Quote::new(coin, currency, 2000.0, ProviderSource::CoinGecko)])
+        }
+    }
+
+    #[test]
+    fn test_offsets_are_bounded_by_the_interval() {
+        let interval = Duration::from_secs(10);
+        let pairs: Vec<_> = Currency::all().iter().map(|&currency| (Coin::ETH, currency)).collect();
+        for (index, &pair) in pairs.iter().enumerate() {
+            let offset = refresh_offset(pair, index, pairs.len(), interval);
+            assert!(offset < interval, "{:?} starts at {:?}", pair, offset);
+        }
+        assert!(refresh_offset((Coin::ETH, Currency::USD), 0, 0, interval) < interval);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_refreshes_are_spread_across_the_interval() {
+        let interval = Duration::from_secs(60);
+        let fetches = Arc::new(Mutex::new(Vec::new()));
+        let provider = RecordingProvider { started: Instant::now(), fetches: fetches.clone() };
+        let cache = Arc::new(MemoryQuoteCache::new(interval));
+        let currencies = Currency::all();
+        let warmer = CacheWarmer::new(ProviderSource::CoinGecko, Box::new(provider), cache.clone(), interval)
+            .with_pairs(currencies.iter().map(|&currency| (Coin::ETH, currency)));
+        let task = tokio::spawn(warmer.run());
+
+        tokio::time::sleep(interval).await;
+        let mut times: Vec<Duration> = fetches.lock().unwrap().iter().map(|(_, at)| *at).collect();
+        times.sort();
+
+        // Every pair refreshed once within the first interval...
+        assert_eq!(times.len(), currencies.len());
+        assert!(times.iter().all(|at| *at < interval));
+        // ...spread evenly rather than bunched on one tick: no gap between
+        // consecutive refreshes, or at either end, spans two slots
+        let slot = interval / currencies.len() as u32;
+        assert!(times[0] < slot * 2 && interval - times[times.len() - 1] < slot * 2, "{:?}", times);
+        assert!(times.windows(2).all(|pair| pair[1] - pair[0] < slot * 2), "{:?}", times);
+        assert!(cache.get((Coin::ETH, Currency::EUR, ProviderSource::CoinGecko)).await.unwrap().is_some());
+
+        // Each pair keeps its phase on later intervals
+        tokio::time::sleep(interval).await;
+        let fetches = fetches.lock().unwrap();
+        assert_eq!(fetches.len(), currencies.len() * 2);
+        for &(currency, at) in &fetches[currencies.len()..] {
+            let (_, first) = fetches.iter().find(|(c, _)| *c == currency).unwrap();
+            assert_eq!(at - *first, interval);
+        }
+        task.abort();
+    }
+}
@@ -0,0 +1,207 @@
+//! EVM chain identifiers.
+//!
+//! Chain ids travel through query parameters, provider URLs and RPC selection,
+//! so they are wrapped in a validated [`ChainId`] instead of a bare `u64`.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::core::errors::Result;
+
+/// A validated EVM chain id (EIP-155).
+///
+/// Zero is never a valid chain id, and ids above the EIP-2294 bound are rejected.
+/// Chains this service knows how to query are available as constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(transparent)]
+pub struct ChainId(u64);
+
+/// Static details for a chain the service supports
+struct KnownChain {
+    id: ChainId,
+    name: &'static str,
+    rpc_env_var: &'static str,
+    blockscout_env_var: &'static str,
+    gas_floors_env_var: &'static str,
+    block_time: Duration,
+}
+
+impl ChainId {
+    /// Largest chain id allowed by EIP-2294
+    pub const MAX: u64 = (u64::MAX / 2) - 36;
+
+    pub const ETHEREUM: ChainId = ChainId(1);
+    pub const OPTIMISM: ChainId = ChainId(10);
+    pub const POLYGON: ChainId = ChainId(137);
+    pub const BASE: ChainId = ChainId(8453);
+    pub const ARBITRUM: ChainId = ChainId(42161);
+    pub const AVALANCHE: ChainId = ChainId(43114);
+    pub const SEPOLIA: ChainId = ChainId(11155111);
+
+    const KNOWN: &'static [KnownChain] = &[
+        KnownChain { id: Self::ETHEREUM, name: "ethereum", rpc_env_var: "ETHEREUM_RPC_URL", blockscout_env_var: "BLOCKSCOUT_URL", gas_floors_env_var: "ETHEREUM_GAS_FLOORS", block_time: Duration::from_secs(12) },
+        KnownChain { id: Self::OPTIMISM, name: "optimism", rpc_env_var: "OPTIMISM_RPC_URL", blockscout_env_var: "OPTIMISM_BLOCKSCOUT_URL", gas_floors_env_var: "OPTIMISM_GAS_FLOORS", block_time: Duration::from_secs(2) },
+        KnownChain { id: Self::POLYGON, name: "polygon", rpc_env_var: "POLYGON_RPC_URL", blockscout_env_var: "POLYGON_BLOCKSCOUT_URL", gas_floors_env_var: "POLYGON_GAS_FLOORS", block_time: Duration::from_secs(2) },
+        KnownChain { id: Self::BASE, name: "base", rpc_env_var: "BASE_RPC_URL", blockscout_env_var: "BASE_BLOCKSCOUT_URL", gas_floors_env_var: "BASE_GAS_FLOORS", block_time: Duration::from_secs(2) },
+        KnownChain { id: Self::ARBITRUM, name: "arbitrum", rpc_env_var: "ARBITRUM_RPC_URL", blockscout_env_var: "ARBITRUM_BLOCKSCOUT_URL", gas_floors_env_var: "ARBITRUM_GAS_FLOORS", block_time: Duration::from_millis(250) },
+        KnownChain { id: Self::AVALANCHE, name: "avalanche", rpc_env_var: "AVALANCHE_RPC_URL", blockscout_env_var: "AVALANCHE_BLOCKSCOUT_URL", gas_floors_env_var: "AVALANCHE_GAS_FLOORS", block_time: Duration::from_secs(2) },
+        KnownChain { id: Self::SEPOLIA, name: "sepolia", rpc_env_var: "SEPOLIA_RPC_URL", blockscout_env_var: "SEPOLIA_BLOCKSCOUT_URL", gas_floors_env_var: "SEPOLIA_GAS_FLOORS", block_time: Duration::from_secs(12) },
+    ];
+
+    /// Creates a chain id, rejecting zero and ids above [`ChainId::MAX`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the id is out of range.
+    pub fn new(id: u64) -> Result<Self> {
+        if id == 0 || id > Self::MAX {
+            anyhow::bail!("invalid chain id {}: must be between 1 and {}", id, Self::MAX);
+        }
+        Ok(Self(id))
+    }
+
+    /// Returns the chain id for a chain this service supports.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the id is out of range or not a known chain.
+    pub fn known(id: u64) -> Result<Self> {
+        let chain = Self::new(id)?;
+        if chain.info().is_none() {
+            anyhow::bail!(
+                "invalid chain id {}: unsupported chain, expected one of: {}",
+                id,
+                Self::all().iter().map(|c| format!("{} ({})", c.0, c.name().unwrap_or_default())).collect::<Vec<_>>().join(", ")
+            );
+        }
+        Ok(chain)
+    }
+
+    /// All chains this service supports
+    pub fn all() -> Vec<ChainId> {
+        Self::KNOWN.iter().map(|chain| chain.id).collect()
+    }
+
+    /// The numeric chain id
+    pub fn get(self) -> u64 {
+        self.0
+    }
+
+    /// Lowercase chain name for known chains
+    pub fn name(self) -> Option<&'static str> {
+        self.info().map(|chain| chain.name)
+    }
+
+    /// Environment variable holding the RPC URL for known chains
+    pub fn rpc_env_var(self) -> Option<&'static str> {
+        self.info().map(|chain| chain.rpc_env_var)
+    }
+
+    /// Environment variable holding the Blockscout instance URL for known chains;
+    /// `BLOCKSCOUT_URL` for Ethereum mainnet
+    pub fn blockscout_env_var(self) -> Option<&'static str> {
+        self.info().map(|chain| chain.blockscout_env_var)
+    }
+
+    /// Environment variable overriding the gas floors for known chains
+    pub fn gas_floors_env_var(self) -> Option<&'static str> {
+        self.info().map(|chain| chain.gas_floors_env_var)
+    }
+
+    /// Typical time between blocks, Ethereum's 12 seconds for chains not listed
+    pub fn block_time(self) -> Duration {
+        self.info().map_or(Duration::from_secs(12), |chain| chain.block_time)
+    }
+
+    fn info(self) -> Option<&'static KnownChain> {
+        Self::KNOWN.iter().find(|chain| chain.id == self)
+    }
+}
+
+impl Default for ChainId {
+    fn default() -> Self {
+        Self::ETHEREUM
+    }
+}
+
+impl fmt::Display for ChainId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ChainId {
+    type Err = anyhow::Error;
+
+    /// Parses a known chain from its numeric id or its name (`8453` or `base`).
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        match s.parse::<u64>() {
+            Ok(id) => Self::known(id),
+            Err(_) => Self::KNOWN
+                .iter()
+                .find(|chain| chain.name.eq_ignore_ascii_case(s))
+                .map(|chain| chain.id)
+                .ok_or_else(|| anyhow::anyhow!("invalid chain id '{}': not a number or known chain name", s)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ChainId {
+    /// Accepts only known chains, as a number or a string
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u64),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(id) => Self::known(id),
+            Raw::Text(text) => text.parse(),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_construction() {
+        assert_eq!(ChainId::new(1).unwrap(), ChainId::ETHEREUM);
+        assert_eq!(ChainId::new(999_999).unwrap().get(), 999_999);
+        assert!(ChainId::new(0).is_err());
+        assert!(ChainId::new(u64::MAX).is_err());
+        assert_eq!(ChainId::default(), ChainId::ETHEREUM);
+    }
+
+    #[test]
+    fn test_known_chain_lookups() {
+        assert_eq!(ChainId::known(8453).unwrap(), ChainId::BASE);
+        assert_eq!(ChainId::BASE.name(), Some("base"));
+        assert_eq!(ChainId::ARBITRUM.rpc_env_var(), Some("ARBITRUM_RPC_URL"));
+        assert_eq!(ChainId::ETHEREUM.blockscout_env_var(), Some("BLOCKSCOUT_URL"));
+        assert_eq!(ChainId::BASE.blockscout_env_var(), Some("BASE_BLOCKSCOUT_URL"));
+        assert_eq!(ChainId::BASE.gas_floors_env_var(), Some("BASE_GAS_FLOORS"));
+        assert_eq!("optimism".parse::<ChainId>().unwrap(), ChainId::OPTIMISM);
+        assert_eq!("10".parse::<ChainId>().unwrap(), ChainId::OPTIMISM);
+        assert_eq!(ChainId::new(999_999).unwrap().name(), None);
+    }
+
+    #[test]
+    fn test_invalid_ids_are_rejected() {
+        assert!(ChainId::known(0).is_err());
+        assert!(ChainId::known(999_999).is_err());
+        assert!("mainnet-ish".parse::<ChainId>().is_err());
+        assert!(serde_json::from_str::<ChainId>("0").is_err());
+        assert!(serde_json::from_str::<ChainId>("\"12345\"").is_err());
+        assert_eq!(serde_json::from_str::<ChainId>("137").unwrap(), ChainId::POLYGON);
+        assert_eq!(serde_json::to_string(&ChainId::POLYGON).unwrap(), "137");
+    }
+}
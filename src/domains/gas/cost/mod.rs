@@ -0,0 +1,333 @@
+//! Transaction fee estimates.
+//!
+//! Turns a gas limit and gas price into a fee in the chain's gas token and in
+//! fiat. The fiat conversion always uses the price of the chain's own gas token
+//! (see [`Coin::native_for_chain`]), so a Polygon fee is priced in MATIC, not ETH.
+//! A [`BatchCost`] prices several [`Operation`]s at once, one [`GasCost`] each,
+//! and a [`GasPriceFiat`] prices each gas tier per gas unit and per transfer.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::core::errors::{InvalidBody, NotSupported, Result};
+use crate::domains::crypto::{Coin, Currency, Quote};
+use crate::domains::gas::chain::ChainId;
+use crate::domains::gas::price::{GasPrice, GasSpeed};
+
+/// Gwei per unit of a chain's gas token
+const GWEI_PER_COIN: f64 = 1e9;
+
+/// Gas used by a plain transfer of a chain's gas token
+pub const NATIVE_TRANSFER_GAS: u64 = 21_000;
+
+/// Typical gas used by an ERC-20 `transfer`
+pub const ERC20_TRANSFER_GAS: u64 = 65_000;
+
+/// A transaction to estimate the fee for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Operation {
+    /// Transfer of the chain's gas token (21000 gas)
+    NativeTransfer,
+    /// ERC-20 `transfer` (65000 gas)
+    Erc20Transfer,
+    /// Any other contract call, with the caller's gas limit
+    ContractCall {
+        /// Gas units the call uses
+        gas_limit: u64,
+    },
+}
+
+impl Operation {
+    /// Gas units the operation uses
+    pub fn gas_limit(&self) -> u64 {
+        match self {
+            Self::NativeTransfer => NATIVE_TRANSFER_GAS,
+            Self::Erc20Transfer => ERC20_TRANSFER_GAS,
+            Self::ContractCall { gas_limit } => *gas_limit,
+        }
+    }
+}
+
+/// Estimated fee for a transaction on one chain
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GasCost {
+    /// Chain the transaction runs on
+    #[schema(value_type = u64, example = 137)]
+    pub chain_id: ChainId,
+    /// Gas units the transaction uses
+    pub gas_limit: u64,
+    /// Gas price, in Gwei
+    pub gas_price_gwei: f64,
+    /// Token the fee is paid in
+    pub native_coin: Coin,
+    /// Fee in Gwei
+    pub fee_gwei: f64,
+    /// Fee in the chain's gas token
+    pub fee_native: f64,
+    /// Currency of `fee_fiat`
+    pub currency: Currency,
+    /// Fee in `currency`, at the gas token's quoted price
+    pub fee_fiat: f64,
+}
+
+impl GasCost {
+    /// Estimates the fee for `gas_limit` units at `gas_price_gwei` on `chain`.
+    ///
+    /// `native_price` must be a quote for the chain's gas token.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NotSupported`] if the chain's gas token is unknown, or an error
+    /// if `native_price` quotes a different coin.
+    pub fn estimate(chain: ChainId, gas_limit: u64, gas_price_gwei: f64, native_price: &Quote) -> Result<Self> {
+        let native_coin = Coin::native_for_chain(chain)
+            .ok_or_else(|| NotSupported::new(format!("fee estimates on chain {}", chain)))?;
+        if native_price.coin != native_coin {
+            anyhow::bail!(
+                "fees on chain {} are paid in {}, but the price given is for {}",
+                chain,
+                native_coin,
+                native_price.coin
+            );
+        }
+
+        let fee_gwei = gas_limit as f64 * gas_price_gwei;
+        let fee_native = fee_gwei / GWEI_PER_COIN;
+        Ok(Self {
+            chain_id: chain,
+            gas_limit,
+            gas_price_gwei,
+            native_coin,
+            fee_gwei,
+            fee_native,
+            currency: native_price.currency,
+            fee_fiat: fee_native * native_price.price,
+        })
+    }
+}
+
+/// Estimated fee for one operation of a batch
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OperationCost {
+    /// The operation as submitted
+    pub operation: Operation,
+    /// Gas units the operation uses
+    pub gas_limit: u64,
+    /// Fee in Gwei
+    pub fee_gwei: f64,
+    /// Fee in the chain's gas token
+    pub fee_native: f64,
+    /// Fee in the batch's currency
+    pub fee_fiat: f64,
+}
+
+/// Estimated total fee for a batch of operations on one chain
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BatchCost {
+    /// Chain the operations run on
+    #[schema(value_type = u64, example = 1)]
+    pub chain_id: ChainId,
+    /// Gas price every operation is priced at, in Gwei
+    pub gas_price_gwei: f64,
+    /// Token the fees are paid in
+    pub native_coin: Coin,
+    /// Currency of the fiat fees
+    pub currency: Currency,
+    /// Gas units of all operations together
+    pub gas_limit: u64,
+    /// Total fee in Gwei
+    pub fee_gwei: f64,
+    /// Total fee in the chain's gas token
+    pub fee_native: f64,
+    /// Total fee in `currency`
+    pub fee_fiat: f64,
+    /// Fee of each operation, in submission order
+    pub operations: Vec<OperationCost>,
+}
+
+impl BatchCost {
+    /// Estimates the fee of each operation with [`GasCost::estimate`] and sums them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidBody`] if `operations` is empty, otherwise the same
+    /// errors as [`GasCost::estimate`].
+    pub fn estimate(chain: ChainId, operations: &[Operation], gas_price_gwei: f64, native_price: &Quote) -> Result<Self> {
+        let Some(native_coin) = Coin::native_for_chain(chain) else {
+            return Err(NotSupported::new(format!("fee estimates on chain {}", chain)).into());
+        };
+        if operations.is_empty() {
+            return Err(InvalidBody::new("operations: at least one operation is required").into());
+        }
+
+        let mut batch = Self {
+            chain_id: chain,
+            gas_price_gwei,
+            native_coin,
+            currency: native_price.currency,
+            gas_limit: 0,
+            fee_gwei: 0.0,
+            fee_native: 0.0,
+            fee_fiat: 0.0,
+            operations: Vec::with_capacity(operations.len()),
+        };
+        for &operation in operations {
+            let cost = GasCost::estimate(chain, operation.gas_limit(), gas_price_gwei, native_price)?;
+            batch.gas_limit = batch.gas_limit.saturating_add(cost.gas_limit);
+            batch.fee_gwei += cost.fee_gwei;
+            batch.fee_native += cost.fee_native;
+            batch.fee_fiat += cost.fee_fiat;
+            batch.operations.push(OperationCost {
+                operation,
+                gas_limit: cost.gas_limit,
+                fee_gwei: cost.fee_gwei,
+                fee_native: cost.fee_native,
+                fee_fiat: cost.fee_fiat,
+            });
+        }
+        Ok(batch)
+    }
+}
+
+/// A gas tier's price in fiat
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+pub struct TierFiatCost {
+    /// Cost of one unit of gas
+    pub per_gas: f64,
+    /// Cost of a plain transfer of the gas token (21000 gas)
+    pub per_transfer: f64,
+}
+
+/// Each gas tier priced in fiat, at the quoted price of the chain's gas token
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GasPriceFiat {
+    /// Token the fees are paid in
+    pub native_coin: Coin,
+    /// Currency the tiers are priced in
+    pub currency: Currency,
+    /// Price of one `native_coin` in `currency`
+    pub native_price: f64,
+    /// Low priority tier
+    pub low: TierFiatCost,
+    /// Average tier
+    pub average: TierFiatCost,
+    /// High priority tier
+    pub high: TierFiatCost,
+}
+
+impl GasPriceFiat {
+    /// Prices each tier of `gas_price` on `chain` with [`GasCost::estimate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`GasCost::estimate`].
+    pub fn new(chain: ChainId, gas_price: &GasPrice, native_price: &Quote) -> Result<Self> {
+        let tier = |speed| -> Result<TierFiatCost> {
+            let gwei = gas_price.tier(speed);
+            Ok(TierFiatCost {
+                per_gas: GasCost::estimate(chain, 1, gwei, native_price)?.fee_fiat,
+                per_transfer: GasCost::estimate(chain, NATIVE_TRANSFER_GAS, gwei, native_price)?.fee_fiat,
+            })
+        };
+        Ok(Self {
+            native_coin: native_price.coin,
+            currency: native_price.currency,
+            native_price: native_price.price,
+            low: tier(GasSpeed::Low)?,
+            average: tier(GasSpeed::Average)?,
+            high: tier(GasSpeed::High)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::crypto::ProviderSource;
+
+    fn price(coin: Coin, price: f64) -> Quote {
+        Quote::new(coin, Currency::USD, price, ProviderSource::CoinGecko)
+    }
+
+    #[test]
+    fn test_polygon_fees_priced_in_matic() {
+        // 21000 gas at 50 gwei = 0.00105 MATIC
+        let cost = GasCost::estimate(ChainId::POLYGON, 21_000, 50.0, &price(Coin::MATIC, 0.5)).unwrap();
+        assert_eq!(cost.native_coin, Coin::MATIC);
+        assert!((cost.fee_native - 0.00105).abs() < 1e-12);
+        assert!((cost.fee_fiat - 0.000525).abs() < 1e-12);
+
+        let err = GasCost::estimate(ChainId::POLYGON, 21_000, 50.0, &price(Coin::ETH, 2000.0)).unwrap_err();
+        assert!(err.to_string().contains("paid in MATIC"), "{}", err);
+    }
+
+    #[test]
+    fn test_mainnet_fees_priced_in_eth() {
+        let cost = GasCost::estimate(ChainId::ETHEREUM, 21_000, 20.0, &price(Coin::ETH, 2000.0)).unwrap();
+        assert_eq!(cost.native_coin, Coin::ETH);
+        assert!((cost.fee_fiat - 0.84).abs() < 1e-9);
+
+        let unknown = ChainId::new(999_999).unwrap();
+        let err = GasCost::estimate(unknown, 21_000, 20.0, &price(Coin::ETH, 2000.0)).unwrap_err();
+        assert!(err.downcast_ref::<NotSupported>().is_some());
+    }
+
+    #[test]
+    fn test_batch_sums_operations() {
+        // 21000 + 65000 + 100000 gas at 10 gwei, with ETH at 2000 USD
+        let operations = [
+            Operation::NativeTransfer,
+            Operation::Erc20Transfer,
+            Operation::ContractCall { gas_limit: 100_000 },
+        ];
+        let batch = BatchCost::estimate(ChainId::ETHEREUM, &operations, 10.0, &price(Coin::ETH, 2000.0)).unwrap();
+
+        assert_eq!(batch.gas_limit, 186_000);
+        assert!((batch.fee_gwei - 1_860_000.0).abs() < 1e-6);
+        assert!((batch.fee_native - 0.00186).abs() < 1e-12);
+        assert!((batch.fee_fiat - 3.72).abs() < 1e-9);
+
+        assert_eq!(batch.operations.len(), 3);
+        assert_eq!(batch.operations[0].operation, Operation::NativeTransfer);
+        assert!((batch.operations[0].fee_fiat - 0.42).abs() < 1e-9);
+        assert_eq!(batch.operations[1].gas_limit, ERC20_TRANSFER_GAS);
+        assert!((batch.operations[2].fee_native - 0.001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_batch_rejects_empty_and_mispriced_batches() {
+        let err = BatchCost::estimate(ChainId::ETHEREUM, &[], 10.0, &price(Coin::ETH, 2000.0)).unwrap_err();
+        assert!(err.downcast_ref::<InvalidBody>().is_some());
+
+        let err = BatchCost::estimate(ChainId::POLYGON, &[Operation::NativeTransfer], 10.0, &price(Coin::ETH, 2000.0))
+            .unwrap_err();
+        assert!(err.to_string().contains("paid in MATIC"), "{}", err);
+    }
+
+    #[test]
+    fn test_gas_price_tiers_in_fiat() {
+        let gas_price = GasPrice { low: 10.0, average: 20.0, high: 40.0, timestamp: chrono::Utc::now(), base_fee_trend: None, base_fee: None, percentile_tiers: Default::default(), estimated_seconds: None };
+        let fiat = GasPriceFiat::new(ChainId::ETHEREUM, &gas_price, &price(Coin::ETH, 2000.0)).unwrap();
+
+        // 20 gwei at 2000 USD: 0.00004 USD per gas, 0.84 USD per transfer
+        assert_eq!(fiat.native_coin, Coin::ETH);
+        assert_eq!(fiat.native_price, 2000.0);
+        assert!((fiat.average.per_gas - 0.00004).abs() < 1e-15);
+        assert!((fiat.average.per_transfer - 0.84).abs() < 1e-9);
+        assert!((fiat.low.per_transfer - 0.42).abs() < 1e-9);
+        assert!((fiat.high.per_transfer - 1.68).abs() < 1e-9);
+
+        assert!(GasPriceFiat::new(ChainId::POLYGON, &gas_price, &price(Coin::ETH, 2000.0)).is_err());
+    }
+
+    #[test]
+    fn test_operation_json_shape() {
+        let operations: Vec<Operation> = serde_json::from_str(
+            r#"[{"type": "native_transfer"}, {"type": "erc20_transfer"}, {"type": "contract_call", "gas_limit": 50000}]"#,
+        )
+        .unwrap();
+        assert_eq!(operations[2], Operation::ContractCall { gas_limit: 50_000 });
+        assert!(serde_json::from_str::<Operation>(r#"{"type": "contract_call"}"#).is_err());
+    }
+}
@@ -3,4 +3,6 @@
 //! This module provides functionality for fetching current gas prices
 //! from various providers like Etherscan and alloy-rs built-in functions.
 
+pub mod chain;
+pub mod cost;
 pub mod price;
\ No newline at end of file
@@ -3,17 +3,111 @@
 //! This module implements gas price fetching using alloy-rs built-in functions
 //! to connect directly to Ethereum nodes.
 
-use super::{GasOracle, GasPrice};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{BaseFeeTrend, GasFloors, GasOracle, GasOracleSource, GasPrice, PercentileTiers};
+use crate::domains::gas::chain::ChainId;
 use crate::core::errors::{Result, ErrorContext};
+use crate::infrastructure::http::{build_client, HttpClientConfig};
+use crate::infrastructure::logging::sample;
 use alloy_provider::{Provider, ProviderBuilder};
-use alloy_rpc_types::FeeHistory;
+use alloy_rpc_types::{BlockNumberOrTag, FeeHistory};
 use async_trait::async_trait;
 use anyhow::Context;
+use reqwest::{Client, Url};
+use tracing::{debug, warn};
+
+/// Reward percentile behind the low/average/high tiers, which are quartiles
+/// across the fee history window of each block's reward at this percentile
+const BASE_REWARD_PERCENTILE: f64 = 25.0;
 
+/// Multipliers applied to `eth_gasPrice` for the low, average and high tiers
+/// when the node has no `eth_feeHistory`
+const LEGACY_MULTIPLIERS: (f64, f64, f64) = (0.9, 1.0, 1.25);
 
 /// Alloy gas price provider using direct Ethereum node connection
 pub struct AlloyGasOracle {
     rpc_url: String,
+    client: Client,
+    block: BlockNumberOrTag,
+    floors: GasFloors,
+    percentile_tiers: PercentileTiers,
+}
+
+/// The requested historical block is beyond the node's chain head
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockNotFound {
+    /// Requested block number
+    pub block: u64,
+    /// Latest block known to the node
+    pub head: u64,
+}
+
+impl std::fmt::Display for BlockNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid block {}: not found (chain head is {})", self.block, self.head)
+    }
+}
+
+impl std::error::Error for BlockNotFound {}
+
+/// Outcome of probing an RPC endpoint with `eth_chainId`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcProbe {
+    /// The node answered with the chain id it is configured for
+    Reachable,
+    /// The node answered with a different chain id, so the URL is likely misconfigured
+    ChainMismatch {
+        /// Chain id the node reported
+        reported: u64,
+    },
+    /// The node could not be reached or returned an error
+    Unreachable(String),
+}
+
+/// Asks the node at `rpc_url` for its chain id and compares it with `expected`.
+///
+/// Never fails: an invalid URL, a timeout or an RPC error are reported as
+/// [`RpcProbe::Unreachable`].
+pub async fn probe_rpc(client: &Client, rpc_url: &str, expected: ChainId, timeout: Duration) -> RpcProbe {
+    let url = match rpc_url.parse() {
+        Ok(url) => url,
+        Err(e) => return RpcProbe::Unreachable(format!("invalid RPC URL: {}", e)),
+    };
+    let provider = ProviderBuilder::new().connect_reqwest(client.clone(), url);
+
+    match tokio::time::timeout(timeout, provider.get_chain_id()).await {
+        Ok(Ok(reported)) if reported == expected.get() => RpcProbe::Reachable,
+        Ok(Ok(reported)) => RpcProbe::ChainMismatch { reported },
+        Ok(Err(e)) => RpcProbe::Unreachable(e.to_string()),
+        Err(_) => RpcProbe::Unreachable(format!("no answer within {:?}", timeout)),
+    }
+}
+
+/// Parses a `block` query value into a block number or tag.
+///
+/// Accepts the named tags (`latest`, `safe`, `finalized`, `earliest`, `pending`),
+/// hex numbers (`0x1234`) and plain decimal numbers (`4660`).
+///
+/// # Errors
+///
+/// Returns an error if the value is neither a known tag nor a block number.
+pub fn parse_block(raw: &str) -> Result<BlockNumberOrTag> {
+    let raw = raw.trim();
+    if !raw.is_empty() && raw.chars().all(|c| c.is_ascii_digit()) {
+        let number = raw
+            .parse::<u64>()
+            .with_context(|| format!("invalid block '{}': number out of range", raw))?;
+        return Ok(BlockNumberOrTag::Number(number));
+    }
+
+    raw.parse().map_err(|_| {
+        anyhow::anyhow!(
+            "invalid block '{}': expected a block number or one of latest, safe, finalized, earliest, pending",
+            raw
+        )
+    })
 }
 
 impl AlloyGasOracle {
@@ -25,20 +119,92 @@ impl AlloyGasOracle {
     ///
     /// # Errors
     ///
-    /// Returns `AlloyError::MissingRpcUrl` if the RPC URL is empty.
+    /// Returns an error if the RPC URL is empty or the HTTP client cannot be created.
     pub fn new(rpc_url: String) -> Result<Self> {
+        let client = build_client(&HttpClientConfig::default())
+            .gas_context("creating HTTP client for Alloy")?;
+
+        Self::new_with_client(rpc_url, client)
+    }
+
+    /// Creates a new Alloy gas oracle that sends RPC requests through an existing HTTP client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC URL is empty.
+    pub fn new_with_client(rpc_url: String, client: Client) -> Result<Self> {
         if rpc_url.is_empty() {
             anyhow::bail!("Ethereum RPC URL cannot be empty");
         }
 
-        Ok(Self { rpc_url })
+        Ok(Self { rpc_url, client, block: BlockNumberOrTag::Latest, floors: GasFloors::default(), percentile_tiers: PercentileTiers::default() })
+    }
+
+    /// Estimates gas as of `block` instead of the latest block.
+    pub fn with_block(mut self, block: BlockNumberOrTag) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// Sets the minimum priority fee per tier, e.g. [`GasFloors::for_chain`] for the node's chain.
+    ///
+    /// Defaults to the mainnet floors of 1, 2 and 3 gwei; [`GasFloors::NONE`]
+    /// returns the computed percentiles unclamped.
+    pub fn with_floors(mut self, floors: GasFloors) -> Self {
+        self.floors = floors;
+        self
+    }
+
+    /// Also prices each of `tiers` at its percentile of the recent blocks' rewards.
+    ///
+    /// Every tier is the base fee plus the median, across the fee history
+    /// window, of each block's reward at the tier's percentile. Floors do not
+    /// apply: the tiers report what the chosen percentile actually paid.
+    pub fn with_percentile_tiers(mut self, tiers: PercentileTiers) -> Self {
+        self.percentile_tiers = tiers;
+        self
+    }
+
+    /// Reward percentiles to request: the base one and every configured tier's, ascending.
+    fn reward_percentiles(&self) -> Vec<f64> {
+        let mut percentiles: Vec<f64> = std::iter::once(BASE_REWARD_PERCENTILE)
+            .chain(self.percentile_tiers.iter().map(|(_, percentile)| percentile))
+            .collect();
+        percentiles.sort_by(f64::total_cmp);
+        percentiles.dedup();
+        percentiles
+    }
+
+    /// Prices each configured percentile tier from `fee_history`, in gwei.
+    ///
+    /// Without reward data every tier is the base fee alone.
+    fn calculate_percentile_tiers(&self, fee_history: &FeeHistory) -> BTreeMap<String, f64> {
+        let Some(base_fee) = fee_history.base_fee_per_gas.last() else {
+            return BTreeMap::new();
+        };
+        let base_fee_gwei = *base_fee as f64 / 1_000_000_000.0;
+        let percentiles = self.reward_percentiles();
+        let rewards = fee_history.reward.as_deref().unwrap_or_default();
+
+        self.percentile_tiers
+            .iter()
+            .map(|(label, percentile)| {
+                let index = percentiles.iter().position(|p| *p == percentile);
+                let mut tips: Vec<f64> = rewards
+                    .iter()
+                    .filter_map(|block| block.get(index?))
+                    .map(|reward| *reward as f64 / 1_000_000_000.0)
+                    .collect();
+                tips.sort_by(f64::total_cmp);
+                let tip = tips.get(tips.len() / 2).copied().unwrap_or(0.0);
+                (label.to_string(), base_fee_gwei + tip)
+            })
+            .collect()
     }
 
 
     /// Calculates gas price percentiles from fee history
     fn calculate_gas_prices(&self, fee_history: &FeeHistory) -> Result<(f64, f64, f64)> {
-        println!("💰 Calculating gas prices from fee history...");
-        
         if fee_history.base_fee_per_gas.is_empty() {
             anyhow::bail!("No base fee data available in fee history");
         }
@@ -48,90 +214,416 @@ impl AlloyGasOracle {
             .last()
             .context("No base fee available in fee history")?;
 
-        println!("⛽ Latest base fee: {} wei", latest_base_fee);
-
         // Convert base fee from wei to gwei (preserve precision)
         let base_fee_gwei = *latest_base_fee as f64 / 1_000_000_000.0;
-        println!("⛽ Base fee in Gwei: {:.6}", base_fee_gwei);
 
         // Calculate priority fees based on historical data
         let mut priority_fees = Vec::new();
         
+        let base_index = self.reward_percentiles().iter().position(|p| *p == BASE_REWARD_PERCENTILE).unwrap_or(0);
         if let Some(reward_percentiles) = &fee_history.reward {
-            println!("💎 Processing {} reward entries", reward_percentiles.len());
-            for (i, rewards) in reward_percentiles.iter().enumerate() {
-                if let Some(reward) = rewards.first() {
-                    let priority_fee_gwei = *reward as f64 / 1_000_000_000.0;
-                    priority_fees.push(priority_fee_gwei);
-                    println!("   Block {}: {:.6} Gwei priority fee", i, priority_fee_gwei);
+            for rewards in reward_percentiles {
+                if let Some(reward) = rewards.get(base_index) {
+                    priority_fees.push(*reward as f64 / 1_000_000_000.0);
                 }
             }
-        } else {
-            println!("⚠️  No reward percentiles data available");
         }
 
-        // If we don't have enough data, use conservative estimates
+        // If we don't have enough data, fall back to the floors
+        let floors = self.floors;
         let (low_priority, avg_priority, high_priority) = if priority_fees.is_empty() {
-            println!("📋 Using conservative priority fee estimates");
-            (1.0f64, 2.0f64, 3.0f64) // Conservative priority fee estimates in gwei
+            (floors.low, floors.average, floors.high)
         } else {
             priority_fees.sort_by(|a, b| a.partial_cmp(b).unwrap());
             let len = priority_fees.len();
-            let low = priority_fees[len / 4].max(1.0); // 25th percentile, minimum 1 gwei
-            let avg = priority_fees[len / 2].max(2.0); // 50th percentile, minimum 2 gwei  
-            let high = priority_fees[len * 3 / 4].max(3.0); // 75th percentile, minimum 3 gwei
-            println!("📊 Calculated priority fees from {} samples: low={:.6}, avg={:.6}, high={:.6}", len, low, avg, high);
+            let low = priority_fees[len / 4].max(floors.low); // 25th percentile
+            let avg = priority_fees[len / 2].max(floors.average); // 50th percentile
+            let high = priority_fees[len * 3 / 4].max(floors.high); // 75th percentile
             (low, avg, high)
         };
 
         // Total gas price = base fee + priority fee
-        let low_gas = base_fee_gwei + low_priority;
-        let avg_gas = base_fee_gwei + avg_priority;
-        let high_gas = base_fee_gwei + high_priority;
-
-        println!("✅ Final gas prices: low={:.6} ({:.6}+{:.6}), avg={:.6} ({:.6}+{:.6}), high={:.6} ({:.6}+{:.6})", 
-            low_gas, base_fee_gwei, low_priority,
-            avg_gas, base_fee_gwei, avg_priority, 
-            high_gas, base_fee_gwei, high_priority
-        );
-
-        Ok((low_gas, avg_gas, high_gas))
+        Ok((base_fee_gwei + low_priority, base_fee_gwei + avg_priority, base_fee_gwei + high_priority))
     }
 }
 
+/// The scheme, host and port of an RPC URL, safe to log.
+///
+/// Infura and Alchemy URLs carry the project key in the path.
+fn rpc_origin(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+/// Derives low/average/high prices in gwei from a legacy `eth_gasPrice` in wei.
+fn legacy_gas_prices(gas_price_wei: u128) -> (f64, f64, f64) {
+    let gas_price = gas_price_wei as f64 / 1_000_000_000.0;
+    let (low, average, high) = LEGACY_MULTIPLIERS;
+    (gas_price * low, gas_price * average, gas_price * high)
+}
+
 #[async_trait]
 impl GasOracle for AlloyGasOracle {
     type Error = anyhow::Error;
 
+    fn name(&self) -> &'static str {
+        GasOracleSource::Alloy.name()
+    }
+
+    #[tracing::instrument(skip(self), fields(provider = "alloy"))]
     async fn get_gas_prices(&self) -> std::result::Result<GasPrice, Self::Error> {
         // Parse RPC URL
-        println!("🔗 Alloy RPC URL: {}", self.rpc_url);
-        let url = self.rpc_url.parse()
+        let url: Url = self.rpc_url.parse()
             .with_context(|| format!("Invalid RPC URL: {}", self.rpc_url))?;
 
+        let verbose = sample::provider_call();
+        if verbose {
+            debug!(rpc_url = %rpc_origin(&url), block = %self.block, "Requesting fee history from Ethereum node");
+        }
+
         // Create provider
-        println!("🔌 Creating Alloy provider...");
-        let provider = ProviderBuilder::new().connect_http(url);
+        let provider = ProviderBuilder::new().connect_reqwest(self.client.clone(), url);
+
+        // A historical block must exist on the node's chain
+        if let BlockNumberOrTag::Number(number) = self.block {
+            let head = provider
+                .get_block_number()
+                .await
+                .gas_context("fetching latest block number from Ethereum node")?;
+            if number > head {
+                return Err(BlockNotFound { block: number, head }.into());
+            }
+        }
 
-        // Get fee history for the last 20 blocks with 25th, 50th, and 75th percentiles
-        println!("📊 Fetching fee history from last 20 blocks...");
+        // Get fee history for the 20 blocks up to the requested block, with the
+        // 25th percentile reward and each configured tier's
         let fee_history = provider
-            .get_fee_history(20, alloy_rpc_types::BlockNumberOrTag::Latest, &[25.0, 50.0, 75.0])
+            .get_fee_history(20, self.block, &self.reward_percentiles())
             .await
-            .gas_context("fetching fee history from Ethereum node")?;
-
-        println!("📈 Fee history received: {} base fees, {} reward entries", 
-            fee_history.base_fee_per_gas.len(),
-            fee_history.reward.as_ref().map_or(0, |r| r.len())
-        );
+            .gas_context("fetching fee history from Ethereum node")
+            .and_then(|fee_history| {
+                if fee_history.base_fee_per_gas.is_empty() {
+                    anyhow::bail!("No base fee data available in fee history");
+                }
+                Ok(fee_history)
+            });
 
-        let (low, average, high) = self.calculate_gas_prices(&fee_history)?;
+        let ((low, average, high), base_fee_trend, base_fee, percentile_tiers) = match fee_history {
+            Ok(fee_history) => {
+                if verbose {
+                    debug!(
+                        base_fees = fee_history.base_fee_per_gas.len(),
+                        reward_entries = fee_history.reward.as_ref().map_or(0, |r| r.len()),
+                        "Fee history received"
+                    );
+                }
+                let trend = BaseFeeTrend::from_base_fees(&fee_history.base_fee_per_gas);
+                let base_fee = fee_history.base_fee_per_gas.last().map(|&wei| wei as f64 / 1_000_000_000.0);
+                let tiers = self.calculate_percentile_tiers(&fee_history);
+                (self.calculate_gas_prices(&fee_history)?, trend, base_fee, tiers)
+            }
+            // eth_gasPrice only knows the current price, so it cannot stand in for a past block
+            Err(e) if matches!(self.block, BlockNumberOrTag::Latest | BlockNumberOrTag::Pending) => {
+                warn!("Fee history unavailable, falling back to eth_gasPrice: {:#}", e);
+                let gas_price = provider
+                    .get_gas_price()
+                    .await
+                    .gas_context("fetching legacy gas price from Ethereum node")?;
+                (legacy_gas_prices(gas_price), None, None, BTreeMap::new())
+            }
+            Err(e) => return Err(e),
+        };
+        if verbose {
+            debug!(low, average, high, base_fee, "Parsed gas prices from Ethereum node");
+        }
 
         Ok(GasPrice {
             low,
             average,
             high,
             timestamp: chrono::Utc::now(),
+            base_fee_trend,
+            base_fee,
+            percentile_tiers,
+            estimated_seconds: None,
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use axum::{extract::State, routing::post, Json, Router};
+    use serde_json::{json, Value};
+
+    type Calls = Arc<Mutex<Vec<Value>>>;
+
+    /// Minimal JSON-RPC node with its head at block 100, recording every request
+    async fn rpc(State(calls): State<Calls>, Json(request): Json<Value>) -> Json<Value> {
+        calls.lock().unwrap().push(request.clone());
+        let result = match request["method"].as_str() {
+            Some("eth_blockNumber") => json!("0x64"),
+            Some("eth_feeHistory") => json!({
+                "oldestBlock": "0x1",
+                "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+                "gasUsedRatio": [0.5],
+                "reward": [["0x3b9aca00", "0x77359400", "0xb2d05e00"]]
+            }),
+            _ => Value::Null,
+        };
+        Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+    }
+
+    async fn mock_node() -> (String, Calls) {
+        let calls = Calls::default();
+        let app = Router::new().route("/", post(rpc)).with_state(calls.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, calls)
+    }
+
+    fn fee_history_block(calls: &Calls) -> Value {
+        calls
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|call| call["method"] == "eth_feeHistory")
+            .map(|call| call["params"][1].clone())
+            .expect("eth_feeHistory was not called")
+    }
+
+    #[test]
+    fn test_rpc_origin_drops_the_project_key() {
+        let url: Url = "https://mainnet.infura.io/v3/secret-project-id".parse().unwrap();
+        assert_eq!(rpc_origin(&url), "https://mainnet.infura.io");
+
+        let url: Url = "http://127.0.0.1:8545/".parse().unwrap();
+        assert_eq!(rpc_origin(&url), "http://127.0.0.1:8545");
+    }
+
+    #[test]
+    fn test_parse_block() {
+        assert_eq!(parse_block("latest").unwrap(), BlockNumberOrTag::Latest);
+        assert_eq!(parse_block("Finalized").unwrap(), BlockNumberOrTag::Finalized);
+        assert_eq!(parse_block("0x1234").unwrap(), BlockNumberOrTag::Number(0x1234));
+        assert_eq!(parse_block("4660").unwrap(), BlockNumberOrTag::Number(4660));
+        assert!(parse_block("yesterday").is_err());
+        assert!(parse_block("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_latest_is_the_default_block() {
+        let (url, calls) = mock_node().await;
+        let oracle = AlloyGasOracle::new(url).unwrap();
+
+        let gas_price = oracle.get_gas_prices().await.unwrap();
+        assert_eq!(fee_history_block(&calls), "latest");
+        // The mock's base fee is flat across the window
+        assert_eq!(gas_price.base_fee_trend, Some(BaseFeeTrend::Stable));
+    }
+
+    #[tokio::test]
+    async fn test_requested_block_is_passed_through() {
+        let (url, calls) = mock_node().await;
+        let oracle = AlloyGasOracle::new(url).unwrap().with_block(BlockNumberOrTag::Number(42));
+
+        oracle.get_gas_prices().await.unwrap();
+        assert_eq!(fee_history_block(&calls), "0x2a");
+
+        let (url, calls) = mock_node().await;
+        let oracle = AlloyGasOracle::new(url).unwrap().with_block(BlockNumberOrTag::Finalized);
+
+        oracle.get_gas_prices().await.unwrap();
+        assert_eq!(fee_history_block(&calls), "finalized");
+    }
+
+    /// Fee history of a quiet rollup: 0.01 gwei base fee, 0.001-0.003 gwei tips
+    fn rollup_fee_history() -> FeeHistory {
+        serde_json::from_value(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x989680", "0x989680"],
+            "gasUsedRatio": [0.1],
+            "reward": [["0xf4240"], ["0x1e8480"], ["0x2dc6c0"], ["0x1e8480"]]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rollup_floors_allow_sub_gwei_estimates() {
+        let oracle = AlloyGasOracle::new("http://localhost:8545".to_string())
+            .unwrap()
+            .with_floors(GasFloors::for_chain(crate::domains::gas::chain::ChainId::BASE));
+
+        let (low, average, high) = oracle.calculate_gas_prices(&rollup_fee_history()).unwrap();
+        assert!((low - 0.012).abs() < 1e-9, "{}", low);
+        assert!((average - 0.012).abs() < 1e-9, "{}", average);
+        assert!((high - 0.013).abs() < 1e-9, "{}", high);
+        assert!(high < 1.0);
+    }
+
+    #[test]
+    fn test_mainnet_floors_are_the_default() {
+        let oracle = AlloyGasOracle::new("http://localhost:8545".to_string()).unwrap();
+
+        let (low, average, high) = oracle.calculate_gas_prices(&rollup_fee_history()).unwrap();
+        assert!((low - 1.01).abs() < 1e-9);
+        assert!((average - 2.01).abs() < 1e-9);
+        assert!((high - 3.01).abs() < 1e-9);
+
+        // Without reward data the floors stand in for the priority fee
+        let mut no_rewards = rollup_fee_history();
+        no_rewards.reward = None;
+        let oracle = oracle.with_floors(GasFloors::ROLLUP);
+        let (low, _, _) = oracle.calculate_gas_prices(&no_rewards).unwrap();
+        assert!((low - 0.011).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_raw_tiers_fall_below_the_floors() {
+        let oracle = AlloyGasOracle::new("http://localhost:8545".to_string())
+            .unwrap()
+            .with_floors(GasFloors::NONE);
+
+        let (low, average, high) = oracle.calculate_gas_prices(&rollup_fee_history()).unwrap();
+        assert!((low - 0.012).abs() < 1e-9, "{}", low);
+        assert!((average - 0.012).abs() < 1e-9, "{}", average);
+        assert!((high - 0.013).abs() < 1e-9, "{}", high);
+        let floors = GasFloors::MAINNET;
+        assert!(low < floors.low && average < floors.average && high < floors.high);
+
+        // Without reward data there is no tip to report
+        let mut no_rewards = rollup_fee_history();
+        no_rewards.reward = None;
+        let (low, _, high) = oracle.calculate_gas_prices(&no_rewards).unwrap();
+        assert!((low - 0.01).abs() < 1e-9 && (high - 0.01).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_percentile_tiers_map_to_their_rewards() {
+        let tiers: PercentileTiers = "economy=p20, standard=p50, priority=p90".parse().unwrap();
+        let oracle = AlloyGasOracle::new("http://localhost:8545".to_string())
+            .unwrap()
+            .with_percentile_tiers(tiers);
+        assert_eq!(oracle.reward_percentiles(), [20.0, 25.0, 50.0, 90.0]);
+
+        // 1 gwei base fee; each block's rewards at p20, p25, p50 and p90, in gwei
+        let gwei = |amount: u128| format!("{:#x}", amount * 1_000_000_000);
+        let rewards = [[1, 2, 3, 9], [1, 2, 4, 10], [2, 3, 5, 12]];
+        let fee_history: FeeHistory = serde_json::from_value(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": [gwei(1), gwei(1), gwei(1), gwei(1)],
+            "gasUsedRatio": [0.5, 0.5, 0.5],
+            "reward": rewards.map(|block| block.map(gwei))
+        }))
+        .unwrap();
+
+        // Each tier is the base fee plus the median across blocks of its percentile
+        let tiers = oracle.calculate_percentile_tiers(&fee_history);
+        assert_eq!(tiers.len(), 3);
+        assert_eq!(tiers["economy"], 2.0);
+        assert_eq!(tiers["standard"], 5.0);
+        assert_eq!(tiers["priority"], 11.0);
+
+        // The fixed tiers still come from the 25th percentile rewards
+        let (low, average, _) = oracle.with_floors(GasFloors::NONE).calculate_gas_prices(&fee_history).unwrap();
+        assert_eq!((low, average), (3.0, 3.0));
+    }
+
+    /// Minimal node without `eth_feeHistory` (or with an empty history), whose
+    /// `eth_gasPrice` is 20 gwei
+    async fn mock_legacy_node(empty_history: bool) -> (String, Calls) {
+        async fn legacy_rpc(State((calls, empty_history)): State<(Calls, bool)>, Json(request): Json<Value>) -> Json<Value> {
+            calls.lock().unwrap().push(request.clone());
+            match request["method"].as_str() {
+                Some("eth_feeHistory") if empty_history => Json(json!({
+                    "jsonrpc": "2.0", "id": request["id"],
+                    "result": { "oldestBlock": "0x0", "baseFeePerGas": [], "gasUsedRatio": [] }
+                })),
+                Some("eth_feeHistory") => Json(json!({
+                    "jsonrpc": "2.0", "id": request["id"],
+                    "error": { "code": -32601, "message": "the method eth_feeHistory does not exist" }
+                })),
+                _ => Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": "0x4a817c800" })),
+            }
+        }
+
+        let calls = Calls::default();
+        let app = Router::new().route("/", post(legacy_rpc)).with_state((calls.clone(), empty_history));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, calls)
+    }
+
+    #[tokio::test]
+    async fn test_legacy_gas_price_when_fee_history_unavailable() {
+        for empty_history in [false, true] {
+            let (url, calls) = mock_legacy_node(empty_history).await;
+            let gas_price = AlloyGasOracle::new(url).unwrap().get_gas_prices().await.unwrap();
+
+            assert!((gas_price.low - 18.0).abs() < 1e-9, "{}", gas_price.low);
+            assert!((gas_price.average - 20.0).abs() < 1e-9);
+            assert!((gas_price.high - 25.0).abs() < 1e-9);
+            // eth_gasPrice has no history to tell a trend from
+            assert_eq!(gas_price.base_fee_trend, None);
+            assert!(calls.lock().unwrap().iter().any(|call| call["method"] == "eth_gasPrice"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_no_legacy_fallback_for_historical_blocks() {
+        let (url, calls) = mock_legacy_node(false).await;
+        let oracle = AlloyGasOracle::new(url).unwrap().with_block(BlockNumberOrTag::Finalized);
+
+        assert!(oracle.get_gas_prices().await.is_err());
+        assert!(calls.lock().unwrap().iter().all(|call| call["method"] != "eth_gasPrice"));
+    }
+
+    #[tokio::test]
+    async fn test_block_beyond_head_is_rejected() {
+        let (url, calls) = mock_node().await;
+        let oracle = AlloyGasOracle::new(url).unwrap().with_block(BlockNumberOrTag::Number(1_000));
+
+        let err = oracle.get_gas_prices().await.unwrap_err();
+        assert_eq!(err.downcast_ref::<BlockNotFound>(), Some(&BlockNotFound { block: 1_000, head: 100 }));
+        assert!(calls.lock().unwrap().iter().all(|call| call["method"] != "eth_feeHistory"));
+    }
+
+    /// JSON-RPC node that only answers `eth_chainId`
+    async fn chain_id_node(chain_id: &'static str) -> String {
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| async move {
+                Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": chain_id }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_probe_flags_unexpected_chain_id() {
+        let client = Client::new();
+        let timeout = Duration::from_secs(2);
+
+        let mainnet = chain_id_node("0x1").await;
+        assert_eq!(probe_rpc(&client, &mainnet, ChainId::ETHEREUM, timeout).await, RpcProbe::Reachable);
+
+        // A Polygon node configured as the Ethereum RPC
+        let polygon = chain_id_node("0x89").await;
+        assert_eq!(
+            probe_rpc(&client, &polygon, ChainId::ETHEREUM, timeout).await,
+            RpcProbe::ChainMismatch { reported: 137 }
+        );
+        assert_eq!(probe_rpc(&client, &polygon, ChainId::POLYGON, timeout).await, RpcProbe::Reachable);
+
+        assert!(matches!(
+            probe_rpc(&client, "http://127.0.0.1:1", ChainId::ETHEREUM, timeout).await,
+            RpcProbe::Unreachable(_)
+        ));
+        assert!(matches!(probe_rpc(&client, "not a url", ChainId::ETHEREUM, timeout).await, RpcProbe::Unreachable(_)));
+    }
+}
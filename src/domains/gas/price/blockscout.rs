@@ -0,0 +1,202 @@
+//! Blockscout gas price oracle implementation.
+//!
+//! This module implements gas price fetching using the public gas price oracle of
+//! a Blockscout explorer instance. Blockscout runs one instance per chain, so the
+//! oracle is configured with that instance's base URL (e.g. `https://eth.blockscout.com`).
+
+use super::{GasEta, GasOracle, GasOracleSource, GasPrice};
+use crate::core::errors::{Result, ErrorContext};
+use crate::core::parse::parse_f64_field;
+use crate::infrastructure::http::{build_client, read_json_body_limited, HttpClientConfig, DEFAULT_MAX_RESPONSE_BYTES};
+use crate::infrastructure::http::retry::RetryPolicy;
+use async_trait::async_trait;
+use anyhow::Context;
+use reqwest::Client;
+use serde_json::Value;
+
+/// Blockscout gas price provider
+pub struct BlockscoutGasOracle {
+    client: Client,
+    base_url: String,
+    max_response_bytes: usize,
+    retry: RetryPolicy,
+}
+
+impl BlockscoutGasOracle {
+    /// Creates a new Blockscout gas oracle for the explorer at `base_url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the base URL is empty or the HTTP client cannot be created.
+    pub fn new(base_url: String) -> Result<Self> {
+        let client = build_client(&HttpClientConfig::default())
+            .gas_context("creating HTTP client for Blockscout")?;
+
+        Self::new_with_client(base_url, client)
+    }
+
+    /// Creates a new Blockscout gas oracle using an existing HTTP client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the base URL is empty.
+    pub fn new_with_client(base_url: String, client: Client) -> Result<Self> {
+        if base_url.is_empty() {
+            anyhow::bail!("Blockscout base URL cannot be empty");
+        }
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Sets the largest response body accepted from the API, in bytes.
+    pub fn with_max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
+    }
+
+    /// Retries transient failures (connection errors, timeouts, `502`-`504`)
+    /// under `policy`; by default requests are never retried.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+}
+
+/// Maps a Blockscout gas price oracle response to low/average/high in Gwei.
+///
+/// Blockscout reports `slow`, `average` and `fast` tiers. Older instances return
+/// each tier as a bare number; newer ones return an object with a `price` field
+/// and, when known, the `base_fee` it includes and the `time` to confirmation.
+fn parse_gas_prices(json: &Value) -> Result<GasPrice> {
+    let tier = |name: &str| {
+        let value = &json[name];
+        let price = value.get("price").unwrap_or(value);
+        parse_f64_field(price, name)
+            .with_context(|| format!("parsing {} gas price from Blockscout", name))
+    };
+
+    Ok(GasPrice {
+        low: tier("slow")?,
+        average: tier("average")?,
+        high: tier("fast")?,
+        timestamp: chrono::Utc::now(),
+        base_fee_trend: None,
+        // Newer instances break each tier down into base and priority fee
+        base_fee: json["average"].get("base_fee").and_then(|base_fee| parse_f64_field(base_fee, "base_fee").ok()),
+        percentile_tiers: Default::default(),
+        estimated_seconds: eta(json),
+    })
+}
+
+/// Reads the per-tier confirmation times newer instances report, in milliseconds.
+fn eta(json: &Value) -> Option<GasEta> {
+    let seconds = |name: &str| {
+        let time = json[name].get("time")?;
+        parse_f64_field(time, "time").ok().map(|millis| millis / 1000.0)
+    };
+    Some(GasEta { low: seconds("slow")?, average: seconds("average")?, high: seconds("fast")? })
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl GasOracle for BlockscoutGasOracle {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        GasOracleSource::Blockscout.name()
+    }
+
+    #[tracing::instrument(skip(self), fields(provider = "blockscout"))]
+    async fn get_gas_prices(&self) -> std::result::Result<GasPrice, Self::Error> {
+        let url = format!("{}/api/v1/gas-price-oracle", self.base_url);
+
+        let response = self.retry.send(|| self.client.get(&url)).await
+            .gas_context("sending request to Blockscout API")?;
+        let body = read_json_body_limited(response, self.max_response_bytes).await
+            .gas_context("reading response body from Blockscout API")?;
+
+        let json: Value = serde_json::from_str(&body)
+            .context("parsing JSON response from Blockscout API")?;
+
+        parse_gas_prices(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Json, Router};
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_legacy_response() {
+        let json = json!({ "slow": 0.61, "average": 0.72, "fast": 1.24 });
+        let gas_price = parse_gas_prices(&json).unwrap();
+
+        assert_eq!(gas_price.low, 0.61);
+        assert_eq!(gas_price.average, 0.72);
+        assert_eq!(gas_price.high, 1.24);
+        assert_eq!(gas_price.estimated_seconds, None);
+    }
+
+    #[test]
+    fn test_parse_response_with_price_objects() {
+        let json = json!({
+            "slow": { "price": 0.61, "fiat_price": "0.01", "time": 15000.0 },
+            "average": { "price": "0.72", "fiat_price": "0.02", "time": 12000.0 },
+            "fast": { "price": 1.24, "fiat_price": "0.03", "time": 9000.0 }
+        });
+        let gas_price = parse_gas_prices(&json).unwrap();
+
+        assert_eq!(gas_price.low, 0.61);
+        assert_eq!(gas_price.average, 0.72);
+        assert_eq!(gas_price.high, 1.24);
+        assert_eq!(gas_price.estimated_seconds, Some(GasEta { low: 15.0, average: 12.0, high: 9.0 }));
+    }
+
+    #[test]
+    fn test_parse_incomplete_response() {
+        let json = json!({ "slow": 0.61, "average": 0.72 });
+        assert!(parse_gas_prices(&json).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_instance() {
+        let app = Router::new().route(
+            "/api/v1/gas-price-oracle",
+            get(|| async { Json(json!({ "slow": 1.0, "average": 2.0, "fast": 3.0 })) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let oracle = BlockscoutGasOracle::new(base_url).unwrap();
+        let gas_price = oracle.get_gas_prices().await.unwrap();
+
+        assert_eq!((gas_price.low, gas_price.average, gas_price.high), (1.0, 2.0, 3.0));
+    }
+
+    #[tokio::test]
+    async fn test_html_error_page_is_reported_as_non_json() {
+        use axum::http::{header, StatusCode};
+
+        let page = "<html><body><h1>502 Bad Gateway</h1><p>cloudflare</p></body></html>";
+        let app = Router::new().route(
+            "/api/v1/gas-price-oracle",
+            get(move || async move { (StatusCode::BAD_GATEWAY, [(header::CONTENT_TYPE, "text/html")], page) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let err = BlockscoutGasOracle::new(base_url).unwrap().get_gas_prices().await.unwrap_err();
+        let non_json = err.downcast_ref::<crate::infrastructure::http::NonJsonResponse>().unwrap();
+        assert_eq!(non_json.status, StatusCode::BAD_GATEWAY);
+        assert!(format!("{:#}", err).contains("Upstream returned non-JSON (status 502 Bad Gateway, content type text/html)"), "{:#}", err);
+    }
+}
@@ -2,12 +2,18 @@
 //!
 //! This module implements gas price fetching using the Etherscan Gas Tracker API.
 
-use super::{GasOracle, GasPrice};
+use super::{GasOracle, GasOracleSource, GasPrice};
+use crate::domains::gas::chain::ChainId;
 use crate::core::errors::{Result, ErrorContext};
+use crate::core::parse::parse_f64_str;
+use crate::infrastructure::http::{build_client, read_json_body_limited, HttpClientConfig, DEFAULT_MAX_RESPONSE_BYTES};
+use crate::infrastructure::http::retry::RetryPolicy;
+use crate::infrastructure::logging::sample;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::Deserialize;
 use anyhow::Context;
+use tracing::debug;
 
 
 /// Etherscan Gas API response structure
@@ -19,6 +25,7 @@ struct EtherscanGasResponse {
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(dead_code)] // Kept for completeness of the upstream payload and debug output
 struct EtherscanGasResult {
     #[serde(rename = "LastBlock")]
     last_block: String,
@@ -34,11 +41,17 @@ struct EtherscanGasResult {
     gas_used_ratio: String,
 }
 
+/// Etherscan v2 API endpoint, serving every supported chain
+pub const API_URL: &str = "https://api.etherscan.io/v2/api";
+
 /// Etherscan gas price provider
 pub struct EtherscanGasOracle {
     client: Client,
     api_key: String,
     base_url: String,
+    chain_id: ChainId,
+    max_response_bytes: usize,
+    retry: RetryPolicy,
 }
 
 impl EtherscanGasOracle {
@@ -50,74 +63,141 @@ impl EtherscanGasOracle {
     ///
     /// # Errors
     ///
-    /// Returns `EtherscanError::MissingApiKey` if the API key is empty.
+    /// Returns an error if the API key is empty or the HTTP client cannot be created.
     pub fn new(api_key: String) -> Result<Self> {
+        let client = build_client(&HttpClientConfig::default())
+            .gas_context("creating HTTP client for Etherscan")?;
+
+        Self::new_with_client(api_key, client)
+    }
+
+    /// Creates a new Etherscan gas oracle using an existing HTTP client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API key is empty.
+    pub fn new_with_client(api_key: String, client: Client) -> Result<Self> {
         if api_key.is_empty() {
             anyhow::bail!("Etherscan API key cannot be empty");
         }
 
         Ok(Self {
-            client: Client::new(),
+            client,
             api_key,
-            base_url: "https://api.etherscan.io/v2/api".to_string(),
+            base_url: API_URL.to_string(),
+            chain_id: ChainId::ETHEREUM,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            retry: RetryPolicy::default(),
         })
     }
 
+    /// Queries the gas oracle of `chain_id` (Etherscan's v2 API serves every chain from one host).
+    pub fn with_chain_id(mut self, chain_id: ChainId) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// Sends requests to a different API endpoint, e.g. a mock server in tests.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Builds the gas oracle request URL for the configured chain.
+    fn gas_oracle_url(&self) -> String {
+        format!(
+            "{}?chainid={}&module=gastracker&action=gasoracle&apikey={}",
+            self.base_url, self.chain_id, self.api_key
+        )
+    }
+
+    /// Sets the largest response body accepted from the API, in bytes.
+    pub fn with_max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
+    }
+
+    /// Retries transient failures (connection errors, timeouts, `502`-`504`)
+    /// under `policy`; by default requests are never retried.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
 }
 
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 impl GasOracle for EtherscanGasOracle {
     type Error = anyhow::Error;
 
+    fn name(&self) -> &'static str {
+        GasOracleSource::Etherscan.name()
+    }
+
+    #[tracing::instrument(skip(self), fields(provider = "etherscan"))]
     async fn get_gas_prices(&self) -> std::result::Result<GasPrice, Self::Error> {
-        let url = format!(
-            "{}?chainid=1&module=gastracker&action=gasoracle&apikey={}",
-            self.base_url, self.api_key
-        );
-
-        println!("🔗 Etherscan API URL: {}", url);
-        
-        let response = self.client.get(&url).send().await
+        let url = self.gas_oracle_url();
+        let verbose = sample::provider_call();
+        if verbose {
+            // The full URL carries the API key
+            debug!(url = %self.base_url, chain_id = %self.chain_id, "Requesting Etherscan gas oracle");
+        }
+
+        let response = self.retry.send(|| self.client.get(&url)).await
             .gas_context("sending request to Etherscan API")?;
-        let body = response.text().await
+        let body = read_json_body_limited(response, self.max_response_bytes).await
             .gas_context("reading response body from Etherscan API")?;
-        
-        println!("📨 Raw API response: {}", body);
-        
+        if verbose {
+            debug!(body = %body, "Etherscan gas oracle response");
+        }
+
         let gas_response: EtherscanGasResponse = serde_json::from_str(&body)
             .context("parsing JSON response from Etherscan API")?;
-        
-        println!("🔍 Parsed response: {:?}", gas_response);
 
         if gas_response.status != "1" {
             anyhow::bail!("Etherscan API error: {}", gas_response.message);
         }
 
         // Parse gas prices from decimal strings to f64 (preserve precision)
-        println!("💰 Parsing gas prices:");
-        println!("   SafeGasPrice: '{}'", gas_response.result.safe_gas_price);
-        println!("   ProposeGasPrice: '{}'", gas_response.result.propose_gas_price);
-        println!("   FastGasPrice: '{}'", gas_response.result.fast_gas_price);
-        
-        let low = gas_response.result.safe_gas_price
-            .parse::<f64>()
-            .with_context(|| format!("Invalid safe gas price '{}'", gas_response.result.safe_gas_price))?;
-
-        let average = gas_response.result.propose_gas_price
-            .parse::<f64>()
-            .with_context(|| format!("Invalid propose gas price '{}'", gas_response.result.propose_gas_price))?;
-
-        let high = gas_response.result.fast_gas_price
-            .parse::<f64>()
-            .with_context(|| format!("Invalid fast gas price '{}'", gas_response.result.fast_gas_price))?;
+        let low = parse_f64_str(&gas_response.result.safe_gas_price, "SafeGasPrice")
+            .context("parsing safe gas price from Etherscan")?;
+
+        let average = parse_f64_str(&gas_response.result.propose_gas_price, "ProposeGasPrice")
+            .context("parsing propose gas price from Etherscan")?;
+
+        let high = parse_f64_str(&gas_response.result.fast_gas_price, "FastGasPrice")
+            .context("parsing fast gas price from Etherscan")?;
             
-        println!("✅ Parsed gas prices: low={:.6}, average={:.6}, high={:.6}", low, average, high);
+        // Only needed for EIP-1559 fees, so a malformed base fee does not fail the quote
+        let base_fee = parse_f64_str(&gas_response.result.suggest_base_fee, "suggestBaseFee").ok();
+
+        if verbose {
+            debug!(low, average, high, base_fee, "Parsed Etherscan gas prices");
+        }
 
         Ok(GasPrice {
             low,
             average,
             high,
             timestamp: chrono::Utc::now(),
+            base_fee_trend: None,
+            base_fee,
+            percentile_tiers: Default::default(),
+            estimated_seconds: None,
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gas_oracle_url_uses_chain_id() {
+        let oracle = EtherscanGasOracle::new("key".to_string()).unwrap();
+        assert!(oracle.gas_oracle_url().contains("chainid=1&"));
+
+        let oracle = oracle.with_chain_id(ChainId::BASE);
+        assert!(oracle.gas_oracle_url().contains("chainid=8453&"));
+    }
+}
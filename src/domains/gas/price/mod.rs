@@ -5,51 +5,586 @@
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
 use utoipa::ToSchema;
 
+use crate::core::errors::{utils::to_json_value, Result as AppResult, SerializationFailed};
+use crate::domains::crypto::{Coin, Currency, PriceProvider};
+use crate::domains::gas::chain::ChainId;
+use crate::domains::gas::cost::GasPriceFiat;
+
 pub mod etherscan;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod alloy;
+pub mod blockscout;
+pub mod quorum;
 
 /// Gas price categories for different transaction priorities
+///
+/// Whole-number prices serialize as JSON integers (`30`, not `30.0`), so values
+/// an upstream reported as integers round-trip unchanged.
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct GasPrice {
     /// Low priority gas price (slower confirmation)
+    #[serde(serialize_with = "serialize_gwei")]
     pub low: f64,
     /// Average gas price (standard confirmation)
+    #[serde(serialize_with = "serialize_gwei")]
     pub average: f64,
     /// High priority gas price (faster confirmation)
+    #[serde(serialize_with = "serialize_gwei")]
     pub high: f64,
-    /// When this gas price data was fetched
+    /// When this gas price data was fetched, in `TIMESTAMP_FORMAT` (RFC 3339 by default)
+    #[serde(with = "crate::domains::timestamp")]
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Direction of the base fee over the recent blocks, when the oracle saw them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_fee_trend: Option<BaseFeeTrend>,
+    /// Base fee for the next block in Gwei, when the oracle reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_fee: Option<f64>,
+    /// Gas price in Gwei for each configured [`PercentileTiers`] label (Alloy provider only)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub percentile_tiers: BTreeMap<String, f64>,
+    /// The oracle's own confirmation time per tier, if it reports one; served
+    /// only on request, see [`GasPrice::eta`]
+    #[serde(skip)]
+    pub estimated_seconds: Option<GasEta>,
+}
+
+impl GasPrice {
+    /// The price for a single tier, in Gwei
+    pub fn tier(&self, speed: GasSpeed) -> f64 {
+        match speed {
+            GasSpeed::Low => self.low,
+            GasSpeed::Average => self.average,
+            GasSpeed::High => self.high,
+        }
+    }
+
+    /// The price for a single tier as a bare JSON number, formatted like the full object
+    pub fn tier_json(&self, speed: GasSpeed) -> AppResult<serde_json::Value> {
+        serialize_gwei(&self.tier(speed), serde_json::value::Serializer)
+            .map_err(|source| SerializationFailed { what: "gas tier".to_string(), source: Box::new(source) }.into())
+    }
+
+    /// Expected seconds to confirmation per tier.
+    ///
+    /// Uses the oracle's estimate when it reported one, and otherwise derives
+    /// one from the chain's `block_time` (see [`GasEta::from_block_time`]).
+    pub fn eta(&self, block_time: Duration) -> GasEta {
+        self.estimated_seconds.unwrap_or_else(|| GasEta::from_block_time(block_time))
+    }
+
+    /// EIP-1559 fee caps for every tier, leaving `headroom` for the base fee to grow.
+    ///
+    /// Returns `None` when the oracle did not report the base fee.
+    pub fn eip1559(&self, headroom: f64) -> Option<GasPrice1559> {
+        let base_fee = self.base_fee?;
+        let fees = |tier_price| Eip1559Fees::new(base_fee, tier_price, headroom);
+        Some(GasPrice1559 {
+            low: fees(self.low),
+            average: fees(self.average),
+            high: fees(self.high),
+        })
+    }
+}
+
+/// Percentile of recent priority fees the low, average and high tiers roughly pay
+pub const TIER_PERCENTILES: [f64; 3] = [25.0, 50.0, 75.0];
+
+/// Expected seconds until a transaction is included, per gas tier
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct GasEta {
+    pub low: f64,
+    pub average: f64,
+    pub high: f64,
+}
+
+impl GasEta {
+    /// Rough estimate from the chain's block time and each tier's [percentile](TIER_PERCENTILES).
+    ///
+    /// A tip at the p-th percentile outbids p% of recent transactions, so the
+    /// transaction is taken to make each block with probability p/100 and to
+    /// wait 100/p blocks on average: four blocks for `low`, one and a third for
+    /// `high`. Ignores congestion beyond what the tips already reflect.
+    pub fn from_block_time(block_time: Duration) -> Self {
+        let [low, average, high] = TIER_PERCENTILES.map(|percentile| block_time.as_secs_f64() * 100.0 / percentile);
+        Self { low, average, high }
+    }
+}
+
+/// Default multiple of the base fee a max fee covers (`GAS_BASE_FEE_HEADROOM`)
+///
+/// At 2, a transaction stays includable through five consecutive full blocks,
+/// each raising the base fee by 12.5%.
+pub const DEFAULT_BASE_FEE_HEADROOM: f64 = 2.0;
+
+/// EIP-1559 fee caps for one tier, in Gwei
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct Eip1559Fees {
+    /// Most paid per gas, base fee included: `base_fee * headroom + max_priority_fee_per_gas`
+    #[serde(serialize_with = "serialize_gwei")]
+    pub max_fee_per_gas: f64,
+    /// Tip per gas for the block producer: what the tier pays above the base fee
+    #[serde(serialize_with = "serialize_gwei")]
+    pub max_priority_fee_per_gas: f64,
+}
+
+impl Eip1559Fees {
+    /// Fee caps for a tier priced at `tier_price` while the base fee is `base_fee`, both in Gwei.
+    ///
+    /// The tip is the tier price less the base fee, never negative. The max fee
+    /// covers the base fee rising to `headroom` times its current value, so the
+    /// transaction is not priced out by a few full blocks.
+    pub fn new(base_fee: f64, tier_price: f64, headroom: f64) -> Self {
+        let max_priority_fee_per_gas = (tier_price - base_fee).max(0.0);
+        Self {
+            max_fee_per_gas: base_fee * headroom + max_priority_fee_per_gas,
+            max_priority_fee_per_gas,
+        }
+    }
+}
+
+/// [`Eip1559Fees`] for each gas tier
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct GasPrice1559 {
+    pub low: Eip1559Fees,
+    pub average: Eip1559Fees,
+    pub high: Eip1559Fees,
+}
+
+/// How the average gas price compares with a client's baseline, in Gwei
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct GasBaseline {
+    /// The baseline the average price is compared against
+    #[serde(serialize_with = "serialize_gwei")]
+    #[schema(example = 20)]
+    pub baseline_gwei: f64,
+    /// Whether the average price is strictly below the baseline
+    pub below_baseline: bool,
+    /// Average price minus the baseline; negative when below it
+    #[serde(serialize_with = "serialize_gwei")]
+    #[schema(example = -2.5)]
+    pub delta_gwei: f64,
+}
+
+impl GasBaseline {
+    /// Compares an `average` gas price with `baseline_gwei`.
+    pub fn new(average: f64, baseline_gwei: f64) -> Self {
+        Self {
+            baseline_gwei,
+            below_baseline: average < baseline_gwei,
+            delta_gwei: average - baseline_gwei,
+        }
+    }
+}
+
+/// Base-fee change, in percent over a window, within which the base fee counts as stable
+pub const STABLE_BASE_FEE_PERCENT: f64 = 5.0;
+
+/// Direction of the base fee over a window of recent blocks.
+///
+/// A rising base fee means waiting will likely cost more; a falling one that
+/// waiting may pay off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BaseFeeTrend {
+    Rising,
+    Falling,
+    /// Within [`STABLE_BASE_FEE_PERCENT`] of where the window started
+    Stable,
+}
+
+impl BaseFeeTrend {
+    /// Classifies a series of base fees, oldest first, by comparing the last with the first.
+    ///
+    /// Returns `None` for fewer than two base fees.
+    pub fn from_base_fees(base_fees: &[u128]) -> Option<Self> {
+        let &[first, .., last] = base_fees else {
+            return None;
+        };
+        if first == 0 {
+            return Some(if last == 0 { BaseFeeTrend::Stable } else { BaseFeeTrend::Rising });
+        }
+
+        let change_percent = (last as f64 - first as f64) / first as f64 * 100.0;
+        Some(if change_percent > STABLE_BASE_FEE_PERCENT {
+            BaseFeeTrend::Rising
+        } else if change_percent < -STABLE_BASE_FEE_PERCENT {
+            BaseFeeTrend::Falling
+        } else {
+            BaseFeeTrend::Stable
+        })
+    }
+}
+
+/// A single gas tier, selected by name.
+///
+/// Accepts the common synonyms for each tier, case-insensitively:
+/// `low`/`slow`/`safe`, `average`/`standard`/`normal`, and `high`/`fast`/`rapid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasSpeed {
+    Low,
+    Average,
+    High,
+}
+
+impl GasSpeed {
+    /// Accepted tier names
+    pub const NAMES: &'static [&'static str] =
+        &["low", "slow", "safe", "average", "standard", "normal", "high", "fast", "rapid"];
+}
+
+impl FromStr for GasSpeed {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> AppResult<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "low" | "slow" | "safe" => Ok(GasSpeed::Low),
+            "average" | "standard" | "normal" => Ok(GasSpeed::Average),
+            "high" | "fast" | "rapid" => Ok(GasSpeed::High),
+            _ => anyhow::bail!(
+                "invalid speed '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            ),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for GasSpeed {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse()
+            .map_err(|_| serde::de::Error::unknown_variant(&raw, Self::NAMES))
+    }
+}
+
+/// Largest integer an `f64` represents exactly (2^53)
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Serializes a Gwei value as an integer when it has no fractional part.
+fn serialize_gwei<S: serde::Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    if value.fract() == 0.0 && value.abs() <= MAX_EXACT_INTEGER {
+        serializer.serialize_i64(*value as i64)
+    } else {
+        serializer.serialize_f64(*value)
+    }
 }
 
 /// Gas price provider sources
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+///
+/// Names are matched case-insensitively when parsed or deserialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
 pub enum GasOracleSource {
     #[serde(rename = "etherscan")]
     Etherscan,
     #[serde(rename = "alloy")]
     Alloy,
+    #[serde(rename = "blockscout")]
+    Blockscout,
+}
+
+impl fmt::Display for GasOracleSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl GasOracleSource {
+    /// Accepted oracle names
+    pub const NAMES: &'static [&'static str] = &["etherscan", "alloy", "blockscout"];
+
+    /// The oracle's name in logs, metrics and query parameters
+    pub const fn name(self) -> &'static str {
+        match self {
+            GasOracleSource::Etherscan => "etherscan",
+            GasOracleSource::Alloy => "alloy",
+            GasOracleSource::Blockscout => "blockscout",
+        }
+    }
+}
+
+impl FromStr for GasOracleSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> AppResult<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "etherscan" => Ok(GasOracleSource::Etherscan),
+            "alloy" => Ok(GasOracleSource::Alloy),
+            "blockscout" => Ok(GasOracleSource::Blockscout),
+            _ => anyhow::bail!(
+                "unknown gas oracle '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            ),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for GasOracleSource {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse()
+            .map_err(|_| serde::de::Error::unknown_variant(&raw, Self::NAMES))
+    }
 }
 
 /// A gas price quote with provider information
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct GasQuote {
+    /// Response contract version, see [`SCHEMA_VERSION`](crate::domains::SCHEMA_VERSION)
+    #[serde(default = "crate::domains::current_schema_version")]
+    #[schema(example = 1)]
+    pub schema_version: u32,
+    /// The gas prices for different priority levels
+    pub gas_price: GasPrice,
+    /// The provider that supplied this quote
+    pub provider: GasOracleSource,
+}
+
+impl GasQuote {
+    /// Serializes the quote with the gas tiers renamed to `labels`.
+    ///
+    /// Only the keys change; values, the timestamp and the provider are untouched.
+    pub fn to_labeled_json(&self, labels: &TierLabels) -> AppResult<serde_json::Value> {
+        let mut value = to_json_value(self, "gas quote")?;
+        if let Some(gas_price) = value.get_mut("gas_price").and_then(|v| v.as_object_mut()) {
+            labels.rename_tiers(gas_price);
+        }
+        Ok(value)
+    }
+
+    /// Like [`to_labeled_json`](Self::to_labeled_json), but serialized as a [`FlatGasQuote`].
+    pub fn to_flat_labeled_json(&self, labels: &TierLabels) -> AppResult<serde_json::Value> {
+        let mut value = to_json_value(&FlatGasQuote::from(self.clone()), "gas quote")?;
+        if let Some(quote) = value.as_object_mut() {
+            labels.rename_tiers(quote);
+        }
+        Ok(value)
+    }
+}
+
+/// A [`GasQuote`] with the gas prices at the top level, alongside `provider`.
+///
+/// Matches the flat shape of a crypto [`Quote`](crate::domains::crypto::Quote),
+/// so clients need not reach into `gas_price`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FlatGasQuote {
+    /// Response contract version, see [`SCHEMA_VERSION`](crate::domains::SCHEMA_VERSION)
+    #[schema(example = 1)]
+    pub schema_version: u32,
     /// The gas prices for different priority levels
+    #[serde(flatten)]
     pub gas_price: GasPrice,
     /// The provider that supplied this quote
     pub provider: GasOracleSource,
 }
 
+impl From<GasQuote> for FlatGasQuote {
+    fn from(quote: GasQuote) -> Self {
+        Self { schema_version: quote.schema_version, gas_price: quote.gas_price, provider: quote.provider }
+    }
+}
+
+/// Keys of a serialized [`GasPrice`] other than the tiers, which tier labels must not shadow
+const RESERVED_GAS_PRICE_KEYS: &[&str] = &["timestamp", "base_fee_trend", "base_fee", "percentile_tiers"];
+
+/// Names used for the low, average and high gas tiers in responses.
+///
+/// Ecosystems disagree on vocabulary (`safe,standard,fast`, `slow,normal,rapid`),
+/// so clients can pick the names they expect. Parses from a comma-separated list
+/// of exactly three distinct labels, ordered from cheapest to fastest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TierLabels {
+    pub low: String,
+    pub average: String,
+    pub high: String,
+}
+
+impl TierLabels {
+    /// Renames the default tier keys in `object` to these labels, leaving other keys alone.
+    pub(crate) fn rename_tiers(&self, object: &mut serde_json::Map<String, serde_json::Value>) {
+        for (default, label) in TierLabels::default().iter().zip(self.iter()) {
+            if default != label
+                && let Some(price) = object.remove(default)
+            {
+                object.insert(label.to_string(), price);
+            }
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &str> {
+        [self.low.as_str(), self.average.as_str(), self.high.as_str()].into_iter()
+    }
+}
+
+impl Default for TierLabels {
+    fn default() -> Self {
+        Self {
+            low: "low".to_string(),
+            average: "average".to_string(),
+            high: "high".to_string(),
+        }
+    }
+}
+
+impl FromStr for TierLabels {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> AppResult<Self> {
+        let labels: Vec<&str> = s.split(',').map(str::trim).collect();
+
+        let [low, average, high] = labels.as_slice() else {
+            anyhow::bail!("invalid tier_labels '{}': expected exactly three comma-separated labels", s);
+        };
+        if labels.iter().any(|label| label.is_empty() || RESERVED_GAS_PRICE_KEYS.contains(label)) {
+            anyhow::bail!(
+                "invalid tier_labels '{}': labels must be non-empty and not one of {}",
+                s,
+                RESERVED_GAS_PRICE_KEYS.join(", ")
+            );
+        }
+        if low == average || low == high || average == high {
+            anyhow::bail!("invalid tier_labels '{}': labels must be distinct", s);
+        }
+
+        Ok(Self {
+            low: low.to_string(),
+            average: average.to_string(),
+            high: high.to_string(),
+        })
+    }
+}
+
+/// Minimum priority fee, in gwei, applied to each tier of a fee-history estimate.
+///
+/// Floors keep estimates from undershooting when recent blocks were nearly empty,
+/// and stand in for the priority fee when a node returns no reward data. Mainnet
+/// needs a tip of a gwei or more to be included promptly, but on rollups the
+/// whole gas price is often a small fraction of a gwei, so their floors are
+/// near zero. Parses from three comma-separated gwei values, low to high.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasFloors {
+    pub low: f64,
+    pub average: f64,
+    pub high: f64,
+}
+
+impl GasFloors {
+    /// Floors for L1 chains: 1, 2 and 3 gwei
+    pub const MAINNET: GasFloors = GasFloors { low: 1.0, average: 2.0, high: 3.0 };
+
+    /// Floors for rollups, where fees are a fraction of a gwei: 0.001 gwei
+    pub const ROLLUP: GasFloors = GasFloors { low: 0.001, average: 0.001, high: 0.001 };
+
+    /// No floors at all, leaving the tips the network actually paid (`raw=true`)
+    pub const NONE: GasFloors = GasFloors { low: 0.0, average: 0.0, high: 0.0 };
+
+    /// Default floors for `chain`: near zero on rollups, [`GasFloors::MAINNET`] elsewhere
+    pub fn for_chain(chain: ChainId) -> Self {
+        match chain {
+            ChainId::OPTIMISM | ChainId::BASE | ChainId::ARBITRUM => Self::ROLLUP,
+            _ => Self::MAINNET,
+        }
+    }
+}
+
+impl Default for GasFloors {
+    fn default() -> Self {
+        Self::MAINNET
+    }
+}
+
+impl FromStr for GasFloors {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> AppResult<Self> {
+        let floors = s
+            .split(',')
+            .map(|floor| floor.trim().parse::<f64>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| anyhow::anyhow!("invalid gas floors '{}': expected three comma-separated gwei values", s))?;
+
+        let [low, average, high] = floors.as_slice() else {
+            anyhow::bail!("invalid gas floors '{}': expected three comma-separated gwei values", s);
+        };
+        if floors.iter().any(|floor| !floor.is_finite() || *floor < 0.0) {
+            anyhow::bail!("invalid gas floors '{}': values must be non-negative", s);
+        }
+
+        Ok(Self { low: *low, average: *average, high: *high })
+    }
+}
+
+/// Named gas tiers, each priced at a percentile of recent priority fees.
+///
+/// Lets operators offer the tiers their clients expect instead of the fixed
+/// low/average/high, e.g. `economy=p20,standard=p50,priority=p90`. Parses from
+/// comma-separated `label=pNN` pairs (the `p` is optional); labels and
+/// percentiles must be distinct and percentiles between 0 and 100.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PercentileTiers(Vec<(String, f64)>);
+
+impl PercentileTiers {
+    /// Whether no tier is configured
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Each tier's label and percentile, in configured order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.0.iter().map(|(label, percentile)| (label.as_str(), *percentile))
+    }
+}
+
+impl FromStr for PercentileTiers {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> AppResult<Self> {
+        let mut tiers: Vec<(String, f64)> = Vec::new();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (label, percentile) = pair
+                .split_once('=')
+                .map(|(label, percentile)| (label.trim(), percentile.trim()))
+                .filter(|(label, _)| !label.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("invalid percentile tier '{}': expected label=pNN, e.g. standard=p50", pair))?;
+            let percentile = percentile
+                .strip_prefix(['p', 'P'])
+                .unwrap_or(percentile)
+                .parse::<f64>()
+                .ok()
+                .filter(|percentile| (0.0..=100.0).contains(percentile))
+                .ok_or_else(|| anyhow::anyhow!("invalid percentile tier '{}': percentile must be between 0 and 100", pair))?;
+            if tiers.iter().any(|(existing, _)| existing == label) {
+                anyhow::bail!("invalid percentile tiers '{}': label '{}' is used twice", s, label);
+            }
+            if tiers.iter().any(|(_, existing)| *existing == percentile) {
+                anyhow::bail!("invalid percentile tiers '{}': percentile {} is used twice", s, percentile);
+            }
+            tiers.push((label.to_string(), percentile));
+        }
+        Ok(Self(tiers))
+    }
+}
+
 /// Trait for gas price oracle providers.
 ///
 /// This trait defines the interface that all gas price providers must implement.
 /// It allows for fetching current gas prices for low, average, and high priority transactions.
-#[async_trait]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 pub trait GasOracle {
     /// The error type returned by this provider
     type Error;
 
+    /// Short lowercase name identifying the oracle in logs and metrics, e.g. `etherscan`.
+    fn name(&self) -> &'static str;
+
     /// Fetches current gas prices for different priority levels.
     ///
     /// # Returns
@@ -61,4 +596,382 @@ pub trait GasOracle {
     /// Returns `Self::Error` if the request fails, the response cannot be parsed,
     /// or the gas price data is unavailable.
     async fn get_gas_prices(&self) -> Result<GasPrice, Self::Error>;
-}
\ No newline at end of file
+}
+
+/// A gas oracle chosen at runtime, e.g. one of several asked by a [`quorum::QuorumGasOracle`]
+pub type DynGasOracle = Box<dyn GasOracle<Error = anyhow::Error> + Send + Sync>;
+
+/// Gas prices converted to fiat, for any [`GasOracle`].
+///
+/// Composes an oracle with a [`PriceProvider`] for the chain's gas token, so
+/// endpoints and tools share one conversion instead of each pricing tiers
+/// themselves. Implemented for every oracle whose errors convert to `anyhow`.
+///
+/// # Examples
+///
+/// ```rust
+/// use boltzmann::domains::crypto::Currency;
+/// use boltzmann::domains::crypto::coingecko::CoinGecko;
+/// use boltzmann::domains::gas::chain::ChainId;
+/// use boltzmann::domains::gas::price::GasOracleExt;
+/// use boltzmann::domains::gas::price::blockscout::BlockscoutGasOracle;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let oracle = BlockscoutGasOracle::new("https://eth.blockscout.com".to_string())?;
+/// let fiat = oracle.get_gas_prices_in(ChainId::ETHEREUM, Currency::EUR, &CoinGecko::new(None)?).await?;
+/// println!("A transfer costs {} at average speed", Currency::EUR.format(fiat.average.per_transfer));
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait GasOracleExt: GasOracle {
+    /// Fetches gas prices and prices each tier in `currency`.
+    ///
+    /// The oracle reports no chain of its own, so `chain` names the one it
+    /// serves; its gas token's price comes from `price_provider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NotSupported`](crate::core::errors::NotSupported) if the chain's gas
+    /// token is unknown, or an error if either the oracle or the price provider fails,
+    /// or the provider has no quote in `currency`.
+    async fn get_gas_prices_in<P>(&self, chain: ChainId, currency: Currency, price_provider: &P) -> AppResult<GasPriceFiat>
+    where
+        P: PriceProvider + Sync + ?Sized,
+        P::Error: Into<anyhow::Error>;
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl<T> GasOracleExt for T
+where
+    T: GasOracle + Sync + ?Sized,
+    T::Error: Into<anyhow::Error>,
+{
+    async fn get_gas_prices_in<P>(&self, chain: ChainId, currency: Currency, price_provider: &P) -> AppResult<GasPriceFiat>
+    where
+        P: PriceProvider + Sync + ?Sized,
+        P::Error: Into<anyhow::Error>,
+    {
+        let native_coin = Coin::native_for_chain(chain).ok_or_else(|| {
+            crate::core::errors::NotSupported::new(format!("fiat gas prices on chain {}", chain))
+        })?;
+        let (gas_price, quotes) = futures::try_join!(
+            async { self.get_gas_prices().await.map_err(Into::into) },
+            async { price_provider.get_quotes(native_coin, &[currency]).await.map_err(Into::into) },
+        )?;
+        let native_price = quotes
+            .into_iter()
+            .find(|quote| quote.coin == native_coin && quote.currency == currency)
+            .ok_or_else(|| anyhow::anyhow!("No {} price in {} to convert gas prices with", native_coin, currency))?;
+
+        GasPriceFiat::new(chain, &gas_price, &native_price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::SCHEMA_VERSION;
+
+    fn sample_quote() -> GasQuote {
+        GasQuote {
+            schema_version: SCHEMA_VERSION,
+            gas_price: GasPrice {
+                low: 1.5,
+                average: 2.0,
+                high: 3.25,
+                timestamp: chrono::Utc::now(),
+                base_fee_trend: None,
+                base_fee: None,
+                percentile_tiers: BTreeMap::new(),
+                estimated_seconds: None,
+            },
+            provider: GasOracleSource::Etherscan,
+        }
+    }
+
+    #[test]
+    fn test_relabeled_output_keeps_values() {
+        let labels: TierLabels = "slow,normal,rapid".parse().unwrap();
+        let json = sample_quote().to_labeled_json(&labels).unwrap();
+        let gas_price = &json["gas_price"];
+
+        assert_eq!(gas_price["slow"], 1.5);
+        assert_eq!(gas_price["normal"], 2.0);
+        assert_eq!(gas_price["rapid"], 3.25);
+        assert!(gas_price.get("low").is_none());
+        assert!(gas_price.get("timestamp").is_some());
+        assert_eq!(json["provider"], "etherscan");
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_flat_quote_has_prices_at_top_level() {
+        let json = sample_quote().to_flat_labeled_json(&TierLabels::default()).unwrap();
+
+        let mut keys: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["average", "high", "low", "provider", "schema_version", "timestamp"]);
+        assert_eq!(json["low"], 1.5);
+        assert_eq!(json["provider"], "etherscan");
+
+        let labels: TierLabels = "slow,normal,rapid".parse().unwrap();
+        let json = sample_quote().to_flat_labeled_json(&labels).unwrap();
+        assert_eq!(json["rapid"], 3.25);
+        assert!(json.get("high").is_none());
+        assert!(json.get("timestamp").is_some());
+    }
+
+    #[test]
+    fn test_default_labels_match_plain_serialization() {
+        let quote = sample_quote();
+        let labeled = quote.to_labeled_json(&TierLabels::default()).unwrap();
+        assert_eq!(labeled, serde_json::to_value(&quote).unwrap());
+    }
+
+    #[test]
+    fn test_speed_synonyms_select_tier() {
+        let gas_price = sample_quote().gas_price;
+        let cases = [
+            ("low", 1.5), ("slow", 1.5), ("safe", 1.5),
+            ("average", 2.0), ("standard", 2.0), ("normal", 2.0),
+            ("high", 3.25), ("fast", 3.25), ("rapid", 3.25), ("FAST", 3.25),
+        ];
+        for (name, expected) in cases {
+            let speed: GasSpeed = name.parse().unwrap();
+            assert_eq!(gas_price.tier(speed), expected, "{}", name);
+        }
+        assert!("turbo".parse::<GasSpeed>().is_err());
+    }
+
+    #[test]
+    fn test_gas_floors_per_chain() {
+        assert_eq!(GasFloors::for_chain(ChainId::ETHEREUM), GasFloors { low: 1.0, average: 2.0, high: 3.0 });
+        assert_eq!(GasFloors::for_chain(ChainId::BASE), GasFloors::ROLLUP);
+        assert!(GasFloors::for_chain(ChainId::ARBITRUM).high < 1.0);
+
+        let floors: GasFloors = "0.01, 0.02,0.05".parse().unwrap();
+        assert_eq!(floors, GasFloors { low: 0.01, average: 0.02, high: 0.05 });
+        assert!("1,2".parse::<GasFloors>().is_err());
+        assert!("1,-2,3".parse::<GasFloors>().is_err());
+        assert!("1,two,3".parse::<GasFloors>().is_err());
+    }
+
+    /// Oracle answering with fixed gas prices
+    struct FixedOracle(GasPrice);
+
+    #[async_trait]
+    impl GasOracle for FixedOracle {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        async fn get_gas_prices(&self) -> anyhow::Result<GasPrice> {
+            Ok(self.0.clone())
+        }
+    }
+
+    /// Provider quoting every coin at a fixed price, in the requested currencies
+    struct FixedPrice(f64);
+
+    #[async_trait]
+    impl PriceProvider for FixedPrice {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        async fn get_quotes(&self, coin: Coin, currencies: &[Currency]) -> anyhow::Result<Vec<crate::domains::crypto::Quote>> {
+            use crate::domains::crypto::{ProviderSource, Quote};
+
+            Ok(currencies
+                .iter()
+                .map(|&currency| Quote::new(coin, currency, self.0, ProviderSource::CoinGecko))
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gas_prices_in_fiat_compose_oracle_and_provider() {
+        let oracle = FixedOracle(sample_quote().gas_price);
+
+        let fiat = oracle.get_gas_prices_in(ChainId::ETHEREUM, Currency::EUR, &FixedPrice(2000.0)).await.unwrap();
+        assert_eq!((fiat.native_coin, fiat.currency, fiat.native_price), (Coin::ETH, Currency::EUR, 2000.0));
+        // 2 gwei at 2000 per ETH: 4e-6 per gas, 0.084 per 21000-gas transfer
+        assert!((fiat.average.per_gas - 4e-6).abs() < 1e-15);
+        assert!((fiat.average.per_transfer - 0.084).abs() < 1e-12);
+        assert!((fiat.high.per_transfer - 0.1365).abs() < 1e-12);
+
+        // Polygon gas is priced in MATIC through the same provider
+        let fiat = oracle.get_gas_prices_in(ChainId::POLYGON, Currency::USD, &FixedPrice(0.5)).await.unwrap();
+        assert_eq!(fiat.native_coin, Coin::MATIC);
+        assert!((fiat.low.per_transfer - 0.00001575).abs() < 1e-15);
+    }
+
+    #[tokio::test]
+    async fn test_gas_prices_in_fiat_fail_without_a_price() {
+        /// Provider that never has a quote
+        struct NoQuotes;
+
+        #[async_trait]
+        impl PriceProvider for NoQuotes {
+            type Error = anyhow::Error;
+
+            fn name(&self) -> &'static str {
+                "no_quotes"
+            }
+
+            async fn get_quotes(&self, _coin: Coin, _currencies: &[Currency]) -> anyhow::Result<Vec<crate::domains::crypto::Quote>> {
+                Ok(Vec::new())
+            }
+        }
+
+        let oracle = FixedOracle(sample_quote().gas_price);
+        let err = oracle.get_gas_prices_in(ChainId::ETHEREUM, Currency::USD, &NoQuotes).await.unwrap_err();
+        assert!(err.to_string().contains("No ETH price in USD"), "{}", err);
+
+        let err = oracle.get_gas_prices_in(ChainId::new(424242).unwrap(), Currency::USD, &FixedPrice(1.0)).await.unwrap_err();
+        assert!(err.downcast_ref::<crate::core::errors::NotSupported>().is_some());
+    }
+
+    #[test]
+    fn test_base_fee_trend_classification() {
+        let gwei = |values: &[u128]| values.iter().map(|value| value * 1_000_000_000).collect::<Vec<_>>();
+
+        assert_eq!(BaseFeeTrend::from_base_fees(&gwei(&[10, 11, 12, 13, 15])), Some(BaseFeeTrend::Rising));
+        assert_eq!(BaseFeeTrend::from_base_fees(&gwei(&[15, 13, 12, 11, 10])), Some(BaseFeeTrend::Falling));
+        assert_eq!(BaseFeeTrend::from_base_fees(&gwei(&[20, 20, 20, 20])), Some(BaseFeeTrend::Stable));
+        // Moves within the threshold are noise
+        assert_eq!(BaseFeeTrend::from_base_fees(&[100, 130, 80, 104]), Some(BaseFeeTrend::Stable));
+        assert_eq!(BaseFeeTrend::from_base_fees(&[0, 0]), Some(BaseFeeTrend::Stable));
+        assert_eq!(BaseFeeTrend::from_base_fees(&[0, 7]), Some(BaseFeeTrend::Rising));
+        assert_eq!(BaseFeeTrend::from_base_fees(&[10]), None);
+
+        let json = serde_json::to_value(BaseFeeTrend::Falling).unwrap();
+        assert_eq!(json, "falling");
+    }
+
+    #[test]
+    fn test_tier_json_is_a_bare_number() {
+        let gas_price = sample_quote().gas_price;
+        assert_eq!(gas_price.tier_json(GasSpeed::Average).unwrap().to_string(), "2");
+        assert_eq!(gas_price.tier_json(GasSpeed::High).unwrap().to_string(), "3.25");
+    }
+
+    #[test]
+    fn test_gas_oracle_source_is_case_insensitive() {
+        for raw in ["\"etherscan\"", "\"Etherscan\"", "\"ETHERSCAN\""] {
+            let source: GasOracleSource = serde_json::from_str(raw).unwrap();
+            assert_eq!(source, GasOracleSource::Etherscan);
+        }
+        assert_eq!("Alloy".parse::<GasOracleSource>().unwrap(), GasOracleSource::Alloy);
+    }
+
+    #[test]
+    fn test_integral_prices_serialize_as_integers() {
+        use crate::core::parse::parse_f64_str;
+
+        let gas_price = GasPrice {
+            low: parse_f64_str("30", "SafeGasPrice").unwrap(),
+            average: parse_f64_str("30.5", "ProposeGasPrice").unwrap(),
+            high: parse_f64_str("0.000000001", "FastGasPrice").unwrap(),
+            timestamp: chrono::Utc::now(),
+            base_fee_trend: None,
+            base_fee: None,
+            percentile_tiers: BTreeMap::new(),
+            estimated_seconds: None,
+        };
+        let json = serde_json::to_string(&gas_price).unwrap();
+
+        assert!(json.contains(r#""low":30,"#));
+        assert!(json.contains(r#""average":30.5,"#));
+        assert!(json.contains(r#""high":1e-9,"#));
+
+        let back: GasPrice = serde_json::from_str(&json).unwrap();
+        assert_eq!((back.low, back.average), (30.0, 30.5));
+    }
+
+    #[test]
+    fn test_tier_labels_validation() {
+        assert_eq!(
+            " safe , standard,fast ".parse::<TierLabels>().unwrap(),
+            TierLabels {
+                low: "safe".to_string(),
+                average: "standard".to_string(),
+                high: "fast".to_string(),
+            }
+        );
+        assert!("slow,normal".parse::<TierLabels>().is_err());
+        assert!("a,b,c,d".parse::<TierLabels>().is_err());
+        assert!("slow,,rapid".parse::<TierLabels>().is_err());
+        assert!("slow,slow,rapid".parse::<TierLabels>().is_err());
+        assert!("slow,timestamp,rapid".parse::<TierLabels>().is_err());
+    }
+
+    #[test]
+    fn test_oracles_report_their_names() {
+        let etherscan = etherscan::EtherscanGasOracle::new("key".to_string()).unwrap();
+        let alloy = alloy::AlloyGasOracle::new("http://localhost:8545".to_string()).unwrap();
+        let blockscout = blockscout::BlockscoutGasOracle::new("https://eth.blockscout.com".to_string()).unwrap();
+        assert_eq!(etherscan.name(), "etherscan");
+        assert_eq!(alloy.name(), "alloy");
+        assert_eq!(blockscout.name(), "blockscout");
+
+        let oracles: Vec<DynGasOracle> = vec![Box::new(etherscan), Box::new(alloy)];
+        let quorum = quorum::QuorumGasOracle::new(oracles, std::num::NonZeroUsize::MIN).unwrap();
+        assert_eq!(quorum.name(), "quorum");
+    }
+
+    #[test]
+    fn test_eip1559_fees_from_base_fee_and_tip() {
+        let fees = Eip1559Fees::new(20.0, 23.0, DEFAULT_BASE_FEE_HEADROOM);
+        assert_eq!(fees, Eip1559Fees { max_fee_per_gas: 43.0, max_priority_fee_per_gas: 3.0 });
+
+        // A tier priced under the base fee tips nothing rather than a negative amount
+        let fees = Eip1559Fees::new(20.0, 18.0, 1.25);
+        assert_eq!(fees, Eip1559Fees { max_fee_per_gas: 25.0, max_priority_fee_per_gas: 0.0 });
+
+        let mut gas_price = sample_quote().gas_price;
+        assert_eq!(gas_price.eip1559(DEFAULT_BASE_FEE_HEADROOM), None);
+        gas_price.base_fee = Some(1.0);
+        let fees = gas_price.eip1559(DEFAULT_BASE_FEE_HEADROOM).unwrap();
+        assert_eq!(fees.low.max_priority_fee_per_gas, 0.5);
+        assert_eq!(fees.high.max_fee_per_gas, 2.0 + 2.25);
+    }
+
+    #[test]
+    fn test_derived_eta_is_shorter_for_higher_tiers() {
+        let eta = GasEta::from_block_time(ChainId::ETHEREUM.block_time());
+        assert_eq!(eta, GasEta { low: 48.0, average: 24.0, high: 16.0 });
+        assert!(eta.high < eta.average && eta.average < eta.low);
+
+        let rollup = GasEta::from_block_time(ChainId::BASE.block_time());
+        assert!(rollup.low < eta.high);
+
+        // An oracle's own estimate wins over the derived one
+        let mut gas_price = sample_quote().gas_price;
+        assert_eq!(gas_price.eta(ChainId::ETHEREUM.block_time()), eta);
+        let reported = GasEta { low: 30.0, average: 15.0, high: 5.0 };
+        gas_price.estimated_seconds = Some(reported);
+        assert_eq!(gas_price.eta(ChainId::ETHEREUM.block_time()), reported);
+    }
+
+    #[test]
+    fn test_percentile_tiers_validation() {
+        let tiers: PercentileTiers = "economy=p20, standard=50,priority=P90".parse().unwrap();
+        assert_eq!(tiers.iter().collect::<Vec<_>>(), [("economy", 20.0), ("standard", 50.0), ("priority", 90.0)]);
+        assert!("".parse::<PercentileTiers>().unwrap().is_empty());
+
+        assert!("economy".parse::<PercentileTiers>().is_err());
+        assert!("=p20".parse::<PercentileTiers>().is_err());
+        assert!("economy=p101".parse::<PercentileTiers>().is_err());
+        assert!("economy=fast".parse::<PercentileTiers>().is_err());
+        assert!("economy=p20,economy=p50".parse::<PercentileTiers>().is_err());
+        assert!("economy=p20,standard=p20".parse::<PercentileTiers>().is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Gas prices agreed on by a quorum of oracles.
+//!
+//! A single oracle can lag behind the chain or misreport a spike. For fee
+//! decisions where that matters, [`QuorumGasOracle`] asks several oracles at
+//! once and only answers when at least `quorum` of them agree: every tier
+//! within the tolerance band of one another's. The answer is the median of the
+//! agreeing oracles' prices; when no large enough group agrees, it fails with
+//! [`QuorumNotReached`] rather than picking a side.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::num::NonZeroUsize;
+//! use boltzmann::domains::gas::price::{DynGasOracle, GasOracle};
+//! use boltzmann::domains::gas::price::blockscout::BlockscoutGasOracle;
+//! use boltzmann::domains::gas::price::etherscan::EtherscanGasOracle;
+//! use boltzmann::domains::gas::price::quorum::QuorumGasOracle;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let oracles: Vec<DynGasOracle> = vec![
+//!     Box::new(EtherscanGasOracle::new("api-key".to_string())?),
+//!     Box::new(BlockscoutGasOracle::new("https://eth.blockscout.com".to_string())?),
+//!     Box::new(BlockscoutGasOracle::new("https://blockscout.example".to_string())?),
+//! ];
+//! // Two of the three must agree within 10%
+//! let oracle = QuorumGasOracle::new(oracles, NonZeroUsize::new(2).unwrap())?.with_tolerance_percent(10.0)?;
+//!
+//! let gas_price = oracle.get_gas_prices().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::num::NonZeroUsize;
+
+use anyhow::bail;
+use async_trait::async_trait;
+use tracing::warn;
+
+use super::{DynGasOracle, GasOracle, GasPrice};
+use crate::core::errors::Result;
+
+/// Default relative difference, in percent, within which two oracles agree
+pub const DEFAULT_TOLERANCE_PERCENT: f64 = 5.0;
+
+/// Fewer than the quorum of oracles agreed on gas prices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuorumNotReached {
+    /// Oracles that had to agree
+    pub quorum: usize,
+    /// Size of the largest group of oracles that did agree
+    pub agreeing: usize,
+    /// Oracles that answered at all
+    pub answered: usize,
+    /// Oracles that were asked
+    pub oracles: usize,
+}
+
+impl fmt::Display for QuorumNotReached {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Gas oracles disagree: {} of {} needed to agree, but at most {} did ({} of {} answered)",
+            self.quorum, self.oracles, self.agreeing, self.answered, self.oracles
+        )
+    }
+}
+
+impl std::error::Error for QuorumNotReached {}
+
+/// Answers with gas prices only when `quorum` of its oracles agree.
+pub struct QuorumGasOracle {
+    oracles: Vec<DynGasOracle>,
+    quorum: NonZeroUsize,
+    tolerance_percent: f64,
+}
+
+impl QuorumGasOracle {
+    /// Asks all of `oracles`, requiring `quorum` of them to agree.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `quorum` is larger than the number of oracles.
+    pub fn new(oracles: Vec<DynGasOracle>, quorum: NonZeroUsize) -> Result<Self> {
+        if quorum.get() > oracles.len() {
+            bail!("invalid quorum: {} of {} gas oracles can never agree", quorum, oracles.len());
+        }
+        Ok(Self { oracles, quorum, tolerance_percent: DEFAULT_TOLERANCE_PERCENT })
+    }
+
+    /// Sets how far apart, in percent, two oracles' prices may be and still agree.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `tolerance_percent` is negative or not finite.
+    pub fn with_tolerance_percent(mut self, tolerance_percent: f64) -> Result<Self> {
+        if !tolerance_percent.is_finite() || tolerance_percent < 0.0 {
+            bail!("invalid tolerance: {}% must be a non-negative number", tolerance_percent);
+        }
+        self.tolerance_percent = tolerance_percent;
+        Ok(self)
+    }
+
+    /// Whether every tier of `price` is within the tolerance band of `reference`'s.
+    fn agrees(&self, reference: &GasPrice, price: &GasPrice) -> bool {
+        let within = |reference: f64, value: f64| (value - reference).abs() <= reference.abs() * self.tolerance_percent / 100.0;
+        within(reference.low, price.low) && within(reference.average, price.average) && within(reference.high, price.high)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl GasOracle for QuorumGasOracle {
+    type Error = anyhow::Error;
+
+    fn name(&self) -> &'static str {
+        "quorum"
+    }
+
+    /// Asks every oracle at once and returns the median of the largest agreeing group.
+    ///
+    /// Oracles that fail are left out; they count against the quorum like a
+    /// disagreeing oracle would.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`QuorumNotReached`] if fewer than `quorum` oracles agree.
+    async fn get_gas_prices(&self) -> Result<GasPrice> {
+        let results = futures::future::join_all(self.oracles.iter().map(|oracle| oracle.get_gas_prices())).await;
+        let prices: Vec<GasPrice> = self
+            .oracles
+            .iter()
+            .zip(results)
+            .filter_map(|(oracle, result)| result.inspect_err(|e| warn!("{} gas oracle failed: {:#}", oracle.name(), e)).ok())
+            .collect();
+
+        // The largest group of prices within tolerance of one of them
+        let group = prices
+            .iter()
+            .map(|reference| prices.iter().filter(|price| self.agrees(reference, price)).collect::<Vec<_>>())
+            .max_by_key(Vec::len)
+            .unwrap_or_default();
+
+        if group.len() < self.quorum.get() {
+            return Err(QuorumNotReached {
+                quorum: self.quorum.get(),
+                agreeing: group.len(),
+                answered: prices.len(),
+                oracles: self.oracles.len(),
+            }
+            .into());
+        }
+
+        Ok(GasPrice {
+            low: median(group.iter().map(|price| price.low)),
+            average: median(group.iter().map(|price| price.average)),
+            high: median(group.iter().map(|price| price.high)),
+            timestamp: group.iter().map(|price| price.timestamp).max().unwrap_or_else(chrono::Utc::now),
+            base_fee_trend: group.iter().find_map(|price| price.base_fee_trend),
+            base_fee: group
+                .iter()
+                .any(|price| price.base_fee.is_some())
+                .then(|| median(group.iter().filter_map(|price| price.base_fee))),
+            percentile_tiers: Default::default(),
+            estimated_seconds: group.iter().find_map(|price| price.estimated_seconds),
+        })
+    }
+}
+
+/// Median of a non-empty set of values
+fn median(values: impl Iterator<Item = f64>) -> f64 {
+    let mut values: Vec<f64> = values.collect();
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Oracle answering with a fixed average price, or failing when `None`
+    struct FixedOracle(Option<f64>);
+
+    #[async_trait]
+    impl GasOracle for FixedOracle {
+        type Error = anyhow::Error;
+
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        async fn get_gas_prices(&self) -> Result<GasPrice> {
+            let average = self.0.ok_or_else(|| anyhow::anyhow!("oracle unavailable"))?;
+            Ok(GasPrice {
+                low: average * 0.8,
+                average,
+                high: average * 1.2,
+                timestamp: chrono::Utc::now(),
+                base_fee_trend: None,
+                base_fee: None,
+                percentile_tiers: Default::default(),
+                estimated_seconds: None,
+            })
+        }
+    }
+
+    fn quorum(averages: &[Option<f64>], quorum: usize) -> QuorumGasOracle {
+        let oracles = averages.iter().map(|&average| Box::new(FixedOracle(average)) as DynGasOracle).collect();
+        QuorumGasOracle::new(oracles, NonZeroUsize::new(quorum).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_agreeing_oracles_return_their_median() {
+        // The outlier is outvoted; the other two are within 5% of each other
+        let oracle = quorum(&[Some(20.0), Some(20.5), Some(35.0)], 2);
+        let price = oracle.get_gas_prices().await.unwrap();
+        assert_eq!(price.average, 20.25);
+        assert!((price.low - 16.2).abs() < 1e-9);
+
+        // A failing oracle does not stop the others from reaching the quorum
+        let oracle = quorum(&[Some(20.0), None, Some(20.4)], 2);
+        assert!((oracle.get_gas_prices().await.unwrap().average - 20.2).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_divergence_beyond_tolerance_is_an_error() {
+        let oracle = quorum(&[Some(20.0), Some(25.0), Some(30.0)], 2);
+        let err = oracle.get_gas_prices().await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<QuorumNotReached>(),
+            Some(&QuorumNotReached { quorum: 2, agreeing: 1, answered: 3, oracles: 3 })
+        );
+
+        // A wider band lets the same readings agree
+        let oracle = quorum(&[Some(20.0), Some(25.0), Some(30.0)], 2).with_tolerance_percent(30.0).unwrap();
+        assert!(oracle.get_gas_prices().await.is_ok());
+
+        // Failed oracles count against the quorum
+        let oracle = quorum(&[Some(20.0), None, None], 2);
+        let err = oracle.get_gas_prices().await.unwrap_err();
+        assert_eq!(err.downcast_ref::<QuorumNotReached>().unwrap().answered, 1);
+    }
+
+    #[test]
+    fn test_impossible_settings_are_rejected() {
+        let oracles: Vec<DynGasOracle> = vec![Box::new(FixedOracle(Some(20.0)))];
+        assert!(QuorumGasOracle::new(oracles, NonZeroUsize::new(2).unwrap()).is_err());
+        assert!(quorum(&[Some(20.0)], 1).with_tolerance_percent(-1.0).is_err());
+        assert!(quorum(&[Some(20.0)], 1).with_tolerance_percent(f64::NAN).is_err());
+    }
+}
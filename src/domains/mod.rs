@@ -3,6 +3,19 @@
 //! This module contains all the core business logic organized by domain:
 //! - `crypto` - Cryptocurrency price providers and related functionality
 //! - `gas` - Gas price oracles and estimation logic
+//! - `timestamp` - How quote timestamps are serialized
 
 pub mod crypto;
-pub mod gas;
\ No newline at end of file
+pub mod gas;
+pub mod timestamp;
+
+/// Version of the response contract for quotes (`Quote`, `GasQuote`).
+///
+/// Serialized as `schema_version` on every quote. Bumped whenever a quote's
+/// shape changes in a way that breaks existing clients, so they can branch on it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Serde default for `schema_version` when reading payloads that predate the field
+pub(crate) fn current_schema_version() -> u32 {
+    SCHEMA_VERSION
+}
@@ -0,0 +1,178 @@
+//! Serialization of quote timestamps.
+//!
+//! Quote and gas price timestamps are serialized as RFC 3339 strings by
+//! default. Consumers that want numbers can switch every timestamp to Unix
+//! seconds or milliseconds with `TIMESTAMP_FORMAT`; the format is process-wide
+//! and set once at startup with [`set_format`].
+//!
+//! Fields use the module as a serde helper:
+//!
+//! ```rust
+//! use chrono::{DateTime, Utc};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Event {
+//!     #[serde(with = "boltzmann::domains::timestamp")]
+//!     timestamp: DateTime<Utc>,
+//! }
+//! ```
+//!
+//! Deserialization accepts all three formats whatever the current one is, so
+//! quotes cached under another format (e.g. in Redis before a restart) still
+//! read back.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Numbers above this are read as milliseconds: as seconds they would be past the year 5000
+const MAX_UNIX_SECONDS: i64 = 100_000_000_000;
+
+/// How timestamps are written in responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC 3339 string, e.g. `2025-10-27T15:30:00Z`
+    #[default]
+    Rfc3339,
+    /// Whole seconds since the Unix epoch
+    UnixSeconds,
+    /// Whole milliseconds since the Unix epoch
+    UnixMillis,
+}
+
+impl TimestampFormat {
+    /// Accepted format names
+    pub const NAMES: &'static [&'static str] = &["rfc3339", "unix_seconds", "unix_millis"];
+
+    /// The process-wide format, [`TimestampFormat::Rfc3339`] unless [`set_format`] changed it.
+    pub fn current() -> Self {
+        match FORMAT.load(Ordering::Relaxed) {
+            1 => TimestampFormat::UnixSeconds,
+            2 => TimestampFormat::UnixMillis,
+            _ => TimestampFormat::Rfc3339,
+        }
+    }
+
+    /// Writes `timestamp` in this format.
+    pub fn serialize<S: Serializer>(self, timestamp: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            TimestampFormat::Rfc3339 => serializer.serialize_str(&timestamp.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)),
+            TimestampFormat::UnixSeconds => serializer.serialize_i64(timestamp.timestamp()),
+            TimestampFormat::UnixMillis => serializer.serialize_i64(timestamp.timestamp_millis()),
+        }
+    }
+}
+
+impl fmt::Display for TimestampFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TimestampFormat::Rfc3339 => "rfc3339",
+            TimestampFormat::UnixSeconds => "unix_seconds",
+            TimestampFormat::UnixMillis => "unix_millis",
+        })
+    }
+}
+
+impl FromStr for TimestampFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "rfc3339" => Ok(TimestampFormat::Rfc3339),
+            "unix_seconds" => Ok(TimestampFormat::UnixSeconds),
+            "unix_millis" => Ok(TimestampFormat::UnixMillis),
+            _ => anyhow::bail!(
+                "unknown timestamp format '{}', expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            ),
+        }
+    }
+}
+
+static FORMAT: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the format every timestamp is serialized in from now on.
+pub fn set_format(format: TimestampFormat) {
+    let value = match format {
+        TimestampFormat::Rfc3339 => 0,
+        TimestampFormat::UnixSeconds => 1,
+        TimestampFormat::UnixMillis => 2,
+    };
+    FORMAT.store(value, Ordering::Relaxed);
+}
+
+/// Serializes a timestamp in the [current](TimestampFormat::current) format.
+pub fn serialize<S: Serializer>(timestamp: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    TimestampFormat::current().serialize(timestamp, serializer)
+}
+
+/// Reads a timestamp written in any [`TimestampFormat`].
+///
+/// Numbers up to 10^11 are seconds and larger ones milliseconds, which holds
+/// for every date between 1973 and the year 5000.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Number(i64),
+        Text(String),
+    }
+
+    let timestamp = match Raw::deserialize(deserializer)? {
+        Raw::Number(seconds) if seconds.abs() <= MAX_UNIX_SECONDS => DateTime::from_timestamp(seconds, 0),
+        Raw::Number(millis) => DateTime::from_timestamp_millis(millis),
+        Raw::Text(text) => {
+            return DateTime::parse_from_rfc3339(&text)
+                .map(|timestamp| timestamp.with_timezone(&Utc))
+                .map_err(serde::de::Error::custom);
+        }
+    };
+    timestamp.ok_or_else(|| serde::de::Error::custom("timestamp out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(format: TimestampFormat, timestamp: &DateTime<Utc>) -> serde_json::Value {
+        format.serialize(timestamp, serde_json::value::Serializer).unwrap()
+    }
+
+    #[test]
+    fn test_each_format_for_a_fixed_timestamp() {
+        let timestamp = DateTime::from_timestamp_millis(1_700_000_000_123).unwrap();
+        assert_eq!(write(TimestampFormat::Rfc3339, &timestamp), "2023-11-14T22:13:20.123Z");
+        assert_eq!(write(TimestampFormat::UnixSeconds, &timestamp), 1_700_000_000);
+        assert_eq!(write(TimestampFormat::UnixMillis, &timestamp), 1_700_000_000_123_i64);
+
+        // The default matches chrono's own serialization
+        assert_eq!(write(TimestampFormat::Rfc3339, &timestamp), serde_json::to_value(timestamp).unwrap());
+
+        let whole = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert_eq!(write(TimestampFormat::Rfc3339, &whole), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn test_reads_every_format_back() {
+        let timestamp = DateTime::from_timestamp_millis(1_700_000_000_123).unwrap();
+        let read = |value: serde_json::Value| deserialize(value).unwrap();
+        assert_eq!(read(write(TimestampFormat::Rfc3339, &timestamp)), timestamp);
+        assert_eq!(read(write(TimestampFormat::UnixMillis, &timestamp)), timestamp);
+        assert_eq!(read(write(TimestampFormat::UnixSeconds, &timestamp)).timestamp(), 1_700_000_000);
+        assert!(deserialize(serde_json::json!("yesterday")).is_err());
+    }
+
+    #[test]
+    fn test_format_names() {
+        for name in TimestampFormat::NAMES {
+            assert_eq!(name.parse::<TimestampFormat>().unwrap().to_string(), *name);
+        }
+        assert_eq!("UNIX_MILLIS".parse::<TimestampFormat>().unwrap(), TimestampFormat::UnixMillis);
+        assert!("iso8601".parse::<TimestampFormat>().is_err());
+    }
+}
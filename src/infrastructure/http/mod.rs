@@ -0,0 +1,543 @@
+//! Outbound HTTP client infrastructure.
+//!
+//! All provider clients are built here so network policy (proxying, redirects,
+//! connect timeouts, response size limits) is configured in one place instead of
+//! per provider.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Context;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, Response, StatusCode};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::{redirect, NoProxy, Proxy};
+use serde_json::Value;
+
+use crate::core::errors::Result;
+
+pub mod retry;
+
+use retry::{DEFAULT_MAX_RETRIES, DEFAULT_RETRY_BUDGET, DEFAULT_RETRY_BUDGET_REFILL_PER_SECOND};
+
+/// Redirects followed before a request fails
+pub const DEFAULT_MAX_REDIRECTS: usize = 3;
+
+/// Largest upstream response body read into memory (2 MiB)
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 2 * 1024 * 1024;
+
+/// Longest DNS resolution plus TCP/TLS connect before a request fails
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Settings applied to every outbound HTTP client
+#[derive(Debug, Clone)]
+pub struct HttpClientConfig {
+    /// Explicit proxy for all outbound traffic (`OUTBOUND_PROXY`).
+    ///
+    /// When unset, the standard `HTTPS_PROXY`/`HTTP_PROXY`/`NO_PROXY` environment
+    /// variables are honored instead.
+    pub proxy: Option<String>,
+    /// Maximum redirects to follow (`HTTP_MAX_REDIRECTS`, `0` disables redirects)
+    pub max_redirects: usize,
+    /// Maximum response body size in bytes (`HTTP_MAX_RESPONSE_BYTES`)
+    pub max_response_bytes: usize,
+    /// How long resolving and connecting to an upstream may take (`CONNECT_TIMEOUT_MS`),
+    /// so a stalled connection fails fast instead of holding the request
+    pub connect_timeout: Duration,
+    /// Retries per upstream request for transient failures (`HTTP_MAX_RETRIES`, `0` disables retries)
+    pub max_retries: usize,
+    /// Retries that can be spent in a burst across all requests (`HTTP_RETRY_BUDGET`)
+    pub retry_budget: usize,
+    /// Retries added back to the budget each second (`HTTP_RETRY_BUDGET_REFILL_PER_SECOND`)
+    pub retry_budget_refill_per_second: usize,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_budget: DEFAULT_RETRY_BUDGET,
+            retry_budget_refill_per_second: DEFAULT_RETRY_BUDGET_REFILL_PER_SECOND,
+        }
+    }
+}
+
+/// An upstream response body was larger than the configured limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseTooLarge {
+    /// The limit that was exceeded, in bytes
+    pub limit: usize,
+}
+
+impl std::fmt::Display for ResponseTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Upstream response body exceeds the {} byte limit", self.limit)
+    }
+}
+
+impl std::error::Error for ResponseTooLarge {}
+
+/// An upstream answered a JSON API request with something else, typically an
+/// HTML outage page or captcha from a CDN in front of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonJsonResponse {
+    /// HTTP status of the upstream response
+    pub status: StatusCode,
+    /// The declared `Content-Type`, if any
+    pub content_type: Option<String>,
+}
+
+impl std::fmt::Display for NonJsonResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Upstream returned non-JSON (status {}", self.status)?;
+        if let Some(content_type) = &self.content_type {
+            write!(f, ", content type {}", content_type)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl std::error::Error for NonJsonResponse {}
+
+impl HttpClientConfig {
+    /// Load HTTP client settings from environment variables
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `OUTBOUND_PROXY` is set but is not a valid proxy URL, if
+    /// a numeric limit cannot be parsed, or if `CONNECT_TIMEOUT_MS` is zero.
+    pub fn from_env() -> Result<Self> {
+        let proxy = std::env::var("OUTBOUND_PROXY")
+            .ok()
+            .filter(|proxy| !proxy.is_empty());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(ref url) = proxy {
+            Proxy::all(url).with_context(|| format!("Invalid OUTBOUND_PROXY URL: {}", url))?;
+        }
+
+        let max_redirects = env_usize("HTTP_MAX_REDIRECTS", DEFAULT_MAX_REDIRECTS)?;
+        let max_response_bytes = env_usize("HTTP_MAX_RESPONSE_BYTES", DEFAULT_MAX_RESPONSE_BYTES)?;
+        let connect_timeout_ms = env_usize("CONNECT_TIMEOUT_MS", DEFAULT_CONNECT_TIMEOUT.as_millis() as usize)?;
+        if connect_timeout_ms == 0 {
+            anyhow::bail!("Invalid CONNECT_TIMEOUT_MS: must be at least 1 millisecond");
+        }
+        let max_retries = env_usize("HTTP_MAX_RETRIES", DEFAULT_MAX_RETRIES)?;
+        let retry_budget = env_usize("HTTP_RETRY_BUDGET", DEFAULT_RETRY_BUDGET)?;
+        let retry_budget_refill_per_second =
+            env_usize("HTTP_RETRY_BUDGET_REFILL_PER_SECOND", DEFAULT_RETRY_BUDGET_REFILL_PER_SECOND)?;
+
+        Ok(Self {
+            proxy,
+            max_redirects,
+            max_response_bytes,
+            connect_timeout: Duration::from_millis(connect_timeout_ms as u64),
+            max_retries,
+            retry_budget,
+            retry_budget_refill_per_second,
+        })
+    }
+}
+
+fn env_usize(name: &str, default: usize) -> Result<usize> {
+    match std::env::var(name) {
+        Ok(value) if !value.is_empty() => value
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid {}: {}", name, value)),
+        _ => Ok(default),
+    }
+}
+
+/// Builds an HTTP client with the given outbound settings.
+///
+/// An explicit proxy still respects `NO_PROXY`, so internal hosts can bypass it.
+/// Redirects are capped at `max_redirects`, and a redirect from HTTPS to plain
+/// HTTP is never followed. Resolving and connecting must finish within
+/// `connect_timeout`.
+///
+/// # Errors
+///
+/// Returns an error if the proxy URL is invalid or the client cannot be created.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn build_client(config: &HttpClientConfig) -> Result<Client> {
+    let max_redirects = config.max_redirects;
+    let redirect_policy = redirect::Policy::custom(move |attempt| {
+        let downgrade = attempt.previous().iter().any(|url| url.scheme() == "https")
+            && attempt.url().scheme() != "https";
+
+        if attempt.previous().len() > max_redirects {
+            attempt.error(format!("too many redirects (limit {})", max_redirects))
+        } else if downgrade {
+            attempt.error("refusing to follow redirect from HTTPS to HTTP")
+        } else {
+            attempt.follow()
+        }
+    });
+    let mut builder = Client::builder()
+        .redirect(redirect_policy)
+        .connect_timeout(config.connect_timeout);
+
+    if let Some(ref url) = config.proxy {
+        let proxy = Proxy::all(url)
+            .with_context(|| format!("Invalid proxy URL: {}", url))?
+            .no_proxy(NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+
+    builder.build().context("building outbound HTTP client")
+}
+
+/// Builds an HTTP client backed by the browser's `fetch`.
+///
+/// The browser owns proxying, redirects and connect timeouts, so only
+/// `max_response_bytes` and the retry settings of `config` apply on wasm32.
+///
+/// # Errors
+///
+/// Returns an error if the client cannot be created.
+#[cfg(target_arch = "wasm32")]
+pub fn build_client(_config: &HttpClientConfig) -> Result<Client> {
+    Client::builder().build().context("building outbound HTTP client")
+}
+
+/// Reads a response body as text, failing once it grows past `max_bytes`.
+///
+/// The body is streamed chunk by chunk, so an oversized response is rejected
+/// without buffering it in full (on wasm32, `fetch` hands over the whole body
+/// and it is checked once read). A declared `Content-Length` over the limit is
+/// rejected before reading anything.
+///
+/// # Errors
+///
+/// Returns [`ResponseTooLarge`] if the body exceeds `max_bytes`, or an error if
+/// reading fails or the body is not valid UTF-8.
+pub async fn read_body_limited(response: Response, max_bytes: usize) -> Result<String> {
+    if response.content_length().is_some_and(|len| len > max_bytes as u64) {
+        return Err(ResponseTooLarge { limit: max_bytes }.into());
+    }
+
+    let body = read_capped(response, max_bytes).await?;
+    String::from_utf8(body).context("response body is not valid UTF-8")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_capped(mut response: Response, max_bytes: usize) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.context("reading response body")? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(ResponseTooLarge { limit: max_bytes }.into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn read_capped(response: Response, max_bytes: usize) -> Result<Vec<u8>> {
+    let body = response.bytes().await.context("reading response body")?;
+    if body.len() > max_bytes {
+        return Err(ResponseTooLarge { limit: max_bytes }.into());
+    }
+    Ok(body.to_vec())
+}
+
+/// Reads a JSON API response body like [`read_body_limited`], rejecting bodies
+/// that are plainly not JSON.
+///
+/// # Errors
+///
+/// Returns [`NonJsonResponse`] for a non-JSON body (see [`ensure_json`]), or
+/// any error from [`read_body_limited`].
+pub async fn read_json_body_limited(response: Response, max_bytes: usize) -> Result<String> {
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = read_body_limited(response, max_bytes).await?;
+    ensure_json(&body, status, content_type.as_deref())?;
+    Ok(body)
+}
+
+/// Fails with [`NonJsonResponse`] when `body` is declared as HTML or starts with `<`.
+///
+/// Outage pages and captchas are HTML, and reporting them with the upstream's
+/// status tells an outage apart from a response we fail to parse. Anything
+/// else is left for the caller's JSON parser, since some APIs serve JSON as
+/// `text/plain`.
+pub fn ensure_json(body: &str, status: StatusCode, content_type: Option<&str>) -> Result<()> {
+    let html = content_type.is_some_and(|content_type| content_type.to_ascii_lowercase().starts_with("text/html"));
+    if html || body.trim_start().starts_with('<') {
+        return Err(NonJsonResponse { status, content_type: content_type.map(str::to_string) }.into());
+    }
+    Ok(())
+}
+
+/// Keeps the most recent raw upstream response body, for debugging.
+///
+/// Disabled by default, in which case nothing is stored. Bodies that are not
+/// valid JSON are kept as a JSON string so they can still be shown.
+#[derive(Debug, Default)]
+pub struct RawCapture {
+    enabled: bool,
+    last: Mutex<Option<Value>>,
+}
+
+impl RawCapture {
+    /// Creates a capture that stores bodies only when `enabled`
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, last: Mutex::new(None) }
+    }
+
+    /// Stores `body` as the most recent response, if capturing is enabled
+    pub fn record(&self, body: &str) {
+        if !self.enabled {
+            return;
+        }
+        let value = serde_json::from_str(body).unwrap_or_else(|_| Value::String(body.to_string()));
+        *self.last.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(value);
+    }
+
+    /// Returns the most recent captured body, if any
+    pub fn last(&self) -> Option<Value> {
+        self.last.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+/// Parsed upstream responses keyed by request URL, with the ETag they were served with.
+///
+/// Providers send the stored ETag as `If-None-Match`; on `304 Not Modified` they
+/// reuse the stored value instead of downloading and parsing the body again.
+#[derive(Debug)]
+pub struct EtagCache<T> {
+    entries: Mutex<HashMap<String, (String, T)>>,
+}
+
+impl<T> Default for EtagCache<T> {
+    fn default() -> Self {
+        Self { entries: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl<T: Clone> EtagCache<T> {
+    /// ETag of the value stored for `url`, to send as `If-None-Match`
+    pub fn etag(&self, url: &str) -> Option<String> {
+        self.lock().get(url).map(|(etag, _)| etag.clone())
+    }
+
+    /// Value stored for `url`, reused when the upstream answers `304`
+    pub fn get(&self, url: &str) -> Option<T> {
+        self.lock().get(url).map(|(_, value)| value.clone())
+    }
+
+    /// Stores the value parsed from a response to `url` along with its ETag
+    pub fn store(&self, url: impl Into<String>, etag: impl Into<String>, value: T) {
+        self.lock().insert(url.into(), (etag.into(), value));
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, (String, T)>> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_html_body_is_reported_as_non_json() {
+        let page = "<!DOCTYPE html><html><head><title>Just a moment...</title></head></html>";
+        let err = ensure_json(page, StatusCode::SERVICE_UNAVAILABLE, Some("text/html; charset=UTF-8")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Upstream returned non-JSON (status 503 Service Unavailable, content type text/html; charset=UTF-8)"
+        );
+
+        // A leading `<` is enough without a content type
+        let err = ensure_json("\n <html>", StatusCode::OK, None).unwrap_err();
+        assert_eq!(err.downcast_ref(), Some(&NonJsonResponse { status: StatusCode::OK, content_type: None }));
+
+        assert!(ensure_json(r#"{"price": 1}"#, StatusCode::OK, Some("application/json")).is_ok());
+        assert!(ensure_json(r#"{"price": 1}"#, StatusCode::OK, Some("text/plain")).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout_fails_stalled_connections_fast() {
+        // A listener whose accept queue is full drops further SYNs, so connecting
+        // stalls like it would to a non-routable address, without relying on the network
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let listener = socket.listen(0).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut queued = Vec::new();
+        for _ in 0..4 {
+            if let Ok(Ok(stream)) =
+                tokio::time::timeout(Duration::from_millis(100), tokio::net::TcpStream::connect(addr)).await
+            {
+                queued.push(stream);
+            }
+        }
+
+        let config = HttpClientConfig { connect_timeout: Duration::from_millis(200), ..HttpClientConfig::default() };
+        let client = build_client(&config).unwrap();
+        let started = std::time::Instant::now();
+        let err = client.get(format!("http://{}/price", addr)).send().await.unwrap_err();
+
+        assert!(err.is_connect() || err.is_timeout(), "{:?}", err);
+        assert!(started.elapsed() < Duration::from_secs(2), "connect took {:?}", started.elapsed());
+        drop(listener);
+    }
+
+    #[tokio::test]
+    async fn test_explicit_proxy_is_applied() {
+        // A minimal proxy that records the request line and answers every request itself
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        let proxy = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).to_string();
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 7\r\nconnection: close\r\n\r\nproxied")
+                .await
+                .unwrap();
+            request
+        });
+
+        let config = HttpClientConfig {
+            proxy: Some(format!("http://{}", proxy_addr)),
+            ..HttpClientConfig::default()
+        };
+        let client = build_client(&config).unwrap();
+
+        let body = client
+            .get("http://upstream.invalid/price")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "proxied");
+
+        let request = proxy.await.unwrap();
+        assert!(request.starts_with("GET http://upstream.invalid/price HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_invalid_proxy_is_rejected() {
+        let config = HttpClientConfig {
+            proxy: Some("not a url".to_string()),
+            ..HttpClientConfig::default()
+        };
+        assert!(build_client(&config).is_err());
+    }
+
+    /// Serves each accepted connection with `response` until the task is dropped
+    async fn serve(response: Vec<u8>) -> (String, tokio::task::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket.write_all(&response).await;
+            }
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected() {
+        let body = "x".repeat(4096);
+
+        // Declared length over the cap fails up front
+        let with_length = format!(
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        // Chunked bodies have no declared length, so the streaming cap must catch them
+        let chunked = format!(
+            "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+            body.len(),
+            body
+        );
+
+        let client = build_client(&HttpClientConfig::default()).unwrap();
+        for raw in [with_length, chunked] {
+            let (url, server) = serve(raw.into_bytes()).await;
+            let response = client.get(&url).send().await.unwrap();
+            let err = read_body_limited(response, 1024).await.unwrap_err();
+            assert_eq!(err.downcast_ref::<ResponseTooLarge>(), Some(&ResponseTooLarge { limit: 1024 }));
+            server.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_is_read() {
+        let (url, server) = serve(
+            b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}".to_vec(),
+        )
+        .await;
+        let client = build_client(&HttpClientConfig::default()).unwrap();
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(read_body_limited(response, 1024).await.unwrap(), "{}");
+        server.abort();
+    }
+
+    #[test]
+    fn test_raw_capture() {
+        let disabled = RawCapture::default();
+        disabled.record(r#"{"price": 1}"#);
+        assert_eq!(disabled.last(), None);
+
+        let enabled = RawCapture::new(true);
+        enabled.record(r#"{"price": 1}"#);
+        assert_eq!(enabled.last(), Some(serde_json::json!({ "price": 1 })));
+        enabled.record("<html>Bad Gateway</html>");
+        assert_eq!(enabled.last(), Some(Value::String("<html>Bad Gateway</html>".to_string())));
+    }
+
+    #[test]
+    fn test_etag_cache() {
+        let cache = EtagCache::default();
+        assert_eq!(cache.etag("/price"), None);
+
+        cache.store("/price", "\"v1\"", 2000.5);
+        assert_eq!(cache.etag("/price").as_deref(), Some("\"v1\""));
+        assert_eq!(cache.get("/price"), Some(2000.5));
+
+        cache.store("/price", "\"v2\"", 2001.0);
+        assert_eq!(cache.get("/price"), Some(2001.0));
+        assert_eq!(cache.get("/other"), None);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_loop_is_capped() {
+        let (url, server) = serve(
+            b"HTTP/1.1 302 Found\r\nlocation: /again\r\ncontent-length: 0\r\nconnection: close\r\n\r\n".to_vec(),
+        )
+        .await;
+        let config = HttpClientConfig {
+            max_redirects: 2,
+            ..HttpClientConfig::default()
+        };
+        let client = build_client(&config).unwrap();
+
+        let err = client.get(&url).send().await.unwrap_err();
+        assert!(err.is_redirect());
+        server.abort();
+    }
+}
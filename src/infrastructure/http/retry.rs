@@ -0,0 +1,229 @@
+//! Retries for transient upstream failures, bounded by a shared budget.
+//!
+//! Retrying every failed request multiplies load on an upstream that is already
+//! struggling. All retries therefore draw from one [`RetryBudget`], a token
+//! bucket shared through `AppState`: each retry spends a token, tokens refill
+//! at a fixed rate, and once the bucket is empty failures are returned
+//! immediately instead of being retried.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::{RequestBuilder, Response, StatusCode};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::time::{sleep, Instant};
+#[cfg(target_arch = "wasm32")]
+use {gloo_timers::future::sleep, web_time::Instant};
+use tracing::{debug, warn};
+
+/// Default retries per request
+pub const DEFAULT_MAX_RETRIES: usize = 2;
+
+/// Default number of retries that can be spent in a burst
+pub const DEFAULT_RETRY_BUDGET: usize = 10;
+
+/// Default retries added back to the budget each second
+pub const DEFAULT_RETRY_BUDGET_REFILL_PER_SECOND: usize = 1;
+
+/// Delay before the first retry; doubled for each further retry
+const BASE_DELAY: Duration = Duration::from_millis(100);
+
+/// Token bucket limiting retries across all requests.
+#[derive(Debug)]
+pub struct RetryBudget {
+    capacity: f64,
+    refill_per_second: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RetryBudget {
+    /// Creates a full budget holding `capacity` retries, regaining
+    /// `refill_per_second` retries each second.
+    pub fn new(capacity: usize, refill_per_second: usize) -> Self {
+        Self {
+            capacity: capacity as f64,
+            refill_per_second: refill_per_second as f64,
+            state: Mutex::new(BucketState { tokens: capacity as f64, refilled_at: Instant::now() }),
+        }
+    }
+
+    /// Spends one retry, returning `false` if the budget is exhausted.
+    pub fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        state.refilled_at = now;
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self::new(DEFAULT_RETRY_BUDGET, DEFAULT_RETRY_BUDGET_REFILL_PER_SECOND)
+    }
+}
+
+/// How a provider retries its upstream requests.
+///
+/// The default never retries.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: usize,
+    budget: Arc<RetryBudget>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_retries: 0, budget: Arc::new(RetryBudget::new(0, 0)) }
+    }
+}
+
+impl RetryPolicy {
+    /// Retries each request up to `max_retries` times, while `budget` allows.
+    pub fn new(max_retries: usize, budget: Arc<RetryBudget>) -> Self {
+        Self { max_retries, budget }
+    }
+
+    /// Sends the request built by `request`, retrying transient failures.
+    ///
+    /// Connection errors, timeouts and `502`/`503`/`504` responses are retried
+    /// with exponential backoff. Any other response is returned as is. When the
+    /// retries or the shared budget run out, the last error or response is
+    /// returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns the last transport error if no attempt got a response.
+    pub async fn send(&self, request: impl Fn() -> RequestBuilder) -> reqwest::Result<Response> {
+        let mut retries = 0;
+        loop {
+            let result = request().send().await;
+            let transient = match &result {
+                Ok(response) => matches!(
+                    response.status(),
+                    StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+                ),
+                Err(e) => is_transient(e),
+            };
+            if !transient || !self.backoff(retries).await {
+                return result;
+            }
+            retries += 1;
+        }
+    }
+
+    /// Waits before retry number `retries + 1`, spending one retry from the budget.
+    ///
+    /// Returns `false` without waiting when this request has used all its
+    /// retries or the shared budget is empty. Providers whose upstream signals
+    /// retryable failures in ways [`send`](Self::send) cannot see (such as in the
+    /// response body) use this to back off under the same limits.
+    pub async fn backoff(&self, retries: usize) -> bool {
+        if retries >= self.max_retries {
+            return false;
+        }
+        if !self.budget.try_acquire() {
+            warn!("Retry budget exhausted, not retrying failed upstream request");
+            return false;
+        }
+
+        let delay = BASE_DELAY * 2u32.pow(retries as u32);
+        debug!("Retrying upstream request in {:?} (retry {}/{})", delay, retries + 1, self.max_retries);
+        sleep(delay).await;
+        true
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout()
+}
+
+/// `fetch` reports a refused or dropped connection as a failed request.
+#[cfg(target_arch = "wasm32")]
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_request() || error.is_timeout()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode as AxumStatus, routing::get, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serves `503` on every request, counting them
+    async fn unavailable() -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new().route(
+            "/",
+            get(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async { AxumStatus::SERVICE_UNAVAILABLE }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, hits)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_budget_refills_over_time() {
+        let budget = RetryBudget::new(2, 1);
+        assert!(budget.try_acquire());
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+
+        // Never refills past capacity
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert!(budget.try_acquire());
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+    }
+
+    #[tokio::test]
+    async fn test_no_retries_once_budget_is_drained() {
+        let (url, hits) = unavailable().await;
+        let client = reqwest::Client::new();
+        let policy = RetryPolicy::new(2, Arc::new(RetryBudget::new(3, 0)));
+
+        // Two retries, then one retry before the budget runs dry
+        let response = policy.send(|| client.get(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+        policy.send(|| client.get(&url)).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 5);
+
+        // Drained: every call is a single attempt
+        policy.send(|| client.get(&url)).await.unwrap();
+        policy.send(|| client.get(&url)).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 7);
+    }
+
+    #[tokio::test]
+    async fn test_default_policy_never_retries() {
+        let (url, hits) = unavailable().await;
+        let client = reqwest::Client::new();
+
+        RetryPolicy::default().send(|| client.get(&url)).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+}
@@ -2,18 +2,31 @@
 //!
 //! This module provides structured logging capabilities using the `tracing` ecosystem.
 //! It includes configuration for different log levels, output formats, and filtering.
+//! When `OTEL_EXPORTER_OTLP_ENDPOINT` is set, spans are also exported to an OTLP collector.
+//! Verbose provider logs can be thinned out with [`sample`].
+//!
+//! On wasm32 only the configuration types and [`sample`] are built; installing
+//! a subscriber is left to the host application.
+
+pub mod sample;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod subscriber;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use subscriber::{init_default_tracing, init_tracing, otel_layer, shutdown_tracing};
 
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, fmt};
 use std::env;
 
 /// Logging configuration levels
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum LogLevel {
     /// Only error messages
     Error,
     /// Error and warning messages  
     Warn,
     /// Error, warning, and info messages (default)
+    #[default]
     Info,
     /// All messages including debug
     Debug,
@@ -34,12 +47,6 @@ impl LogLevel {
     }
 }
 
-impl Default for LogLevel {
-    fn default() -> Self {
-        LogLevel::Info
-    }
-}
-
 /// Logging configuration options
 #[derive(Debug, Clone)]
 pub struct LogConfig {
@@ -51,6 +58,8 @@ pub struct LogConfig {
     pub with_timestamps: bool,
     /// Whether to include target information (module names)
     pub with_target: bool,
+    /// OTLP collector endpoint; spans are exported only when set
+    pub otlp_endpoint: Option<String>,
 }
 
 impl Default for LogConfig {
@@ -60,6 +69,7 @@ impl Default for LogConfig {
             json_format: false,
             with_timestamps: true,
             with_target: true,
+            otlp_endpoint: None,
         }
     }
 }
@@ -80,69 +90,16 @@ impl LogConfig {
             .unwrap_or_default()
             .to_lowercase() == "json";
 
+        let otlp_endpoint = env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .ok()
+            .filter(|endpoint| !endpoint.is_empty());
+
         Self {
             level,
             json_format,
             with_timestamps: true,
             with_target: true,
+            otlp_endpoint,
         }
     }
 }
-
-/// Initialize the global tracing subscriber with the given configuration.
-/// 
-/// This should be called once at application startup, before any logging occurs.
-/// 
-/// # Arguments
-/// 
-/// * `config` - The logging configuration to use
-/// 
-/// # Examples
-/// 
-/// ```rust
-/// use boltzmann::infrastructure::logging::{init_tracing, LogConfig};
-/// 
-/// // Initialize with default configuration
-/// init_tracing(LogConfig::default()).expect("Failed to initialize logging");
-/// 
-/// // Initialize from environment
-/// init_tracing(LogConfig::from_env()).expect("Failed to initialize logging");
-/// ```
-pub fn init_tracing(config: LogConfig) -> Result<(), Box<dyn std::error::Error>> {
-    // Create the base EnvFilter
-    let env_filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new(config.level.as_filter()));
-
-    if config.json_format {
-        // JSON format for production/structured logging
-        tracing_subscriber::registry()
-            .with(env_filter)
-            .with(
-                fmt::layer()
-                    .json()
-                    .with_target(config.with_target)
-            )
-            .try_init()?;
-    } else {
-        // Human-readable format for development
-        tracing_subscriber::registry()
-            .with(env_filter)
-            .with(
-                fmt::layer()
-                    .with_target(config.with_target)
-            )
-            .try_init()?;
-    }
-
-    Ok(())
-}
-
-/// Initialize tracing with sensible defaults.
-/// 
-/// This is a convenience function that initializes tracing with:
-/// - Log level from RUST_LOG environment variable (default: info)
-/// - Human-readable format for development
-/// - Timestamps and target information enabled
-pub fn init_default_tracing() -> Result<(), Box<dyn std::error::Error>> {
-    init_tracing(LogConfig::from_env())
-}
\ No newline at end of file
@@ -0,0 +1,81 @@
+//! Sampling of verbose provider logs.
+//!
+//! Providers can log the full detail of every upstream call (request URL, raw
+//! body, parsed values), which floods the logs under load. With
+//! `PROVIDER_LOG_SAMPLE=N` only one call in `N` logs that detail; warnings and
+//! errors are logged on every call regardless. The rate is process-wide and
+//! set once at startup with [`set_provider_rate`].
+//!
+//! A provider decides once per call whether it is sampled and gates all of
+//! its detail on that decision, so a sampled call logs its whole story:
+//!
+//! ```rust
+//! use boltzmann::infrastructure::logging::sample;
+//!
+//! let verbose = sample::provider_call();
+//! if verbose {
+//!     tracing::debug!(url = "https://api.example.com", "Requesting prices");
+//! }
+//! ```
+
+use std::num::NonZeroU64;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Picks one call in every `rate` for verbose logging.
+#[derive(Debug)]
+pub struct LogSampler {
+    rate: AtomicU64,
+    calls: AtomicU64,
+}
+
+impl LogSampler {
+    /// Creates a sampler logging one call in every `rate`.
+    pub const fn new(rate: NonZeroU64) -> Self {
+        Self {
+            rate: AtomicU64::new(rate.get()),
+            calls: AtomicU64::new(0),
+        }
+    }
+
+    /// Changes how many calls share one verbose log.
+    pub fn set_rate(&self, rate: NonZeroU64) {
+        self.rate.store(rate.get(), Ordering::Relaxed);
+    }
+
+    /// Counts a call and returns whether it should log its full detail.
+    ///
+    /// The first call is always sampled, then every `rate`th one after it.
+    pub fn sample(&self) -> bool {
+        let rate = self.rate.load(Ordering::Relaxed).max(1);
+        self.calls.fetch_add(1, Ordering::Relaxed).is_multiple_of(rate)
+    }
+}
+
+/// Every provider call is logged in full unless `PROVIDER_LOG_SAMPLE` says otherwise
+static PROVIDER_CALLS: LogSampler = LogSampler::new(NonZeroU64::MIN);
+
+/// Sets how many provider calls share one verbose log from now on.
+pub fn set_provider_rate(rate: NonZeroU64) {
+    PROVIDER_CALLS.set_rate(rate);
+}
+
+/// Counts a provider call and returns whether it should log its full detail.
+pub fn provider_call() -> bool {
+    PROVIDER_CALLS.sample()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_in_n_calls_is_sampled() {
+        let sampler = LogSampler::new(NonZeroU64::new(4).unwrap());
+        let sampled = (0..1000).filter(|_| sampler.sample()).count();
+        assert_eq!(sampled, 250);
+
+        // Every call is sampled at a rate of one
+        sampler.set_rate(NonZeroU64::MIN);
+        assert!((0..10).all(|_| sampler.sample()));
+    }
+}
@@ -0,0 +1,138 @@
+//! Global subscriber setup: formatted output and optional OTLP export.
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::{layer::SubscriberExt, registry::LookupSpan, util::SubscriberInitExt, EnvFilter, Layer, fmt};
+use std::sync::OnceLock;
+
+use super::LogConfig;
+
+/// Tracer provider kept alive for the lifetime of the process so spans can be flushed on shutdown
+static TRACER_PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// Initialize the global tracing subscriber with the given configuration.
+/// 
+/// This should be called once at application startup, before any logging occurs.
+/// 
+/// # Arguments
+/// 
+/// * `config` - The logging configuration to use
+/// 
+/// # Examples
+/// 
+/// ```rust,no_run
+/// use boltzmann::infrastructure::logging::{init_tracing, LogConfig};
+/// 
+/// // Initialize with default configuration
+/// init_tracing(LogConfig::default()).expect("Failed to initialize logging");
+/// 
+/// // Initialize from environment
+/// init_tracing(LogConfig::from_env()).expect("Failed to initialize logging");
+/// ```
+pub fn init_tracing(config: LogConfig) -> Result<(), Box<dyn std::error::Error>> {
+    // Create the base EnvFilter
+    let env_filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(config.level.as_filter()));
+
+    let fmt_layer = match (config.json_format, config.with_timestamps) {
+        // JSON format for production/structured logging
+        (true, true) => fmt::layer().json().with_target(config.with_target).boxed(),
+        (true, false) => fmt::layer().json().without_time().with_target(config.with_target).boxed(),
+        // Human-readable format for development
+        (false, true) => fmt::layer().with_target(config.with_target).boxed(),
+        (false, false) => fmt::layer().without_time().with_target(config.with_target).boxed(),
+    };
+
+    // OpenTelemetry export alongside the fmt layer, only when a collector is configured
+    let otel = match config.otlp_endpoint {
+        Some(_) => {
+            let provider = otlp_tracer_provider()?;
+            let layer = otel_layer(&provider);
+            opentelemetry::global::set_tracer_provider(provider.clone());
+            let _ = TRACER_PROVIDER.set(provider);
+            Some(layer)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(fmt_layer)
+        .with(otel)
+        .try_init()?;
+
+    Ok(())
+}
+
+/// Initialize tracing with sensible defaults.
+/// 
+/// This is a convenience function that initializes tracing with:
+/// - Log level from RUST_LOG environment variable (default: info)
+/// - Human-readable format for development
+/// - Timestamps and target information enabled
+pub fn init_default_tracing() -> Result<(), Box<dyn std::error::Error>> {
+    init_tracing(LogConfig::from_env())
+}
+
+/// Creates a tracer provider that batches spans to the OTLP collector.
+///
+/// The exporter reads `OTEL_EXPORTER_OTLP_ENDPOINT` (and the other standard
+/// `OTEL_EXPORTER_OTLP_*` variables) itself and posts to its `/v1/traces` path.
+fn otlp_tracer_provider() -> Result<SdkTracerProvider, Box<dyn std::error::Error>> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("boltzmann").build())
+        .build())
+}
+
+/// Builds a tracing layer that exports spans through the given tracer provider.
+pub fn otel_layer<S>(provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, SdkTracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer("boltzmann"))
+}
+
+/// Flushes and shuts down the OpenTelemetry exporter, if one was initialized.
+///
+/// Call this once before the process exits so batched spans are not lost.
+pub fn shutdown_tracing() {
+    if let Some(provider) = TRACER_PROVIDER.get()
+        && let Err(e) = provider.shutdown()
+    {
+        eprintln!("Failed to shut down OpenTelemetry exporter: {}", e);
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use opentelemetry_sdk::trace::InMemorySpanExporter;
+    use tracing_subscriber::Registry;
+
+    #[test]
+    fn test_handler_span_is_exported() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = Registry::default().with(otel_layer(&provider));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+            let app_state = crate::core::config::AppState::new(crate::core::config::Config::default());
+            let _ = runtime.block_on(crate::api::routes::health::health_check(axum::extract::State(app_state)));
+        });
+        provider.force_flush().unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        assert!(spans.iter().any(|span| span.name == "health_check"));
+    }
+}
@@ -2,5 +2,10 @@
 //!
 //! This module contains infrastructure-level components that support the application:
 //! - `logging` - Structured logging and tracing configuration
+//! - `http` - Outbound HTTP client construction
+//! - `redis` - Redis-backed quote cache (requires the `redis` feature)
 
-pub mod logging;
\ No newline at end of file
+pub mod http;
+pub mod logging;
+#[cfg(feature = "redis")]
+pub mod redis;
\ No newline at end of file
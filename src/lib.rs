@@ -1,4 +1,5 @@
 pub mod domains;
+#[cfg(feature = "server")]
 pub mod api;
 pub mod core;
 pub mod infrastructure;
@@ -8,15 +8,16 @@
 //! The server follows modular Axum patterns with centralized configuration
 //! and comprehensive error handling for production use.
 
-mod domains;
-mod api;
-mod core;
-mod infrastructure;
+use boltzmann::core;
 
 #[cfg(not(target_arch = "wasm32"))]
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    Ok(core::server::start().await?)
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // The runtime is built before configuration loads, so read .env first;
+    // Config::from_env will not read it again
+    core::config::load_dotenv();
+
+    let runtime = core::runtime::build_runtime(core::runtime::worker_threads_from_env()?)?;
+    Ok(runtime.block_on(core::server::start())?)
 }
 
 #[cfg(target_arch = "wasm32")]
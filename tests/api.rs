@@ -0,0 +1,1040 @@
+//! End-to-end tests of the HTTP layer.
+//!
+//! Each test builds the full router with upstream base URLs pointed at a local
+//! mock server, sends requests through it, and checks status codes and body shapes.
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use boltzmann::api::routes::create_router;
+use boltzmann::core::config::{AppState, Config};
+use boltzmann::domains::crypto::aggregate::AggregateStrategy;
+use boltzmann::domains::crypto::{Coin, Currency, ProviderSource, Quote};
+use boltzmann::domains::SCHEMA_VERSION;
+use serde_json::{json, Value};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+/// Serves `app` on a random local port and returns its base URL.
+async fn mock_upstream(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+/// Sends a GET request through the full router and returns status and JSON body.
+///
+/// Bodies that are not JSON come back as `null`.
+async fn get_json(config: Config, uri: &str) -> (StatusCode, Value) {
+    get_json_from(create_router(AppState::new(config)), uri).await
+}
+
+/// Like [`get_json`], but through an existing router so state is shared across requests.
+async fn get_json_from(router: Router, uri: &str) -> (StatusCode, Value) {
+    let response = router
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+/// Sends a GET request through a fresh router and returns the body text exactly as sent.
+async fn get_text(config: Config, uri: &str) -> String {
+    let response = create_router(AppState::new(config))
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+/// Sends a POST request with a JSON body through a fresh router and returns status and JSON body.
+async fn post_json(config: Config, uri: &str, body: Value) -> (StatusCode, Value) {
+    let request = Request::post(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = create_router(AppState::new(config)).oneshot(request).await.unwrap();
+
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+fn coingecko_price(price: f64) -> Router {
+    Router::new().route(
+        "/simple/price",
+        get(move || async move { Json(json!({ "ethereum": { "usd": price, "last_updated_at": 1700000000 } })) }),
+    )
+}
+
+fn coinmarketcap_price(price: f64) -> Router {
+    Router::new().route(
+        "/v2/cryptocurrency/quotes/latest",
+        get(move || async move { Json(json!({ "data": { "1027": { "quote": { "USD": { "price": price } } } } })) }),
+    )
+}
+
+fn binance_price(price: &'static str) -> Router {
+    Router::new().route(
+        "/api/v3/ticker/bookTicker",
+        get(move || async move { Json(json!({ "symbol": "ETHUSDT", "bidPrice": price, "askPrice": price })) }),
+    )
+}
+
+fn failing(path: &'static str, status: StatusCode) -> Router {
+    Router::new().route(path, get(move || async move { (status, "upstream unavailable").into_response() }))
+}
+
+#[tokio::test]
+async fn crypto_prices_happy_path() {
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.5)).await,
+        ..Config::default()
+    };
+
+    let (status, body) = get_json(config, "/api/v1/crypto/prices?amount=2").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let quotes = body["quotes"].as_array().unwrap();
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes[0]["schema_version"], SCHEMA_VERSION);
+    assert_eq!(quotes[0]["provider"], "coingecko");
+    assert_eq!(quotes[0]["coin"], "ETH");
+    assert_eq!(quotes[0]["currency"], "USD");
+    assert_eq!(quotes[0]["currency_numeric"], 840);
+    // CoinGecko is an index, not an exchange
+    assert!(quotes[0]["market"].is_null());
+    assert_eq!(quotes[0]["price"], 2000.5);
+    assert_eq!(quotes[0]["quote_per_amount"]["total_price"], 4001.0);
+    assert!(body["meta"]["attribution"].is_string());
+}
+
+#[tokio::test]
+async fn crypto_prices_report_cache_hits() {
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.5)).await,
+        ..Config::default()
+    };
+    let router = create_router(AppState::new(config));
+
+    let (status, body) = get_json_from(router.clone(), "/api/v1/crypto/prices").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"][0]["cached"], false);
+    assert_eq!(body["quotes"][0]["age_seconds"], 0);
+
+    let (status, body) = get_json_from(router, "/api/v1/crypto/prices?amount=2").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"][0]["cached"], true);
+    assert_eq!(body["quotes"][0]["age_seconds"], 0);
+    assert_eq!(body["quotes"][0]["quote_per_amount"]["total_price"], 4001.0);
+}
+
+/// Flushes the quote cache through `router`, with `key` as the bearer token if given.
+async fn flush_cache(router: Router, uri: &str, key: Option<&str>) -> (StatusCode, Value) {
+    let mut request = Request::post(uri);
+    if let Some(key) = key {
+        request = request.header("authorization", format!("Bearer {}", key));
+    }
+    let response = router.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn admin_cache_flush_makes_the_next_read_miss() {
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.5)).await,
+        admin_api_key: Some("secret".to_string()),
+        ..Config::default()
+    };
+    let router = create_router(AppState::new(config));
+
+    get_json_from(router.clone(), "/api/v1/crypto/prices").await;
+    let (_, body) = get_json_from(router.clone(), "/api/v1/crypto/prices").await;
+    assert_eq!(body["quotes"][0]["cached"], true);
+
+    // Without the key, or with the wrong one, nothing is flushed
+    let (status, _) = flush_cache(router.clone(), "/api/v1/admin/cache/flush", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = flush_cache(router.clone(), "/api/v1/admin/cache/flush", Some("guess")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Flushing another provider leaves CoinGecko's quote cached
+    let (status, body) = flush_cache(router.clone(), "/api/v1/admin/cache/flush?provider=binance", Some("secret")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "flushed": 0, "provider": "binance" }));
+
+    let (status, body) = flush_cache(router.clone(), "/api/v1/admin/cache/flush", Some("secret")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "flushed": 1 }));
+
+    let (_, body) = get_json_from(router, "/api/v1/crypto/prices").await;
+    assert_eq!(body["quotes"][0]["cached"], false);
+}
+
+#[tokio::test]
+async fn admin_cache_flush_is_disabled_without_a_key() {
+    let (status, _) = flush_cache(create_router(AppState::new(Config::default())), "/api/v1/admin/cache/flush", Some("secret")).await;
+    assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+}
+
+#[tokio::test]
+async fn crypto_prices_fall_back_when_rate_limited() {
+    let config = Config {
+        coingecko_base_url: mock_upstream(failing("/simple/price", StatusCode::TOO_MANY_REQUESTS)).await,
+        coinmarketcap_api_key: Some("test-key".to_string()),
+        coinmarketcap_base_url: mock_upstream(coinmarketcap_price(2001.0)).await,
+        ..Config::default()
+    };
+
+    let (status, body) = get_json(config, "/api/v1/crypto/prices").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let quotes = body["quotes"].as_array().unwrap();
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes[0]["provider"], "coinmarketcap");
+    assert_eq!(quotes[0]["price"], 2001.0);
+    assert!(body["meta"].get("attribution").is_none());
+}
+
+#[tokio::test]
+async fn crypto_prices_fail_when_every_provider_fails() {
+    let config = Config {
+        coingecko_base_url: mock_upstream(failing("/simple/price", StatusCode::TOO_MANY_REQUESTS)).await,
+        coinmarketcap_api_key: Some("test-key".to_string()),
+        coinmarketcap_base_url: mock_upstream(failing(
+            "/v2/cryptocurrency/quotes/latest",
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ))
+        .await,
+        ..Config::default()
+    };
+
+    let (status, body) = get_json(config, "/api/v1/crypto/prices").await;
+
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["error"]["status"], 500);
+    assert!(body["error"]["details"].as_str().unwrap().contains("No quotes available"));
+}
+
+#[tokio::test]
+async fn crypto_prices_cache_fetch_currencies_from_one_call() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let coingecko = Router::new().route(
+        "/simple/price",
+        get(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Json(json!({ "ethereum": { "usd": 2000.0, "eur": 1850.0, "last_updated_at": 1700000000 } })) }
+        }),
+    );
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko).await,
+        cache_fetch_currencies: vec![Currency::USD, Currency::EUR],
+        ..Config::default()
+    };
+    let router = create_router(AppState::new(config));
+
+    let (status, body) = get_json_from(router.clone(), "/api/v1/crypto/prices?currency=USD").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"].as_array().unwrap().len(), 1);
+
+    // EUR came with the USD call, so it is served without another
+    let (status, body) = get_json_from(router, "/api/v1/crypto/prices?currency=EUR").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"][0]["currency"], "EUR");
+    assert_eq!(body["quotes"][0]["cached"], true);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn crypto_prices_retry_strict_fetch_currencies_with_only_the_requested_ones() {
+    // CoinGecko has no JPY price, which fails the widened call in strict mode
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let coingecko = Router::new().route(
+        "/simple/price",
+        get(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Json(json!({ "ethereum": { "usd": 2000.0, "eur": 1850.0, "last_updated_at": 1700000000 } })) }
+        }),
+    );
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko).await,
+        strict_currencies: true,
+        cache_fetch_currencies: vec![Currency::USD, Currency::JPY],
+        ..Config::default()
+    };
+
+    let (status, body) = get_json(config, "/api/v1/crypto/prices?currency=USD").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"][0]["currency"], "USD");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn crypto_prices_for_all_currencies() {
+    let coingecko = Router::new().route(
+        "/simple/price",
+        get(|| async {
+            Json(json!({
+                "ethereum": {
+                    "usd": 2000.0, "eur": 1850.0, "chf": 1780.0, "cny": 14400.0,
+                    "gbp": 1590.0, "jpy": 300000.0, "cad": 2750.0, "aud": 3050.0,
+                    "last_updated_at": 1700000000
+                }
+            }))
+        }),
+    );
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko).await,
+        ..Config::default()
+    };
+
+    let (status, body) = get_json(config, "/api/v1/crypto/prices?currency=all").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let quotes = body["quotes"].as_array().unwrap();
+    assert_eq!(quotes.len(), 8);
+    let currencies: Vec<&str> = quotes.iter().map(|q| q["currency"].as_str().unwrap()).collect();
+    assert_eq!(currencies, ["USD", "EUR", "CHF", "CNY", "GBP", "JPY", "CAD", "AUD"]);
+}
+
+#[tokio::test]
+async fn crypto_prices_aggregate_only_trusted_providers() {
+    // CoinMarketCap as a primary, far off CoinGecko's price
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.0)).await,
+        coinmarketcap_api_key: Some("test-key".to_string()),
+        coinmarketcap_base_url: mock_upstream(coinmarketcap_price(2600.0)).await,
+        fallback_providers: vec![],
+        ..Config::default()
+    };
+
+    let (status, body) = get_json(config.clone(), "/api/v1/crypto/prices?amount=2").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"].as_array().unwrap().len(), 2);
+    assert_eq!(body["aggregates"][0]["price"], 2300.0);
+    assert_eq!(body["aggregates"][0]["total_price"], 4600.0);
+
+    let trusted = Config {
+        aggregate_providers: Some(vec![ProviderSource::CoinGecko]),
+        aggregate_min_providers: NonZeroUsize::MIN,
+        ..config
+    };
+    let (status, body) = get_json(trusted, "/api/v1/crypto/prices?amount=2").await;
+    assert_eq!(status, StatusCode::OK);
+    // CoinMarketCap's quote is still returned, but left out of the aggregate
+    assert_eq!(body["quotes"].as_array().unwrap().len(), 2);
+    let aggregates = body["aggregates"].as_array().unwrap();
+    assert_eq!(aggregates.len(), 1);
+    assert_eq!(aggregates[0]["currency"], "USD");
+    assert_eq!(aggregates[0]["price"], 2000.0);
+    assert_eq!(aggregates[0]["total_price"], 4000.0);
+    assert_eq!(aggregates[0]["providers"], json!(["coingecko"]));
+}
+
+#[tokio::test]
+async fn crypto_prices_aggregate_needs_min_providers() {
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.0)).await,
+        coinmarketcap_api_key: Some("test-key".to_string()),
+        coinmarketcap_base_url: mock_upstream(coinmarketcap_price(2600.0)).await,
+        fallback_providers: vec![],
+        ..Config::default()
+    };
+
+    // Two providers answered: the default minimum of 2 is met
+    let (status, body) = get_json(config.clone(), "/api/v1/crypto/prices").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["aggregates"][0]["price"], 2300.0);
+    assert!(body["meta"].get("warnings").is_none());
+
+    // Only CoinGecko answered: its quote is listed, but there is no aggregate
+    let one_down = Config {
+        coinmarketcap_base_url: mock_upstream(failing("/v2/cryptocurrency/quotes/latest", StatusCode::BAD_GATEWAY)).await,
+        ..config
+    };
+    let (status, body) = get_json(one_down, "/api/v1/crypto/prices").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"].as_array().unwrap().len(), 1);
+    assert_eq!(body["aggregates"], json!([]));
+    assert_eq!(
+        body["meta"]["warnings"],
+        json!(["No ETH/USD aggregate: 1 of the required 2 trusted providers answered"])
+    );
+}
+
+#[tokio::test]
+async fn crypto_prices_report_weights_and_weigh_the_aggregate() {
+    let mut config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.0)).await,
+        coinmarketcap_api_key: Some("test-key".to_string()),
+        coinmarketcap_base_url: mock_upstream(coinmarketcap_price(2600.0)).await,
+        fallback_providers: vec![],
+        aggregate_strategy: AggregateStrategy::Weighted,
+        ..Config::default()
+    };
+    config.provider_weights.insert(ProviderSource::CoinMarketCap, 0.2);
+
+    let (status, body) = get_json(config, "/api/v1/crypto/prices").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let weight_of = |provider: &str| {
+        body["quotes"].as_array().unwrap().iter().find(|quote| quote["provider"] == provider).unwrap()["weight"].clone()
+    };
+    assert_eq!(weight_of("coinmarketcap"), 0.2);
+    assert_eq!(weight_of("coingecko"), 1.0);
+    // (2000 * 1 + 2600 * 0.2) / 1.2
+    assert_eq!(body["aggregates"][0]["strategy"], "weighted");
+    assert!((body["aggregates"][0]["price"].as_f64().unwrap() - 2100.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn crypto_prices_at_a_past_time_come_from_history() {
+    let history = Router::new().route(
+        "/coins/ethereum/market_chart/range",
+        get(|| async {
+            Json(json!({ "prices": [[1705319700000_i64, 2510.0], [1705320000000_i64, 2520.5], [1705320300000_i64, 2530.0]] }))
+        }),
+    );
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.5).merge(history)).await,
+        ..Config::default()
+    };
+    let router = create_router(AppState::new(config));
+
+    // Without `at` the live price is served
+    let (status, body) = get_json_from(router.clone(), "/api/v1/crypto/prices").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"][0]["price"], 2000.5);
+
+    // 13:00+01:00 is 12:00 UTC, the middle sample
+    let (status, body) =
+        get_json_from(router.clone(), "/api/v1/crypto/prices?amount=2&at=2024-01-15T13:00:00%2B01:00").await;
+    assert_eq!(status, StatusCode::OK);
+    let quote = &body["quotes"][0];
+    assert_eq!(quote["provider"], "coingecko");
+    assert_eq!(quote["price"], 2520.5);
+    assert_eq!(quote["timestamp"], "2024-01-15T12:00:00Z");
+    assert_eq!(quote["quote_per_amount"]["total_price"], 5041.0);
+    // One provider is too few for an aggregate
+    assert_eq!(body["aggregates"], json!([]));
+
+    // History is never served from or stored in the quote cache
+    let (_, body) = get_json_from(router, "/api/v1/crypto/prices").await;
+    assert_eq!(body["quotes"][0]["price"], 2000.5);
+}
+
+#[tokio::test]
+async fn crypto_prices_at_a_future_time_are_bad_requests() {
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.5)).await,
+        ..Config::default()
+    };
+    let at = (chrono::Utc::now() + chrono::Duration::hours(1)).format("%Y-%m-%dT%H:%M:%SZ");
+
+    let (status, body) = get_json(config, &format!("/api/v1/crypto/prices?at={}", at)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"]["details"].as_str().unwrap().contains("is in the future"));
+}
+
+#[tokio::test]
+async fn crypto_market_data_from_coingecko() {
+    let coingecko = Router::new().route(
+        "/coins/ethereum",
+        get(|| async {
+            Json(json!({
+                "id": "ethereum",
+                "market_data": {
+                    "current_price": { "usd": 2000.5 },
+                    "market_cap": { "usd": 240500000000.0 },
+                    "total_volume": { "usd": 15300000000.0 },
+                    "circulating_supply": 120200000.5,
+                    "price_change_percentage_24h": null
+                },
+                "last_updated": "2023-11-14T22:13:20.000Z"
+            }))
+        }),
+    );
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko).await,
+        ..Config::default()
+    };
+
+    let (status, body) = get_json(config, "/api/v1/crypto/market?coin=ETH&currency=USD").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["provider"], "coingecko");
+    assert_eq!(body["price"], 2000.5);
+    assert_eq!(body["market_cap"], 240500000000.0);
+    assert_eq!(body["total_volume_24h"], 15300000000.0);
+    assert!(body["price_change_percentage_24h"].is_null());
+}
+
+#[tokio::test]
+async fn crypto_spread_across_three_providers() {
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.0)).await,
+        coinmarketcap_api_key: Some("test-key".to_string()),
+        coinmarketcap_base_url: mock_upstream(coinmarketcap_price(2010.0)).await,
+        binance_base_url: mock_upstream(binance_price("1990.00")).await,
+        ..Config::default()
+    };
+
+    let (status, body) = get_json(config.clone(), "/api/v1/crypto/spread?currency=USD&threshold=1.5").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let quotes = body["quotes"].as_array().unwrap();
+    assert_eq!(quotes.len(), 3);
+    for quote in quotes {
+        let expected_market = if quote["provider"] == "binance" { json!("ETHUSDT") } else { Value::Null };
+        assert_eq!(quote["market"], expected_market, "{}", quote["provider"]);
+    }
+    assert_eq!(body["min"], 1990.0);
+    assert_eq!(body["max"], 2010.0);
+    assert_eq!(body["mean"], 2000.0);
+    assert!((body["spread_percent"].as_f64().unwrap() - 1.0).abs() < 1e-9);
+    assert_eq!(body["exceeds_threshold"], false);
+
+    // Same prices, tighter threshold
+    let (status, body) = get_json(config, "/api/v1/crypto/spread?threshold=0.5").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["threshold_percent"], 0.5);
+    assert_eq!(body["exceeds_threshold"], true);
+}
+
+#[tokio::test]
+async fn crypto_spread_rejects_negative_threshold() {
+    let (status, body) = get_json(Config::default(), "/api/v1/crypto/spread?threshold=-1").await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["status"], 400);
+    assert_eq!(
+        body["error"]["details"],
+        "Invalid query parameters: threshold: must be a non-negative percentage, got -1"
+    );
+}
+
+#[tokio::test]
+async fn crypto_prices_reject_invalid_params() {
+    let (status, body) = get_json(Config::default(), "/api/v1/crypto/prices?currency=XYZ").await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["status"], 400);
+    assert!(body["error"]["message"].is_string());
+    assert!(body["error"]["details"].as_str().unwrap().contains("invalid currency 'XYZ'"));
+
+    let (status, body) = get_json(Config::default(), "/api/v1/crypto/prices?amount=lots").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"]["details"].as_str().unwrap().contains("amount"));
+}
+
+#[tokio::test]
+async fn crypto_prices_report_unpriced_currencies() {
+    let coingecko = || {
+        Router::new().route(
+            "/simple/price",
+            get(|| async { Json(json!({ "ethereum": { "usd": 2000.0, "eur": 1850.0, "last_updated_at": 1700000000 } })) }),
+        )
+    };
+    let uri = "/api/v1/crypto/prices?currencies=USD,JPY,EUR";
+
+    let lenient = Config {
+        coingecko_base_url: mock_upstream(coingecko()).await,
+        ..Config::default()
+    };
+    let (status, body) = get_json(lenient, uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"].as_array().unwrap().len(), 2);
+    assert_eq!(body["meta"]["missing_currencies"], json!(["JPY"]));
+
+    let strict = Config {
+        coingecko_base_url: mock_upstream(coingecko()).await,
+        strict_currencies: true,
+        ..Config::default()
+    };
+    let (status, body) = get_json(strict, uri).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body["error"]["details"].as_str().unwrap().contains("No quotes available"));
+}
+
+#[tokio::test]
+async fn crypto_prices_reject_amounts_with_unrepresentable_totals() {
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.5)).await,
+        ..Config::default()
+    };
+    let router = create_router(AppState::new(config));
+
+    let (status, body) = get_json_from(router.clone(), &format!("/api/v1/crypto/prices?amount={}", usize::MAX)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let details = body["error"]["details"].as_str().unwrap();
+    assert!(details.contains("above the maximum"), "{}", details);
+    assert!(!body.to_string().contains("inf"));
+
+    // Large but sane amounts are still priced
+    let (status, body) = get_json_from(router, "/api/v1/crypto/prices?amount=1000000").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"][0]["quote_per_amount"]["total_price"], 2000500000.0);
+}
+
+#[tokio::test]
+async fn crypto_prices_reject_disallowed_currencies() {
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.5)).await,
+        allowed_coins: Some(vec![Coin::ETH]),
+        allowed_currencies: Some(vec![Currency::USD, Currency::EUR]),
+        ..Config::default()
+    };
+    let router = create_router(AppState::new(config));
+
+    let (status, body) = get_json_from(router.clone(), "/api/v1/crypto/prices?currency=GBP").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"]["details"].as_str().unwrap().contains("currency GBP is not available"));
+
+    let (status, _) = get_json_from(router.clone(), "/api/v1/crypto/market?coin=AVAX").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = get_json_from(router, "/api/v1/crypto/prices?currency=USD").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"][0]["price"], 2000.5);
+}
+
+#[tokio::test]
+async fn crypto_prices_with_no_resolvable_currency_are_bad_requests() {
+    // `currency=all` expands to ALL_CURRENCIES, none of which is allowed
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.5)).await,
+        all_currencies: vec![Currency::GBP, Currency::JPY],
+        allowed_currencies: Some(vec![Currency::USD]),
+        ..Config::default()
+    };
+
+    let (status, body) = get_json(config, "/api/v1/crypto/prices?currency=all").await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["status"], 400);
+    assert!(body["error"]["details"].as_str().unwrap().contains("No currencies requested"));
+}
+
+#[tokio::test]
+async fn gas_prices_happy_path() {
+    let etherscan = Router::new().route(
+        "/",
+        get(|| async {
+            Json(json!({
+                "status": "1",
+                "message": "OK",
+                "result": {
+                    "LastBlock": "19000000",
+                    "SafeGasPrice": "30",
+                    "ProposeGasPrice": "31.5",
+                    "FastGasPrice": "35",
+                    "suggestBaseFee": "29.1",
+                    "gasUsedRatio": "0.5,0.6"
+                }
+            }))
+        }),
+    );
+    let config = Config {
+        etherscan_api_key: Some("test-key".to_string()),
+        etherscan_base_url: mock_upstream(etherscan).await,
+        ..Config::default()
+    };
+
+    let router = create_router(AppState::new(config));
+    let (status, body) = get_json_from(router.clone(), "/api/v1/gas/prices?provider=etherscan").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["schema_version"], SCHEMA_VERSION);
+    assert_eq!(body["provider"], "etherscan");
+    assert_eq!(body["gas_price"]["low"], 30);
+    assert_eq!(body["gas_price"]["average"], 31.5);
+    assert_eq!(body["gas_price"]["high"], 35);
+    assert!(body["gas_price"]["timestamp"].is_string());
+
+    let (status, body) = get_json_from(router, "/api/v1/gas/prices?provider=etherscan&flat=true").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["provider"], "etherscan");
+    assert_eq!(body["low"], 30);
+    assert_eq!(body["high"], 35);
+    assert!(body["timestamp"].is_string());
+    assert!(body.get("gas_price").is_none());
+    assert!(body.get("usd").is_none());
+}
+
+#[tokio::test]
+async fn gas_prices_include_usd_only_when_requested() {
+    let etherscan = Router::new().route(
+        "/",
+        get(|| async {
+            Json(json!({
+                "status": "1",
+                "message": "OK",
+                "result": {
+                    "LastBlock": "19000000",
+                    "SafeGasPrice": "10",
+                    "ProposeGasPrice": "20",
+                    "FastGasPrice": "40",
+                    "suggestBaseFee": "9.5",
+                    "gasUsedRatio": "0.5,0.6"
+                }
+            }))
+        }),
+    );
+    let config = Config {
+        etherscan_api_key: Some("test-key".to_string()),
+        etherscan_base_url: mock_upstream(etherscan).await,
+        coingecko_base_url: mock_upstream(coingecko_price(2000.0)).await,
+        ..Config::default()
+    };
+    let router = create_router(AppState::new(config));
+
+    let (status, body) = get_json_from(router.clone(), "/api/v1/gas/prices").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("usd").is_none());
+
+    // 20 gwei at 2000 USD: 0.00004 USD per gas, 0.84 USD per 21000 gas
+    let (status, body) = get_json_from(router.clone(), "/api/v1/gas/prices?include_usd=true&tier_labels=slow,normal,rapid").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["gas_price"]["normal"], 20);
+    assert_eq!(body["usd"]["native_coin"], "ETH");
+    assert_eq!(body["usd"]["native_price"], 2000.0);
+    assert!((body["usd"]["normal"]["per_gas"].as_f64().unwrap() - 0.00004).abs() < 1e-15);
+    assert!((body["usd"]["normal"]["per_transfer"].as_f64().unwrap() - 0.84).abs() < 1e-9);
+    assert!((body["usd"]["rapid"]["per_transfer"].as_f64().unwrap() - 1.68).abs() < 1e-9);
+
+    let (status, body) = get_json_from(router, "/api/v1/gas/prices?include_usd=true&speed=fast").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"]["details"].as_str().unwrap().contains("include_usd"));
+}
+
+#[tokio::test]
+async fn gas_batch_cost_sums_operations() {
+    let etherscan = Router::new().route(
+        "/",
+        get(|| async {
+            Json(json!({
+                "status": "1",
+                "message": "OK",
+                "result": {
+                    "LastBlock": "19000000",
+                    "SafeGasPrice": "5",
+                    "ProposeGasPrice": "10",
+                    "FastGasPrice": "20",
+                    "suggestBaseFee": "4.2",
+                    "gasUsedRatio": "0.5,0.6"
+                }
+            }))
+        }),
+    );
+    let config = Config {
+        etherscan_api_key: Some("test-key".to_string()),
+        etherscan_base_url: mock_upstream(etherscan).await,
+        coingecko_base_url: mock_upstream(coingecko_price(2000.0)).await,
+        ..Config::default()
+    };
+    let operations = json!([{ "type": "native_transfer" }, { "type": "contract_call", "gas_limit": 79000 }]);
+
+    let (status, body) = post_json(config, "/api/v1/gas/cost/batch", json!({ "operations": operations })).await;
+
+    // 100000 gas at 10 gwei = 0.001 ETH = 2 USD
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["native_coin"], "ETH");
+    assert_eq!(body["currency"], "USD");
+    assert_eq!(body["gas_price_gwei"], 10.0);
+    assert_eq!(body["gas_limit"], 100_000);
+    assert!((body["fee_native"].as_f64().unwrap() - 0.001).abs() < 1e-12);
+    assert!((body["fee_fiat"].as_f64().unwrap() - 2.0).abs() < 1e-9);
+    assert_eq!(body["operations"][0]["operation"]["type"], "native_transfer");
+    assert_eq!(body["operations"][1]["gas_limit"], 79_000);
+    assert!((body["operations"][1]["fee_fiat"].as_f64().unwrap() - 1.58).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn gas_batch_cost_rejects_invalid_bodies() {
+    for body in [json!({ "operations": [] }), json!({ "operations": [{ "type": "contract_call" }] })] {
+        let (status, response) = post_json(Config::default(), "/api/v1/gas/cost/batch", body.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+        assert_eq!(response["error"]["status"], 400);
+    }
+}
+
+#[tokio::test]
+async fn gas_batch_cost_rejects_empty_operations() {
+    let (status, body) = post_json(Config::default(), "/api/v1/gas/cost/batch", json!({ "operations": [] })).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["status"], 400);
+    assert_eq!(body["error"]["details"], "Invalid request body: operations: at least one operation is required");
+}
+
+#[tokio::test]
+async fn gas_prices_fail_when_upstream_fails() {
+    let config = Config {
+        etherscan_api_key: Some("test-key".to_string()),
+        etherscan_base_url: mock_upstream(failing("/", StatusCode::TOO_MANY_REQUESTS)).await,
+        ..Config::default()
+    };
+
+    let (status, body) = get_json(config, "/api/v1/gas/prices").await;
+
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["error"]["status"], 500);
+}
+
+#[tokio::test]
+async fn gas_prices_reject_invalid_params() {
+    let (status, body) = get_json(Config::default(), "/api/v1/gas/prices?speed=turbo").await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["status"], 400);
+    assert!(body["error"]["details"].as_str().unwrap().contains("turbo"));
+}
+
+#[tokio::test]
+async fn gas_prices_unconfigured_provider_is_not_implemented() {
+    let (status, body) = get_json(Config::default(), "/api/v1/gas/prices?provider=etherscan").await;
+
+    assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+    assert!(body["error"]["details"].as_str().unwrap().contains("ETHERSCAN_API_KEY"));
+}
+
+#[tokio::test]
+async fn concurrent_gas_requests_share_one_upstream_lookup() {
+    // A slow JSON-RPC node, so every request arrives while the first lookup is in flight
+    let fee_history_calls = Arc::new(AtomicUsize::new(0));
+    let calls = fee_history_calls.clone();
+    let node = Router::new().route(
+        "/",
+        post(move |Json(request): Json<Value>| {
+            let calls = calls.clone();
+            async move {
+                if request["method"] == "eth_feeHistory" {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                let result = match request["method"].as_str() {
+                    Some("eth_feeHistory") => json!({
+                        "oldestBlock": "0x1",
+                        "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+                        "gasUsedRatio": [0.5],
+                        "reward": [["0x3b9aca00", "0x77359400", "0xb2d05e00"]]
+                    }),
+                    _ => Value::Null,
+                };
+                Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+            }
+        }),
+    );
+    let config = Config { ethereum_rpc_url: Some(mock_upstream(node).await), ..Config::default() };
+    let router = create_router(AppState::new(config));
+
+    let responses = futures::future::join_all(
+        (0..20).map(|_| get_json_from(router.clone(), "/api/v1/gas/prices?provider=alloy")),
+    )
+    .await;
+
+    for (status, body) in &responses {
+        assert_eq!(*status, StatusCode::OK);
+        assert_eq!(body["provider"], "alloy");
+    }
+    assert_eq!(fee_history_calls.load(Ordering::SeqCst), 1);
+
+    // Once it finished, the next request looks up again
+    let (status, _) = get_json_from(router, "/api/v1/gas/prices?provider=alloy").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fee_history_calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn gas_endpoints_answer_503_when_started_without_a_gas_oracle() {
+    // What `REQUIRE_GAS=false` without ETHEREUM_RPC_URL produces
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.5)).await,
+        gas_enabled: false,
+        ..Config::default()
+    };
+
+    let (status, body) = get_json(config.clone(), "/api/v1/crypto/prices").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"][0]["price"], 2000.5);
+
+    let (status, body) = get_json(config.clone(), "/api/v1/gas/prices?provider=alloy").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(body["error"]["details"].as_str().unwrap().contains("Gas endpoints are disabled"));
+
+    let operations = json!([{ "type": "native_transfer" }]);
+    let (status, _) = post_json(config, "/api/v1/gas/cost/batch", json!({ "operations": operations })).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn health_reports_configured_providers() {
+    let (status, body) = get_json(Config::default(), "/api/v1/health").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["providers"]["coingecko"]["configured"], true);
+    assert_eq!(body["providers"]["etherscan"]["configured"], false);
+}
+
+#[tokio::test]
+async fn limits_report_configured_values() {
+    let mut config = Config {
+        max_inflight_requests: std::num::NonZeroUsize::new(64).unwrap(),
+        quote_cache_ttl: std::time::Duration::from_secs(30),
+        ..Config::default()
+    };
+    config.http.max_response_bytes = 4096;
+    config.provider_rpm.insert(ProviderSource::CoinGecko, std::num::NonZeroU32::new(30).unwrap());
+
+    let (status, body) = get_json(config, "/api/v1/limits").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["max_inflight_requests"], 64);
+    assert_eq!(body["max_request_body_bytes"], 2 * 1024 * 1024);
+    assert_eq!(body["max_upstream_response_bytes"], 4096);
+    assert_eq!(body["provider_requests_per_minute"], json!({ "coingecko": 30 }));
+    assert_eq!(body["provider_quota_max_wait_seconds"], 5);
+    assert_eq!(body["quote_cache_ttl_seconds"], 30);
+}
+
+#[tokio::test]
+async fn map_responses_serialize_keys_in_sorted_order() {
+    // Quotas inserted in different orders must still serialize identically
+    let config = |order: [ProviderSource; 3]| {
+        let mut config = Config::default();
+        for provider in order {
+            let rpm = if provider == ProviderSource::Binance { 1200 } else { 30 };
+            config.provider_rpm.insert(provider, std::num::NonZeroU32::new(rpm).unwrap());
+        }
+        config
+    };
+    let first = config([ProviderSource::CoinMarketCap, ProviderSource::Binance, ProviderSource::CoinGecko]);
+    let second = config([ProviderSource::CoinGecko, ProviderSource::CoinMarketCap, ProviderSource::Binance]);
+
+    let limits = get_text(first.clone(), "/api/v1/limits").await;
+    assert_eq!(limits, get_text(second, "/api/v1/limits").await);
+    assert!(limits.contains(r#"{"binance":1200,"coingecko":30,"coinmarketcap":30}"#), "{}", limits);
+
+    let health = get_text(first.clone(), "/api/v1/health").await;
+    assert_eq!(health, get_text(first, "/api/v1/health").await);
+    let positions: Vec<usize> = ["\"alloy\"", "\"blockscout\"", "\"coingecko\"", "\"etherscan\""]
+        .iter()
+        .map(|provider| health.find(provider).unwrap())
+        .collect();
+    assert!(positions.is_sorted(), "{}", health);
+}
+
+#[tokio::test]
+async fn responses_are_messagepack_when_accepted() {
+    #[derive(serde::Deserialize)]
+    struct Prices {
+        quotes: Vec<Quote>,
+    }
+
+    let config = Config {
+        coingecko_base_url: mock_upstream(coingecko_price(2000.5)).await,
+        ..Config::default()
+    };
+    let router = create_router(AppState::new(config));
+    let request = Request::get("/api/v1/crypto/prices?amount=2&pretty=true")
+        .header("accept", "application/msgpack")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/msgpack");
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let prices: Prices = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(prices.quotes[0].price, 2000.5);
+    assert_eq!(prices.quotes[0].quote_per_amount.total_price, 4001.0);
+
+    // JSON stays the default
+    let (status, body) = get_json_from(router, "/api/v1/crypto/prices").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["quotes"][0]["price"], 2000.5);
+}
+
+#[tokio::test]
+async fn debug_responses_echo_the_quote_cache_keys() {
+    let upstream = mock_upstream(coingecko_price(2000.5)).await;
+    let cache_key = |debug: bool, production: bool| {
+        let config = Config {
+            coingecko_base_url: upstream.clone(),
+            debug,
+            production,
+            ..Config::default()
+        };
+        async move {
+            let response = create_router(AppState::new(config))
+                .oneshot(Request::get("/api/v1/crypto/prices?currencies=USD,EUR&amount=3").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            response.headers().get("x-cache-key").map(|value| value.to_str().unwrap().to_string())
+        }
+    };
+
+    // One key per provider and currency; the amount is not part of it
+    assert_eq!(cache_key(true, false).await.as_deref(), Some("ETH:USD:coingecko, ETH:EUR:coingecko"));
+    assert_eq!(cache_key(false, false).await, None);
+    assert_eq!(cache_key(true, true).await, None);
+}
+
+#[tokio::test]
+async fn requests_beyond_inflight_cap_are_shed() {
+    // CoinGecko answers slowly, keeping the first request in flight
+    let slow_coingecko = Router::new().route(
+        "/simple/price",
+        get(|| async {
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            Json(json!({ "ethereum": { "usd": 2000.0, "last_updated_at": 1700000000 } }))
+        }),
+    );
+    let config = Config {
+        coingecko_base_url: mock_upstream(slow_coingecko).await,
+        max_inflight_requests: std::num::NonZeroUsize::new(2).unwrap(),
+        ..Config::default()
+    };
+    let router = create_router(AppState::new(config));
+
+    let in_flight: Vec<_> = (0..2)
+        .map(|_| tokio::spawn(get_json_from(router.clone(), "/api/v1/crypto/prices?provider=coingecko")))
+        .collect();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let started = std::time::Instant::now();
+    let (status, body) = get_json_from(router.clone(), "/api/v1/ping").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["error"]["status"], 503);
+    assert!(started.elapsed() < std::time::Duration::from_millis(100));
+
+    // Health checks bypass the cap
+    let (status, _) = get_json_from(router.clone(), "/api/v1/health").await;
+    assert_eq!(status, StatusCode::OK);
+
+    for request in in_flight {
+        assert_eq!(request.await.unwrap().0, StatusCode::OK);
+    }
+    let (status, _) = get_json_from(router, "/api/v1/crypto/prices?provider=coingecko").await;
+    assert_eq!(status, StatusCode::OK);
+}